@@ -1,3 +1,5 @@
+use alloc::vec;
+use alloc::vec::Vec;
 use core::ops::{Add, Mul, Sub};
 
 use p3_field::{Algebra, ExtensionField, Field, PrimeCharacteristicRing};
@@ -9,9 +11,144 @@ pub trait BaseAir<F>: Sync {
     /// The number of columns (a.k.a. registers) in this AIR.
     fn width(&self) -> usize;
 
+    /// The number of extension field columns in this AIR's main trace.
+    ///
+    /// These follow the base field columns, and are committed along with them, flattened to their
+    /// base field coefficients. So a main trace over a degree `D` extension has
+    /// `width() + D * ext_width()` base field columns. They are accessed through
+    /// [`ExtensionMainBuilder`].
+    fn ext_width(&self) -> usize {
+        0
+    }
+
     fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
         None
     }
+
+    /// The number of extension field columns in this AIR's permutation trace.
+    ///
+    /// The permutation trace is committed in a second phase, after the main trace commitment, and
+    /// may depend on challenges sampled after the main trace is fixed. It is accessed through
+    /// [`PermutationAirBuilder`].
+    fn permutation_width(&self) -> usize {
+        0
+    }
+
+    /// The number of challenges available to the permutation trace.
+    fn num_permutation_challenges(&self) -> usize {
+        0
+    }
+
+    /// Generate the permutation trace from the main trace and the permutation challenges.
+    ///
+    /// AIRs with a nonzero [`permutation_width`](Self::permutation_width) must override this to
+    /// return a trace of that width and the same height as `main`.
+    fn permutation_trace<EF>(
+        &self,
+        _main: &RowMajorMatrix<F>,
+        _challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+    {
+        None
+    }
+
+    /// The number of consecutive rows each constraint can refer to.
+    ///
+    /// Constraints may read rows `0..window_size()` of the matrices returned by the builder, i.e.
+    /// row `i` up to row `i + window_size() - 1`, and may use
+    /// [`is_transition_window`](AirBuilder::is_transition_window) for sizes `2..=window_size()`.
+    /// Each extra row adds an opening point for every committed trace. AIRs with interactions need
+    /// a window of at least 2, as their LogUp running sum relates each row to the next.
+    fn window_size(&self) -> usize {
+        2
+    }
+
+    /// A canonical encoding of this AIR, bound into the Fiat-Shamir transcript before any
+    /// challenge is sampled so that proofs for different AIRs never share a transcript prefix.
+    ///
+    /// By default this is `None`, and provers derive a fingerprint from the AIR's symbolic
+    /// constraints. AIRs may override this, e.g. with a precomputed digest of their constraints,
+    /// to avoid re-deriving it for every proof.
+    fn fingerprint(&self) -> Option<Vec<F>> {
+        None
+    }
+
+    /// How provers should pad a trace whose height is not a power of two.
+    ///
+    /// By default this is `None`, and traces must already have a power of two height. Whichever
+    /// strategy an AIR picks, the padding rows must satisfy its constraints.
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        None
+    }
+
+    /// Short columns which repeat cyclically down the trace, so that row `i` of a column `c` holds
+    /// `c[i % c.len()]`, e.g. round constants or round flags.
+    ///
+    /// Periodic columns are not committed. Verifiers evaluate them at the opening point
+    /// themselves, as polynomials of degree less than their length in a power of the trace
+    /// domain's points. Each column's length must be a power of two no larger than the trace
+    /// height. Constraints access them through [`PeriodicAirBuilder`].
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
+}
+
+/// A strategy for padding a trace to a power of two height. See [`BaseAir::trace_padding`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TracePadding<F> {
+    /// Pad with rows of zeros.
+    Zero,
+    /// Pad with copies of the trace's last row.
+    RepeatLastRow,
+    /// Pad with copies of the given row, which must have the trace's width.
+    Row(Vec<F>),
+    /// Pad with copies of the given rows, repeated in order and truncated at the padded height,
+    /// e.g. the rows of a dummy instance of a computation spanning several rows. They must have
+    /// the trace's width.
+    Rows(RowMajorMatrix<F>),
+}
+
+impl<F: Field> TracePadding<F> {
+    /// Pad `trace` with rows following this strategy, up to the next power of two height.
+    pub fn pad(&self, trace: &mut RowMajorMatrix<F>) {
+        let height = trace.height();
+        let padded_height = height.next_power_of_two();
+        if padded_height == height {
+            return;
+        }
+
+        // The padding rows, flattened, which are repeated until the trace is padded.
+        let width = trace.width();
+        let last_row;
+        let zero_row;
+        let rows: &[F] = match self {
+            Self::Zero => {
+                zero_row = F::zero_vec(width);
+                &zero_row
+            }
+            Self::RepeatLastRow => {
+                assert!(height > 0, "cannot repeat the last row of an empty trace");
+                last_row = trace.row_slice(height - 1).to_vec();
+                &last_row
+            }
+            Self::Row(row) => {
+                assert_eq!(row.len(), width, "padding row has the wrong width");
+                row
+            }
+            Self::Rows(rows) => {
+                assert_eq!(rows.width(), width, "padding rows have the wrong width");
+                assert!(rows.height() > 0, "there must be at least one padding row");
+                &rows.values
+            }
+        };
+        let num_padding_values = (padded_height - height) * width;
+        trace
+            .values
+            .extend(rows.iter().cycle().take(num_padding_values).copied());
+    }
 }
 
 ///  An AIR with 0 or more public values.
@@ -139,6 +276,14 @@ pub trait AirBuilderWithPublicValues: AirBuilder {
     fn public_values(&self) -> &[Self::PublicVar];
 }
 
+/// A builder which exposes the current row of an AIR's
+/// [`periodic_columns`](BaseAir::periodic_columns).
+pub trait PeriodicAirBuilder: AirBuilder {
+    type PeriodicVar: Into<Self::Expr> + Copy;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar];
+}
+
 pub trait PairBuilder: AirBuilder {
     fn preprocessed(&self) -> Self::M;
 }
@@ -170,6 +315,14 @@ pub trait ExtensionBuilder: AirBuilder {
     }
 }
 
+/// A builder which exposes the extension field columns of the main trace. See
+/// [`BaseAir::ext_width`].
+pub trait ExtensionMainBuilder: ExtensionBuilder {
+    type MEF: Matrix<Self::VarEF>;
+
+    fn main_ext(&self) -> Self::MEF;
+}
+
 pub trait PermutationAirBuilder: ExtensionBuilder {
     type MP: Matrix<Self::VarEF>;
 
@@ -219,6 +372,14 @@ impl<AB: AirBuilder> AirBuilder for FilteredAirBuilder<'_, AB> {
     }
 }
 
+impl<AB: PeriodicAirBuilder> PeriodicAirBuilder for FilteredAirBuilder<'_, AB> {
+    type PeriodicVar = AB::PeriodicVar;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.inner.periodic_values()
+    }
+}
+
 impl<AB: ExtensionBuilder> ExtensionBuilder for FilteredAirBuilder<'_, AB> {
     type EF = AB::EF;
     type ExprEF = AB::ExprEF;
@@ -232,6 +393,14 @@ impl<AB: ExtensionBuilder> ExtensionBuilder for FilteredAirBuilder<'_, AB> {
     }
 }
 
+impl<AB: ExtensionMainBuilder> ExtensionMainBuilder for FilteredAirBuilder<'_, AB> {
+    type MEF = AB::MEF;
+
+    fn main_ext(&self) -> Self::MEF {
+        self.inner.main_ext()
+    }
+}
+
 impl<AB: PermutationAirBuilder> PermutationAirBuilder for FilteredAirBuilder<'_, AB> {
     type MP = AB::MP;
 
@@ -0,0 +1,92 @@
+use alloc::vec::Vec;
+
+use p3_field::Field;
+
+use crate::{BaseAir, VirtualPairCol};
+
+/// Whether an interaction adds a message to a bus or removes one from it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractionKind {
+    Send,
+    Receive,
+}
+
+/// A message sent to or received from a bus by every row of an AIR.
+///
+/// On each row, the message is the tuple `values` evaluated on that row, and it is sent (or
+/// received) `multiplicity` times. A bus balances if, over all AIRs and all rows, every message is
+/// sent exactly as many times as it is received.
+#[derive(Clone, Debug)]
+pub struct Interaction<F: Field> {
+    pub bus: usize,
+    pub kind: InteractionKind,
+    pub values: Vec<VirtualPairCol<F>>,
+    pub multiplicity: VirtualPairCol<F>,
+}
+
+/// Records the bus interactions of an AIR.
+#[derive(Debug)]
+pub struct InteractionBuilder<F: Field> {
+    interactions: Vec<Interaction<F>>,
+}
+
+impl<F: Field> Default for InteractionBuilder<F> {
+    fn default() -> Self {
+        Self {
+            interactions: Vec::new(),
+        }
+    }
+}
+
+impl<F: Field> InteractionBuilder<F> {
+    /// Send the message `values` to `bus`, `multiplicity` times on each row.
+    pub fn send<I>(&mut self, bus: usize, values: I, multiplicity: VirtualPairCol<F>)
+    where
+        I: IntoIterator<Item = VirtualPairCol<F>>,
+    {
+        self.push(bus, InteractionKind::Send, values, multiplicity);
+    }
+
+    /// Receive the message `values` from `bus`, `multiplicity` times on each row.
+    pub fn receive<I>(&mut self, bus: usize, values: I, multiplicity: VirtualPairCol<F>)
+    where
+        I: IntoIterator<Item = VirtualPairCol<F>>,
+    {
+        self.push(bus, InteractionKind::Receive, values, multiplicity);
+    }
+
+    fn push<I>(
+        &mut self,
+        bus: usize,
+        kind: InteractionKind,
+        values: I,
+        multiplicity: VirtualPairCol<F>,
+    ) where
+        I: IntoIterator<Item = VirtualPairCol<F>>,
+    {
+        self.interactions.push(Interaction {
+            bus,
+            kind,
+            values: values.into_iter().collect(),
+            multiplicity,
+        });
+    }
+
+    pub fn interactions(self) -> Vec<Interaction<F>> {
+        self.interactions
+    }
+}
+
+/// An AIR which may send messages to and receive messages from buses shared with other AIRs.
+///
+/// AIRs without any interactions can use the default (empty) implementation.
+pub trait InteractionAir<F: Field>: BaseAir<F> {
+    fn eval_interactions(&self, _builder: &mut InteractionBuilder<F>) {}
+}
+
+/// Collect all interactions of `air`.
+pub fn get_interactions<F: Field, A: InteractionAir<F>>(air: &A) -> Vec<Interaction<F>> {
+    let mut builder = InteractionBuilder::default();
+    air.eval_interactions(&mut builder);
+    builder.interactions()
+}
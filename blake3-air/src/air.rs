@@ -3,7 +3,7 @@ use core::borrow::Borrow;
 
 use itertools::izip;
 use p3_air::utils::{add2, add3, pack_bits_le, xor_32_shift};
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, TracePadding};
 use p3_field::{PrimeCharacteristicRing, PrimeField64};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
@@ -228,6 +228,11 @@ impl<F> BaseAir<F> for Blake3Air {
     fn width(&self) -> usize {
         NUM_BLAKE3_COLS
     }
+
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        // Each row is an independent hash, so a copy of the last one is valid padding.
+        Some(TracePadding::RepeatLastRow)
+    }
 }
 
 impl<AB: AirBuilder> Air<AB> for Blake3Air {
@@ -18,15 +18,12 @@ pub fn generate_trace_rows<F: PrimeField64>(
     extra_capacity_bits: usize,
 ) -> RowMajorMatrix<F> {
     let num_rows = inputs.len();
-    assert!(
-        num_rows.is_power_of_two(),
-        "Callers expected to pad inputs to VECTOR_LEN times a power of two"
-    );
-
     let trace_length = num_rows * NUM_BLAKE3_COLS;
 
-    // We allocate extra_capacity_bits now as this will be needed by the dft.
-    let mut long_trace = F::zero_vec(trace_length << extra_capacity_bits);
+    // We allocate extra_capacity_bits now as this will be needed by the dft, along with room for
+    // the prover to pad the trace to a power of two height.
+    let mut long_trace =
+        F::zero_vec((num_rows.next_power_of_two() * NUM_BLAKE3_COLS) << extra_capacity_bits);
     long_trace.truncate(trace_length);
 
     let mut trace = RowMajorMatrix::new(long_trace, NUM_BLAKE3_COLS);
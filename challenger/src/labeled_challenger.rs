@@ -0,0 +1,351 @@
+use alloc::vec::Vec;
+
+use p3_field::{BasedVectorSpace, Field, PrimeField, PrimeField32};
+use p3_symmetric::{CryptographicHasher, CryptographicPermutation};
+
+use crate::{
+    CanObserve, CanSample, CanSampleBits, DuplexChallenger, FieldChallenger, HashChallenger,
+    MultiField32Challenger,
+};
+
+/// A challenger which can absorb domain-separation labels.
+pub trait CanObserveLabel {
+    /// Observe `label`, tagging a message of `len` values which are observed or sampled next.
+    ///
+    /// The label is observed as the bytes given by [`label_bytes`], which encode it injectively.
+    fn observe_label(&mut self, label: &[u8], len: usize);
+}
+
+/// The bytes absorbed for `label`, tagging a message of `len` values: the length of the label as a
+/// little-endian `u64`, the label itself, then `len` as a little-endian `u64`.
+pub fn label_bytes(label: &[u8], len: usize) -> impl Iterator<Item = u8> + '_ {
+    (label.len() as u64)
+        .to_le_bytes()
+        .into_iter()
+        .chain(label.iter().copied())
+        .chain((len as u64).to_le_bytes())
+}
+
+/// A transcript in which every observed and sampled message is tagged with a label, similar to
+/// Merlin transcripts.
+///
+/// Each label is absorbed by the inner challenger before its message, so a prover and verifier
+/// which disagree on the order or meaning of messages derive different challenges, rather than
+/// silently agreeing on a reordered transcript.
+#[derive(Clone, Debug)]
+pub struct LabeledChallenger<Inner> {
+    inner: Inner,
+}
+
+impl<Inner: CanObserveLabel> LabeledChallenger<Inner> {
+    /// A transcript for the protocol named by `protocol_label`, which is observed first.
+    pub fn new(protocol_label: &[u8], mut inner: Inner) -> Self {
+        inner.observe_label(protocol_label, 0);
+        Self { inner }
+    }
+
+    /// The inner challenger, e.g. to run a sub-protocol which observes and samples unlabeled values.
+    /// It should be preceded by a labeled message of its own.
+    pub const fn inner_mut(&mut self) -> &mut Inner {
+        &mut self.inner
+    }
+
+    pub fn into_inner(self) -> Inner {
+        self.inner
+    }
+
+    pub fn observe_labeled<T>(&mut self, label: &[u8], value: T)
+    where
+        Inner: CanObserve<T>,
+    {
+        self.inner.observe_label(label, 1);
+        self.inner.observe(value);
+    }
+
+    pub fn observe_labeled_slice<T: Clone>(&mut self, label: &[u8], values: &[T])
+    where
+        Inner: CanObserve<T>,
+    {
+        self.inner.observe_label(label, values.len());
+        self.inner.observe_slice(values);
+    }
+
+    pub fn observe_labeled_algebra_element<F: Field, A: BasedVectorSpace<F>>(
+        &mut self,
+        label: &[u8],
+        alg_elem: A,
+    ) where
+        Inner: FieldChallenger<F>,
+    {
+        self.inner.observe_label(label, A::DIMENSION);
+        self.inner.observe_algebra_element(alg_elem);
+    }
+
+    pub fn sample_labeled<T>(&mut self, label: &[u8]) -> T
+    where
+        Inner: CanSample<T>,
+    {
+        self.inner.observe_label(label, 1);
+        self.inner.sample()
+    }
+
+    pub fn sample_labeled_vec<T>(&mut self, label: &[u8], n: usize) -> Vec<T>
+    where
+        Inner: CanSample<T>,
+    {
+        self.inner.observe_label(label, n);
+        self.inner.sample_vec(n)
+    }
+
+    pub fn sample_labeled_bits(&mut self, label: &[u8], bits: usize) -> usize
+    where
+        Inner: CanSampleBits<usize>,
+    {
+        self.inner.observe_label(label, 1);
+        self.inner.sample_bits(bits)
+    }
+
+    pub fn sample_labeled_algebra_element<F: Field, A: BasedVectorSpace<F>>(
+        &mut self,
+        label: &[u8],
+    ) -> A
+    where
+        Inner: FieldChallenger<F>,
+    {
+        self.inner.observe_label(label, A::DIMENSION);
+        self.inner.sample_algebra_element()
+    }
+}
+
+impl<F, P, const WIDTH: usize, const RATE: usize> CanObserveLabel
+    for DuplexChallenger<F, P, WIDTH, RATE>
+where
+    F: Field,
+    P: CryptographicPermutation<[F; WIDTH]>,
+{
+    /// Each byte of the label is observed as a field element.
+    fn observe_label(&mut self, label: &[u8], len: usize) {
+        for byte in label_bytes(label, len) {
+            self.observe(F::from_u8(byte));
+        }
+    }
+}
+
+impl<F, H, const OUT_LEN: usize> CanObserveLabel for HashChallenger<F, H, OUT_LEN>
+where
+    F: Field,
+    H: CryptographicHasher<F, [F; OUT_LEN]>,
+{
+    /// Each byte of the label is observed as a field element.
+    fn observe_label(&mut self, label: &[u8], len: usize) {
+        for byte in label_bytes(label, len) {
+            self.observe(F::from_u8(byte));
+        }
+    }
+}
+
+impl<F, PF, P, const WIDTH: usize, const RATE: usize> CanObserveLabel
+    for MultiField32Challenger<F, PF, P, WIDTH, RATE>
+where
+    F: PrimeField32,
+    PF: PrimeField,
+    P: CryptographicPermutation<[PF; WIDTH]>,
+{
+    /// Each byte of the label is observed as an element of `F`, which are packed into elements of
+    /// `PF` along with the values which follow.
+    fn observe_label(&mut self, label: &[u8], len: usize) {
+        for byte in label_bytes(label, len) {
+            self.observe(F::from_u8(byte));
+        }
+    }
+}
+
+impl<C: CanObserveLabel> CanObserveLabel for &mut C {
+    #[inline(always)]
+    fn observe_label(&mut self, label: &[u8], len: usize) {
+        (*self).observe_label(label, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_goldilocks::Goldilocks;
+    use p3_symmetric::Permutation;
+
+    use super::*;
+    use crate::SerializingChallenger32;
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+
+    #[derive(Clone, Debug)]
+    struct TestPermutation {}
+
+    impl<R: Field> Permutation<[R; 16]> for TestPermutation {
+        fn permute_mut(&self, input: &mut [R; 16]) {
+            // Mix every element into every other, so that observations can't cancel out.
+            let sum: R = input.iter().copied().sum();
+            for (i, x) in input.iter_mut().enumerate() {
+                *x = (*x + sum) * R::from_usize(i + 2);
+            }
+        }
+    }
+
+    impl<R: Field> CryptographicPermutation<[R; 16]> for TestPermutation {}
+
+    /// A byte hasher which isn't cryptographic, but depends on every input byte and its position.
+    #[derive(Clone, Debug)]
+    struct TestHasher {}
+
+    impl CryptographicHasher<u8, [u8; 4]> for TestHasher {
+        fn hash_iter<I>(&self, input: I) -> [u8; 4]
+        where
+            I: IntoIterator<Item = u8>,
+        {
+            let hash = input.into_iter().fold(0x811c9dc5_u32, |hash, byte| {
+                (hash ^ byte as u32).wrapping_mul(0x01000193)
+            });
+            hash.to_le_bytes()
+        }
+    }
+
+    impl CryptographicHasher<F, [F; 4]> for TestHasher {
+        fn hash_iter<I>(&self, input: I) -> [F; 4]
+        where
+            I: IntoIterator<Item = F>,
+        {
+            let hash = input
+                .into_iter()
+                .fold(F::ONE, |hash, x| (hash + x) * F::from_u32(0x01000193));
+            [hash, hash.square(), hash.cube(), hash.exp_const_u64::<4>()]
+        }
+    }
+
+    type Duplex = DuplexChallenger<F, TestPermutation, 16, 8>;
+    type Serializing = SerializingChallenger32<F, HashChallenger<u8, TestHasher, 4>>;
+    type FieldHash = HashChallenger<F, TestHasher, 4>;
+    type MultiField = MultiField32Challenger<F, Goldilocks, TestPermutation, 16, 8>;
+
+    fn duplex() -> LabeledChallenger<Duplex> {
+        LabeledChallenger::new(b"test", Duplex::new(TestPermutation {}))
+    }
+
+    fn serializing() -> LabeledChallenger<Serializing> {
+        LabeledChallenger::new(
+            b"test",
+            Serializing::new(HashChallenger::new(vec![], TestHasher {})),
+        )
+    }
+
+    fn multi_field() -> LabeledChallenger<MultiField> {
+        LabeledChallenger::new(b"test", MultiField::new(TestPermutation {}).unwrap())
+    }
+
+    /// Runs a small protocol, with the messages `a` and `b` given in either order.
+    fn transcript<Inner>(mut challenger: LabeledChallenger<Inner>, swap: bool) -> (EF, usize)
+    where
+        Inner: CanObserveLabel + FieldChallenger<F>,
+    {
+        let (a, b) = (F::from_u8(3), F::from_u8(5));
+        if swap {
+            challenger.observe_labeled(b"b", b);
+            challenger.observe_labeled(b"a", a);
+        } else {
+            challenger.observe_labeled(b"a", a);
+            challenger.observe_labeled(b"b", b);
+        }
+        let alpha: EF = challenger.sample_labeled_algebra_element(b"alpha");
+        (alpha, challenger.sample_labeled_bits(b"index", 20))
+    }
+
+    /// Checks that a labeled transcript over `inner` observes each label, with its bytes as field
+    /// elements, before its message.
+    fn check_labels_are_observed<Inner>(inner: Inner)
+    where
+        Inner: CanObserveLabel + CanObserve<F> + CanSample<F> + Clone,
+    {
+        let mut challenger = LabeledChallenger::new(b"test", inner.clone());
+        challenger.observe_labeled_slice(b"values", &[F::ONE, F::TWO]);
+        let sample: F = challenger.sample_labeled(b"sample");
+
+        let mut expected = inner;
+        for (label, len) in [(&b"test"[..], 0), (b"values", 2)] {
+            label_bytes(label, len).for_each(|byte| expected.observe(F::from_u8(byte)));
+        }
+        expected.observe_slice(&[F::ONE, F::TWO]);
+        label_bytes(b"sample", 1).for_each(|byte| expected.observe(F::from_u8(byte)));
+        assert_eq!(sample, expected.sample());
+    }
+
+    #[test]
+    fn labels_are_observed() {
+        check_labels_are_observed(Duplex::new(TestPermutation {}));
+        check_labels_are_observed(FieldHash::new(vec![], TestHasher {}));
+        check_labels_are_observed(MultiField::new(TestPermutation {}).unwrap());
+    }
+
+    #[test]
+    fn same_transcript_same_challenges() {
+        assert_eq!(transcript(duplex(), false), transcript(duplex(), false));
+        assert_eq!(
+            transcript(serializing(), false),
+            transcript(serializing(), false)
+        );
+        assert_eq!(
+            transcript(multi_field(), false),
+            transcript(multi_field(), false)
+        );
+    }
+
+    #[test]
+    fn swapped_messages_change_challenges() {
+        assert_ne!(transcript(duplex(), false).0, transcript(duplex(), true).0);
+        assert_ne!(
+            transcript(serializing(), false).0,
+            transcript(serializing(), true).0
+        );
+        assert_ne!(
+            transcript(multi_field(), false).0,
+            transcript(multi_field(), true).0
+        );
+    }
+
+    #[test]
+    fn labels_change_challenges() {
+        let mut challenger = duplex();
+        let mut relabeled = duplex();
+        challenger.observe_labeled(b"commit", F::ONE);
+        relabeled.observe_labeled(b"commitment", F::ONE);
+        assert_ne!(
+            challenger.sample_labeled::<F>(b"alpha"),
+            relabeled.sample_labeled::<F>(b"alpha")
+        );
+
+        let mut other_protocol = LabeledChallenger::new(b"other", Duplex::new(TestPermutation {}));
+        let mut challenger = duplex();
+        assert_ne!(
+            challenger.sample_labeled::<F>(b"alpha"),
+            other_protocol.sample_labeled::<F>(b"alpha")
+        );
+    }
+
+    #[test]
+    fn message_lengths_are_observed() {
+        // The same values, split differently between two labels.
+        let mut challenger = duplex();
+        challenger.observe_labeled_slice(b"a", &[F::ONE, F::TWO]);
+        challenger.observe_labeled_slice(b"b", &[F::ONE]);
+        let mut resplit = duplex();
+        resplit.observe_labeled_slice(b"a", &[F::ONE]);
+        resplit.observe_labeled_slice(b"b", &[F::TWO, F::ONE]);
+        assert_ne!(
+            challenger.sample_labeled::<F>(b"alpha"),
+            resplit.sample_labeled::<F>(b"alpha")
+        );
+    }
+}
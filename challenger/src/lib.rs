@@ -7,6 +7,7 @@ extern crate alloc;
 mod duplex_challenger;
 mod grinding_challenger;
 mod hash_challenger;
+mod labeled_challenger;
 mod multi_field_challenger;
 mod serializing_challenger;
 
@@ -16,6 +17,7 @@ use core::array;
 pub use duplex_challenger::*;
 pub use grinding_challenger::*;
 pub use hash_challenger::*;
+pub use labeled_challenger::*;
 pub use multi_field_challenger::*;
 use p3_field::{BasedVectorSpace, Field};
 pub use serializing_challenger::*;
@@ -3,12 +3,13 @@ use core::marker::PhantomData;
 
 use p3_field::{BasedVectorSpace, PrimeField32, PrimeField64};
 use p3_maybe_rayon::prelude::*;
-use p3_symmetric::{CryptographicHasher, Hash};
+use p3_symmetric::{CryptographicHasher, Hash, MerkleCap};
 use p3_util::log2_ceil_u64;
 use tracing::instrument;
 
 use crate::{
-    CanObserve, CanSample, CanSampleBits, FieldChallenger, GrindingChallenger, HashChallenger,
+    CanObserve, CanObserveLabel, CanSample, CanSampleBits, FieldChallenger, GrindingChallenger,
+    HashChallenger, label_bytes,
 };
 
 /// Given a challenger that can observe and sample bytes, produces a challenger that is able to
@@ -77,6 +78,18 @@ impl<F: PrimeField32, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F,
     }
 }
 
+impl<F: PrimeField32, const N: usize, Inner: CanObserve<u8>> CanObserve<MerkleCap<F, u8, N>>
+    for SerializingChallenger32<F, Inner>
+{
+    fn observe(&mut self, cap: MerkleCap<F, u8, N>) {
+        // The cap's length comes first, so that caps of different heights can't collide.
+        self.inner.observe_slice(&(cap.len() as u32).to_le_bytes());
+        for digest in cap {
+            self.observe(Hash::<F, u8, N>::from(digest));
+        }
+    }
+}
+
 impl<F: PrimeField32, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F, u64, N>>
     for SerializingChallenger32<F, Inner>
 {
@@ -87,6 +100,17 @@ impl<F: PrimeField32, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F,
     }
 }
 
+impl<F: PrimeField32, const N: usize, Inner: CanObserve<u8>> CanObserve<MerkleCap<F, u64, N>>
+    for SerializingChallenger32<F, Inner>
+{
+    fn observe(&mut self, cap: MerkleCap<F, u64, N>) {
+        self.inner.observe_slice(&(cap.len() as u32).to_le_bytes());
+        for digest in cap {
+            self.observe(Hash::<F, u64, N>::from(digest));
+        }
+    }
+}
+
 impl<F, EF, Inner> CanSample<EF> for SerializingChallenger32<F, Inner>
 where
     F: PrimeField32,
@@ -158,6 +182,15 @@ where
 {
 }
 
+impl<F: PrimeField32, Inner: CanObserve<u8>> CanObserveLabel for SerializingChallenger32<F, Inner> {
+    /// The bytes of the label are observed by the inner challenger as they are.
+    fn observe_label(&mut self, label: &[u8], len: usize) {
+        for byte in label_bytes(label, len) {
+            self.inner.observe(byte);
+        }
+    }
+}
+
 impl<F: PrimeField64, Inner: CanObserve<u8>> SerializingChallenger64<F, Inner> {
     pub const fn new(inner: Inner) -> Self {
         Self {
@@ -194,6 +227,17 @@ impl<F: PrimeField64, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F,
     }
 }
 
+impl<F: PrimeField64, const N: usize, Inner: CanObserve<u8>> CanObserve<MerkleCap<F, u8, N>>
+    for SerializingChallenger64<F, Inner>
+{
+    fn observe(&mut self, cap: MerkleCap<F, u8, N>) {
+        self.inner.observe_slice(&(cap.len() as u64).to_le_bytes());
+        for digest in cap {
+            self.observe(Hash::<F, u8, N>::from(digest));
+        }
+    }
+}
+
 impl<F: PrimeField64, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F, u64, N>>
     for SerializingChallenger64<F, Inner>
 {
@@ -204,6 +248,17 @@ impl<F: PrimeField64, const N: usize, Inner: CanObserve<u8>> CanObserve<Hash<F,
     }
 }
 
+impl<F: PrimeField64, const N: usize, Inner: CanObserve<u8>> CanObserve<MerkleCap<F, u64, N>>
+    for SerializingChallenger64<F, Inner>
+{
+    fn observe(&mut self, cap: MerkleCap<F, u64, N>) {
+        self.inner.observe_slice(&(cap.len() as u64).to_le_bytes());
+        for digest in cap {
+            self.observe(Hash::<F, u64, N>::from(digest));
+        }
+    }
+}
+
 impl<F, EF, Inner> CanSample<EF> for SerializingChallenger64<F, Inner>
 where
     F: PrimeField64,
@@ -275,3 +330,11 @@ where
     Inner: CanSample<u8> + CanObserve<u8> + Clone + Send + Sync,
 {
 }
+
+impl<F: PrimeField64, Inner: CanObserve<u8>> CanObserveLabel for SerializingChallenger64<F, Inner> {
+    fn observe_label(&mut self, label: &[u8], len: usize) {
+        for byte in label_bytes(label, len) {
+            self.inner.observe(byte);
+        }
+    }
+}
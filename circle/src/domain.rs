@@ -11,6 +11,7 @@ use p3_util::{log2_ceil_usize, log2_strict_usize};
 use tracing::instrument;
 
 use crate::point::Point;
+use crate::{CfftView, CircleEvaluations};
 
 /// A twin-coset of the circle group on F. It has a power-of-two size and an arbitrary shift.
 ///
@@ -96,6 +97,9 @@ impl<F: ComplexExtendable> CircleDomain<F> {
 impl<F: ComplexExtendable> PolynomialSpace for CircleDomain<F> {
     type Val = F;
 
+    // A standard position coset of size 2^n is a coset of the subgroup of size 2^(n + 1).
+    const MAX_LOG_SIZE: usize = F::CIRCLE_TWO_ADICITY - 1;
+
     fn size(&self) -> usize {
         1 << self.log_n
     }
@@ -212,6 +216,60 @@ impl<F: ComplexExtendable> PolynomialSpace for CircleDomain<F> {
             inv_vanishing: sels.iter().map(|s| s.inv_vanishing).collect(),
         }
     }
+
+    /// The `i`'th point of a standard position coset of size `n` is `2i + 1` times a generator of
+    /// the subgroup of size `2n`. Doubling it maps it to the `(i mod n/2)`'th point of the standard
+    /// position coset of size `n/2`, so the column is the polynomial interpolating it over the
+    /// standard position coset of size `column.len()`, evaluated at the doubled point.
+    fn periodic_column_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        column: &[Self::Val],
+        point: Ext,
+    ) -> Ext {
+        assert!(self.is_standard());
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_n);
+        if log_period == 0 {
+            return column[0].into();
+        }
+        let point = iterate(Point::from_projective_line(point), |p| p.double())
+            .nth(self.log_n - log_period)
+            .unwrap();
+        periodic_evaluations(column).evaluate_at_point(point)[0]
+    }
+
+    fn periodic_column_on_coset(&self, column: &[Self::Val], coset: Self) -> Vec<Self::Val> {
+        assert!(self.is_standard() && coset.is_standard());
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_n);
+        if log_period == 0 {
+            return vec![column[0]; coset.size()];
+        }
+        let log_doublings = self.log_n - log_period;
+        let evals = periodic_evaluations(column);
+
+        // The doubled points of a standard position coset repeat after this many points.
+        let num_distinct = (coset.size() >> log_doublings).max(1);
+        let values = coset
+            .points()
+            .take(num_distinct)
+            .map(|p| {
+                let p = iterate(p, |p| p.double()).nth(log_doublings).unwrap();
+                evals.evaluate_at_point(p)[0]
+            })
+            .collect_vec();
+        values.into_iter().cycle().take(coset.size()).collect()
+    }
+}
+
+/// The evaluations of a periodic column over the standard position coset of its size.
+fn periodic_evaluations<F: ComplexExtendable>(
+    column: &[F],
+) -> CircleEvaluations<F, CfftView<RowMajorMatrix<F>>> {
+    CircleEvaluations::from_natural_order(
+        CircleDomain::standard(log2_strict_usize(column.len())),
+        RowMajorMatrix::new_col(column.to_vec()),
+    )
 }
 
 // 0 1 2 .. len-1 len len len-1 .. 1 0 0 1 ..
@@ -232,7 +290,6 @@ mod tests {
     use rand::rngs::SmallRng;
 
     use super::*;
-    use crate::CircleEvaluations;
 
     fn assert_is_twin_coset<F: ComplexExtendable>(d: CircleDomain<F>) {
         let pts = d.points().collect_vec();
@@ -395,6 +452,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn periodic_column() {
+        type F = Mersenne31;
+        let log_n = 6;
+        let n = 1 << log_n;
+        let column = [3, 1, 4, 1, 5, 9, 2, 6].map(F::from_u8);
+
+        let d = CircleDomain::<F>::standard(log_n);
+        let coset = d.create_disjoint_domain(4 * n);
+        let values = d.periodic_column_on_coset(&column, coset);
+
+        // periodic_column_on_coset matches periodic_column_at_point
+        for (p, &value) in coset.points().zip(&values) {
+            let p = p.to_projective_line().unwrap();
+            assert_eq!(d.periodic_column_at_point(&column, p), value);
+        }
+
+        // The column repeats on the domain, and has degree less than n.
+        let coeffs = CircleEvaluations::from_natural_order(coset, RowMajorMatrix::new_col(values))
+            .interpolate();
+        let (lo, hi) = coeffs.split_rows(n);
+        assert_eq!(hi.values, vec![F::ZERO; hi.values.len()]);
+        let on_d = CircleEvaluations::evaluate(d, lo.to_row_major_matrix())
+            .to_natural_order()
+            .to_row_major_matrix()
+            .values;
+        assert_eq!(on_d, column.iter().copied().cycle().take(n).collect_vec());
+    }
+
     #[test]
     fn test_circle_domain() {
         do_test_circle_domain(4, 8);
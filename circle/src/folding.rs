@@ -5,7 +5,7 @@ use core::marker::PhantomData;
 use itertools::Itertools;
 use p3_commit::Mmcs;
 use p3_field::extension::ComplexExtendable;
-use p3_field::{ExtensionField, batch_multiplicative_inverse};
+use p3_field::{ExtensionField, Field, batch_multiplicative_inverse};
 use p3_fri::FriGenericConfig;
 use p3_matrix::Matrix;
 use p3_util::{log2_strict_usize, reverse_bits_len};
@@ -127,6 +127,63 @@ pub(crate) fn fold_x_row<F: ComplexExtendable, EF: ExtensionField<F>>(
     (sum + beta * diff).halve()
 }
 
+/// The x coordinate of the point at `index` of a domain after the first fold, of size
+/// `2^log_height`. Points come in pairs `(x, -x)`, the pair at `index >> 1` being folded by
+/// `fold_x_row`.
+pub(crate) fn folded_x<F: ComplexExtendable>(index: usize, log_height: usize) -> F {
+    let x = CircleDomain::<F>::standard(log_height + 1)
+        .nth_x_twiddle(reverse_bits_len(index >> 1, log_height - 1));
+    if index & 1 == 0 { x } else { -x }
+}
+
+/// Interpolate `evals`, over a domain after the first fold, into the coefficients of a polynomial
+/// in x.
+///
+/// The basis is that of the circle FFT without its y factor: the coefficient at index `i` is that
+/// of the product of `v_j(x)` over the bits `j` set in `i`, where `v_0(x) = x` and
+/// `v_{j+1}(x) = 2 v_j(x)^2 - 1`. Folding at `beta` maps coefficients `c` to
+/// `c[2i] + beta * c[2i + 1]`.
+pub(crate) fn interpolate_folded<F: ComplexExtendable, EF: ExtensionField<F>>(
+    evals: Vec<EF>,
+) -> Vec<EF> {
+    if evals.len() == 1 {
+        return evals;
+    }
+    let log_n = log2_strict_usize(evals.len());
+    let twiddles =
+        batch_multiplicative_inverse(&CircleDomain::<F>::standard(log_n + 1).x_twiddles(0));
+    // Split `f(x) = f_0(2x^2 - 1) + x f_1(2x^2 - 1)` into its halves, over the folded domain.
+    let (evens, odds): (Vec<EF>, Vec<EF>) = evals
+        .chunks_exact(2)
+        .zip(twiddles)
+        .map(|(pair, t)| {
+            (
+                (pair[0] + pair[1]).halve(),
+                ((pair[0] - pair[1]) * t).halve(),
+            )
+        })
+        .unzip();
+    interpolate_folded::<F, EF>(evens)
+        .into_iter()
+        .interleave(interpolate_folded::<F, EF>(odds))
+        .collect()
+}
+
+/// Evaluate a polynomial in x, given by its coefficients as returned by [`interpolate_folded`],
+/// at `x`.
+pub(crate) fn eval_folded<F: Field, EF: ExtensionField<F>>(coeffs: &[EF], x: F) -> EF {
+    let mut coeffs = coeffs.to_vec();
+    let mut v = x;
+    while coeffs.len() > 1 {
+        coeffs = coeffs
+            .chunks_exact(2)
+            .map(|pair| pair[0] + pair[1] * v)
+            .collect();
+        v = v.square().double() - F::ONE;
+    }
+    coeffs[0]
+}
+
 #[cfg(test)]
 mod tests {
     use itertools::iproduct;
@@ -189,4 +246,39 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn interpolate_folded_roundtrip() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        for (log_n, log_blowup) in iproduct!(2..6, 1..3) {
+            let values = CircleEvaluations::evaluate(
+                CircleDomain::standard(log_n + log_blowup),
+                RowMajorMatrix::rand(&mut rng, 1 << log_n, 1),
+            )
+            .to_cfft_order()
+            .values;
+            let mut values = fold_y(rng.random(), RowMajorMatrix::new(values, 2));
+
+            for _ in 0..log_n {
+                let log_height = log2_strict_usize(values.len());
+                let coeffs = interpolate_folded::<F, F>(values.clone());
+                // The polynomial has low degree, and evaluates back to the same values.
+                let degree_bound = values.len() >> log_blowup;
+                assert!(coeffs[degree_bound..].iter().all(|c| c.is_zero()));
+                for (i, &v) in values.iter().enumerate() {
+                    let x = folded_x::<F>(i, log_height);
+                    assert_eq!(eval_folded(&coeffs[..degree_bound], x), v);
+                }
+
+                // Folding the evaluations folds the coefficients.
+                let beta: F = rng.random();
+                values = fold_x(beta, RowMajorMatrix::new(values, 2));
+                let folded_coeffs = coeffs
+                    .chunks_exact(2)
+                    .map(|pair| pair[0] + beta * pair[1])
+                    .collect_vec();
+                assert_eq!(interpolate_folded::<F, F>(values.clone()), folded_coeffs);
+            }
+        }
+    }
 }
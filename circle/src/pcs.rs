@@ -1,16 +1,16 @@
 use alloc::borrow::Cow;
-use alloc::collections::BTreeMap;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec;
 use alloc::vec::Vec;
 use core::marker::PhantomData;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs, PolynomialSpace};
+use p3_commit::{CommitError, MultiMmcs, OpenedValues, Pcs, PolynomialSpace};
 use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
-use p3_fri::FriConfig;
 use p3_fri::verifier::FriError;
+use p3_fri::{FriConfig, claimed_width};
 use p3_matrix::dense::{DenseMatrix, RowMajorMatrix};
 use p3_matrix::row_index_mapped::RowIndexMappedView;
 use p3_matrix::{Dimensions, Matrix};
@@ -36,7 +36,12 @@ pub struct CirclePcs<Val: Field, InputMmcs, FriMmcs> {
 }
 
 impl<Val: Field, InputMmcs, FriMmcs> CirclePcs<Val, InputMmcs, FriMmcs> {
+    /// # Panics
+    ///
+    /// Circle FRI only folds by 2 in each round, so this panics if `fri_config.max_log_arity` is
+    /// not 1.
     pub const fn new(mmcs: InputMmcs, fri_config: FriConfig<FriMmcs>) -> Self {
+        assert_binary_folding(&fri_config);
         Self {
             mmcs,
             fri_config,
@@ -45,11 +50,19 @@ impl<Val: Field, InputMmcs, FriMmcs> CirclePcs<Val, InputMmcs, FriMmcs> {
     }
 }
 
+const fn assert_binary_folding<M>(fri_config: &FriConfig<M>) {
+    assert!(
+        fri_config.max_log_arity == 1,
+        "CirclePcs only supports max_log_arity = 1"
+    );
+}
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct BatchOpening<Val: Field, InputMmcs: Mmcs<Val>> {
-    pub(crate) opened_values: Vec<Vec<Val>>,
-    pub(crate) opening_proof: <InputMmcs as Mmcs<Val>>::Proof,
+pub struct BatchOpening<Val: Field, InputMmcs: MultiMmcs<Val>> {
+    /// For each query, the opened row of each matrix.
+    pub(crate) opened_values: Vec<Vec<Vec<Val>>>,
+    pub(crate) opening_proof: <InputMmcs as MultiMmcs<Val>>::MultiProof,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -57,12 +70,13 @@ pub struct BatchOpening<Val: Field, InputMmcs: Mmcs<Val>> {
 pub struct CircleInputProof<
     Val: Field,
     Challenge: Field,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiMmcs<Val>,
+    FriMmcs: MultiMmcs<Challenge>,
 > {
     input_openings: Vec<BatchOpening<Val, InputMmcs>>,
-    first_layer_siblings: Vec<Challenge>,
-    first_layer_proof: FriMmcs::Proof,
+    /// For each query, the sibling of its first layer opening at each height.
+    first_layer_siblings: Vec<Vec<Challenge>>,
+    first_layer_proof: FriMmcs::MultiProof,
 }
 
 #[derive(Debug)]
@@ -80,8 +94,8 @@ pub enum InputError<InputMmcsError, FriMmcsError> {
 pub struct CirclePcsProof<
     Val: Field,
     Challenge: Field,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiMmcs<Val>,
+    FriMmcs: MultiMmcs<Challenge>,
     Witness,
 > {
     first_layer_commitment: FriMmcs::Commitment,
@@ -99,8 +113,8 @@ impl<Val, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
 where
     Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiMmcs<Val>,
+    FriMmcs: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<FriMmcs::Commitment>,
 {
     type Domain = CircleDomain<Val>;
@@ -110,22 +124,42 @@ where
     type Proof = CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Challenger::Witness>;
     type Error = FriError<FriMmcs::Error, InputError<InputMmcs::Error, FriMmcs::Error>>;
 
+    fn parameters(&self) -> Vec<usize> {
+        self.fri_config.parameters()
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         CircleDomain::standard(log2_strict_usize(degree))
     }
 
-    fn commit(
+    fn try_commit(
         &self,
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
-    ) -> (Self::Commitment, Self::ProverData) {
+    ) -> Result<(Self::Commitment, Self::ProverData), CommitError> {
+        assert_binary_folding(&self.fri_config);
+        for (index, (domain, evals)) in evaluations.iter().enumerate() {
+            // CirclePcs cannot commit to a matrix with fewer than 4 rows, because we bivariate
+            // fold one bit, and fri needs one more bit.
+            if domain.log_n < 2
+                || domain.log_n + self.fri_config.log_blowup > CircleDomain::<Val>::MAX_LOG_SIZE
+            {
+                return Err(CommitError::UnsupportedDomainSize {
+                    index,
+                    log_size: domain.log_n,
+                });
+            }
+            if domain.size() != evals.height() {
+                return Err(CommitError::HeightMismatch {
+                    index,
+                    domain_size: domain.size(),
+                    height: evals.height(),
+                });
+            }
+        }
+
         let ldes = evaluations
             .into_iter()
             .map(|(domain, evals)| {
-                assert!(
-                    domain.log_n >= 2,
-                    "CirclePcs cannot commit to a matrix with fewer than 4 rows.",
-                    // (because we bivariate fold one bit, and fri needs one more bit)
-                );
                 CircleEvaluations::from_natural_order(domain, evals)
                     .extrapolate(CircleDomain::standard(
                         domain.log_n + self.fri_config.log_blowup,
@@ -133,8 +167,7 @@ where
                     .to_cfft_order()
             })
             .collect_vec();
-        let (comm, mmcs_data) = self.mmcs.commit(ldes);
-        (comm, mmcs_data)
+        Ok(self.mmcs.commit(ldes))
     }
 
     fn get_evaluations_on_domain<'a>(
@@ -179,6 +212,10 @@ where
                     points_for_mats.len(),
                     "Mismatched number of matrices and points"
                 );
+                assert!(
+                    points_for_mats.iter().all(|points| !points.is_empty()),
+                    "each matrix must be opened at one or more points"
+                );
                 izip!(mats, points_for_mats)
                     .map(|(mat, points_for_mat)| {
                         let log_height = log2_strict_usize(mat.height());
@@ -305,17 +342,21 @@ where
         let g: CircleFriConfig<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriGenericConfig(PhantomData);
 
-        let fri_proof = prove(&g, &self.fri_config, fri_input, challenger, |index| {
+        let fri_proof = prove(&g, &self.fri_config, fri_input, challenger, |indices| {
             // CircleFriFolder asks for an extra query index bit, so we use that here to index
             // the first layer fold.
 
-            // Open the input (big opening, lots of columns) at the full index...
+            // Open the input (big opening, lots of columns) at the full indices...
             let input_openings = rounds
                 .iter()
                 .map(|(data, _)| {
                     let log_max_batch_height = log2_strict_usize(self.mmcs.get_max_height(data));
-                    let reduced_index = index >> (log_max_height - log_max_batch_height);
-                    let (opened_values, opening_proof) = self.mmcs.open_batch(reduced_index, data);
+                    let reduced_indices = indices
+                        .iter()
+                        .map(|index| index >> (log_max_height - log_max_batch_height))
+                        .collect_vec();
+                    let (opened_values, opening_proof) =
+                        self.mmcs.open_multi_batch(&reduced_indices, data);
                     BatchOpening {
                         opened_values,
                         opening_proof,
@@ -323,17 +364,21 @@ where
                 })
                 .collect();
 
-            // We committed to first_layer in pairs, so open the reduced index and include the sibling
-            // as part of the input proof.
-            let (first_layer_values, first_layer_proof) = self
-                .fri_config
-                .mmcs
-                .open_batch(index >> 1, &first_layer_data);
-            let first_layer_siblings = izip!(&first_layer_values, &log_heights)
-                .map(|(v, log_height)| {
-                    let reduced_index = index >> (log_max_height - log_height);
-                    let sibling_index = (reduced_index & 1) ^ 1;
-                    v[sibling_index]
+            // We committed to first_layer in pairs, so open the reduced indices and include the
+            // siblings as part of the input proof.
+            let (first_layer_values, first_layer_proof) = self.fri_config.mmcs.open_multi_batch(
+                &indices.iter().map(|index| index >> 1).collect_vec(),
+                &first_layer_data,
+            );
+            let first_layer_siblings = izip!(indices, &first_layer_values)
+                .map(|(index, values)| {
+                    izip!(values, &log_heights)
+                        .map(|(v, log_height)| {
+                            let reduced_index = index >> (log_max_height - log_height);
+                            let sibling_index = (reduced_index & 1) ^ 1;
+                            v[sibling_index]
+                        })
+                        .collect()
                 })
                 .collect();
             CircleInputProof {
@@ -374,6 +419,19 @@ where
         proof: &Self::Proof,
         challenger: &mut Challenger,
     ) -> Result<(), Self::Error> {
+        assert_binary_folding(&self.fri_config);
+        // Each matrix's width is known only from its claimed evaluations, and no matrix can be
+        // committed to on a domain of fewer than 4 points.
+        if rounds
+            .iter()
+            .flat_map(|(_, mats)| mats)
+            .any(|(domain, points_and_values)| {
+                domain.log_n < 2 || claimed_width(points_and_values).is_none()
+            })
+        {
+            return Err(FriError::InputError(InputError::InputShapeError));
+        }
+
         // Write evaluations to challenger
         for (_, round) in &rounds {
             for (_, mat) in round {
@@ -390,9 +448,24 @@ where
         challenger.observe(proof.first_layer_commitment.clone());
         let bivariate_beta: Challenge = challenger.sample_algebra_element();
 
+        let log_global_max_height = rounds
+            .iter()
+            .flat_map(|(_, mats)| mats.iter().map(|(domain, _)| domain.log_n))
+            .max()
+            .map_or(0, |log_n| log_n + self.fri_config.log_blowup);
+
+        // The proof must fold from the tallest claimed domain down to its final polynomial.
         // +1 to account for first layer
-        let log_global_max_height =
-            proof.fri_proof.commit_phase_commits.len() + self.fri_config.log_blowup + 1;
+        let final_poly_len = proof.fri_proof.final_poly.len();
+        if !final_poly_len.is_power_of_two()
+            || proof.fri_proof.commit_phase_commits.len()
+                + self.fri_config.log_blowup
+                + log2_strict_usize(final_poly_len)
+                + 1
+                != log_global_max_height
+        {
+            return Err(FriError::InvalidProofShape);
+        }
 
         let g: CircleFriConfig<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriGenericConfig(PhantomData);
@@ -402,141 +475,170 @@ where
             &self.fri_config,
             &proof.fri_proof,
             challenger,
-            |index, input_proof| {
-                // log_height -> (alpha_offset, ro)
-                let mut reduced_openings = BTreeMap::new();
-
+            |indices, input_proof| {
                 let CircleInputProof {
                     input_openings,
                     first_layer_siblings,
                     first_layer_proof,
                 } = input_proof;
 
+                if first_layer_siblings.len() != indices.len() {
+                    return Err(InputError::InputShapeError);
+                }
+
+                // For each index, log_height -> (alpha_offset, ro)
+                let mut reduced_openings = vec![BTreeMap::new(); indices.len()];
+
                 for (batch_opening, (batch_commit, mats)) in
                     zip_eq(input_openings, &rounds, InputError::InputShapeError)?
                 {
-                    let batch_heights: Vec<usize> = mats
+                    if batch_opening.opened_values.len() != indices.len() {
+                        return Err(InputError::InputShapeError);
+                    }
+
+                    let batch_dims = mats
                         .iter()
-                        .map(|(domain, _)| (domain.size() << self.fri_config.log_blowup))
-                        .collect_vec();
-                    let batch_dims: Vec<Dimensions> = batch_heights
+                        .map(|(domain, points_and_values)| {
+                            Some(Dimensions {
+                                width: claimed_width(points_and_values)?,
+                                height: domain.size() << self.fri_config.log_blowup,
+                            })
+                        })
+                        .collect::<Option<Vec<_>>>()
+                        .ok_or(InputError::InputShapeError)?;
+
+                    // An empty batch has no height, and fails verification.
+                    let bits_reduced = batch_dims.iter().map(|dims| dims.height).max().map_or(
+                        0,
+                        |batch_max_height| {
+                            log_global_max_height - log2_strict_usize(batch_max_height)
+                        },
+                    );
+                    let reduced_indices = indices
                         .iter()
-                        // todo: mmcs doesn't really need width
-                        .map(|&height| Dimensions { width: 0, height })
+                        .map(|index| index >> bits_reduced)
                         .collect_vec();
 
-                    let (dims, idx) = if let Some(log_batch_max_height) =
-                        batch_heights.iter().max().map(|x| log2_strict_usize(*x))
-                    {
-                        (
-                            &batch_dims[..],
-                            index >> (log_global_max_height - log_batch_max_height),
-                        )
-                    } else {
-                        // Empty batch?
-                        (&[][..], 0)
-                    };
-
                     self.mmcs
-                        .verify_batch(
+                        .verify_multi_batch(
                             batch_commit,
-                            dims,
-                            idx,
+                            &batch_dims,
+                            &reduced_indices,
                             &batch_opening.opened_values,
                             &batch_opening.opening_proof,
                         )
                         .map_err(InputError::InputMmcsError)?;
 
-                    for (ps_at_x, (mat_domain, mat_points_and_values)) in zip_eq(
-                        &batch_opening.opened_values,
-                        mats,
-                        InputError::InputShapeError,
-                    )? {
-                        let log_height = mat_domain.log_n + self.fri_config.log_blowup;
-                        let bits_reduced = log_global_max_height - log_height;
-                        let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
+                    for (&index, opened_values, reduced_openings) in
+                        izip!(indices, &batch_opening.opened_values, &mut reduced_openings)
+                    {
+                        for (ps_at_x, (mat_domain, mat_points_and_values)) in
+                            zip_eq(opened_values, mats, InputError::InputShapeError)?
+                        {
+                            let log_height = mat_domain.log_n + self.fri_config.log_blowup;
+                            let bits_reduced = log_global_max_height - log_height;
+                            let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
 
-                        let committed_domain = CircleDomain::standard(log_height);
-                        let x = committed_domain.nth_point(orig_idx);
+                            let committed_domain = CircleDomain::standard(log_height);
+                            let x = committed_domain.nth_point(orig_idx);
 
-                        let (alpha_offset, ro) = reduced_openings
-                            .entry(log_height)
-                            .or_insert((Challenge::ONE, Challenge::ZERO));
-                        let alpha_pow_width_2 = alpha.exp_u64(ps_at_x.len() as u64).square();
+                            let (alpha_offset, ro) = reduced_openings
+                                .entry(log_height)
+                                .or_insert((Challenge::ONE, Challenge::ZERO));
+                            let alpha_pow_width_2 = alpha.exp_u64(ps_at_x.len() as u64).square();
 
-                        for (zeta_uni, ps_at_zeta) in mat_points_and_values {
-                            let zeta = Point::from_projective_line(*zeta_uni);
+                            for (zeta_uni, ps_at_zeta) in mat_points_and_values {
+                                let zeta = Point::from_projective_line(*zeta_uni);
 
-                            *ro += *alpha_offset
-                                * deep_quotient_reduce_row(alpha, x, zeta, ps_at_x, ps_at_zeta);
+                                *ro += *alpha_offset
+                                    * deep_quotient_reduce_row(alpha, x, zeta, ps_at_x, ps_at_zeta);
 
-                            *alpha_offset *= alpha_pow_width_2;
+                                *alpha_offset *= alpha_pow_width_2;
+                            }
                         }
                     }
                 }
 
                 // Verify bivariate fold and lambda correction
 
-                let (mut fri_input, fl_dims, fl_leaves): (Vec<_>, Vec<_>, Vec<_>) = zip_eq(
-                    zip_eq(
-                        reduced_openings,
-                        first_layer_siblings,
-                        InputError::InputShapeError,
-                    )?,
-                    &proof.lambdas,
-                    InputError::InputShapeError,
-                )?
-                .map(|(((log_height, (_, ro)), &fl_sib), &lambda)| {
-                    assert!(log_height > 0);
-
-                    let orig_size = log_height - self.fri_config.log_blowup;
-                    let bits_reduced = log_global_max_height - log_height;
-                    let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
-
-                    let lde_domain = CircleDomain::standard(log_height);
-                    let p: Point<Val> = lde_domain.nth_point(orig_idx);
-
-                    let lambda_corrected = ro - lambda * p.v_n(orig_size);
-
-                    let mut fl_values = vec![lambda_corrected; 2];
-                    fl_values[((index >> bits_reduced) & 1) ^ 1] = fl_sib;
-
-                    let fri_input = (
-                        // - 1 here is because we have already folded a layer.
-                        log_height - 1,
-                        fold_y_row(
-                            index >> (bits_reduced + 1),
-                            // - 1 here is log_arity.
-                            log_height - 1,
-                            bivariate_beta,
-                            fl_values.iter().copied(),
-                        ),
-                    );
-
-                    let fl_dims = Dimensions {
-                        width: 0,
+                // The first layer holds one matrix of pairs for each height of the inputs.
+                let fl_dims = rounds
+                    .iter()
+                    .flat_map(|(_, mats)| {
+                        mats.iter()
+                            .map(|(domain, _)| domain.log_n + self.fri_config.log_blowup)
+                    })
+                    .collect::<BTreeSet<_>>()
+                    .into_iter()
+                    .map(|log_height| Dimensions {
+                        width: 2,
                         height: 1 << (log_height - 1),
-                    };
-
-                    (fri_input, fl_dims, fl_values)
-                })
-                .multiunzip();
-
-                // sort descending
-                fri_input.reverse();
+                    })
+                    .collect_vec();
+
+                let mut fl_leaves = Vec::with_capacity(indices.len());
+                let fri_inputs = izip!(indices, reduced_openings, first_layer_siblings)
+                    .map(|(&index, reduced_openings, first_layer_siblings)| {
+                        let (mut fri_input, fl_values): (Vec<_>, Vec<_>) = zip_eq(
+                            zip_eq(
+                                reduced_openings,
+                                first_layer_siblings,
+                                InputError::InputShapeError,
+                            )?,
+                            &proof.lambdas,
+                            InputError::InputShapeError,
+                        )?
+                        .map(|(((log_height, (_, ro)), &fl_sib), &lambda)| {
+                            assert!(log_height > 0);
+
+                            let orig_size = log_height - self.fri_config.log_blowup;
+                            let bits_reduced = log_global_max_height - log_height;
+                            let orig_idx = cfft_permute_index(index >> bits_reduced, log_height);
+
+                            let lde_domain = CircleDomain::standard(log_height);
+                            let p: Point<Val> = lde_domain.nth_point(orig_idx);
+
+                            let lambda_corrected = ro - lambda * p.v_n(orig_size);
+
+                            let mut fl_values = vec![lambda_corrected; 2];
+                            fl_values[((index >> bits_reduced) & 1) ^ 1] = fl_sib;
+
+                            let fri_input = (
+                                // - 1 here is because we have already folded a layer.
+                                log_height - 1,
+                                fold_y_row(
+                                    index >> (bits_reduced + 1),
+                                    // - 1 here is log_arity.
+                                    log_height - 1,
+                                    bivariate_beta,
+                                    fl_values.iter().copied(),
+                                ),
+                            );
+
+                            (fri_input, fl_values)
+                        })
+                        .unzip();
+
+                        // sort descending
+                        fri_input.reverse();
+
+                        fl_leaves.push(fl_values);
+                        Ok(fri_input)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
 
                 self.fri_config
                     .mmcs
-                    .verify_batch(
+                    .verify_multi_batch(
                         &proof.first_layer_commitment,
                         &fl_dims,
-                        index >> 1,
+                        &indices.iter().map(|index| index >> 1).collect_vec(),
                         &fl_leaves,
                         first_layer_proof,
                     )
                     .map_err(InputError::FirstLayerMmcsError)?;
 
-                Ok(fri_input)
+                Ok(fri_inputs)
             },
         )
     }
@@ -546,10 +648,11 @@ where
 mod tests {
     use p3_challenger::{HashChallenger, SerializingChallenger32};
     use p3_commit::ExtensionMmcs;
+    use p3_field::PrimeCharacteristicRing;
     use p3_field::extension::BinomialExtensionField;
     use p3_fri::create_test_fri_config;
     use p3_keccak::Keccak256Hash;
-    use p3_merkle_tree::MerkleTreeMmcs;
+    use p3_merkle_tree::{MerkleTreeError, MerkleTreeMmcs};
     use p3_mersenne_31::Mersenne31;
     use p3_symmetric::{CompressionFunctionFromHasher, SerializingHasher32};
     use rand::rngs::SmallRng;
@@ -557,43 +660,57 @@ mod tests {
 
     use super::*;
 
-    #[test]
-    fn circle_pcs() {
-        // Very simple pcs test. More rigorous tests in p3_fri/tests/pcs.
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Mersenne31, 3>;
 
-        let mut rng = SmallRng::seed_from_u64(0);
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
 
-        type Val = Mersenne31;
-        type Challenge = BinomialExtensionField<Mersenne31, 3>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
 
-        type ByteHash = Keccak256Hash;
-        type FieldHash = SerializingHasher32<ByteHash>;
+    type MyPcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+
+    fn get_pcs() -> MyPcs {
         let byte_hash = ByteHash {};
         let field_hash = FieldHash::new(byte_hash);
-
-        type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
         let compress = MyCompress::new(byte_hash);
-
-        type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
         let val_mmcs = ValMmcs::new(field_hash, compress);
-
-        type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
 
-        type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
-
         let fri_config = create_test_fri_config(challenge_mmcs, 0);
 
-        type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
-        let pcs = Pcs {
+        MyPcs {
             mmcs: val_mmcs,
             fri_config,
             _phantom: PhantomData,
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "CirclePcs only supports max_log_arity = 1")]
+    fn higher_arity_rejected() {
+        let pcs = get_pcs();
+        let fri_config = FriConfig {
+            max_log_arity: 2,
+            ..pcs.fri_config
         };
+        MyPcs::new(pcs.mmcs, fri_config);
+    }
+
+    #[test]
+    fn circle_pcs() {
+        // Very simple pcs test. More rigorous tests in p3_fri/tests/pcs.
+
+        let mut rng = SmallRng::seed_from_u64(0);
+        let pcs = get_pcs();
 
         let log_n = 10;
 
-        let d = <Pcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
+        let d = <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
             &pcs,
             1 << log_n,
         );
@@ -601,14 +718,14 @@ mod tests {
         let evals = RowMajorMatrix::rand(&mut rng, 1 << log_n, 1);
 
         let (comm, data) =
-            <Pcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, vec![(d, evals)]);
+            <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, vec![(d, evals)]);
 
         let zeta: Challenge = rng.random();
 
-        let mut chal = Challenger::from_hasher(vec![], byte_hash);
+        let mut chal = Challenger::from_hasher(vec![], ByteHash {});
         let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut chal);
 
-        let mut chal = Challenger::from_hasher(vec![], byte_hash);
+        let mut chal = Challenger::from_hasher(vec![], ByteHash {});
         pcs.verify(
             vec![(comm, vec![(d, vec![(zeta, values[0][0][0].clone())])])],
             &proof,
@@ -616,4 +733,82 @@ mod tests {
         )
         .expect("verify err");
     }
+
+    #[test]
+    fn tampered_row_widths_rejected() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let pcs = get_pcs();
+
+        let d = <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
+            &pcs,
+            1 << 5,
+        );
+        let mats = [3, 5].map(|width| (d, RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, width)));
+        let (comm, data) =
+            <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, mats.to_vec());
+
+        let zeta: Challenge = rng.random();
+
+        let mut chal = Challenger::from_hasher(vec![], ByteHash {});
+        let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]; 2])], &mut chal);
+        let claims = vec![(
+            comm,
+            values[0]
+                .iter()
+                .map(|mat_values| (d, vec![(zeta, mat_values[0].clone())]))
+                .collect_vec(),
+        )];
+        let verify = |proof| {
+            let mut chal = Challenger::from_hasher(vec![], ByteHash {});
+            pcs.verify(claims.clone(), proof, &mut chal)
+        };
+        verify(&proof).expect("verify err");
+
+        // Moving a value from the end of one row to the start of the next leaves the hash of the
+        // leaf unchanged, so it must be caught by the input MMCS checking widths.
+        let mut tampered = proof.clone();
+        for rows in &mut tampered.fri_proof.input_proof.input_openings[0].opened_values {
+            let value = rows[0].pop().unwrap();
+            rows[1].insert(0, value);
+        }
+        assert!(matches!(
+            verify(&tampered),
+            Err(FriError::InputError(InputError::InputMmcsError(
+                MerkleTreeError::WrongWidth
+            )))
+        ));
+    }
+
+    #[test]
+    fn wrong_number_of_rounds_rejected() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let pcs = get_pcs();
+
+        let d = <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::natural_domain_for_degree(
+            &pcs,
+            1 << 5,
+        );
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, 1);
+        let (comm, data) =
+            <MyPcs as p3_commit::Pcs<Challenge, Challenger>>::commit(&pcs, vec![(d, evals)]);
+
+        let zeta: Challenge = rng.random();
+
+        let mut chal = Challenger::from_hasher(vec![], ByteHash {});
+        let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut chal);
+        let claims = vec![(comm, vec![(d, vec![(zeta, values[0][0][0].clone())])])];
+
+        // The claimed domain, not the proof, determines how many rounds of folding there are.
+        for final_poly_len in [0, 2] {
+            let mut tampered = proof.clone();
+            tampered.fri_proof.commit_phase_commits.pop();
+            tampered.fri_proof.commit_phase_opening_proofs.pop();
+            tampered.fri_proof.final_poly = vec![Challenge::ZERO; final_poly_len];
+            let mut chal = Challenger::from_hasher(vec![], ByteHash {});
+            assert!(matches!(
+                pcs.verify(claims.clone(), &tampered, &mut chal),
+                Err(FriError::InvalidProofShape)
+            ));
+        }
+    }
 }
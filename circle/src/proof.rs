@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use p3_commit::Mmcs;
+use p3_commit::MultiMmcs;
 use p3_field::Field;
 use serde::{Deserialize, Serialize};
 
@@ -9,34 +9,32 @@ use serde::{Deserialize, Serialize};
     serialize = "Witness: Serialize, InputProof: Serialize",
     deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
 ))]
-pub struct CircleFriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
+pub struct CircleFriProof<F: Field, M: MultiMmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
-    pub query_proofs: Vec<CircleQueryProof<F, M, InputProof>>,
-    // This could become Vec<FC::Challenge> if this library was generalized to support non-constant
-    // final polynomials.
-    pub final_poly: F,
+    pub query_proofs: Vec<CircleQueryProof<F>>,
+    /// For each commit phase commitment, a proof of the openings of all queries in that round.
+    pub commit_phase_opening_proofs: Vec<M::MultiProof>,
+    /// A proof of the openings of the inputs at all queried indices.
+    pub input_proof: InputProof,
+    /// The coefficients of the final polynomial, in x, in the basis of the circle FFT without its
+    /// y factor.
+    pub final_poly: Vec<F>,
     pub pow_witness: Witness,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-#[serde(bound(
-    serialize = "InputProof: Serialize",
-    deserialize = "InputProof: Deserialize<'de>",
-))]
-pub struct CircleQueryProof<F: Field, M: Mmcs<F>, InputProof> {
-    pub input_proof: InputProof,
+#[serde(bound = "")]
+pub struct CircleQueryProof<F: Field> {
     /// For each commit phase commitment, this contains openings of a commit phase codeword at the
-    /// queried location, along with an opening proof.
-    pub commit_phase_openings: Vec<CircleCommitPhaseProofStep<F, M>>,
+    /// queried location.
+    pub commit_phase_openings: Vec<CircleCommitPhaseProofStep<F>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct CircleCommitPhaseProofStep<F: Field, M: Mmcs<F>> {
+pub struct CircleCommitPhaseProofStep<F: Field> {
     /// The opening of the commit phase codeword at the sibling location.
     // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
     // folding arities besides 2, meaning that there can be multiple siblings.
     pub sibling_value: F,
-
-    pub opening_proof: M::Proof,
 }
@@ -4,13 +4,15 @@ use core::iter;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_commit::MultiMmcs;
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::{FriConfig, FriGenericConfig};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_strict_usize;
 use tracing::{info_span, instrument};
 
+use crate::folding::interpolate_folded;
 use crate::{CircleCommitPhaseProofStep, CircleFriProof, CircleQueryProof};
 
 #[instrument(name = "FRI prover", skip_all)]
@@ -19,12 +21,12 @@ pub fn prove<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize) -> G::InputProof,
+    open_input: impl Fn(&[usize]) -> G::InputProof,
 ) -> CircleFriProof<Challenge, M, Challenger::Witness, G::InputProof>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
-    M: Mmcs<Challenge>,
+    M: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
@@ -42,32 +44,42 @@ where
 
     let pow_witness = challenger.grind(config.proof_of_work_bits);
 
-    let query_proofs = info_span!("query phase").in_scope(|| {
+    let indices =
         iter::repeat_with(|| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
             .take(config.num_queries)
-            .map(|index| CircleQueryProof {
-                input_proof: open_input(index),
-                commit_phase_openings: answer_query(
-                    config,
-                    &commit_phase_result.data,
-                    index >> g.extra_query_index_bits(),
-                ),
-            })
-            .collect()
-    });
+            .collect_vec();
+
+    let (query_proofs, commit_phase_opening_proofs, input_proof) = info_span!("query phase")
+        .in_scope(|| {
+            let (query_proofs, commit_phase_opening_proofs) = answer_queries(
+                config,
+                &commit_phase_result.data,
+                indices
+                    .iter()
+                    .map(|index| index >> g.extra_query_index_bits())
+                    .collect(),
+            );
+            (
+                query_proofs,
+                commit_phase_opening_proofs,
+                open_input(&indices),
+            )
+        });
 
     CircleFriProof {
         commit_phase_commits: commit_phase_result.commits,
         query_proofs,
+        commit_phase_opening_proofs,
+        input_proof,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
     }
 }
 
-struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
+struct CommitPhaseResult<F: Field, M: MultiMmcs<F>> {
     commits: Vec<M::Commitment>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
-    final_poly: F,
+    final_poly: Vec<F>,
 }
 
 #[instrument(name = "commit phase", skip_all)]
@@ -78,9 +90,9 @@ fn commit_phase<G, Val, Challenge, M, Challenger>(
     challenger: &mut Challenger,
 ) -> CommitPhaseResult<Challenge, M>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
-    M: Mmcs<Challenge>,
+    M: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
@@ -89,7 +101,9 @@ where
     let mut commits = vec![];
     let mut data = vec![];
 
-    while folded.len() > config.blowup() {
+    // Inputs shorter than the final domain are folded in too, ending with a shorter final
+    // polynomial.
+    while folded.len() > config.blowup() * config.final_poly_len() || inputs_iter.peek().is_some() {
         let leaves = RowMajorMatrix::new(folded, 2);
         let (commit, prover_data) = config.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
@@ -107,13 +121,20 @@ where
         }
     }
 
-    // We should be left with `blowup` evaluations of a constant polynomial.
-    assert_eq!(folded.len(), config.blowup());
-    let final_poly = folded[0];
-    for x in folded {
-        assert_eq!(x, final_poly);
+    // The evaluation domain is "blown-up" relative to the degree of the final polynomial, so all
+    // coefficients beyond `folded.len() / blowup` should be zero.
+    let final_poly_len = folded.len() >> config.log_blowup;
+    let mut final_poly = interpolate_folded::<Val, Challenge>(folded);
+    debug_assert!(
+        final_poly[final_poly_len..].iter().all(|x| x.is_zero()),
+        "All coefficients beyond final_poly_len must be zero"
+    );
+    final_poly.truncate(final_poly_len);
+
+    // Observe all coefficients of the final polynomial.
+    for &x in &final_poly {
+        challenger.observe_algebra_element(x);
     }
-    challenger.observe_algebra_element(final_poly);
 
     CommitPhaseResult {
         commits,
@@ -122,33 +143,50 @@ where
     }
 }
 
-fn answer_query<F, M>(
+/// Open the commit phase codewords at the queried indices, each reduced to the domain of its round.
+///
+/// Returns the proof of each query, and for each round, a proof of the openings of all queries.
+fn answer_queries<F, M>(
     config: &FriConfig<M>,
     commit_phase_commits: &[M::ProverData<RowMajorMatrix<F>>],
-    index: usize,
-) -> Vec<CircleCommitPhaseProofStep<F, M>>
+    indices: Vec<usize>,
+) -> (Vec<CircleQueryProof<F>>, Vec<M::MultiProof>)
 where
     F: Field,
-    M: Mmcs<F>,
+    M: MultiMmcs<F>,
 {
-    commit_phase_commits
+    let mut query_proofs = vec![
+        CircleQueryProof {
+            commit_phase_openings: vec![],
+        };
+        indices.len()
+    ];
+
+    let opening_proofs = commit_phase_commits
         .iter()
         .enumerate()
         .map(|(i, commit)| {
-            let index_i = index >> i;
-            let index_i_sibling = index_i ^ 1;
-            let index_pair = index_i >> 1;
-
-            let (mut opened_rows, opening_proof) = config.mmcs.open_batch(index_pair, commit);
-            assert_eq!(opened_rows.len(), 1);
-            let opened_row = opened_rows.pop().unwrap();
-            assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
-            let sibling_value = opened_row[index_i_sibling % 2];
-
-            CircleCommitPhaseProofStep {
-                sibling_value,
-                opening_proof,
+            let index_pairs = indices.iter().map(|index| index >> (i + 1)).collect_vec();
+
+            let (opened_rows, opening_proof) = config.mmcs.open_multi_batch(&index_pairs, commit);
+            for (query_proof, mut opened_rows, index) in
+                izip!(&mut query_proofs, opened_rows, &indices)
+            {
+                let index_i = index >> i;
+                let index_i_sibling = index_i ^ 1;
+
+                assert_eq!(opened_rows.len(), 1);
+                let opened_row = opened_rows.pop().unwrap();
+                assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
+                let sibling_value = opened_row[index_i_sibling % 2];
+
+                query_proof
+                    .commit_phase_openings
+                    .push(CircleCommitPhaseProofStep { sibling_value });
             }
+            opening_proof
         })
-        .collect()
+        .collect();
+
+    (query_proofs, opening_proofs)
 }
@@ -1,15 +1,18 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use itertools::Itertools;
+use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_commit::MultiMmcs;
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::verifier::FriError;
 use p3_fri::{FriConfig, FriGenericConfig};
 use p3_matrix::Dimensions;
+use p3_util::log2_strict_usize;
 use p3_util::zip_eq::zip_eq;
 
+use crate::folding::{eval_folded, folded_x};
 use crate::{CircleCommitPhaseProofStep, CircleFriProof};
 
 pub fn verify<G, Val, Challenge, M, Challenger>(
@@ -17,12 +20,12 @@ pub fn verify<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     proof: &CircleFriProof<Challenge, M, Challenger::Witness, G::InputProof>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+    open_input: impl Fn(&[usize], &G::InputProof) -> Result<Vec<Vec<(usize, Challenge)>>, G::InputError>,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
-    M: Mmcs<Challenge>,
+    M: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
@@ -35,10 +38,22 @@ where
         })
         .collect();
 
-    // Observe the claimed final polynomial.
-    challenger.observe_algebra_element(proof.final_poly);
+    // Observe all coefficients of the final polynomial.
+    proof
+        .final_poly
+        .iter()
+        .for_each(|x| challenger.observe_algebra_element(*x));
+
+    if proof.query_proofs.len() != config.num_queries
+        || proof.commit_phase_opening_proofs.len() != proof.commit_phase_commits.len()
+    {
+        return Err(FriError::InvalidProofShape);
+    }
 
-    if proof.query_proofs.len() != config.num_queries {
+    // The final polynomial is shorter than `final_poly_len` if an input is shorter than the final
+    // domain.
+    if !proof.final_poly.len().is_power_of_two() || proof.final_poly.len() > config.final_poly_len()
+    {
         return Err(FriError::InvalidProofShape);
     }
 
@@ -47,55 +62,86 @@ where
         return Err(FriError::InvalidPowWitness);
     }
 
+    // The log of the final domain size.
+    let log_final_height = config.log_blowup + log2_strict_usize(proof.final_poly.len());
+
     // The log of the maximum domain size.
-    let log_max_height = proof.commit_phase_commits.len() + config.log_blowup;
+    let log_max_height = proof.commit_phase_commits.len() + log_final_height;
+
+    let indices = (0..config.num_queries)
+        .map(|_| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
+        .collect_vec();
+    let reduced_openings =
+        open_input(&indices, &proof.input_proof).map_err(FriError::InputError)?;
+    if reduced_openings.len() != config.num_queries {
+        return Err(FriError::InvalidProofShape);
+    }
 
-    for qp in &proof.query_proofs {
-        let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
-        let ro = open_input(index, &qp.input_proof).map_err(FriError::InputError)?;
+    // For each round, the index and evaluations of the pair opened by each query.
+    let mut opened_rows = vec![vec![]; proof.commit_phase_commits.len()];
 
+    for (qp, &index, ro) in izip!(&proof.query_proofs, &indices, reduced_openings) {
         debug_assert!(
             ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
             "reduced openings sorted by height descending"
         );
 
+        let domain_index = index >> g.extra_query_index_bits();
+
         // Starting at the evaluation at `index` of the initial domain,
         // perform fri folds until the domain size reaches the final domain size.
-        // Check after each fold that the pair of sibling evaluations at the current
-        // node match the commitment.
-        let folded_eval = verify_query(
+        // The pair of sibling evaluations at each node is checked against the commitments below.
+        let folded_eval = verify_query::<_, _, M::Error>(
             g,
-            config,
-            index >> g.extra_query_index_bits(),
+            domain_index,
             zip_eq(
-                zip_eq(
-                    &betas,
-                    &proof.commit_phase_commits,
-                    FriError::InvalidProofShape,
-                )?,
+                zip_eq(&betas, &mut opened_rows, FriError::InvalidProofShape)?,
                 &qp.commit_phase_openings,
                 FriError::InvalidProofShape,
             )?,
             ro,
             log_max_height,
+            log_final_height,
         )?;
 
-        // As we fold until the polynomial is constant, proof.final_poly should be a constant value and
-        // we do not need to do any polynomial evaluations.
-        if folded_eval != proof.final_poly {
+        // The folded evaluation is at the point of the final domain reached by the query's index.
+        let final_index = domain_index >> proof.commit_phase_commits.len();
+        let x = folded_x::<Val>(final_index, log_final_height);
+        if eval_folded(&proof.final_poly, x) != folded_eval {
             return Err(FriError::FinalPolyMismatch);
         }
     }
 
+    // Verify the commitment to the pairs opened in each round, for all queries at once.
+    for (log_folded_height, comm, opening_proof, rows) in izip!(
+        (log_final_height..log_max_height).rev(),
+        &proof.commit_phase_commits,
+        &proof.commit_phase_opening_proofs,
+        opened_rows
+    ) {
+        let dims = &[Dimensions {
+            width: 2,
+            height: 1 << log_folded_height,
+        }];
+        let (row_indices, evals): (Vec<_>, Vec<_>) = rows
+            .into_iter()
+            .map(|(index, evals)| (index, vec![evals]))
+            .unzip();
+        config
+            .mmcs
+            .verify_multi_batch(comm, dims, &row_indices, &evals, opening_proof)
+            .map_err(FriError::CommitPhaseMmcsError)?;
+    }
+
     Ok(())
 }
 
-type CommitStep<'a, F, M> = (
+type CommitStep<'a, F> = (
     (
         &'a F, // The challenge point beta used for the next fold of Circle-FRI evaluations.
-        &'a <M as Mmcs<F>>::Commitment, // A commitment to the Circle-FRI evaluations on the current domain.
+        &'a mut Vec<(usize, Vec<F>)>, // The pairs opened so far in the current round.
     ),
-    &'a CircleCommitPhaseProofStep<F, M>, // The sibling and opening proof for the current Circle-FRI node.
+    &'a CircleCommitPhaseProofStep<F>, // The sibling for the current Circle-FRI node.
 );
 
 /// Verifies a single query chain in the Circle-FRI proof.
@@ -103,29 +149,27 @@ type CommitStep<'a, F, M> = (
 /// Given an initial `index` corresponding to a point in the initial domain
 /// and a series of `reduced_openings` corresponding to evaluations of
 /// polynomials to be added in at specific domain sizes, perform the standard
-/// sequence of Circle-FRI folds, checking at each step that the pair of sibling evaluations
-/// match the commitment.
-fn verify_query<'a, G, F, M>(
+/// sequence of Circle-FRI folds, recording at each step the pair of sibling evaluations so that
+/// they can be checked against the commitment.
+fn verify_query<'a, G, F, CommitMmcsErr>(
     g: &G,
-    config: &FriConfig<M>,
     mut index: usize,
-    steps: impl ExactSizeIterator<Item = CommitStep<'a, F, M>>,
+    steps: impl ExactSizeIterator<Item = CommitStep<'a, F>>,
     reduced_openings: Vec<(usize, F)>,
     log_max_height: usize,
-) -> Result<F, FriError<M::Error, G::InputError>>
+    log_final_height: usize,
+) -> Result<F, FriError<CommitMmcsErr, G::InputError>>
 where
     F: Field,
-    M: Mmcs<F> + 'a,
     G: FriGenericConfig<F>,
 {
     let mut folded_eval = F::ZERO;
     let mut ro_iter = reduced_openings.into_iter().peekable();
 
     // We start with evaluations over a domain of size (1 << log_max_height). We fold
-    // using FRI until the domain size reaches (1 << log_final_height). This is equal to 1 << log_blowup
-    // currently as we have not yet implemented early stopping.
-    for (log_folded_height, ((&beta, comm), opening)) in zip_eq(
-        (config.log_blowup..log_max_height).rev(),
+    // using FRI until the domain size reaches (1 << log_final_height).
+    for (log_folded_height, ((&beta, opened_rows), opening)) in zip_eq(
+        (log_final_height..log_max_height).rev(),
         steps,
         FriError::InvalidProofShape,
     )? {
@@ -140,24 +184,21 @@ where
         let mut evals = vec![folded_eval; 2];
         evals[index_sibling % 2] = opening.sibling_value;
 
-        let dims = &[Dimensions {
-            width: 2,
-            height: 1 << log_folded_height,
-        }];
-
         // Replace index with the index of the parent fri node.
         index >>= 1;
 
-        // Verify the commitment to the evaluations of the sibling nodes.
-        config
-            .mmcs
-            .verify_batch(comm, dims, index, &[evals.clone()], &opening.opening_proof)
-            .map_err(FriError::CommitPhaseMmcsError)?;
+        // Record the evaluations of the sibling nodes, to check against the commitment.
+        opened_rows.push((index, evals.clone()));
 
         // Fold the pair of evaluations of sibling nodes into the evaluation of the parent fri node.
         folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
     }
 
+    // An input may have the height of the final domain.
+    if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_final_height) {
+        folded_eval += ro;
+    }
+
     // If ro_iter is not empty, we failed to fold in some polynomial evaluations.
     if ro_iter.next().is_some() {
         return Err(FriError::InvalidProofShape);
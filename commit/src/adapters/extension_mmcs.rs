@@ -6,7 +6,7 @@ use p3_field::{ExtensionField, Field};
 use p3_matrix::extension::FlatMatrixView;
 use p3_matrix::{Dimensions, Matrix};
 
-use crate::Mmcs;
+use crate::{Mmcs, MmcsShape, MultiMmcs};
 
 #[derive(Clone, Debug)]
 pub struct ExtensionMmcs<F, EF, InnerMmcs> {
@@ -45,19 +45,7 @@ where
         prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<EF>>, Self::Proof) {
         let (opened_base_values, proof) = self.inner.open_batch(index, prover_data);
-        let opened_ext_values = opened_base_values
-            .into_iter()
-            .map(|row| {
-                // By construction, the width of the row is a multiple of EF::DIMENSION.
-                // So there will be no remainder when we call chunks_exact.
-                row.chunks_exact(EF::DIMENSION)
-                    // As each chunk has length EF::DIMENSION, from_basis_coefficients_slice
-                    // will produce some(elem) which into_iter converts to the iterator once(elem).
-                    .flat_map(EF::from_basis_coefficients_slice)
-                    .collect()
-            })
-            .collect();
-        (opened_ext_values, proof)
+        (to_ext_rows(opened_base_values), proof)
     }
 
     fn get_matrices<'a, M: Matrix<EF>>(&self, prover_data: &'a Self::ProverData<M>) -> Vec<&'a M> {
@@ -68,6 +56,10 @@ where
             .collect()
     }
 
+    fn shape(&self) -> MmcsShape {
+        self.inner.shape()
+    }
+
     fn verify_batch(
         &self,
         commit: &Self::Commitment,
@@ -76,23 +68,87 @@ where
         opened_values: &[Vec<EF>],
         proof: &Self::Proof,
     ) -> Result<(), Self::Error> {
-        let opened_base_values: Vec<Vec<F>> = opened_values
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .flat_map(|el| el.as_basis_coefficients_slice())
-                    .copied()
-                    .collect()
-            })
-            .collect();
-        let base_dimensions = dimensions
-            .iter()
-            .map(|dim| Dimensions {
-                width: dim.width * EF::DIMENSION,
-                height: dim.height,
-            })
-            .collect::<Vec<_>>();
+        let opened_base_values = to_base_rows(opened_values);
+        let base_dimensions = to_base_dimensions::<F, EF>(dimensions);
         self.inner
             .verify_batch(commit, &base_dimensions, index, &opened_base_values, proof)
     }
 }
+
+impl<F, EF, InnerMmcs> MultiMmcs<EF> for ExtensionMmcs<F, EF, InnerMmcs>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    InnerMmcs: MultiMmcs<F>,
+{
+    type MultiProof = InnerMmcs::MultiProof;
+
+    fn open_multi_batch<M: Matrix<EF>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<EF>>>, Self::MultiProof) {
+        let (opened_base_values, proof) = self.inner.open_multi_batch(indices, prover_data);
+        let opened_ext_values = opened_base_values.into_iter().map(to_ext_rows).collect();
+        (opened_ext_values, proof)
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<EF>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        let opened_base_values = opened_values
+            .iter()
+            .map(|rows| to_base_rows(rows))
+            .collect::<Vec<_>>();
+        let base_dimensions = to_base_dimensions::<F, EF>(dimensions);
+        self.inner.verify_multi_batch(
+            commit,
+            &base_dimensions,
+            indices,
+            &opened_base_values,
+            proof,
+        )
+    }
+}
+
+fn to_ext_rows<F: Field, EF: ExtensionField<F>>(rows: Vec<Vec<F>>) -> Vec<Vec<EF>> {
+    rows.into_iter()
+        .map(|row| {
+            // By construction, the width of the row is a multiple of EF::DIMENSION.
+            // So there will be no remainder when we call chunks_exact.
+            row.chunks_exact(EF::DIMENSION)
+                // As each chunk has length EF::DIMENSION, from_basis_coefficients_slice
+                // will produce some(elem) which into_iter converts to the iterator once(elem).
+                .flat_map(EF::from_basis_coefficients_slice)
+                .collect()
+        })
+        .collect()
+}
+
+fn to_base_rows<F: Field, EF: ExtensionField<F>>(rows: &[Vec<EF>]) -> Vec<Vec<F>> {
+    rows.iter()
+        .map(|row| {
+            row.iter()
+                .flat_map(|el| el.as_basis_coefficients_slice())
+                .copied()
+                .collect()
+        })
+        .collect()
+}
+
+fn to_base_dimensions<F: Field, EF: ExtensionField<F>>(
+    dimensions: &[Dimensions],
+) -> Vec<Dimensions> {
+    dimensions
+        .iter()
+        .map(|dim| Dimensions {
+            width: dim.width * EF::DIMENSION,
+            height: dim.height,
+        })
+        .collect()
+}
@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use itertools::Itertools;
+use itertools::{Itertools, izip};
 use p3_field::coset::TwoAdicMultiplicativeCoset;
 use p3_field::{
     ExtensionField, Field, TwoAdicField, batch_multiplicative_inverse,
@@ -56,6 +56,10 @@ pub trait PolynomialSpace: Copy {
     /// The base field `F`.
     type Val: Field;
 
+    /// The log of the size of the largest space of this kind, e.g. the two-adicity of the field
+    /// for multiplicative cosets.
+    const MAX_LOG_SIZE: usize;
+
     /// The number of elements of the space.
     fn size(&self) -> usize;
 
@@ -129,11 +133,28 @@ pub trait PolynomialSpace: Copy {
     ///
     /// Note that these may not be normalized.
     fn selectors_on_coset(&self, coset: Self) -> LagrangeSelectors<Vec<Self::Val>>;
+
+    /// Evaluate a periodic column at the given point.
+    ///
+    /// The column is the polynomial whose value at the `i`'th point of this space is
+    /// `column[i % column.len()]`, and which has degree less than `column.len()` in some power of
+    /// the point. The length of `column` must be a power of two no larger than `self.size()`.
+    fn periodic_column_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        column: &[Self::Val],
+        point: Ext,
+    ) -> Ext;
+
+    /// Evaluate a periodic column, as in [`periodic_column_at_point`](Self::periodic_column_at_point),
+    /// at all points of the given disjoint `PolynomialSpace`.
+    fn periodic_column_on_coset(&self, column: &[Self::Val], coset: Self) -> Vec<Self::Val>;
 }
 
 impl<Val: TwoAdicField> PolynomialSpace for TwoAdicMultiplicativeCoset<Val> {
     type Val = Val;
 
+    const MAX_LOG_SIZE: usize = Val::TWO_ADICITY;
+
     fn size(&self) -> usize {
         self.size()
     }
@@ -280,4 +301,56 @@ impl<Val: TwoAdicField> PolynomialSpace for TwoAdicMultiplicativeCoset<Val> {
                 .collect(),
         }
     }
+
+    /// The `i`'th point of the coset `gH` is `gh^i`, and `(g^{-1}X)^{|H|/p}` maps it to `w^{i mod p}`,
+    /// where `w` generates the subgroup of size `p = column.len()`. So the column is the polynomial
+    /// interpolating it over that subgroup, evaluated at `(g^{-1}X)^{|H|/p}`.
+    fn periodic_column_at_point<Ext: ExtensionField<Val>>(
+        &self,
+        column: &[Val],
+        point: Ext,
+    ) -> Ext {
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_size());
+        let x = (point * self.shift().inverse()).exp_power_of_2(self.log_size() - log_period);
+        interpolate_subgroup_at(column, x)
+    }
+
+    fn periodic_column_on_coset(&self, column: &[Val], coset: Self) -> Vec<Val> {
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_size());
+        let log_step = self.log_size() - log_period;
+
+        // The powers we interpolate at repeat after this many points of the coset.
+        let num_distinct = (coset.size() >> log_step).max(1);
+        let xs = cyclic_subgroup_coset_known_order(
+            coset.subgroup_generator().exp_power_of_2(log_step),
+            (coset.shift() * self.shift().inverse()).exp_power_of_2(log_step),
+            num_distinct,
+        )
+        .map(|x| interpolate_subgroup_at(column, x))
+        .collect_vec();
+        xs.into_iter().cycle().take(coset.size()).collect()
+    }
+}
+
+/// Evaluate at `x` the polynomial of degree less than `evals.len()` which takes the values `evals`
+/// on the subgroup of that size.
+fn interpolate_subgroup_at<F: TwoAdicField, Ext: ExtensionField<F>>(evals: &[F], x: Ext) -> Ext {
+    let log_n = log2_strict_usize(evals.len());
+    let subgroup = F::two_adic_generator(log_n)
+        .powers()
+        .take(evals.len())
+        .collect_vec();
+    let diffs = subgroup.iter().map(|&w| x - w).collect_vec();
+    if let Some(i) = diffs.iter().position(|diff| diff.is_zero()) {
+        return evals[i].into();
+    }
+
+    // The Lagrange basis polynomial of `w^i` is `w^i (X^n - 1) / (n (X - w^i))`.
+    let diff_invs = batch_multiplicative_inverse(&diffs);
+    let sum = izip!(evals, subgroup, diff_invs)
+        .map(|(&eval, w, diff_inv)| diff_inv * (w * eval))
+        .sum::<Ext>();
+    sum * (x.exp_power_of_2(log_n) - Ext::ONE) * F::from_usize(evals.len()).inverse()
 }
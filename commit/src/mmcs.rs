@@ -1,6 +1,7 @@
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
+use core::mem::size_of;
 
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::{Dimensions, Matrix};
@@ -66,11 +67,21 @@ pub trait Mmcs<T: Send + Sync>: Clone {
             .unwrap_or_else(|| panic!("No committed matrices?"))
     }
 
+    /// The shape of the trees this MMCS commits to, for estimating the size of its commitments and
+    /// proofs. By default, a binary tree whose commitment is its root.
+    fn shape(&self) -> MmcsShape {
+        MmcsShape {
+            arity: 2,
+            cap_height: 0,
+            digest_bytes: size_of::<Self::Commitment>(),
+        }
+    }
+
     /// Verify a batch opening.
     /// `index` is the row index we're opening for each matrix, following the same
     /// semantics as `open_batch`.
     /// `dimensions` is a slice whose ith element is the dimensions of the matrix being opened
-    /// in the ith opening
+    /// in the ith opening, whose width the ith opened row must have.
     fn verify_batch(
         &self,
         commit: &Self::Commitment,
@@ -80,3 +91,101 @@ pub trait Mmcs<T: Send + Sync>: Clone {
         proof: &Self::Proof,
     ) -> Result<(), Self::Error>;
 }
+
+/// An [`Mmcs`] which opens a batch of rows at several indices at once, with a proof which may share
+/// data between them.
+///
+/// An MMCS with no more compact proof can open each index on its own, by setting
+/// `type MultiProof = Vec<Self::Proof>` and implementing the methods with
+/// [`open_multi_batch_per_index`] and [`verify_multi_batch_per_index`].
+pub trait MultiMmcs<T: Send + Sync>: Mmcs<T> {
+    /// A proof of openings at several indices at once.
+    type MultiProof: Clone + Serialize + DeserializeOwned;
+
+    /// Opens a batch of rows from committed matrices at each of `indices`.
+    /// returns `(openings, proof)`
+    /// where `openings[q]` is the opening at `indices[q]`, as returned by `open_batch`, and `proof`
+    /// proves all of them at once. Indices may repeat.
+    fn open_multi_batch<M: Matrix<T>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<T>>>, Self::MultiProof);
+
+    /// Verify openings at several indices, as produced by `open_multi_batch`.
+    /// `opened_values[q]` is the opening at `indices[q]`, following the same semantics as
+    /// `verify_batch`.
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<T>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error>;
+}
+
+/// Opens a batch of rows at each of `indices` with [`Mmcs::open_batch`], for an MMCS whose
+/// [`MultiMmcs::MultiProof`] is a proof per index.
+pub fn open_multi_batch_per_index<T, Mc, M>(
+    mmcs: &Mc,
+    indices: &[usize],
+    prover_data: &Mc::ProverData<M>,
+) -> (Vec<Vec<Vec<T>>>, Vec<Mc::Proof>)
+where
+    T: Send + Sync,
+    Mc: Mmcs<T>,
+    M: Matrix<T>,
+{
+    indices
+        .iter()
+        .map(|&index| mmcs.open_batch(index, prover_data))
+        .unzip()
+}
+
+/// Verifies openings at each of `indices` with [`Mmcs::verify_batch`], as produced by
+/// [`open_multi_batch_per_index`].
+pub fn verify_multi_batch_per_index<T, Mc>(
+    mmcs: &Mc,
+    commit: &Mc::Commitment,
+    dimensions: &[Dimensions],
+    indices: &[usize],
+    opened_values: &[Vec<Vec<T>>],
+    proofs: &[Mc::Proof],
+) -> Result<(), Mc::Error>
+where
+    T: Send + Sync,
+    Mc: Mmcs<T>,
+{
+    assert_eq!(indices.len(), opened_values.len());
+    assert_eq!(indices.len(), proofs.len());
+    for ((&index, opened_values), proof) in indices.iter().zip(opened_values).zip(proofs) {
+        mmcs.verify_batch(commit, dimensions, index, opened_values, proof)?;
+    }
+    Ok(())
+}
+
+/// The shape of the trees committed to by an [`Mmcs`], as returned by [`Mmcs::shape`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MmcsShape {
+    /// The number of children of each internal node.
+    pub arity: usize,
+    /// The number of layers below the root at which trees are committed to.
+    pub cap_height: usize,
+    /// The size of a digest in bytes.
+    pub digest_bytes: usize,
+}
+
+impl MmcsShape {
+    /// The number of layers of nodes below the committed cap of a tree with `2^log_leaves` leaves.
+    pub fn num_layers(&self, log_leaves: usize) -> usize {
+        let layers = log_leaves.div_ceil(self.arity.ilog2() as usize);
+        layers - self.cap_height.min(layers)
+    }
+
+    /// The size in bytes of the commitment to a tree with `2^log_leaves` leaves.
+    pub fn commitment_bytes(&self, log_leaves: usize) -> usize {
+        let layers = log_leaves.div_ceil(self.arity.ilog2() as usize);
+        self.arity.pow(self.cap_height.min(layers) as u32) * self.digest_bytes
+    }
+}
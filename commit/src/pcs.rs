@@ -9,7 +9,7 @@ use p3_matrix::dense::RowMajorMatrix;
 use serde::Serialize;
 use serde::de::DeserializeOwned;
 
-use crate::PolynomialSpace;
+use crate::{MmcsShape, PolynomialSpace};
 
 pub type Val<D> = <D as PolynomialSpace>::Val;
 
@@ -35,14 +35,67 @@ where
 
     type Error: Debug;
 
+    /// Whether committed polynomials are hidden, i.e. randomized outside of their domain.
+    ///
+    /// A hiding scheme may double the degree of each committed polynomial, which callers must
+    /// account for when choosing the domains they evaluate commitments over.
+    const ZK: bool = false;
+
+    /// Parameters of this scheme which affect soundness, such as the blowup factor and number of
+    /// queries of FRI. Provers and verifiers bind these into their transcripts, so that proofs
+    /// under different parameters never share a transcript prefix.
+    fn parameters(&self) -> Vec<usize> {
+        Vec::new()
+    }
+
     /// This should return a coset domain (s.t. Domain::next_point returns Some)
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain;
 
+    /// Commit to the given evaluations, each over its domain.
+    ///
+    /// Returns an error if a matrix's height does not match the size of its domain, or if this
+    /// scheme cannot commit over a domain of that size.
+    #[allow(clippy::type_complexity)]
+    fn try_commit(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
+    ) -> Result<(Self::Commitment, Self::ProverData), CommitError>;
+
+    /// Like [`try_commit`](Self::try_commit), but panics on evaluations of an invalid shape.
     #[allow(clippy::type_complexity)]
     fn commit(
         &self,
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
-    ) -> (Self::Commitment, Self::ProverData);
+    ) -> (Self::Commitment, Self::ProverData) {
+        self.try_commit(evaluations)
+            .expect("invalid evaluations to commit")
+    }
+
+    /// Commit to the chunks of one or more quotient polynomials.
+    ///
+    /// Each entry holds the evaluations of a quotient polynomial over its domain, and the number
+    /// of chunks to split it into. The chunks of every quotient are committed in order, over the
+    /// domains given by [`PolynomialSpace::split_domains`].
+    ///
+    /// The default implementation commits to the chunks from [`PolynomialSpace::split_evals`].
+    /// Hiding schemes may instead randomize the chunks, so long as the quotient recombined from
+    /// them is unchanged.
+    #[allow(clippy::type_complexity)]
+    fn commit_quotients(
+        &self,
+        quotients: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>, usize)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let chunks = quotients
+            .into_iter()
+            .flat_map(|(domain, evals, num_chunks)| {
+                domain
+                    .split_domains(num_chunks)
+                    .into_iter()
+                    .zip(domain.split_evals(num_chunks, evals))
+            })
+            .collect();
+        self.commit(chunks)
+    }
 
     fn get_evaluations_on_domain<'a>(
         &self,
@@ -51,6 +104,12 @@ where
         domain: Self::Domain,
     ) -> Self::EvaluationsOnDomain<'a>;
 
+    /// Open each matrix of each round at its points.
+    ///
+    /// # Panics
+    ///
+    /// Every matrix must be opened at one or more points, as the verifier learns the width of a
+    /// matrix only from the values claimed at its points.
     fn open(
         &self,
         // For each round,
@@ -65,6 +124,8 @@ where
         challenger: &mut Challenger,
     ) -> (OpenedValues<Challenge>, Self::Proof);
 
+    /// Verify the values claimed for each matrix at its points. A matrix claimed at no points is
+    /// rejected, since its width would be unknown.
     #[allow(clippy::type_complexity)]
     fn verify(
         &self,
@@ -89,6 +150,60 @@ where
     ) -> Result<(), Self::Error>;
 }
 
+/// A PCS which can describe the shape of its proofs, e.g. to estimate their size without
+/// producing one.
+pub trait ShapedPcs<Challenge, Challenger>: Pcs<Challenge, Challenger>
+where
+    Challenge: ExtensionField<Val<Self::Domain>>,
+{
+    fn shape(&self) -> PcsShape;
+}
+
+/// The shape of the proofs of a PCS which opens committed codewords at random queries, and proves
+/// they're close to low-degree by folding them, as returned by [`ShapedPcs::shape`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct PcsShape {
+    /// The shape of the trees committing to batches of codewords.
+    pub input_mmcs: MmcsShape,
+    /// The shape of the trees committing to each folded codeword.
+    pub fold_mmcs: MmcsShape,
+    /// The log of the factor by which polynomials are extended before being committed.
+    pub log_blowup: usize,
+    /// The log of the number of coefficients of the final polynomial, which is sent in the clear.
+    pub log_final_poly_len: usize,
+    /// The log of the largest number of values folded into one.
+    pub max_log_arity: usize,
+    /// The number of queries at which each committed codeword is opened.
+    pub num_queries: usize,
+}
+
+impl PcsShape {
+    pub const fn final_poly_len(&self) -> usize {
+        1 << self.log_final_poly_len
+    }
+
+    /// The log of the arity of the round folding a codeword of size `2^log_height`: the largest
+    /// allowed by `max_log_arity` which doesn't fold past the final codeword.
+    pub fn log_arity_of_round(&self, log_height: usize) -> usize {
+        self.max_log_arity
+            .min(log_height.saturating_sub(self.log_blowup + self.log_final_poly_len))
+    }
+}
+
+/// An error returned by [`Pcs::try_commit`] for evaluations of an invalid shape.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum CommitError {
+    /// The matrix at `index` has `height` rows, but its domain has `domain_size` points.
+    HeightMismatch {
+        index: usize,
+        domain_size: usize,
+        height: usize,
+    },
+    /// The domain of the matrix at `index`, of size `2^log_size`, is too small or too large for
+    /// this scheme, e.g. because its low-degree extension would exceed the field's two-adicity.
+    UnsupportedDomainSize { index: usize, log_size: usize },
+}
+
 pub type OpenedValues<F> = Vec<OpenedValuesForRound<F>>;
 pub type OpenedValuesForRound<F> = Vec<OpenedValuesForMatrix<F>>;
 pub type OpenedValuesForMatrix<F> = Vec<OpenedValuesForPoint<F>>;
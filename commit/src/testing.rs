@@ -12,7 +12,7 @@ use p3_util::log2_strict_usize;
 use p3_util::zip_eq::zip_eq;
 use serde::{Deserialize, Serialize};
 
-use crate::{OpenedValues, Pcs};
+use crate::{CommitError, OpenedValues, Pcs};
 
 /// A trivial PCS: its commitment is simply the coefficients of each poly.
 #[derive(Debug)]
@@ -59,17 +59,29 @@ where
         TwoAdicMultiplicativeCoset::new(Val::ONE, log2_strict_usize(degree)).unwrap()
     }
 
-    fn commit(
+    fn try_commit(
         &self,
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
-    ) -> (Self::Commitment, Self::ProverData) {
+    ) -> Result<(Self::Commitment, Self::ProverData), CommitError> {
         let coeffs: Vec<_> = evaluations
             .into_iter()
-            .map(|(domain, evals)| {
+            .enumerate()
+            .map(|(index, (domain, evals))| {
                 let log_domain_size = log2_strict_usize(domain.size());
                 // for now, only commit on larger domain than natural
-                assert!(log_domain_size >= self.log_n);
-                assert_eq!(domain.size(), evals.height());
+                if log_domain_size < self.log_n {
+                    return Err(CommitError::UnsupportedDomainSize {
+                        index,
+                        log_size: log_domain_size,
+                    });
+                }
+                if domain.size() != evals.height() {
+                    return Err(CommitError::HeightMismatch {
+                        index,
+                        domain_size: domain.size(),
+                        height: evals.height(),
+                    });
+                }
                 // coset_idft_batch
                 let mut coeffs = self.dft.idft_batch(evals);
                 coeffs
@@ -80,13 +92,13 @@ where
                             *coeff *= weight;
                         })
                     });
-                coeffs
+                Ok(coeffs)
             })
-            .collect();
-        (
+            .collect::<Result<_, _>>()?;
+        Ok((
             coeffs.clone().into_iter().map(|m| m.values).collect(),
             coeffs,
-        )
+        ))
     }
 
     fn get_evaluations_on_domain<'a>(
@@ -1,4 +1,4 @@
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, BaseAir, PeriodicAirBuilder, TracePadding};
 use p3_blake3_air::Blake3Air;
 use p3_challenger::FieldChallenger;
 use p3_commit::PolynomialSpace;
@@ -49,7 +49,7 @@ pub enum ProofObjective<
 /// the output of some number of hashes using a given hash function.
 pub trait ExampleHashAir<F: Field, SC: StarkGenericConfig>:
     BaseAir<F>
-    + for<'a> Air<DebugConstraintBuilder<'a, F>>
+    + for<'a> Air<DebugConstraintBuilder<'a, F, SC::Challenge>>
     + Air<SymbolicAirBuilder<F>>
     + for<'a> Air<ProverConstraintFolder<'a, SC>>
     + for<'a> Air<VerifierConstraintFolder<'a, SC>>
@@ -92,10 +92,28 @@ impl<
             Self::Keccak(k_air) => <KeccakAir as BaseAir<F>>::width(k_air),
         }
     }
+
+    #[inline]
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        match self {
+            Self::Blake3(b3_air) => <Blake3Air as BaseAir<F>>::periodic_columns(b3_air),
+            Self::Poseidon2(p2_air) => p2_air.periodic_columns(),
+            Self::Keccak(k_air) => <KeccakAir as BaseAir<F>>::periodic_columns(k_air),
+        }
+    }
+
+    #[inline]
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        match self {
+            Self::Blake3(b3_air) => <Blake3Air as BaseAir<F>>::trace_padding(b3_air),
+            Self::Poseidon2(p2_air) => p2_air.trace_padding(),
+            Self::Keccak(k_air) => <KeccakAir as BaseAir<F>>::trace_padding(k_air),
+        }
+    }
 }
 
 impl<
-    AB: AirBuilder,
+    AB: PeriodicAirBuilder,
     LinearLayers: GenericPoseidon2LinearLayers<AB::Expr, WIDTH>,
     const WIDTH: usize,
     const SBOX_DEGREE: u64,
@@ -1,3 +1,4 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
@@ -7,8 +8,13 @@ use p3_matrix::Matrix;
 #[derive(Debug)]
 pub struct FriConfig<M> {
     pub log_blowup: usize,
-    // TODO: This parameter and FRI early stopping are not yet implemented in `CirclePcs`.
+    /// The log of the length of the final polynomial, at which FRI stops folding. Circle FRI may
+    /// stop with a shorter polynomial, if one of its inputs is shorter.
     pub log_final_poly_len: usize,
+    /// The log of the largest folding arity. Each round folds by `2^max_log_arity`, or by less
+    /// where that would skip past the height of an input or of the final polynomial.
+    // TODO: Folding arities above 2 are not yet implemented in `CirclePcs`.
+    pub max_log_arity: usize,
     pub num_queries: usize,
     pub proof_of_work_bits: usize,
     pub mmcs: M,
@@ -27,10 +33,37 @@ impl<M> FriConfig<M> {
     /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture.
     ///
     /// Certain users may instead want to look at proven soundness, a more complex calculation which
-    /// isn't currently supported by this crate.
+    /// is provided by [`Self::proven_soundness_bits`].
     pub const fn conjectured_soundness_bits(&self) -> usize {
         self.log_blowup * self.num_queries + self.proof_of_work_bits
     }
+
+    /// The log of the arity of a folding round over a domain of size `2^log_height`: the largest
+    /// allowed by `max_log_arity` which doesn't fold past the next input, of size
+    /// `2^log_next_input_height`, or past the final domain.
+    pub fn log_arity_of_round(
+        &self,
+        log_height: usize,
+        log_next_input_height: Option<usize>,
+    ) -> usize {
+        let log_final_height = self.log_blowup + self.log_final_poly_len;
+        let log_stop_height =
+            log_next_input_height.map_or(log_final_height, |h| h.max(log_final_height));
+        self.max_log_arity
+            .min(log_height.saturating_sub(log_stop_height))
+    }
+
+    /// The parameters of this FRI instance which affect soundness, in a fixed order, for binding
+    /// into a Fiat-Shamir transcript.
+    pub fn parameters(&self) -> Vec<usize> {
+        vec![
+            self.log_blowup,
+            self.log_final_poly_len,
+            self.max_log_arity,
+            self.num_queries,
+            self.proof_of_work_bits,
+        ]
+    }
 }
 
 /// Whereas `FriConfig` encompasses parameters the end user can set, `FriGenericConfig` is
@@ -44,8 +77,8 @@ pub trait FriGenericConfig<F: Field> {
     fn extra_query_index_bits(&self) -> usize;
 
     /// Fold a row, returning a single column.
-    /// Right now the input row will always be 2 columns wide,
-    /// but we may support higher folding arity in the future.
+    /// The input row has `2^k` columns when folding with arity `2^k`; configs which only support
+    /// arity 2 may panic on wider rows.
     fn fold_row(
         &self,
         index: usize,
@@ -67,6 +100,7 @@ pub const fn create_test_fri_config<Mmcs>(
     FriConfig {
         log_blowup: 2,
         log_final_poly_len,
+        max_log_arity: 1,
         num_queries: 2,
         proof_of_work_bits: 1,
         mmcs,
@@ -79,6 +113,7 @@ pub const fn create_benchmark_fri_config<Mmcs>(mmcs: Mmcs) -> FriConfig<Mmcs> {
     FriConfig {
         log_blowup: 1,
         log_final_poly_len: 0,
+        max_log_arity: 1,
         num_queries: 100,
         proof_of_work_bits: 16,
         mmcs,
@@ -3,7 +3,7 @@ use core::cell::RefCell;
 use core::fmt::Debug;
 
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs};
+use p3_commit::{CommitError, MultiMmcs, OpenedValues, Pcs, PcsShape, PolynomialSpace, ShapedPcs};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::coset::TwoAdicMultiplicativeCoset;
 use p3_field::{ExtensionField, Field, TwoAdicField};
@@ -22,6 +22,11 @@ use crate::{BatchOpening, FriConfig, FriProof, TwoAdicFriPcs};
 
 /// A hiding FRI PCS. Both MMCSs must also be hiding; this is not enforced at compile time so it's
 /// the user's responsibility to configure.
+///
+/// Besides adding random codewords to each batch, every committed polynomial `p` over a coset `gH`
+/// is replaced by `p + Z_{gH} r` for a uniformly random `r` of degree less than `|H|`, so that
+/// openings outside of `gH` reveal nothing about `p`. Committed polynomials thus have twice their
+/// usual degree, and are committed over the coset `gH'` where `|H'| = 2|H|`.
 #[derive(Debug)]
 pub struct HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R> {
     inner: TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs>,
@@ -46,14 +51,33 @@ impl<Val, Dft, InputMmcs, FriMmcs, R> HidingFriPcs<Val, Dft, InputMmcs, FriMmcs,
     }
 }
 
+impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger, R> ShapedPcs<Challenge, Challenger>
+    for HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R>
+where
+    Val: TwoAdicField,
+    StandardUniform: Distribution<Val>,
+    Dft: TwoAdicSubgroupDft<Val>,
+    InputMmcs: MultiMmcs<Val>,
+    FriMmcs: MultiMmcs<Challenge>,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    Challenger:
+        FieldChallenger<Val> + CanObserve<FriMmcs::Commitment> + GrindingChallenger<Witness = Val>,
+    R: Rng + Send + Sync,
+{
+    /// The shape of the inner PCS. The random codewords added to each batch are not included.
+    fn shape(&self) -> PcsShape {
+        ShapedPcs::<Challenge, Challenger>::shape(&self.inner)
+    }
+}
+
 impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger, R> Pcs<Challenge, Challenger>
     for HidingFriPcs<Val, Dft, InputMmcs, FriMmcs, R>
 where
     Val: TwoAdicField,
     StandardUniform: Distribution<Val>,
     Dft: TwoAdicSubgroupDft<Val>,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiMmcs<Val>,
+    FriMmcs: MultiMmcs<Challenge>,
     Challenge: TwoAdicField + ExtensionField<Val>,
     Challenger:
         FieldChallenger<Val> + CanObserve<FriMmcs::Commitment> + GrindingChallenger<Witness = Val>,
@@ -74,24 +98,78 @@ where
     );
     type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
 
+    const ZK: bool = true;
+
+    fn parameters(&self) -> Vec<usize> {
+        let mut parameters = <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<
+            Challenge,
+            Challenger,
+        >>::parameters(&self.inner);
+        parameters.push(self.num_random_codewords);
+        parameters
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::natural_domain_for_degree(
             &self.inner, degree)
     }
 
-    fn commit(
+    fn try_commit(
         &self,
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
-    ) -> (Self::Commitment, Self::ProverData) {
+    ) -> Result<(Self::Commitment, Self::ProverData), CommitError> {
+        // Check heights before randomizing, since randomization doubles them along with the domains.
+        for (index, (domain, evals)) in evaluations.iter().enumerate() {
+            if domain.size() != evals.height() {
+                return Err(CommitError::HeightMismatch {
+                    index,
+                    domain_size: domain.size(),
+                    height: evals.height(),
+                });
+            }
+        }
+
+        let rng = &mut *self.rng.borrow_mut();
         let randomized_evaluations = evaluations
             .into_iter()
             .map(|(domain, mat)| {
+                let mat = add_random_rows(mat, &mut *rng);
                 (
-                    domain,
-                    add_random_cols(mat, self.num_random_codewords, &mut *self.rng.borrow_mut()),
+                    doubled_domain(domain),
+                    add_random_cols(mat, self.num_random_codewords, &mut *rng),
                 )
             })
             .collect();
+        <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::try_commit(
+            &self.inner,
+            randomized_evaluations,
+        )
+    }
+
+    fn commit_quotients(
+        &self,
+        quotients: Vec<(Self::Domain, RowMajorMatrix<Val>, usize)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let rng = &mut *self.rng.borrow_mut();
+        let randomized_evaluations = quotients
+            .into_iter()
+            .flat_map(|(domain, evals, num_chunks)| {
+                let chunk_domains = domain.split_domains(num_chunks);
+                let chunks = domain.split_evals(num_chunks, evals);
+                let masked_chunks =
+                    mask_quotient_chunks(&self.inner.dft, &chunk_domains, chunks, &mut *rng);
+                chunk_domains
+                    .into_iter()
+                    .zip(masked_chunks)
+                    .map(|(domain, mat)| {
+                        (
+                            doubled_domain(domain),
+                            add_random_cols(mat, self.num_random_codewords, &mut *rng),
+                        )
+                    })
+                    .collect::<Vec<_>>()
+            })
+            .collect();
         <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::commit(
             &self.inner,
             randomized_evaluations,
@@ -190,10 +268,19 @@ where
                 for (point, rand_point) in
                     zip_eq(mat.1.iter_mut(), rand_mat, FriError::InvalidProofShape)?
                 {
+                    if rand_point.len() != self.num_random_codewords {
+                        return Err(FriError::InvalidProofShape);
+                    }
                     point.1.extend(rand_point);
                 }
             }
         }
+        // Every polynomial was committed over a coset twice the size of its domain.
+        for (_, mats) in &mut rounds {
+            for (domain, _) in mats {
+                *domain = doubled_domain(*domain);
+            }
+        }
         self.inner.verify(rounds, inner_proof, challenger)
     }
 }
@@ -226,3 +313,106 @@ where
         });
     result
 }
+
+/// The coset with the same shift as `domain` and twice its size.
+fn doubled_domain<Val: TwoAdicField>(
+    domain: TwoAdicMultiplicativeCoset<Val>,
+) -> TwoAdicMultiplicativeCoset<Val> {
+    TwoAdicMultiplicativeCoset::new(domain.shift(), domain.log_size() + 1)
+        .expect("domain is too large to randomize")
+}
+
+/// Interleave the rows of `mat`, the evaluations of some `p` over a coset `gH`, with random rows.
+///
+/// The result holds the evaluations over `gH'`, where `|H'| = 2|H|`, of `p + Z_{gH} r` for a
+/// uniformly random `r` of degree less than `|H|`.
+#[instrument(level = "debug", skip_all)]
+fn add_random_rows<Val, R>(mat: RowMajorMatrix<Val>, rng: &mut R) -> RowMajorMatrix<Val>
+where
+    Val: Field,
+    R: Rng,
+    StandardUniform: Distribution<Val>,
+{
+    let w = mat.width();
+    let mut values = Vec::with_capacity(2 * mat.values.len());
+    for row in mat.row_slices() {
+        values.extend_from_slice(row);
+        values.extend((0..w).map(|_| rng.random::<Val>()));
+    }
+    RowMajorMatrix::new(values, w)
+}
+
+/// Mask the chunks `q_i` of a quotient polynomial, given by their evaluations over the cosets
+/// `c_i H` in `domains`, as `q_i + Z_{c_i H} r_i` for random `r_i` of degree less than `|H|`.
+///
+/// The verifier recombines the chunks as `sum_i q_i(zeta) prod_{j != i} Z_{c_j H}(zeta) / d_i`,
+/// where `d_i = prod_{j != i} Z_{c_j H}(c_i)`. The masks therefore contribute
+/// `prod_j Z_{c_j H}(zeta) sum_i r_i(zeta) / d_i`, which we cancel by choosing the last mask as
+/// `r_{k-1} = -d_{k-1} sum_{i < k - 1} r_i / d_i`.
+///
+/// Returns the evaluations of the masked chunks over the cosets `c_i H'`, where `|H'| = 2|H|`.
+#[instrument(level = "debug", skip_all)]
+fn mask_quotient_chunks<Val, Dft, R>(
+    dft: &Dft,
+    domains: &[TwoAdicMultiplicativeCoset<Val>],
+    chunks: Vec<RowMajorMatrix<Val>>,
+    rng: &mut R,
+) -> Vec<RowMajorMatrix<Val>>
+where
+    Val: TwoAdicField,
+    Dft: TwoAdicSubgroupDft<Val>,
+    R: Rng,
+    StandardUniform: Distribution<Val>,
+{
+    let (height, width) = (chunks[0].height(), chunks[0].width());
+    let denominators = domains
+        .iter()
+        .enumerate()
+        .map(|(i, domain)| {
+            domains
+                .iter()
+                .enumerate()
+                .filter(|&(j, _)| j != i)
+                .map(|(_, other)| other.vanishing_poly_at_point(domain.first_point()))
+                .product::<Val>()
+        })
+        .collect::<Vec<_>>();
+
+    let (last_denominator, denominators) = denominators.split_last().unwrap();
+    let mut masks = denominators
+        .iter()
+        .map(|_| RowMajorMatrix::<Val>::rand(rng, height, width))
+        .collect::<Vec<_>>();
+    let mut last_mask = RowMajorMatrix::new(Val::zero_vec(height * width), width);
+    for (mask, &denominator) in masks.iter().zip(denominators) {
+        let scale = -*last_denominator * denominator.inverse();
+        last_mask
+            .values
+            .iter_mut()
+            .zip(&mask.values)
+            .for_each(|(l, &r)| *l += r * scale);
+    }
+    masks.push(last_mask);
+
+    domains
+        .iter()
+        .zip(chunks)
+        .zip(masks)
+        .map(|((domain, chunk), mask)| {
+            let shift = domain.shift();
+            // With Z_{c H}(X) = (X / c)^|H| - 1, the coefficients of q + Z_{c H} r are those of
+            // q - r followed by those of c^{-|H|} r.
+            let mut coeffs = dft.coset_idft_batch(chunk, shift);
+            let shift_inv_pow = shift.inverse().exp_power_of_2(domain.log_size());
+            coeffs
+                .values
+                .iter_mut()
+                .zip(&mask.values)
+                .for_each(|(c, &r)| *c -= r);
+            coeffs
+                .values
+                .extend(mask.values.iter().map(|&r| r * shift_inv_pow));
+            dft.coset_dft_batch(coeffs, shift).to_row_major_matrix()
+        })
+        .collect()
+}
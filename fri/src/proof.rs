@@ -1,6 +1,6 @@
 use alloc::vec::Vec;
 
-use p3_commit::Mmcs;
+use p3_commit::MultiMmcs;
 use p3_field::Field;
 use serde::{Deserialize, Serialize};
 
@@ -9,32 +9,29 @@ use serde::{Deserialize, Serialize};
     serialize = "Witness: Serialize, InputProof: Serialize",
     deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
 ))]
-pub struct FriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
+pub struct FriProof<F: Field, M: MultiMmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
-    pub query_proofs: Vec<QueryProof<F, M, InputProof>>,
+    pub query_proofs: Vec<QueryProof<F>>,
+    /// For each commit phase commitment, a proof of the openings of all queries in that round.
+    pub commit_phase_opening_proofs: Vec<M::MultiProof>,
+    /// A proof of the openings of the inputs at all queried indices.
+    pub input_proof: InputProof,
     pub final_poly: Vec<F>,
     pub pow_witness: Witness,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
-#[serde(bound(
-    serialize = "InputProof: Serialize",
-    deserialize = "InputProof: Deserialize<'de>",
-))]
-pub struct QueryProof<F: Field, M: Mmcs<F>, InputProof> {
-    pub input_proof: InputProof,
+#[serde(bound = "")]
+pub struct QueryProof<F: Field> {
     /// For each commit phase commitment, this contains openings of a commit phase codeword at the
-    /// queried location, along with an opening proof.
-    pub commit_phase_openings: Vec<CommitPhaseProofStep<F, M>>,
+    /// queried location.
+    pub commit_phase_openings: Vec<CommitPhaseProofStep<F>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct CommitPhaseProofStep<F: Field, M: Mmcs<F>> {
-    /// The opening of the commit phase codeword at the sibling location.
-    // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
-    // folding arities besides 2, meaning that there can be multiple siblings.
-    pub sibling_value: F,
-
-    pub opening_proof: M::Proof,
+pub struct CommitPhaseProofStep<F: Field> {
+    /// The openings of the commit phase codeword at the other points of the queried coset, in the
+    /// order they appear in the committed row.
+    pub sibling_values: Vec<F>,
 }
@@ -4,7 +4,7 @@ use core::iter;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_commit::MultiMmcs;
 use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
 use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::dense::RowMajorMatrix;
@@ -19,12 +19,12 @@ pub fn prove<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize) -> G::InputProof,
+    open_input: impl Fn(&[usize]) -> G::InputProof,
 ) -> FriProof<Challenge, M, Challenger::Witness, G::InputProof>
 where
     Val: Field,
     Challenge: ExtensionField<Val> + TwoAdicField,
-    M: Mmcs<Challenge>,
+    M: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
@@ -42,35 +42,48 @@ where
     if config.log_final_poly_len > 0 {
         assert!(log_min_height > config.log_final_poly_len + config.log_blowup);
     }
+    assert!(config.max_log_arity > 0, "FRI must fold by at least 2");
 
     let commit_phase_result = commit_phase(g, config, inputs, challenger);
 
     let pow_witness = challenger.grind(config.proof_of_work_bits);
 
-    let query_proofs = info_span!("query phase").in_scope(|| {
+    let indices =
         iter::repeat_with(|| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
             .take(config.num_queries)
-            .map(|index| QueryProof {
-                input_proof: open_input(index),
-                commit_phase_openings: answer_query(
-                    config,
-                    &commit_phase_result.data,
-                    index >> g.extra_query_index_bits(),
-                ),
-            })
-            .collect()
-    });
+            .collect_vec();
+
+    let (query_proofs, commit_phase_opening_proofs, input_proof) = info_span!("query phase")
+        .in_scope(|| {
+            let (query_proofs, commit_phase_opening_proofs) = answer_queries(
+                config,
+                &commit_phase_result.log_arities,
+                &commit_phase_result.data,
+                indices
+                    .iter()
+                    .map(|index| index >> g.extra_query_index_bits())
+                    .collect(),
+            );
+            (
+                query_proofs,
+                commit_phase_opening_proofs,
+                open_input(&indices),
+            )
+        });
 
     FriProof {
         commit_phase_commits: commit_phase_result.commits,
         query_proofs,
+        commit_phase_opening_proofs,
+        input_proof,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
     }
 }
 
-struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
+struct CommitPhaseResult<F: Field, M: MultiMmcs<F>> {
     commits: Vec<M::Commitment>,
+    log_arities: Vec<usize>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
     final_poly: Vec<F>,
 }
@@ -85,17 +98,23 @@ fn commit_phase<G, Val, Challenge, M, Challenger>(
 where
     Val: Field,
     Challenge: ExtensionField<Val> + TwoAdicField,
-    M: Mmcs<Challenge>,
+    M: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
     let mut inputs_iter = inputs.into_iter().peekable();
     let mut folded = inputs_iter.next().unwrap();
     let mut commits = vec![];
+    let mut log_arities = vec![];
     let mut data = vec![];
 
     while folded.len() > config.blowup() * config.final_poly_len() {
-        let leaves = RowMajorMatrix::new(folded, 2);
+        let log_arity = config.log_arity_of_round(
+            log2_strict_usize(folded.len()),
+            inputs_iter.peek().map(|v| log2_strict_usize(v.len())),
+        );
+        // Each row holds a coset of the current domain, of size `2^log_arity`, in bit-reversed order.
+        let leaves = RowMajorMatrix::new(folded, 1 << log_arity);
         let (commit, prover_data) = config.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
 
@@ -105,6 +124,7 @@ where
         folded = g.fold_matrix(beta, leaves.as_view());
 
         commits.push(commit);
+        log_arities.push(log_arity);
         data.push(prover_data);
 
         if let Some(v) = inputs_iter.next_if(|v| v.len() == folded.len()) {
@@ -141,38 +161,60 @@ where
 
     CommitPhaseResult {
         commits,
+        log_arities,
         data,
         final_poly,
     }
 }
 
-fn answer_query<F, M>(
+/// Open the commit phase codewords at the queried indices, each reduced to the domain of its round.
+///
+/// Returns the proof of each query, and for each round, a proof of the openings of all queries.
+fn answer_queries<F, M>(
     config: &FriConfig<M>,
+    log_arities: &[usize],
     commit_phase_commits: &[M::ProverData<RowMajorMatrix<F>>],
-    index: usize,
-) -> Vec<CommitPhaseProofStep<F, M>>
+    mut indices: Vec<usize>,
+) -> (Vec<QueryProof<F>>, Vec<M::MultiProof>)
 where
     F: Field,
-    M: Mmcs<F>,
+    M: MultiMmcs<F>,
 {
-    commit_phase_commits
-        .iter()
-        .enumerate()
-        .map(|(i, commit)| {
-            let index_i = index >> i;
-            let index_i_sibling = index_i ^ 1;
-            let index_pair = index_i >> 1;
-
-            let (mut opened_rows, opening_proof) = config.mmcs.open_batch(index_pair, commit);
-            assert_eq!(opened_rows.len(), 1);
-            let opened_row = opened_rows.pop().unwrap();
-            assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
-            let sibling_value = opened_row[index_i_sibling % 2];
-
-            CommitPhaseProofStep {
-                sibling_value,
-                opening_proof,
+    let mut query_proofs = vec![
+        QueryProof {
+            commit_phase_openings: vec![],
+        };
+        indices.len()
+    ];
+
+    let opening_proofs = izip!(log_arities, commit_phase_commits)
+        .map(|(&log_arity, commit)| {
+            let indices_in_coset = indices
+                .iter()
+                .map(|index| index % (1 << log_arity))
+                .collect_vec();
+            indices.iter_mut().for_each(|index| *index >>= log_arity);
+
+            let (opened_rows, opening_proof) = config.mmcs.open_multi_batch(&indices, commit);
+            for (query_proof, mut opened_rows, index_in_coset) in
+                izip!(&mut query_proofs, opened_rows, indices_in_coset)
+            {
+                assert_eq!(opened_rows.len(), 1);
+                let mut sibling_values = opened_rows.pop().unwrap();
+                assert_eq!(
+                    sibling_values.len(),
+                    1 << log_arity,
+                    "Committed data should be in cosets of the folding arity"
+                );
+                sibling_values.remove(index_in_coset);
+
+                query_proof
+                    .commit_phase_openings
+                    .push(CommitPhaseProofStep { sibling_values });
             }
+            opening_proof
         })
-        .collect()
+        .collect();
+
+    (query_proofs, opening_proofs)
 }
@@ -0,0 +1,332 @@
+//! Provable soundness of FRI-based STARKs, following the proximity gaps analysis of
+//! [BCIKS20](https://eprint.iacr.org/2020/654) as summarized in
+//! [Hab22](https://eprint.iacr.org/2022/1216).
+//!
+//! The soundness error is bounded by the sum of four terms:
+//! - the ALI error, the probability that batching the constraints with powers of a random `alpha`
+//!   cancels out the violated ones, for some codeword in the list around the trace commitment,
+//! - the DEEP error, the probability that the out-of-domain point `zeta` lets a prover claim values
+//!   consistent with the constraints for some codeword in the list around its commitment,
+//! - the commit phase error, the probability that batching or folding maps a far codeword close to
+//!   the code,
+//! - the query phase error, the probability that every query misses the disagreement with the
+//!   code, scaled down by the proof of work.
+
+use libm::{log2, pow, sqrt};
+use p3_field::Field;
+
+use crate::FriConfig;
+
+/// The largest Johnson bound parameter `m` considered. The commit phase error grows with `m^7`, so
+/// larger values never help for practical field sizes.
+const MAX_JOHNSON_PARAMETER: usize = 64;
+
+/// The largest number of queries considered by [`search_fri_parameters`].
+const MAX_NUM_QUERIES: usize = 1000;
+
+/// Properties of a STARK instance which, together with the FRI parameters, determine its
+/// provable soundness.
+#[derive(Clone, Copy, Debug)]
+pub struct SoundnessParams {
+    /// The number of bits of the base field's order, rounded down.
+    pub base_field_bits: usize,
+    /// The degree of the extension field from which challenges are drawn.
+    pub extension_degree: usize,
+    pub log_trace_height: usize,
+    /// The number of polynomials batched into the FRI instance.
+    pub num_polynomials: usize,
+    /// The number of the AIR's constraints, which are batched with powers of a single challenge.
+    pub num_constraints: usize,
+    /// The maximum degree of the AIR's constraints.
+    pub constraint_degree: usize,
+}
+
+impl SoundnessParams {
+    /// Parameters for an instance over the base field `F`, with challenges drawn from its extension
+    /// of degree `extension_degree`.
+    pub fn new<F: Field>(
+        extension_degree: usize,
+        log_trace_height: usize,
+        num_polynomials: usize,
+        num_constraints: usize,
+        constraint_degree: usize,
+    ) -> Self {
+        Self {
+            base_field_bits: F::bits() - 1,
+            extension_degree,
+            log_trace_height,
+            num_polynomials,
+            num_constraints,
+            constraint_degree,
+        }
+    }
+
+    const fn log_field_size(&self) -> usize {
+        self.base_field_bits * self.extension_degree
+    }
+}
+
+/// The proximity parameter regime in which soundness is analyzed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundnessRegime {
+    /// Proximity up to the unique decoding radius `(1 - rho) / 2`, where every word has at most one
+    /// close codeword.
+    UniqueDecoding,
+    /// Proximity up to the Johnson bound `1 - sqrt(rho)`, where lists of close codewords are
+    /// small but the proximity gaps bounds are weaker.
+    Johnson,
+}
+
+/// The parameters of a [`FriConfig`] which affect soundness, as selected by
+/// [`search_fri_parameters`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FriParameters {
+    pub log_blowup: usize,
+    pub log_final_poly_len: usize,
+    pub max_log_arity: usize,
+    pub num_queries: usize,
+    pub proof_of_work_bits: usize,
+}
+
+impl<M> FriConfig<M> {
+    /// Returns the provable soundness bits of this FRI instance, for a STARK described by `params`,
+    /// analyzed in `regime`.
+    ///
+    /// Unlike [`Self::conjectured_soundness_bits`], this accounts for the size of the field, the
+    /// trace length, the number of batched polynomials and the number and degree of constraints.
+    pub fn proven_soundness_bits(&self, params: &SoundnessParams, regime: SoundnessRegime) -> f64 {
+        let fri_parameters = FriParameters {
+            log_blowup: self.log_blowup,
+            log_final_poly_len: self.log_final_poly_len,
+            max_log_arity: self.max_log_arity,
+            num_queries: self.num_queries,
+            proof_of_work_bits: self.proof_of_work_bits,
+        };
+        proven_soundness_bits(params, regime, &fri_parameters)
+    }
+}
+
+/// Returns the provable soundness bits of FRI with parameters `fri`, for a STARK described by
+/// `params`, analyzed in `regime`.
+pub fn proven_soundness_bits(
+    params: &SoundnessParams,
+    regime: SoundnessRegime,
+    fri: &FriParameters,
+) -> f64 {
+    let error = match regime {
+        SoundnessRegime::UniqueDecoding => soundness_error(params, fri, None),
+        SoundnessRegime::Johnson => (3..=MAX_JOHNSON_PARAMETER)
+            .map(|m| soundness_error(params, fri, Some(m)))
+            .fold(f64::INFINITY, f64::min),
+    };
+    -log2(error.min(1.0))
+}
+
+/// Search for FRI parameters reaching `target_bits` of provable soundness in `regime`.
+///
+/// Among blowups up to `2^max_log_blowup` and proofs of work up to `max_proof_of_work_bits`, this
+/// picks the parameters with the fewest query bytes, estimated as the number of queries times the
+/// Merkle path length. Ties are broken in favour of smaller blowups, then of less proof of work,
+/// since both only cost the prover; so proof of work which doesn't save a query is not used.
+/// Returns `None` if no parameters reach the target, e.g. because the field is too small for the
+/// commit phase error.
+pub fn search_fri_parameters(
+    params: &SoundnessParams,
+    regime: SoundnessRegime,
+    target_bits: usize,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+    max_log_blowup: usize,
+    max_proof_of_work_bits: usize,
+) -> Option<FriParameters> {
+    (1..=max_log_blowup)
+        .flat_map(|log_blowup| {
+            (0..=max_proof_of_work_bits)
+                .map(move |proof_of_work_bits| (log_blowup, proof_of_work_bits))
+        })
+        .filter_map(|(log_blowup, proof_of_work_bits)| {
+            let with_queries = |num_queries| FriParameters {
+                log_blowup,
+                log_final_poly_len,
+                max_log_arity,
+                num_queries,
+                proof_of_work_bits,
+            };
+            // Soundness grows with the number of queries, so binary search for the fewest.
+            let reaches_target = |num_queries| {
+                proven_soundness_bits(params, regime, &with_queries(num_queries))
+                    >= target_bits as f64
+            };
+            if !reaches_target(MAX_NUM_QUERIES) {
+                return None;
+            }
+            let (mut lo, mut hi) = (0, MAX_NUM_QUERIES);
+            while lo < hi {
+                let mid = (lo + hi) / 2;
+                if reaches_target(mid) {
+                    hi = mid;
+                } else {
+                    lo = mid + 1;
+                }
+            }
+            Some(with_queries(lo))
+        })
+        .min_by_key(|p| {
+            (
+                p.num_queries * (params.log_trace_height + p.log_blowup),
+                p.log_blowup,
+                p.proof_of_work_bits,
+            )
+        })
+}
+
+/// The soundness error in the unique decoding regime if `johnson_parameter` is `None`, and
+/// otherwise in the Johnson regime with the given parameter `m >= 3`.
+fn soundness_error(
+    params: &SoundnessParams,
+    fri: &FriParameters,
+    johnson_parameter: Option<usize>,
+) -> f64 {
+    let field_size = pow(2.0, params.log_field_size() as f64);
+    let rate = pow(2.0, -(fri.log_blowup as f64));
+    let trace_height = pow(2.0, params.log_trace_height as f64);
+    let domain_size = trace_height * pow(2.0, fri.log_blowup as f64);
+    // Every round folds by the largest arity, except possibly the last one.
+    let log_reduction = params
+        .log_trace_height
+        .saturating_sub(fri.log_final_poly_len);
+    let arities = (0..log_reduction)
+        .step_by(fri.max_log_arity.max(1))
+        .map(|r| pow(2.0, fri.max_log_arity.min(log_reduction - r) as f64));
+    let (sum_arities, sum_fold_degrees) =
+        arities.fold((0.0, 0.0), |(sum, degrees), a| (sum + a, degrees + a - 1.0));
+    // The polynomials are batched with powers of a single challenge, a curve of degree
+    // `num_polynomials - 1`. The first fold is always at least a line.
+    let batching_degree = params.num_polynomials.saturating_sub(1).max(1) as f64;
+
+    let (list_size, commit_error, query_error) = match johnson_parameter {
+        None => {
+            // Folding by `a` combines the coset's parts along a curve of degree `a - 1`.
+            let commit_error = (batching_degree + sum_fold_degrees) * domain_size / field_size;
+            (1.0, commit_error, (1.0 + rate) / 2.0)
+        }
+        Some(m) => {
+            let m = m as f64;
+            let sqrt_rate = sqrt(rate);
+            // [BCIKS20] Theorem 8.3, with the first term scaled by the degree of the batching curve.
+            let commit_error = (batching_degree * pow(m + 0.5, 7.0) * domain_size * domain_size
+                / (3.0 * rate * sqrt_rate)
+                + (2.0 * m + 1.0) * (domain_size + 1.0) * sum_arities / sqrt_rate)
+                / field_size;
+            // The Johnson bound for proximity `1 - sqrt(rate) (1 + 1 / 2m)`.
+            (m / rate, commit_error, sqrt_rate * (1.0 + 1.0 / (2.0 * m)))
+        }
+    };
+
+    // A violated constraint survives the random linear combination of all constraints unless
+    // `alpha` is a root of a nonzero polynomial of degree `num_constraints - 1`, for one of the
+    // listed codewords.
+    let ali_error = list_size * params.num_constraints.saturating_sub(1) as f64 / field_size;
+    // Two distinct polynomials of degree at most `constraint_degree * trace_height` agree on at
+    // most that many points, and `zeta` must hit such a point for one of the listed codewords.
+    let deep_error =
+        list_size * params.constraint_degree as f64 * trace_height / (field_size - domain_size);
+    let query_error =
+        pow(query_error, fri.num_queries as f64) * pow(2.0, -(fri.proof_of_work_bits as f64));
+    ali_error + deep_error + commit_error + query_error
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_goldilocks::Goldilocks;
+
+    use super::*;
+
+    fn params(extension_degree: usize) -> SoundnessParams {
+        SoundnessParams::new::<BabyBear>(extension_degree, 20, 100, 50, 3)
+    }
+
+    #[test]
+    fn test_proven_below_conjectured() {
+        let config = FriConfig {
+            log_blowup: 1,
+            log_final_poly_len: 0,
+            max_log_arity: 1,
+            num_queries: 100,
+            proof_of_work_bits: 16,
+            mmcs: (),
+        };
+        let conjectured = config.conjectured_soundness_bits() as f64;
+        for regime in [SoundnessRegime::UniqueDecoding, SoundnessRegime::Johnson] {
+            let bits = config.proven_soundness_bits(&params(4), regime);
+            assert!(bits > 0.0 && bits < conjectured);
+        }
+    }
+
+    #[test]
+    fn test_soundness_monotone() {
+        let fri = |max_log_arity, num_queries| FriParameters {
+            log_blowup: 2,
+            log_final_poly_len: 0,
+            max_log_arity,
+            num_queries,
+            proof_of_work_bits: 0,
+        };
+        let bits = |p, fri| proven_soundness_bits(&p, SoundnessRegime::Johnson, &fri);
+        assert!(bits(params(4), fri(1, 60)) > bits(params(4), fri(1, 30)));
+        assert!(bits(params(5), fri(1, 60)) > bits(params(4), fri(1, 60)));
+
+        // Over a field of 2^124 elements, the commit phase error caps the soundness.
+        let capped = bits(params(4), fri(1, 1000));
+        assert!(capped < 124.0);
+        assert_eq!(capped, bits(params(4), fri(1, 2000)));
+        // Folding by larger arities loses a little soundness in the commit phase.
+        assert!(bits(params(4), fri(3, 1000)) < capped);
+        // So does batching more constraints.
+        let many_constraints = SoundnessParams {
+            num_constraints: 1 << 40,
+            ..params(4)
+        };
+        assert!(bits(many_constraints, fri(1, 1000)) < capped);
+    }
+
+    #[test]
+    fn test_search_fri_parameters() {
+        let params = SoundnessParams::new::<Goldilocks>(3, 20, 100, 50, 3);
+        for regime in [SoundnessRegime::UniqueDecoding, SoundnessRegime::Johnson] {
+            let found = search_fri_parameters(&params, regime, 100, 0, 2, 4, 16).unwrap();
+            let bits = |num_queries, proof_of_work_bits| {
+                let fri = FriParameters {
+                    num_queries,
+                    proof_of_work_bits,
+                    ..found
+                };
+                proven_soundness_bits(&params, regime, &fri)
+            };
+            let pow_bits = found.proof_of_work_bits;
+            assert_eq!(found.max_log_arity, 2);
+            assert!(pow_bits <= 16);
+            assert!(bits(found.num_queries, pow_bits) >= 100.0);
+            assert!(bits(found.num_queries - 1, pow_bits) < 100.0);
+            // Less proof of work would need more queries.
+            if pow_bits > 0 {
+                assert!(bits(found.num_queries, pow_bits - 1) < 100.0);
+            }
+        }
+
+        // With no proof of work allowed, more queries are needed.
+        let without_pow =
+            search_fri_parameters(&params, SoundnessRegime::Johnson, 100, 0, 2, 4, 0).unwrap();
+        let with_pow =
+            search_fri_parameters(&params, SoundnessRegime::Johnson, 100, 0, 2, 4, 16).unwrap();
+        assert_eq!(without_pow.proof_of_work_bits, 0);
+        assert!(with_pow.num_queries < without_pow.num_queries);
+
+        // Goldilocks' quadratic extension is too small for 128 bits in the Johnson regime.
+        let params = SoundnessParams::new::<Goldilocks>(2, 20, 100, 50, 3);
+        assert!(
+            search_fri_parameters(&params, SoundnessRegime::Johnson, 128, 0, 1, 4, 16).is_none()
+        );
+    }
+}
@@ -2,11 +2,12 @@ use alloc::collections::BTreeMap;
 use alloc::vec;
 use alloc::vec::Vec;
 use core::fmt::Debug;
+use core::iter;
 use core::marker::PhantomData;
 
 use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs};
+use p3_commit::{CommitError, Mmcs, MultiMmcs, OpenedValues, Pcs, PcsShape, ShapedPcs};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::coset::TwoAdicMultiplicativeCoset;
 use p3_field::{
@@ -15,7 +16,7 @@ use p3_field::{
 };
 use p3_interpolation::interpolate_coset;
 use p3_matrix::bitrev::{BitReversalPerm, BitReversedMatrixView, BitReversibleMatrix};
-use p3_matrix::dense::{DenseMatrix, RowMajorMatrix};
+use p3_matrix::dense::{DenseMatrix, DenseStorage, OwnedDenseStorage, RowMajorMatrix};
 use p3_matrix::{Dimensions, Matrix};
 use p3_maybe_rayon::prelude::*;
 use p3_util::linear_map::LinearMap;
@@ -25,17 +26,24 @@ use serde::{Deserialize, Serialize};
 use tracing::{info_span, instrument};
 
 use crate::verifier::{self, FriError};
-use crate::{FriConfig, FriGenericConfig, FriProof, prover};
+use crate::{FriConfig, FriGenericConfig, FriProof, fold_even_odd, prover};
 
+/// A PCS committing to the low-degree extensions of matrices with `InputMmcs`, and proving their
+/// openings with FRI.
+///
+/// The extensions are kept in `LdeStorage` until they're opened, e.g. `MmapStorage` from
+/// `p3_matrix::mmap` to keep them on disk for traces too large for memory.
 #[derive(Debug)]
-pub struct TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
-    dft: Dft,
+pub struct TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage = Vec<Val>> {
+    pub(crate) dft: Dft,
     mmcs: InputMmcs,
     fri: FriConfig<FriMmcs>,
-    _phantom: PhantomData<Val>,
+    _phantom: PhantomData<(Val, LdeStorage)>,
 }
 
-impl<Val, Dft, InputMmcs, FriMmcs> TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
+impl<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
+    TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
+{
     pub const fn new(dft: Dft, mmcs: InputMmcs, fri: FriConfig<FriMmcs>) -> Self {
         Self {
             dft,
@@ -46,11 +54,13 @@ impl<Val, Dft, InputMmcs, FriMmcs> TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
     }
 }
 
+/// The openings of a committed batch at every queried index.
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct BatchOpening<Val: Field, InputMmcs: Mmcs<Val>> {
-    pub opened_values: Vec<Vec<Val>>,
-    pub opening_proof: <InputMmcs as Mmcs<Val>>::Proof,
+pub struct BatchOpening<Val: Field, InputMmcs: MultiMmcs<Val>> {
+    /// For each query, the opened row of each matrix.
+    pub opened_values: Vec<Vec<Vec<Val>>>,
+    pub opening_proof: <InputMmcs as MultiMmcs<Val>>::MultiProof,
 }
 
 pub struct TwoAdicFriGenericConfig<InputProof, InputError>(
@@ -77,27 +87,44 @@ impl<F: TwoAdicField, InputProof, InputError: Debug> FriGenericConfig<F>
         beta: F,
         evals: impl Iterator<Item = F>,
     ) -> F {
-        let arity = 2;
-        let log_arity = 1;
-        let (e0, e1) = evals
-            .collect_tuple()
-            .expect("TwoAdicFriFolder only supports arity=2");
+        let mut evals = evals.collect_vec();
+        let arity = evals.len();
+        let log_arity = log2_strict_usize(arity);
+        // The row holds the evaluations over a coset `s H` of the subgroup `H` of order
+        // `2^log_arity`, in bit-reversed order.
         // If performance critical, make this API stateful to avoid this
-        // This is a bit more math than is necessary, but leaving it here
-        // in case we want higher arity in the future
         let subgroup_start = F::two_adic_generator(log_height + log_arity)
             .exp_u64(reverse_bits_len(index, log_height) as u64);
-        let mut xs = F::two_adic_generator(log_arity)
-            .shifted_powers(subgroup_start)
-            .take(arity)
-            .collect_vec();
-        reverse_slice_index_bits(&mut xs);
-        assert_eq!(log_arity, 1, "can only interpolate two points for now");
+
+        if log_arity == 1 {
+            let mut xs = F::two_adic_generator(log_arity)
+                .shifted_powers(subgroup_start)
+                .take(arity)
+                .collect_vec();
+            reverse_slice_index_bits(&mut xs);
+            // interpolate and evaluate at beta
+            let (e0, e1) = (evals[0], evals[1]);
+            return e0 + (beta - xs[0]) * (e1 - e0) / (xs[1] - xs[0]);
+        }
+
+        reverse_slice_index_bits(&mut evals);
         // interpolate and evaluate at beta
-        e0 + (beta - xs[0]) * (e1 - e0) / (xs[1] - xs[0])
+        interpolate_coset(&RowMajorMatrix::new_col(evals), subgroup_start, beta, None)[0]
     }
 
     fn fold_matrix<M: Matrix<F>>(&self, beta: F, m: M) -> Vec<F> {
+        if m.width() > 2 {
+            // Each row is a coset of size `2^k` in bit-reversed order, so folding the rows is the
+            // same as folding the flattened evaluations in half `k` times, with challenges
+            // `beta, beta^2, beta^4, ...`.
+            let log_arity = log2_strict_usize(m.width());
+            return iter::successors(Some(beta), |beta| Some(beta.square()))
+                .take(log_arity)
+                .fold(m.to_row_major_matrix().values, |evals, beta| {
+                    fold_even_odd(evals, beta)
+                });
+        }
+
         // We use the fact that
         //     p_e(x^2) = (p(x) + p(-x)) / 2
         //     p_o(x^2) = (p(x) - p(-x)) / (2 x)
@@ -131,48 +158,119 @@ impl<F: TwoAdicField, InputProof, InputError: Debug> FriGenericConfig<F>
     }
 }
 
-impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
-    for TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs>
+impl<Val, Dft, InputMmcs, FriMmcs, LdeStorage, Challenge, Challenger>
+    ShapedPcs<Challenge, Challenger> for TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
 where
     Val: TwoAdicField,
     Dft: TwoAdicSubgroupDft<Val>,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiMmcs<Val>,
+    LdeStorage: OwnedDenseStorage<Val> + 'static,
+    FriMmcs: MultiMmcs<Challenge>,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    Challenger:
+        FieldChallenger<Val> + CanObserve<FriMmcs::Commitment> + GrindingChallenger<Witness = Val>,
+{
+    fn shape(&self) -> PcsShape {
+        PcsShape {
+            input_mmcs: self.mmcs.shape(),
+            fold_mmcs: self.fri.mmcs.shape(),
+            log_blowup: self.fri.log_blowup,
+            log_final_poly_len: self.fri.log_final_poly_len,
+            max_log_arity: self.fri.max_log_arity,
+            num_queries: self.fri.num_queries,
+        }
+    }
+}
+
+impl<Val, Dft, InputMmcs, FriMmcs, LdeStorage, Challenge, Challenger> Pcs<Challenge, Challenger>
+    for TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs, LdeStorage>
+where
+    Val: TwoAdicField,
+    Dft: TwoAdicSubgroupDft<Val>,
+    InputMmcs: MultiMmcs<Val>,
+    LdeStorage: OwnedDenseStorage<Val> + 'static,
+    FriMmcs: MultiMmcs<Challenge>,
     Challenge: TwoAdicField + ExtensionField<Val>,
     Challenger:
         FieldChallenger<Val> + CanObserve<FriMmcs::Commitment> + GrindingChallenger<Witness = Val>,
 {
     type Domain = TwoAdicMultiplicativeCoset<Val>;
     type Commitment = InputMmcs::Commitment;
-    type ProverData = InputMmcs::ProverData<RowMajorMatrix<Val>>;
+    type ProverData = InputMmcs::ProverData<DenseMatrix<Val, LdeStorage>>;
     type EvaluationsOnDomain<'a> = BitReversedMatrixView<DenseMatrix<Val, &'a [Val]>>;
     type Proof = FriProof<Challenge, FriMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>;
     type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
 
+    fn parameters(&self) -> Vec<usize> {
+        self.fri.parameters()
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         // This panics if (and only if) `degree` is not a power of 2 or `degree`
         // > `1 << Val::TWO_ADICITY`.
         TwoAdicMultiplicativeCoset::new(Val::ONE, log2_strict_usize(degree)).unwrap()
     }
 
-    fn commit(
+    fn try_commit(
         &self,
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
-    ) -> (Self::Commitment, Self::ProverData) {
+    ) -> Result<(Self::Commitment, Self::ProverData), CommitError> {
+        for (index, (domain, evals)) in evaluations.iter().enumerate() {
+            if domain.log_size() + self.fri.log_blowup > Val::TWO_ADICITY {
+                return Err(CommitError::UnsupportedDomainSize {
+                    index,
+                    log_size: domain.log_size(),
+                });
+            }
+            if domain.size() != evals.height() {
+                return Err(CommitError::HeightMismatch {
+                    index,
+                    domain_size: domain.size(),
+                    height: evals.height(),
+                });
+            }
+        }
+
         let ldes: Vec<_> = evaluations
             .into_iter()
             .map(|(domain, evals)| {
-                assert_eq!(domain.size(), evals.height());
                 let shift = Val::GENERATOR / domain.shift();
-                // Commit to the bit-reversed LDE.
-                self.dft
-                    .coset_lde_batch(evals, self.fri.log_blowup, shift)
-                    .bit_reverse_rows()
-                    .to_row_major_matrix()
+                if LdeStorage::ON_HEAP {
+                    // Commit to the bit-reversed LDE.
+                    let lde = self
+                        .dft
+                        .coset_lde_batch(evals, self.fri.log_blowup, shift)
+                        .bit_reverse_rows()
+                        .to_row_major_matrix();
+                    return DenseMatrix::new(LdeStorage::from_vec(lde.values), lde.width);
+                }
+
+                let width = evals.width();
+                let log_height = log2_strict_usize(evals.height());
+                let coset_len = width << log_height;
+                let g_lde = Val::two_adic_generator(log_height + self.fri.log_blowup);
+                let coeffs = self.dft.idft_batch(evals);
+
+                // The bit-reversed LDE's rows are the bit-reversed evaluations over each coset of
+                // the original domain in turn. Computing them one coset at a time and writing each
+                // into the LDE's storage keeps only one coset on the heap, rather than the whole
+                // LDE.
+                let mut lde = LdeStorage::from_elem(coset_len << self.fri.log_blowup, Val::ZERO);
+                for (i, coset) in lde.borrow_mut().chunks_exact_mut(coset_len).enumerate() {
+                    let coset_shift =
+                        shift * g_lde.exp_u64(reverse_bits_len(i, self.fri.log_blowup) as u64);
+                    let coset_evals = self
+                        .dft
+                        .coset_dft_batch(coeffs.clone(), coset_shift)
+                        .bit_reverse_rows()
+                        .to_row_major_matrix();
+                    coset.copy_from_slice(&coset_evals.values);
+                }
+                DenseMatrix::new(lde, width)
             })
             .collect();
 
-        self.mmcs.commit(ldes)
+        Ok(self.mmcs.commit(ldes))
     }
 
     fn get_evaluations_on_domain<'a>(
@@ -201,177 +299,18 @@ where
         )>,
         challenger: &mut Challenger,
     ) -> (OpenedValues<Challenge>, Self::Proof) {
-        /*
-
-        A quick rundown of the optimizations in this function:
-        We are trying to compute sum_i alpha^i * (p(X) - y)/(X - z),
-        for each z an opening point, y = p(z). Each p(X) is given as evaluations in bit-reversed order
-        in the columns of the matrices. y is computed by barycentric interpolation.
-        X and p(X) are in the base field; alpha, y and z are in the extension.
-        The primary goal is to minimize extension multiplications.
-
-        - Instead of computing all alpha^i, we just compute alpha^i for i up to the largest width
-        of a matrix, then multiply by an "alpha offset" when accumulating.
-              a^0 x0 + a^1 x1 + a^2 x2 + a^3 x3 + ...
-            = a^0 ( a^0 x0 + a^1 x1 ) + a^2 ( a^0 x2 + a^1 x3 ) + ...
-            (see `alpha_pows`, `alpha_pow_offset`, `num_reduced`)
-
-        - For each unique point z, we precompute 1/(X-z) for the largest subgroup opened at this point.
-        Since we compute it in bit-reversed order, smaller subgroups can simply truncate the vector.
-            (see `inv_denoms`)
-
-        - Then, for each matrix (with columns p_i) and opening point z, we want:
-            for each row (corresponding to subgroup element X):
-                reduced[X] += alpha_offset * sum_i [ alpha^i * inv_denom[X] * (p_i[X] - y[i]) ]
-
-            We can factor out inv_denom, and expand what's left:
-                reduced[X] += alpha_offset * inv_denom[X] * sum_i [ alpha^i * p_i[X] - alpha^i * y[i] ]
-
-            And separate the sum:
-                reduced[X] += alpha_offset * inv_denom[X] * [ sum_i [ alpha^i * p_i[X] ] - sum_i [ alpha^i * y[i] ] ]
-
-            And now the last sum doesn't depend on X, so we can precompute that for the matrix, too.
-            So the hot loop (that depends on both X and i) is just:
-                sum_i [ alpha^i * p_i[X] ]
-
-            with alpha^i an extension, p_i[X] a base
-
-        */
-
-        let mats_and_points = rounds
-            .iter()
-            .map(|(data, points)| {
-                let mats = self
-                    .mmcs
-                    .get_matrices(data)
-                    .into_iter()
-                    .map(|m| m.as_view())
-                    .collect_vec();
-                debug_assert_eq!(
-                    mats.len(),
-                    points.len(),
-                    "each matrix should have a corresponding set of evaluation points"
-                );
-                (mats, points)
-            })
-            .collect_vec();
-
-        // Find the maximum height of a matrix in the batch.
-        let global_max_height = mats_and_points
-            .iter()
-            .flat_map(|(mats, _)| mats.iter().map(|m| m.height()))
-            .max()
-            .expect("No Matrices Supplied?");
-        let log_global_max_height = log2_strict_usize(global_max_height);
-
-        // For each unique opening point z, we will find the largest degree bound
-        // for that point, and precompute 1/(z - X) for the largest subgroup (in bitrev order).
-        let inv_denoms = compute_inverse_denominators(&mats_and_points, Val::GENERATOR);
-
-        // Evaluate coset representations and write openings to the challenger
-        let all_opened_values = mats_and_points
-            .iter()
-            .map(|(mats, points)| {
-                izip!(mats.iter(), points.iter())
-                    .map(|(mat, points_for_mat)| {
-                        points_for_mat
-                            .iter()
-                            .map(|&point| {
-                                let _guard =
-                                    info_span!("evaluate matrix", dims = %mat.dimensions())
-                                        .entered();
-
-                                // Use Barycentric interpolation to evaluate the matrix at the given point.
-                                let ys =
-                                    info_span!("compute opened values with Lagrange interpolation")
-                                        .in_scope(|| {
-                                            let h = mat.height() >> self.fri.log_blowup;
-                                            let (low_coset, _) = mat.split_rows(h);
-                                            let mut inv_denoms =
-                                                inv_denoms.get(&point).unwrap()[..h].to_vec();
-                                            reverse_slice_index_bits(&mut inv_denoms);
-                                            interpolate_coset(
-                                                &BitReversalPerm::new_view(low_coset),
-                                                Val::GENERATOR,
-                                                point,
-                                                Some(&inv_denoms),
-                                            )
-                                        });
-                                ys.iter()
-                                    .for_each(|&y| challenger.observe_algebra_element(y));
-                                ys
-                            })
-                            .collect_vec()
-                    })
-                    .collect_vec()
-            })
-            .collect_vec();
-
-        // Batch combination challenge
-        let alpha: Challenge = challenger.sample_algebra_element();
-
-        let mut num_reduced = [0; 32];
-        let mut reduced_openings: [_; 32] = core::array::from_fn(|_| None);
-
-        for ((mats, points), openings_for_round) in
-            mats_and_points.iter().zip(all_opened_values.iter())
-        {
-            for (mat, points_for_mat, openings_for_mat) in
-                izip!(mats.iter(), points.iter(), openings_for_round.iter())
-            {
-                let _guard =
-                    info_span!("reduce matrix quotient", dims = %mat.dimensions()).entered();
-
-                let log_height = log2_strict_usize(mat.height());
-                let reduced_opening_for_log_height = reduced_openings[log_height]
-                    .get_or_insert_with(|| vec![Challenge::ZERO; mat.height()]);
-                debug_assert_eq!(reduced_opening_for_log_height.len(), mat.height());
-
-                let mat_compressed = info_span!("compress mat")
-                    .in_scope(|| mat.dot_ext_powers(alpha).collect::<Vec<_>>());
-
-                for (&point, openings) in points_for_mat.iter().zip(openings_for_mat) {
-                    let alpha_pow_offset = alpha.exp_u64(num_reduced[log_height] as u64);
-                    let reduced_openings: Challenge =
-                        dot_product(alpha.powers(), openings.iter().copied());
-
-                    info_span!("reduce rows").in_scope(|| {
-                        mat_compressed
-                            .par_iter()
-                            .zip(reduced_opening_for_log_height.par_iter_mut())
-                            // This might be longer, but zip will truncate to smaller subgroup
-                            // (which is ok because it's bitrev)
-                            .zip(inv_denoms.get(&point).unwrap().par_iter())
-                            .for_each(|((&reduced_row, ro), &inv_denom)| {
-                                *ro +=
-                                    alpha_pow_offset * (reduced_openings - reduced_row) * inv_denom
-                            });
-                    });
-
-                    num_reduced[log_height] += mat.width();
-                }
-            }
-        }
+        let (all_opened_values, fri_input) =
+            open_and_reduce(&self.mmcs, self.fri.log_blowup, &rounds, challenger);
 
-        let fri_input = reduced_openings.into_iter().rev().flatten().collect_vec();
+        let log_global_max_height =
+            log2_strict_usize(fri_input.first().expect("No Matrices Supplied?").len());
+        let data = rounds.iter().map(|(data, _)| *data).collect_vec();
 
         let g: TwoAdicFriGenericConfigForMmcs<Val, InputMmcs> =
             TwoAdicFriGenericConfig(PhantomData);
 
-        let fri_proof = prover::prove(&g, &self.fri, fri_input, challenger, |index| {
-            rounds
-                .iter()
-                .map(|(data, _)| {
-                    let log_max_height = log2_strict_usize(self.mmcs.get_max_height(data));
-                    let bits_reduced = log_global_max_height - log_max_height;
-                    let reduced_index = index >> bits_reduced;
-                    let (opened_values, opening_proof) = self.mmcs.open_batch(reduced_index, data);
-                    BatchOpening {
-                        opened_values,
-                        opening_proof,
-                    }
-                })
-                .collect()
+        let fri_proof = prover::prove(&g, &self.fri, fri_input, challenger, |indices| {
+            open_input(&self.mmcs, log_global_max_height, &data, indices)
         });
 
         (all_opened_values, fri_proof)
@@ -398,6 +337,15 @@ where
         proof: &Self::Proof,
         challenger: &mut Challenger,
     ) -> Result<(), Self::Error> {
+        // Each matrix's width is known only from its claimed evaluations.
+        if rounds
+            .iter()
+            .flat_map(|(_, mats)| mats)
+            .any(|(_, points_and_values)| claimed_width(points_and_values).is_none())
+        {
+            return Err(FriError::InvalidProofShape);
+        }
+
         // Write evaluations to challenger
         for (_, round) in &rounds {
             for (_, mat) in round {
@@ -412,103 +360,396 @@ where
         // Batch combination challenge
         let alpha: Challenge = challenger.sample_algebra_element();
 
-        let log_global_max_height =
-            proof.commit_phase_commits.len() + self.fri.log_blowup + self.fri.log_final_poly_len;
+        // FRI checks that its folding rounds start from this height.
+        let log_global_max_height = log_global_max_height(&rounds, self.fri.log_blowup);
 
         let g: TwoAdicFriGenericConfigForMmcs<Val, InputMmcs> =
             TwoAdicFriGenericConfig(PhantomData);
 
-        verifier::verify(&g, &self.fri, proof, challenger, |index, input_proof| {
-            // TODO: separate this out into functions
+        verifier::verify(
+            &g,
+            &self.fri,
+            proof,
+            log_global_max_height,
+            challenger,
+            |indices, input_proof| {
+                let mut reduced_openings = verify_and_reduce_input(
+                    &self.mmcs,
+                    self.fri.log_blowup,
+                    &rounds,
+                    alpha,
+                    log_global_max_height,
+                    indices,
+                    input_proof,
+                )?;
+
+                // `reduced_openings` would have a log_height = log_blowup entry only if there was a
+                // trace matrix of height 1. In this case the reduced opening can be skipped as it will
+                // not be checked against any commit phase commit.
+                for ro_for_query in &mut reduced_openings {
+                    if let Some(&(log_height, ro)) = ro_for_query.last()
+                        && log_height == self.fri.log_blowup
+                    {
+                        assert!(ro.is_zero());
+                        ro_for_query.pop();
+                    }
+                }
 
-            // log_height -> (alpha_pow, reduced_opening)
-            let mut reduced_openings = BTreeMap::<usize, (Challenge, Challenge)>::new();
+                Ok(reduced_openings)
+            },
+        )?;
 
-            for (batch_opening, (batch_commit, mats)) in
-                zip_eq(input_proof, &rounds, FriError::InvalidProofShape)?
-            {
-                let batch_heights = mats
-                    .iter()
-                    .map(|(domain, _)| domain.size() << self.fri.log_blowup)
-                    .collect_vec();
-                let batch_dims = batch_heights
+        Ok(())
+    }
+}
+
+/// The claims of a batch of commitments, as passed to [`Pcs::verify`]: for each round, its
+/// commitment, and for each matrix, its domain and its values at each opened point.
+pub type InputClaims<Val, Challenge, Commitment> = [(
+    Commitment,
+    Vec<(
+        TwoAdicMultiplicativeCoset<Val>,
+        Vec<(Challenge, Vec<Challenge>)>,
+    )>,
+)];
+
+/// Open the matrices of each round at their points, observing the opened values, and batch them
+/// into DEEP quotients with a challenge `alpha` sampled afterwards.
+///
+/// Returns the opened values, and for each height of the committed matrices, in descending order,
+/// the evaluations over the LDE domain of that height, in bit-reversed order, of the sum of
+/// `alpha^i (p_i(X) - p_i(z)) / (X - z)` over the columns `p_i` of the matrices of that height and
+/// their points `z`.
+#[allow(clippy::type_complexity)]
+pub fn open_and_reduce<Val, Challenge, InputMmcs, LdeStorage, Challenger>(
+    mmcs: &InputMmcs,
+    log_blowup: usize,
+    rounds: &[(
+        &InputMmcs::ProverData<DenseMatrix<Val, LdeStorage>>,
+        Vec<Vec<Challenge>>,
+    )],
+    challenger: &mut Challenger,
+) -> (OpenedValues<Challenge>, Vec<Vec<Challenge>>)
+where
+    Val: TwoAdicField,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    InputMmcs: MultiMmcs<Val>,
+    LdeStorage: DenseStorage<Val>,
+    Challenger: FieldChallenger<Val>,
+{
+    /*
+
+    A quick rundown of the optimizations in this function:
+    We are trying to compute sum_i alpha^i * (p(X) - y)/(X - z),
+    for each z an opening point, y = p(z). Each p(X) is given as evaluations in bit-reversed order
+    in the columns of the matrices. y is computed by barycentric interpolation.
+    X and p(X) are in the base field; alpha, y and z are in the extension.
+    The primary goal is to minimize extension multiplications.
+
+    - Instead of computing all alpha^i, we just compute alpha^i for i up to the largest width
+    of a matrix, then multiply by an "alpha offset" when accumulating.
+          a^0 x0 + a^1 x1 + a^2 x2 + a^3 x3 + ...
+        = a^0 ( a^0 x0 + a^1 x1 ) + a^2 ( a^0 x2 + a^1 x3 ) + ...
+        (see `alpha_pows`, `alpha_pow_offset`, `num_reduced`)
+
+    - For each unique point z, we precompute 1/(X-z) for the largest subgroup opened at this point.
+    Since we compute it in bit-reversed order, smaller subgroups can simply truncate the vector.
+        (see `inv_denoms`)
+
+    - Then, for each matrix (with columns p_i) and opening point z, we want:
+        for each row (corresponding to subgroup element X):
+            reduced[X] += alpha_offset * sum_i [ alpha^i * inv_denom[X] * (p_i[X] - y[i]) ]
+
+        We can factor out inv_denom, and expand what's left:
+            reduced[X] += alpha_offset * inv_denom[X] * sum_i [ alpha^i * p_i[X] - alpha^i * y[i] ]
+
+        And separate the sum:
+            reduced[X] += alpha_offset * inv_denom[X] * [ sum_i [ alpha^i * p_i[X] ] - sum_i [ alpha^i * y[i] ] ]
+
+        And now the last sum doesn't depend on X, so we can precompute that for the matrix, too.
+        So the hot loop (that depends on both X and i) is just:
+            sum_i [ alpha^i * p_i[X] ]
+
+        with alpha^i an extension, p_i[X] a base
+
+    */
+
+    let mats_and_points = rounds
+        .iter()
+        .map(|(data, points)| {
+            let mats = mmcs
+                .get_matrices(data)
+                .into_iter()
+                .map(|m| m.as_view())
+                .collect_vec();
+            debug_assert_eq!(
+                mats.len(),
+                points.len(),
+                "each matrix should have a corresponding set of evaluation points"
+            );
+            assert!(
+                points
                     .iter()
-                    // TODO: MMCS doesn't really need width; we put 0 for now.
-                    .map(|&height| Dimensions { width: 0, height })
-                    .collect_vec();
-
-                if let Some(batch_max_height) = batch_heights.iter().max() {
-                    let log_batch_max_height = log2_strict_usize(*batch_max_height);
-                    let bits_reduced = log_global_max_height - log_batch_max_height;
-                    let reduced_index = index >> bits_reduced;
-
-                    self.mmcs.verify_batch(
-                        batch_commit,
-                        &batch_dims,
-                        reduced_index,
-                        &batch_opening.opened_values,
-                        &batch_opening.opening_proof,
-                    )
-                } else {
-                    // Empty batch?
-                    self.mmcs.verify_batch(
-                        batch_commit,
-                        &[],
-                        0,
-                        &batch_opening.opened_values,
-                        &batch_opening.opening_proof,
-                    )
-                }
-                .map_err(FriError::InputError)?;
-
-                for (mat_opening, (mat_domain, mat_points_and_values)) in zip_eq(
-                    &batch_opening.opened_values,
-                    mats,
-                    FriError::InvalidProofShape,
-                )? {
-                    let log_height = log2_strict_usize(mat_domain.size()) + self.fri.log_blowup;
-
-                    let bits_reduced = log_global_max_height - log_height;
-                    let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
-
-                    // todo: this can be nicer with domain methods?
-
-                    let x = Val::GENERATOR
-                        * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
-
-                    let (alpha_pow, ro) = reduced_openings
-                        .entry(log_height)
-                        .or_insert((Challenge::ONE, Challenge::ZERO));
-
-                    for (z, ps_at_z) in mat_points_and_values {
-                        for (&p_at_x, &p_at_z) in
-                            zip_eq(mat_opening, ps_at_z, FriError::InvalidProofShape)?
-                        {
-                            let quotient = (-p_at_z + p_at_x) / (-*z + x);
-                            *ro += *alpha_pow * quotient;
-                            *alpha_pow *= alpha;
-                        }
-                    }
-                }
+                    .all(|points_for_mat| !points_for_mat.is_empty()),
+                "each matrix must be opened at one or more points"
+            );
+            (mats, points)
+        })
+        .collect_vec();
+
+    // For each unique opening point z, we will find the largest degree bound
+    // for that point, and precompute 1/(z - X) for the largest subgroup (in bitrev order).
+    let inv_denoms = compute_inverse_denominators(&mats_and_points, Val::GENERATOR);
+
+    // Evaluate coset representations and write openings to the challenger
+    let all_opened_values = mats_and_points
+        .iter()
+        .map(|(mats, points)| {
+            izip!(mats.iter(), points.iter())
+                .map(|(mat, points_for_mat)| {
+                    points_for_mat
+                        .iter()
+                        .map(|&point| {
+                            let _guard =
+                                info_span!("evaluate matrix", dims = %mat.dimensions()).entered();
+
+                            // Use Barycentric interpolation to evaluate the matrix at the given point.
+                            let ys =
+                                info_span!("compute opened values with Lagrange interpolation")
+                                    .in_scope(|| {
+                                        let h = mat.height() >> log_blowup;
+                                        let (low_coset, _) = mat.split_rows(h);
+                                        let mut inv_denoms =
+                                            inv_denoms.get(&point).unwrap()[..h].to_vec();
+                                        reverse_slice_index_bits(&mut inv_denoms);
+                                        interpolate_coset(
+                                            &BitReversalPerm::new_view(low_coset),
+                                            Val::GENERATOR,
+                                            point,
+                                            Some(&inv_denoms),
+                                        )
+                                    });
+                            ys.iter()
+                                .for_each(|&y| challenger.observe_algebra_element(y));
+                            ys
+                        })
+                        .collect_vec()
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    // Batch combination challenge
+    let alpha: Challenge = challenger.sample_algebra_element();
+
+    let mut num_reduced = [0; 32];
+    let mut reduced_openings: [_; 32] = core::array::from_fn(|_| None);
+
+    for ((mats, points), openings_for_round) in mats_and_points.iter().zip(all_opened_values.iter())
+    {
+        for (mat, points_for_mat, openings_for_mat) in
+            izip!(mats.iter(), points.iter(), openings_for_round.iter())
+        {
+            let _guard = info_span!("reduce matrix quotient", dims = %mat.dimensions()).entered();
+
+            let log_height = log2_strict_usize(mat.height());
+            let reduced_opening_for_log_height = reduced_openings[log_height]
+                .get_or_insert_with(|| vec![Challenge::ZERO; mat.height()]);
+            debug_assert_eq!(reduced_opening_for_log_height.len(), mat.height());
+
+            let mat_compressed = info_span!("compress mat")
+                .in_scope(|| mat.dot_ext_powers(alpha).collect::<Vec<_>>());
+
+            for (&point, openings) in points_for_mat.iter().zip(openings_for_mat) {
+                let alpha_pow_offset = alpha.exp_u64(num_reduced[log_height] as u64);
+                let reduced_openings: Challenge =
+                    dot_product(alpha.powers(), openings.iter().copied());
+
+                info_span!("reduce rows").in_scope(|| {
+                    mat_compressed
+                        .par_iter()
+                        .zip(reduced_opening_for_log_height.par_iter_mut())
+                        // This might be longer, but zip will truncate to smaller subgroup
+                        // (which is ok because it's bitrev)
+                        .zip(inv_denoms.get(&point).unwrap().par_iter())
+                        .for_each(|((&reduced_row, ro), &inv_denom)| {
+                            *ro += alpha_pow_offset * (reduced_openings - reduced_row) * inv_denom
+                        });
+                });
+
+                num_reduced[log_height] += mat.width();
+            }
+        }
+    }
+
+    let reduced_openings = reduced_openings.into_iter().rev().flatten().collect_vec();
+    (all_opened_values, reduced_openings)
+}
+
+/// Open each round's batch at each of `indices`, indices into the tallest LDE domain, of height
+/// `2^log_global_max_height`.
+pub fn open_input<Val, InputMmcs, LdeStorage>(
+    mmcs: &InputMmcs,
+    log_global_max_height: usize,
+    data: &[&InputMmcs::ProverData<DenseMatrix<Val, LdeStorage>>],
+    indices: &[usize],
+) -> Vec<BatchOpening<Val, InputMmcs>>
+where
+    Val: Field,
+    InputMmcs: MultiMmcs<Val>,
+    LdeStorage: DenseStorage<Val>,
+{
+    data.iter()
+        .map(|data| {
+            let log_max_height = log2_strict_usize(mmcs.get_max_height(data));
+            let bits_reduced = log_global_max_height - log_max_height;
+            let reduced_indices = indices
+                .iter()
+                .map(|index| index >> bits_reduced)
+                .collect_vec();
+            let (opened_values, opening_proof) = mmcs.open_multi_batch(&reduced_indices, data);
+            BatchOpening {
+                opened_values,
+                opening_proof,
             }
+        })
+        .collect()
+}
+
+/// The log of the height of the tallest LDE domain of the matrices in `rounds`.
+pub fn log_global_max_height<Val: TwoAdicField, Challenge, Commitment>(
+    rounds: &InputClaims<Val, Challenge, Commitment>,
+    log_blowup: usize,
+) -> usize {
+    rounds
+        .iter()
+        .flat_map(|(_, mats)| mats.iter().map(|(domain, _)| domain.log_size()))
+        .max()
+        .map_or(0, |log_size| log_size + log_blowup)
+}
+
+/// The width of a matrix, as known to the verifier from the number of its claimed evaluations at
+/// each point.
+///
+/// Returns `None` if the matrix is opened at no points, so that the verifier doesn't know its
+/// width, or if the points claim different numbers of evaluations.
+pub fn claimed_width<Challenge>(
+    points_and_values: &[(Challenge, Vec<Challenge>)],
+) -> Option<usize> {
+    let (_, first_values) = points_and_values.first()?;
+    points_and_values
+        .iter()
+        .all(|(_, values)| values.len() == first_values.len())
+        .then_some(first_values.len())
+}
+
+/// Verify the openings of each round's batch at each of `indices` against its commitment, and
+/// reduce them to the values at each index of the DEEP quotients computed by [`open_and_reduce`].
+///
+/// Returns, for each index, the reduced openings of every height, in descending order.
+#[allow(clippy::type_complexity)]
+pub fn verify_and_reduce_input<Val, Challenge, InputMmcs, CommitMmcsErr>(
+    mmcs: &InputMmcs,
+    log_blowup: usize,
+    rounds: &InputClaims<Val, Challenge, InputMmcs::Commitment>,
+    alpha: Challenge,
+    log_global_max_height: usize,
+    indices: &[usize],
+    input_proof: &[BatchOpening<Val, InputMmcs>],
+) -> Result<Vec<Vec<(usize, Challenge)>>, FriError<CommitMmcsErr, InputMmcs::Error>>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val>,
+    InputMmcs: MultiMmcs<Val>,
+{
+    // For each index, log_height -> (alpha_pow, reduced_opening)
+    let mut reduced_openings =
+        vec![BTreeMap::<usize, (Challenge, Challenge)>::new(); indices.len()];
+
+    for (batch_opening, (batch_commit, mats)) in
+        zip_eq(input_proof, rounds, FriError::InvalidProofShape)?
+    {
+        if batch_opening.opened_values.len() != indices.len() {
+            return Err(FriError::InvalidProofShape);
+        }
+
+        let batch_dims = mats
+            .iter()
+            .map(|(domain, points_and_values)| {
+                Some(Dimensions {
+                    width: claimed_width(points_and_values)?,
+                    height: domain.size() << log_blowup,
+                })
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or(FriError::InvalidProofShape)?;
+
+        // An empty batch has no height, and fails verification.
+        let bits_reduced = batch_dims
+            .iter()
+            .map(|dims| dims.height)
+            .max()
+            .map_or(0, |batch_max_height| {
+                log_global_max_height - log2_strict_usize(batch_max_height)
+            });
+        let reduced_indices = indices
+            .iter()
+            .map(|index| index >> bits_reduced)
+            .collect_vec();
+
+        mmcs.verify_multi_batch(
+            batch_commit,
+            &batch_dims,
+            &reduced_indices,
+            &batch_opening.opened_values,
+            &batch_opening.opening_proof,
+        )
+        .map_err(FriError::InputError)?;
+
+        for (&index, opened_values, reduced_openings) in
+            izip!(indices, &batch_opening.opened_values, &mut reduced_openings)
+        {
+            for (mat_opening, (mat_domain, mat_points_and_values)) in
+                zip_eq(opened_values, mats, FriError::InvalidProofShape)?
+            {
+                let log_height = log2_strict_usize(mat_domain.size()) + log_blowup;
 
-            // `reduced_openings` would have a log_height = log_blowup entry only if there was a
-            // trace matrix of height 1. In this case the reduced opening can be skipped as it will
-            // not be checked against any commit phase commit.
-            if let Some((_alpha_pow, ro)) = reduced_openings.remove(&self.fri.log_blowup) {
-                assert!(ro.is_zero());
+                let bits_reduced = log_global_max_height - log_height;
+                let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
+
+                // todo: this can be nicer with domain methods?
+
+                let x = Val::GENERATOR
+                    * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
+
+                let (alpha_pow, ro) = reduced_openings
+                    .entry(log_height)
+                    .or_insert((Challenge::ONE, Challenge::ZERO));
+
+                for (z, ps_at_z) in mat_points_and_values {
+                    for (&p_at_x, &p_at_z) in
+                        zip_eq(mat_opening, ps_at_z, FriError::InvalidProofShape)?
+                    {
+                        let quotient = (-p_at_z + p_at_x) / (-*z + x);
+                        *ro += *alpha_pow * quotient;
+                        *alpha_pow *= alpha;
+                    }
+                }
             }
+        }
+    }
 
-            // Return reduced openings descending by log_height.
-            Ok(reduced_openings
+    // Return reduced openings descending by log_height.
+    Ok(reduced_openings
+        .into_iter()
+        .map(|reduced_openings| {
+            reduced_openings
                 .into_iter()
                 .rev()
                 .map(|(log_height, (_alpha_pow, ro))| (log_height, ro))
-                .collect())
-        })?;
-
-        Ok(())
-    }
+                .collect()
+        })
+        .collect())
 }
 
 #[instrument(skip_all)]
@@ -1,13 +1,13 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use itertools::Itertools;
+use itertools::{Itertools, izip};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_commit::MultiMmcs;
 use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::Dimensions;
-use p3_util::reverse_bits_len;
 use p3_util::zip_eq::zip_eq;
+use p3_util::{log2_strict_usize, reverse_bits_len};
 
 use crate::{CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof};
 
@@ -20,20 +20,28 @@ pub enum FriError<CommitMmcsErr, InputError> {
     InvalidPowWitness,
 }
 
+/// Verify a FRI proof that the inputs opened by `open_input`, the tallest of height
+/// `2^log_max_height`, are close to low-degree polynomials.
+///
+/// The arity of each folding round is determined by `config` and the heights of the inputs, as
+/// given by [`FriConfig::log_arity_of_round`]; every query must fold by the same arities, which
+/// must fold from `2^log_max_height` down to the final domain. The arities aren't sent in the
+/// proof.
 pub fn verify<G, Val, Challenge, M, Challenger>(
     g: &G,
     config: &FriConfig<M>,
     proof: &FriProof<Challenge, M, Challenger::Witness, G::InputProof>,
+    log_max_height: usize,
     challenger: &mut Challenger,
     open_input: impl Fn(
-        usize,
+        &[usize],
         &G::InputProof,
-    ) -> Result<Vec<(usize, Challenge)>, FriError<M::Error, G::InputError>>,
+    ) -> Result<Vec<Vec<(usize, Challenge)>>, FriError<M::Error, G::InputError>>,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
     Val: Field,
     Challenge: ExtensionField<Val> + TwoAdicField,
-    M: Mmcs<Challenge>,
+    M: MultiMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
@@ -52,7 +60,9 @@ where
         .iter()
         .for_each(|x| challenger.observe_algebra_element(*x));
 
-    if proof.query_proofs.len() != config.num_queries {
+    if proof.query_proofs.len() != config.num_queries
+        || proof.commit_phase_opening_proofs.len() != proof.commit_phase_commits.len()
+    {
         return Err(FriError::InvalidProofShape);
     }
 
@@ -61,17 +71,21 @@ where
         return Err(FriError::InvalidPowWitness);
     }
 
-    // The log of the maximum domain size.
-    let log_max_height =
-        proof.commit_phase_commits.len() + config.log_blowup + config.log_final_poly_len;
-
     // The log of the final domain size.
     let log_final_height = config.log_blowup + config.log_final_poly_len;
 
-    for qp in &proof.query_proofs {
-        let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
-        let ro = open_input(index, &qp.input_proof)?;
+    let indices = (0..config.num_queries)
+        .map(|_| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
+        .collect_vec();
+    let reduced_openings = open_input(&indices, &proof.input_proof)?;
+    if reduced_openings.len() != config.num_queries {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    // For each round, the index and evaluations of the row opened by each query.
+    let mut opened_rows = vec![vec![]; proof.commit_phase_commits.len()];
 
+    for (qp, &index, ro) in izip!(&proof.query_proofs, &indices, reduced_openings) {
         debug_assert!(
             ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
             "reduced openings sorted by height descending"
@@ -81,18 +95,13 @@ where
 
         // Starting at the evaluation at `index` of the initial domain,
         // perform fri folds until the domain size reaches the final domain size.
-        // Check after each fold that the pair of sibling evaluations at the current
-        // node match the commitment.
+        // The coset of sibling evaluations at each node is checked against the commitments below.
         let folded_eval = verify_query(
             g,
             config,
             &mut domain_index,
             zip_eq(
-                zip_eq(
-                    &betas,
-                    &proof.commit_phase_commits,
-                    FriError::InvalidProofShape,
-                )?,
+                zip_eq(&betas, &mut opened_rows, FriError::InvalidProofShape)?,
                 &qp.commit_phase_openings,
                 FriError::InvalidProofShape,
             )?,
@@ -121,15 +130,47 @@ where
         }
     }
 
+    // Verify the commitment to the rows opened in each round, for all queries at once. Each row is
+    // a coset of the arity which `verify_query` derived for its round, and all queries must agree
+    // on it.
+    let mut log_height = log_max_height;
+    for (comm, opening_proof, rows) in izip!(
+        &proof.commit_phase_commits,
+        &proof.commit_phase_opening_proofs,
+        opened_rows
+    ) {
+        let Some(arity) = rows.first().map(|(_, evals)| evals.len()) else {
+            // There are no queries, so nothing was opened.
+            break;
+        };
+        if rows.iter().any(|(_, evals)| evals.len() != arity) {
+            return Err(FriError::InvalidProofShape);
+        }
+        let log_arity = log2_strict_usize(arity);
+        log_height -= log_arity;
+        let dims = &[Dimensions {
+            width: 1 << log_arity,
+            height: 1 << log_height,
+        }];
+        let (row_indices, evals): (Vec<_>, Vec<_>) = rows
+            .into_iter()
+            .map(|(index, evals)| (index, vec![evals]))
+            .unzip();
+        config
+            .mmcs
+            .verify_multi_batch(comm, dims, &row_indices, &evals, opening_proof)
+            .map_err(FriError::CommitPhaseMmcsError)?;
+    }
+
     Ok(())
 }
 
-type CommitStep<'a, F, M> = (
+type CommitStep<'a, F> = (
     (
         &'a F, // The challenge point beta used for the next fold of FRI evaluations.
-        &'a <M as Mmcs<F>>::Commitment, // A commitment to the FRI evaluations on the current domain.
+        &'a mut Vec<(usize, Vec<F>)>, // The rows opened so far in the current round.
     ),
-    &'a CommitPhaseProofStep<F, M>, // The sibling and opening proof for the current FRI node.
+    &'a CommitPhaseProofStep<F>, // The siblings for the current FRI node.
 );
 
 /// Verifies a single query chain in the FRI proof.
@@ -137,59 +178,57 @@ type CommitStep<'a, F, M> = (
 /// Given an initial `index` corresponding to a point in the initial domain
 /// and a series of `reduced_openings` corresponding to evaluations of
 /// polynomials to be added in at specific domain sizes, perform the standard
-/// sequence of FRI folds, checking at each step that the pair of sibling evaluations
-/// match the commitment.
+/// sequence of FRI folds, recording at each step the coset of sibling evaluations so that
+/// they can be checked against the commitment.
 fn verify_query<'a, G, F, M>(
     g: &G,
     config: &FriConfig<M>,
     index: &mut usize,
-    steps: impl ExactSizeIterator<Item = CommitStep<'a, F, M>>,
+    steps: impl ExactSizeIterator<Item = CommitStep<'a, F>>,
     reduced_openings: Vec<(usize, F)>,
     log_max_height: usize,
     log_final_height: usize,
 ) -> Result<F, FriError<M::Error, G::InputError>>
 where
     F: Field,
-    M: Mmcs<F> + 'a,
+    M: MultiMmcs<F>,
     G: FriGenericConfig<F>,
 {
     let mut folded_eval = F::ZERO;
     let mut ro_iter = reduced_openings.into_iter().peekable();
+    let mut log_height = log_max_height;
 
     // We start with evaluations over a domain of size (1 << log_max_height). We fold
     // using FRI until the domain size reaches (1 << log_final_height).
-    for (log_folded_height, ((&beta, comm), opening)) in zip_eq(
-        (log_final_height..log_max_height).rev(),
-        steps,
-        FriError::InvalidProofShape,
-    )? {
+    for ((&beta, opened_rows), opening) in steps {
         // If there are new polynomials to roll in at this height, do so.
-        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
+        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_height) {
             folded_eval += ro;
         }
 
-        // Get the index of the other sibling of the current fri node.
-        let index_sibling = *index ^ 1;
-
-        let mut evals = vec![folded_eval; 2];
-        evals[index_sibling % 2] = opening.sibling_value;
+        let log_arity = config.log_arity_of_round(log_height, ro_iter.peek().map(|(lh, _)| *lh));
+        if log_arity == 0 || opening.sibling_values.len() != (1 << log_arity) - 1 {
+            return Err(FriError::InvalidProofShape);
+        }
+        let log_folded_height = log_height - log_arity;
 
-        let dims = &[Dimensions {
-            width: 2,
-            height: 1 << log_folded_height,
-        }];
+        // Insert the current evaluation among its siblings, at its position in the coset.
+        let mut evals = opening.sibling_values.clone();
+        evals.insert(*index % (1 << log_arity), folded_eval);
 
         // Replace index with the index of the parent fri node.
-        *index >>= 1;
+        *index >>= log_arity;
 
-        // Verify the commitment to the evaluations of the sibling nodes.
-        config
-            .mmcs
-            .verify_batch(comm, dims, *index, &[evals.clone()], &opening.opening_proof)
-            .map_err(FriError::CommitPhaseMmcsError)?;
+        // Record the evaluations of the sibling nodes, to check against the commitment.
+        opened_rows.push((*index, evals.clone()));
 
-        // Fold the pair of evaluations of sibling nodes into the evaluation of the parent fri node.
+        // Fold the coset of evaluations of sibling nodes into the evaluation of the parent fri node.
         folded_eval = g.fold_row(*index, log_folded_height, beta, evals.into_iter());
+        log_height = log_folded_height;
+    }
+
+    if log_height != log_final_height {
+        return Err(FriError::InvalidProofShape);
     }
 
     // If ro_iter is not empty, we failed to fold in some polynomial evaluations.
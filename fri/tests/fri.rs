@@ -3,11 +3,12 @@ use core::marker::PhantomData;
 
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::{CanSampleBits, DuplexChallenger, FieldChallenger};
-use p3_commit::ExtensionMmcs;
+use p3_commit::{ExtensionMmcs, Mmcs};
 use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
 use p3_field::extension::BinomialExtensionField;
 use p3_field::{Field, PrimeCharacteristicRing};
-use p3_fri::{FriConfig, TwoAdicFriGenericConfig, prover, verifier};
+use p3_fri::verifier::FriError;
+use p3_fri::{FriConfig, FriProof, TwoAdicFriGenericConfig, prover, verifier};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::util::reverse_matrix_index_bits;
@@ -28,8 +29,14 @@ type ValMmcs =
 type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
 type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
 type MyFriConfig = FriConfig<ChallengeMmcs>;
-
-fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm, MyFriConfig) {
+type MyFriProof = FriProof<Challenge, ChallengeMmcs, Val, Vec<Vec<(usize, Challenge)>>>;
+type MyFriError = FriError<<ChallengeMmcs as Mmcs<Challenge>>::Error, ()>;
+
+fn get_ldt_for_testing<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+) -> (Perm, MyFriConfig) {
     let perm = Perm::new_from_rng_128(rng);
     let hash = MyHash::new(perm.clone());
     let compress = MyCompress::new(perm.clone());
@@ -37,6 +44,7 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
     let fri_config = FriConfig {
         log_blowup: 1,
         log_final_poly_len,
+        max_log_arity,
         num_queries: 10,
         proof_of_work_bits: 8,
         mmcs,
@@ -44,8 +52,19 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
     (perm, fri_config)
 }
 
-fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
-    let (perm, fc) = get_ldt_for_testing(rng, log_final_poly_len);
+fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize, max_log_arity: usize) {
+    run_fri_ldt(rng, log_final_poly_len, max_log_arity, |_, _| {}).unwrap();
+}
+
+/// Prove the low-degree test for random inputs, then verify the proof after `tamper` modified it
+/// and the log of the maximum height expected by the verifier.
+fn run_fri_ldt<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    max_log_arity: usize,
+    tamper: impl FnOnce(&mut MyFriProof, &mut usize),
+) -> Result<(), MyFriError> {
+    let (perm, fc) = get_ldt_for_testing(rng, log_final_poly_len, max_log_arity);
     let dft = Radix2Dit::default();
 
     let shift = Val::GENERATOR;
@@ -59,7 +78,7 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         })
         .collect();
 
-    let (proof, p_sample) = {
+    let (mut proof, mut log_max_height, p_sample) = {
         // Prover world
         let mut chal = Challenger::new(perm.clone());
         let alpha: Challenge = chal.sample_algebra_element();
@@ -90,41 +109,48 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         let log_max_height = log2_strict_usize(input[0].len());
 
         let proof = prover::prove(
-            &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+            &TwoAdicFriGenericConfig::<Vec<Vec<(usize, Challenge)>>, ()>(PhantomData),
             &fc,
             input.clone(),
             &mut chal,
-            |idx| {
+            |indices| {
                 // As our "input opening proof", just pass through the literal reduced openings.
-                let mut ro = vec![];
-                for v in &input {
-                    let log_height = log2_strict_usize(v.len());
-                    ro.push((log_height, v[idx >> (log_max_height - log_height)]));
-                }
-                ro.sort_by_key(|(lh, _)| Reverse(*lh));
-                ro
+                indices
+                    .iter()
+                    .map(|idx| {
+                        let mut ro = vec![];
+                        for v in &input {
+                            let log_height = log2_strict_usize(v.len());
+                            ro.push((log_height, v[idx >> (log_max_height - log_height)]));
+                        }
+                        ro.sort_by_key(|(lh, _)| Reverse(*lh));
+                        ro
+                    })
+                    .collect()
             },
         );
 
-        (proof, chal.sample_bits(8))
+        (proof, log_max_height, chal.sample_bits(8))
     };
+    tamper(&mut proof, &mut log_max_height);
 
     let mut v_challenger = Challenger::new(perm);
     let _alpha: Challenge = v_challenger.sample_algebra_element();
     verifier::verify(
-        &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+        &TwoAdicFriGenericConfig::<Vec<Vec<(usize, Challenge)>>, ()>(PhantomData),
         &fc,
         &proof,
+        log_max_height,
         &mut v_challenger,
-        |_index, proof| Ok(proof.clone()),
-    )
-    .unwrap();
+        |_indices, proof| Ok(proof.clone()),
+    )?;
 
     assert_eq!(
         p_sample,
         v_challenger.sample_bits(8),
         "prover and verifier transcript have same state after FRI"
     );
+    Ok(())
 }
 
 #[test]
@@ -132,7 +158,17 @@ fn test_fri_ldt() {
     // FRI is kind of flaky depending on indexing luck
     for i in 0..4 {
         let mut rng = SmallRng::seed_from_u64(i as u64);
-        do_test_fri_ldt(&mut rng, i + 1);
+        do_test_fri_ldt(&mut rng, i + 1, 1);
+    }
+}
+
+#[test]
+fn test_fri_ldt_higher_arity() {
+    // The inputs have log heights 6 to 10, so rounds with large arities are cut short to roll them
+    // in at the right heights.
+    for max_log_arity in 2..5 {
+        let mut rng = SmallRng::seed_from_u64(max_log_arity as u64);
+        do_test_fri_ldt(&mut rng, 1, max_log_arity);
     }
 }
 
@@ -143,6 +179,32 @@ fn test_fri_ldt_should_panic() {
     // FRI is kind of flaky depending on indexing luck
     for i in 0..4 {
         let mut rng = SmallRng::seed_from_u64(i);
-        do_test_fri_ldt(&mut rng, 5);
+        do_test_fri_ldt(&mut rng, 5, 1);
     }
 }
+
+#[test]
+fn test_fri_ldt_wrong_max_height() {
+    for delta in [-1, 1] {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let result = run_fri_ldt(&mut rng, 1, 2, |_, log_max_height| {
+            *log_max_height = log_max_height.checked_add_signed(delta).unwrap();
+        });
+        assert!(matches!(result, Err(FriError::InvalidProofShape)));
+    }
+}
+
+#[test]
+fn test_fri_ldt_inconsistent_arities() {
+    // The last query opens a coset of 2 rather than 4 in a round folding by 4.
+    let mut rng = SmallRng::seed_from_u64(1);
+    let result = run_fri_ldt(&mut rng, 1, 2, |proof, _| {
+        let steps = &mut proof.query_proofs.last_mut().unwrap().commit_phase_openings;
+        let step = steps
+            .iter_mut()
+            .find(|step| step.sibling_values.len() == 3)
+            .unwrap();
+        step.sibling_values.truncate(1);
+    });
+    assert!(matches!(result, Err(FriError::InvalidProofShape)));
+}
@@ -0,0 +1,116 @@
+//! Checks that committing with on-disk LDEs never holds a whole LDE on the heap.
+//!
+//! This is its own test binary, as it counts the heap usage of the whole process.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::{ExtensionMmcs, Pcs};
+use p3_dft::Radix2DitParallel;
+use p3_field::Field;
+use p3_field::extension::BinomialExtensionField;
+use p3_fri::{FriConfig, TwoAdicFriPcs};
+use p3_matrix::dense::{OwnedDenseStorage, RowMajorMatrix};
+use p3_matrix::mmap::MmapStorage;
+use p3_merkle_tree::{DigestStorage, MerkleTreeMmcs};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// The system allocator, keeping track of the peak number of bytes allocated at once.
+struct PeakAlloc;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for PeakAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+            PEAK.fetch_max(allocated, Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: PeakAlloc = PeakAlloc;
+
+type Val = BabyBear;
+type Challenge = BinomialExtensionField<Val, 4>;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Dft = Radix2DitParallel<Val>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+const LOG_HEIGHT: usize = 10;
+const WIDTH: usize = 64;
+const LOG_BLOWUP: usize = 3;
+const LDE_BYTES: usize = (WIDTH << (LOG_HEIGHT + LOG_BLOWUP)) * size_of::<Val>();
+
+/// The most bytes on the heap at once while committing to a random trace, beyond those allocated
+/// before.
+fn peak_heap_during_commit<LdeStorage>() -> usize
+where
+    LdeStorage: OwnedDenseStorage<Val> + 'static,
+{
+    let perm = Perm::new_from_rng_128(&mut SmallRng::seed_from_u64(0));
+    let val_mmcs = ValMmcs::new_with_digest_storage(
+        MyHash::new(perm.clone()),
+        MyCompress::new(perm),
+        0,
+        DigestStorage::Disk,
+    );
+    let fri_config = FriConfig {
+        log_blowup: LOG_BLOWUP,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        num_queries: 10,
+        proof_of_work_bits: 8,
+        mmcs: ChallengeMmcs::new(val_mmcs.clone()),
+    };
+    let pcs = TwoAdicFriPcs::<Val, Dft, ValMmcs, ChallengeMmcs, LdeStorage>::new(
+        Dft::default(),
+        val_mmcs,
+        fri_config,
+    );
+    let domain =
+        <_ as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 1 << LOG_HEIGHT);
+    let trace =
+        RowMajorMatrix::<Val>::rand(&mut SmallRng::seed_from_u64(1), 1 << LOG_HEIGHT, WIDTH);
+
+    let before = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(before, Ordering::Relaxed);
+    let committed = <_ as Pcs<Challenge, Challenger>>::commit(&pcs, vec![(domain, trace)]);
+    let peak = PEAK.load(Ordering::Relaxed) - before;
+    drop(committed);
+    peak
+}
+
+#[test]
+fn on_disk_lde_stays_off_the_heap() {
+    let heap_peak = peak_heap_during_commit::<Vec<Val>>();
+    let disk_peak = peak_heap_during_commit::<MmapStorage<Val>>();
+    assert!(
+        heap_peak >= LDE_BYTES,
+        "an LDE on the heap takes {LDE_BYTES} bytes, but the peak was {heap_peak}"
+    );
+    // The trace, its coefficients, and a coset or two of the LDE are still on the heap.
+    assert!(
+        disk_peak <= LDE_BYTES / 4,
+        "an LDE on disk should leave most of its {LDE_BYTES} bytes off the heap, but the peak was \
+         {disk_peak}"
+    );
+}
@@ -1,13 +1,15 @@
 use itertools::{Itertools, izip};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::{CanObserve, DuplexChallenger, FieldChallenger};
-use p3_commit::{ExtensionMmcs, Pcs, PolynomialSpace};
+use p3_commit::{CommitError, ExtensionMmcs, Pcs, PolynomialSpace};
 use p3_dft::Radix2DitParallel;
 use p3_field::extension::BinomialExtensionField;
-use p3_field::{ExtensionField, Field};
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing};
+use p3_fri::verifier::FriError;
 use p3_fri::{FriConfig, TwoAdicFriPcs};
 use p3_matrix::dense::RowMajorMatrix;
-use p3_merkle_tree::MerkleTreeMmcs;
+use p3_matrix::mmap::MmapStorage;
+use p3_merkle_tree::{DigestStorage, MerkleTreeError, MerkleTreeMmcs};
 use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
 use rand::distr::{Distribution, StandardUniform};
 use rand::rngs::SmallRng;
@@ -95,6 +97,45 @@ fn do_test_fri_pcs<Val, Challenge, Challenger, P>(
         .unwrap()
 }
 
+fn do_test_commit_errors<Val, Challenge, Challenger, P>((pcs, _): &(P, Challenger))
+where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Val: Field,
+    StandardUniform: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+{
+    let mut rng = seeded_rng();
+    let valid = (
+        pcs.natural_domain_for_degree(1 << 3),
+        RowMajorMatrix::<Val>::rand(&mut rng, 1 << 3, 5),
+    );
+
+    let short = (
+        pcs.natural_domain_for_degree(1 << 4),
+        RowMajorMatrix::<Val>::rand(&mut rng, 1 << 3, 5),
+    );
+    assert_eq!(
+        pcs.try_commit(vec![valid.clone(), short]).err(),
+        Some(CommitError::HeightMismatch {
+            index: 1,
+            domain_size: 1 << 4,
+            height: 1 << 3
+        })
+    );
+
+    // The domain's low-degree extension would be larger than any domain.
+    let log_size = P::Domain::MAX_LOG_SIZE;
+    let large = (
+        pcs.natural_domain_for_degree(1 << log_size),
+        RowMajorMatrix::<Val>::rand(&mut rng, 1, 5),
+    );
+    assert_eq!(
+        pcs.try_commit(vec![valid, large]).err(),
+        Some(CommitError::UnsupportedDomainSize { index: 1, log_size })
+    );
+}
+
 // Set it up so we create tests inside a module for each pcs, so we get nice error reports
 // specific to a failing PCS.
 macro_rules! make_tests_for_pcs {
@@ -134,6 +175,12 @@ macro_rules! make_tests_for_pcs {
             }
         }
 
+        #[test]
+        fn commit_errors() {
+            let p = $p;
+            $crate::do_test_commit_errors(&p);
+        }
+
         #[test]
         fn multiple_rounds() {
             let p = $p;
@@ -167,32 +214,132 @@ mod babybear_fri_pcs {
     type Dft = Radix2DitParallel<Val>;
     type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
     type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    // Keeps its LDEs and their Merkle trees in temporary files.
+    type DiskPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs, MmapStorage<Val>>;
+
+    fn get_pcs(log_blowup: usize, max_log_arity: usize) -> (MyPcs, Challenger) {
+        get_pcs_with_storage(log_blowup, max_log_arity, DigestStorage::Memory)
+    }
 
-    fn get_pcs(log_blowup: usize) -> (MyPcs, Challenger) {
+    fn get_disk_pcs(log_blowup: usize, max_log_arity: usize) -> (DiskPcs, Challenger) {
+        get_pcs_with_storage(log_blowup, max_log_arity, DigestStorage::Disk)
+    }
+
+    fn get_pcs_with_storage<LdeStorage>(
+        log_blowup: usize,
+        max_log_arity: usize,
+        digest_storage: DigestStorage,
+    ) -> (
+        TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs, LdeStorage>,
+        Challenger,
+    ) {
         let perm = Perm::new_from_rng_128(&mut seeded_rng());
         let hash = MyHash::new(perm.clone());
         let compress = MyCompress::new(perm.clone());
 
-        let val_mmcs = ValMmcs::new(hash, compress);
+        let val_mmcs = ValMmcs::new_with_digest_storage(hash, compress, 0, digest_storage);
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
 
         let fri_config = FriConfig {
             log_blowup,
             log_final_poly_len: 0,
+            max_log_arity,
             num_queries: 10,
             proof_of_work_bits: 8,
             mmcs: challenge_mmcs,
         };
 
-        let pcs = MyPcs::new(Dft::default(), val_mmcs, fri_config);
+        let pcs = TwoAdicFriPcs::new(Dft::default(), val_mmcs, fri_config);
         (pcs, Challenger::new(perm))
     }
 
+    #[test]
+    fn tampered_row_widths_rejected() {
+        let (pcs, challenger) = get_pcs(1, 1);
+        let mut rng = seeded_rng();
+        let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 1 << 5);
+        let mats =
+            [3, 5].map(|width| (domain, RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, width)));
+        let (commit, data) = <MyPcs as Pcs<Challenge, Challenger>>::commit(&pcs, mats.to_vec());
+
+        let mut p_challenger = challenger.clone();
+        p_challenger.observe(commit.clone());
+        let zeta: Challenge = p_challenger.sample_algebra_element();
+        let (openings, proof) = pcs.open(vec![(&data, vec![vec![zeta]; 2])], &mut p_challenger);
+        let claims = vec![(
+            commit.clone(),
+            openings[0]
+                .iter()
+                .map(|values| (domain, vec![(zeta, values[0].clone())]))
+                .collect_vec(),
+        )];
+        let verify = |proof| {
+            let mut v_challenger = challenger.clone();
+            v_challenger.observe(commit.clone());
+            let _: Challenge = v_challenger.sample_algebra_element();
+            pcs.verify(claims.clone(), proof, &mut v_challenger)
+        };
+        verify(&proof).expect("expected verification to succeed");
+
+        // Moving a value from the end of one row to the start of the next leaves the hash of the
+        // leaf unchanged, so it must be caught by the input MMCS checking widths.
+        let mut tampered = proof.clone();
+        for rows in &mut tampered.input_proof[0].opened_values {
+            let value = rows[0].pop().unwrap();
+            rows[1].insert(0, value);
+        }
+        assert!(matches!(
+            verify(&tampered),
+            Err(FriError::InputError(MerkleTreeError::WrongWidth))
+        ));
+
+        // An extra value at the end of a row.
+        let mut tampered = proof.clone();
+        for rows in &mut tampered.input_proof[0].opened_values {
+            rows[1].push(Val::ZERO);
+        }
+        assert!(matches!(
+            verify(&tampered),
+            Err(FriError::InputError(MerkleTreeError::WrongWidth))
+        ));
+
+        // A matrix opened at no points has no width known to the verifier, so it is rejected.
+        let mut unopened_claims = claims.clone();
+        unopened_claims[0].1[1].1.clear();
+        assert!(matches!(
+            pcs.verify(unopened_claims, &proof, &mut challenger.clone()),
+            Err(FriError::InvalidProofShape)
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "each matrix must be opened at one or more points")]
+    fn unopened_matrix_panics() {
+        let (pcs, mut challenger) = get_pcs(1, 1);
+        let mut rng = seeded_rng();
+        let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 1 << 5);
+        let mats =
+            [3, 5].map(|width| (domain, RowMajorMatrix::<Val>::rand(&mut rng, 1 << 5, width)));
+        let (_, data) = <MyPcs as Pcs<Challenge, Challenger>>::commit(&pcs, mats.to_vec());
+
+        let zeta: Challenge = challenger.sample_algebra_element();
+        pcs.open(vec![(&data, vec![vec![zeta], vec![]])], &mut challenger);
+    }
+
     mod blowup_1 {
-        make_tests_for_pcs!(super::get_pcs(1));
+        make_tests_for_pcs!(super::get_pcs(1, 1));
     }
     mod blowup_2 {
-        make_tests_for_pcs!(super::get_pcs(2));
+        make_tests_for_pcs!(super::get_pcs(2, 1));
+    }
+    mod arity_4 {
+        make_tests_for_pcs!(super::get_pcs(1, 2));
+    }
+    mod arity_16 {
+        make_tests_for_pcs!(super::get_pcs(2, 4));
+    }
+    mod on_disk {
+        make_tests_for_pcs!(super::get_disk_pcs(1, 2));
     }
 }
 
@@ -223,7 +370,7 @@ mod m31_fri_pcs {
 
     type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
 
-    fn get_pcs(log_blowup: usize) -> (Pcs, Challenger) {
+    fn get_pcs(log_blowup: usize, log_final_poly_len: usize) -> (Pcs, Challenger) {
         let byte_hash = ByteHash {};
         let field_hash = FieldHash::new(byte_hash);
         let compress = MyCompress::new(byte_hash);
@@ -231,7 +378,8 @@ mod m31_fri_pcs {
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
         let fri_config = FriConfig {
             log_blowup,
-            log_final_poly_len: 0,
+            log_final_poly_len,
+            max_log_arity: 1,
             num_queries: 10,
             proof_of_work_bits: 8,
             mmcs: challenge_mmcs,
@@ -245,9 +393,15 @@ mod m31_fri_pcs {
     }
 
     mod blowup_1 {
-        make_tests_for_pcs!(super::get_pcs(1));
+        make_tests_for_pcs!(super::get_pcs(1, 0));
     }
     mod blowup_2 {
-        make_tests_for_pcs!(super::get_pcs(2));
+        make_tests_for_pcs!(super::get_pcs(2, 0));
+    }
+    mod final_poly_2 {
+        make_tests_for_pcs!(super::get_pcs(1, 1));
+    }
+    mod final_poly_8 {
+        make_tests_for_pcs!(super::get_pcs(2, 3));
     }
 }
@@ -2,8 +2,8 @@ use alloc::vec::Vec;
 use core::array;
 use core::borrow::Borrow;
 
-use p3_air::{Air, AirBuilder, BaseAir};
-use p3_field::{PrimeCharacteristicRing, PrimeField64};
+use p3_air::{Air, AirBuilder, BaseAir, PeriodicAirBuilder, TracePadding};
+use p3_field::{Field, PrimeCharacteristicRing, PrimeField64};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use rand::rngs::SmallRng;
@@ -11,8 +11,11 @@ use rand::{Rng, SeedableRng};
 
 use crate::columns::{KeccakCols, NUM_KECCAK_COLS};
 use crate::constants::rc_value_bit;
-use crate::round_flags::eval_round_flags;
-use crate::{BITS_PER_LIMB, NUM_ROUNDS, U64_LIMBS, generate_trace_rows};
+use crate::round_flags::{eval_round_flags, round_flag_columns};
+use crate::{
+    BITS_PER_LIMB, NUM_ROUND_BLOCKS, ROUNDS_PER_BLOCK, U64_LIMBS, generate_trace_rows,
+    zero_permutation_rows,
+};
 
 /// Assumes the field size is at least 16 bits.
 #[derive(Debug)]
@@ -30,13 +33,21 @@ impl KeccakAir {
     }
 }
 
-impl<F> BaseAir<F> for KeccakAir {
+impl<F: Field> BaseAir<F> for KeccakAir {
     fn width(&self) -> usize {
         NUM_KECCAK_COLS
     }
+
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        Some(TracePadding::Rows(zero_permutation_rows()))
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        round_flag_columns()
+    }
 }
 
-impl<AB: AirBuilder> Air<AB> for KeccakAir {
+impl<AB: PeriodicAirBuilder> Air<AB> for KeccakAir {
     #[inline]
     fn eval(&self, builder: &mut AB) {
         eval_round_flags(builder);
@@ -46,15 +57,20 @@ impl<AB: AirBuilder> Air<AB> for KeccakAir {
         let local: &KeccakCols<AB::Var> = (*local).borrow();
         let next: &KeccakCols<AB::Var> = (*next).borrow();
 
-        let first_step = local.step_flags[0];
-        let final_step = local.step_flags[NUM_ROUNDS - 1];
+        // The flags of the rounds within each block. A round's flag is the product of this and the
+        // flag of its block.
+        let round_flags: [AB::Expr; ROUNDS_PER_BLOCK] =
+            array::from_fn(|k| builder.periodic_values()[k].into());
+        let first_step = local.round_block_flags[0] * round_flags[0].clone();
+        let final_step = local.round_block_flags[NUM_ROUND_BLOCKS - 1]
+            * round_flags[ROUNDS_PER_BLOCK - 1].clone();
         let not_final_step = AB::Expr::ONE - final_step;
 
         // If this is the first step, the input A must match the preimage.
         for y in 0..5 {
             for x in 0..5 {
                 builder
-                    .when(first_step)
+                    .when(first_step.clone())
                     .assert_zeros::<U64_LIMBS, _>(array::from_fn(|limb| {
                         local.preimage[y][x][limb] - local.a[y][x][limb]
                     }));
@@ -176,10 +192,13 @@ impl<AB: AirBuilder> Air<AB> for KeccakAir {
 
         let get_xored_bit = |i| {
             let mut rc_bit_i = AB::Expr::ZERO;
-            for r in 0..NUM_ROUNDS {
-                let this_round = local.step_flags[r];
-                let this_round_constant = AB::Expr::from_bool(rc_value_bit(r, i) != 0);
-                rc_bit_i += this_round * this_round_constant;
+            for (j, &this_block) in local.round_block_flags.iter().enumerate() {
+                // The bit of the round constant within this block, which is a sum of round flags.
+                let block_rc_bit_i: AB::Expr = (0..ROUNDS_PER_BLOCK)
+                    .filter(|&k| rc_value_bit(j * ROUNDS_PER_BLOCK + k, i) != 0)
+                    .map(|k| round_flags[k].clone())
+                    .sum();
+                rc_bit_i += this_block * block_rc_bit_i;
             }
 
             rc_bit_i.xor(&local.a_prime_prime_0_0_bits[i].into())
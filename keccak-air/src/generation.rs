@@ -3,26 +3,29 @@ use core::array;
 use core::mem::transmute;
 
 use p3_air::utils::{u64_to_16_bit_limbs, u64_to_bits_le};
-use p3_field::PrimeField64;
+use p3_field::{Field, PrimeField64};
 use p3_matrix::dense::RowMajorMatrix;
-use p3_maybe_rayon::iter::repeat_n;
 use p3_maybe_rayon::prelude::*;
 use tracing::instrument;
 
 use crate::columns::{KeccakCols, NUM_KECCAK_COLS};
-use crate::{NUM_ROUNDS, R, RC, U64_LIMBS};
+use crate::{NUM_ROUNDS, R, RC, ROUNDS_PER_BLOCK, U64_LIMBS};
 
 // TODO: Take generic iterable
+/// Generate a trace of `NUM_ROUNDS` rows for each input. It isn't padded to a power of two height;
+/// `prove` pads it with the rows of [`zero_permutation_rows`], as the AIR's padding strategy.
 #[instrument(name = "generate Keccak trace", skip_all)]
 pub fn generate_trace_rows<F: PrimeField64>(
     inputs: Vec<[u64; 25]>,
     extra_capacity_bits: usize,
 ) -> RowMajorMatrix<F> {
-    let num_rows = (inputs.len() * NUM_ROUNDS).next_power_of_two();
+    let num_rows = inputs.len() * NUM_ROUNDS;
     let trace_length = num_rows * NUM_KECCAK_COLS;
 
-    // We allocate extra_capacity_bits now as this will be needed by the dft.
-    let mut long_trace = F::zero_vec(trace_length << extra_capacity_bits);
+    // We allocate extra_capacity_bits now as this will be needed by the dft, along with room for
+    // the padding rows.
+    let mut long_trace =
+        F::zero_vec((num_rows.next_power_of_two() * NUM_KECCAK_COLS) << extra_capacity_bits);
     long_trace.truncate(trace_length);
 
     let mut trace = RowMajorMatrix::new(long_trace, NUM_KECCAK_COLS);
@@ -31,13 +34,8 @@ pub fn generate_trace_rows<F: PrimeField64>(
     assert!(suffix.is_empty(), "Alignment should match");
     assert_eq!(rows.len(), num_rows);
 
-    let num_padding_inputs = num_rows.div_ceil(NUM_ROUNDS) - inputs.len();
-    let padded_inputs = inputs
-        .into_par_iter()
-        .chain(repeat_n([0; 25], num_padding_inputs));
-
     rows.par_chunks_mut(NUM_ROUNDS)
-        .zip(padded_inputs)
+        .zip(inputs)
         .for_each(|(row, input)| {
             generate_trace_rows_for_perm(row, input);
         });
@@ -45,8 +43,18 @@ pub fn generate_trace_rows<F: PrimeField64>(
     trace
 }
 
-/// `rows` will normally consist of 24 rows, with an exception for the final row.
-fn generate_trace_rows_for_perm<F: PrimeField64>(rows: &mut [KeccakCols<F>], input: [u64; 25]) {
+/// The rows of a permutation of the zero state, with which traces are padded.
+pub fn zero_permutation_rows<F: Field>() -> RowMajorMatrix<F> {
+    let mut trace = RowMajorMatrix::new(F::zero_vec(NUM_ROUNDS * NUM_KECCAK_COLS), NUM_KECCAK_COLS);
+    let (prefix, rows, suffix) = unsafe { trace.values.align_to_mut::<KeccakCols<F>>() };
+    assert!(prefix.is_empty(), "Alignment should match");
+    assert!(suffix.is_empty(), "Alignment should match");
+    generate_trace_rows_for_perm(rows, [0; 25]);
+    trace
+}
+
+/// `rows` must consist of `NUM_ROUNDS` rows, one per round.
+fn generate_trace_rows_for_perm<F: Field>(rows: &mut [KeccakCols<F>], input: [u64; 25]) {
     let mut current_state: [[u64; 5]; 5] = unsafe { transmute(input) };
 
     let initial_state: [[[F; 4]; 5]; 5] =
@@ -74,12 +82,12 @@ fn generate_trace_rows_for_perm<F: PrimeField64>(rows: &mut [KeccakCols<F>], inp
     }
 }
 
-fn generate_trace_row_for_round<F: PrimeField64>(
+fn generate_trace_row_for_round<F: Field>(
     row: &mut KeccakCols<F>,
     round: usize,
     current_state: &mut [[u64; 5]; 5],
 ) {
-    row.step_flags[round] = F::ONE;
+    row.round_block_flags[round / ROUNDS_PER_BLOCK] = F::ONE;
 
     // Populate C[x] = xor(A[x, 0], A[x, 1], A[x, 2], A[x, 3], A[x, 4]).
     let state_c: [u64; 5] = current_state.map(|row| row.iter().fold(0, |acc, y| acc ^ y));
@@ -16,6 +16,10 @@ pub use constants::*;
 pub use generation::*;
 
 pub const NUM_ROUNDS: usize = 24;
+/// The rounds are grouped into blocks of this many, each with a committed flag. It must be a power
+/// of two, since the rounds within a block are flagged by periodic columns.
+const ROUNDS_PER_BLOCK: usize = 8;
+const NUM_ROUND_BLOCKS: usize = NUM_ROUNDS / ROUNDS_PER_BLOCK;
 const BITS_PER_LIMB: usize = 16;
 pub const U64_LIMBS: usize = 64 / BITS_PER_LIMB;
 const RATE_BITS: usize = 1088;
@@ -1,30 +1,58 @@
+use alloc::vec::Vec;
 use core::array;
 use core::borrow::Borrow;
 
-use p3_air::AirBuilder;
+use p3_air::{AirBuilder, PeriodicAirBuilder};
+use p3_field::{Field, PrimeCharacteristicRing};
 use p3_matrix::Matrix;
 
-use crate::NUM_ROUNDS;
 use crate::columns::KeccakCols;
+use crate::{NUM_ROUND_BLOCKS, ROUNDS_PER_BLOCK};
 
-const NUM_ROUNDS_MIN_1: usize = NUM_ROUNDS - 1;
+const NUM_ROUND_BLOCKS_MIN_1: usize = NUM_ROUND_BLOCKS - 1;
+
+/// The flags for each round within a block, as periodic columns: the `k`th column is 1 on the
+/// `k`th row of each block of `ROUNDS_PER_BLOCK` rows, and 0 elsewhere.
+///
+/// A permutation occupies `NUM_ROUNDS` rows, which isn't a power of two, so its round flags can't
+/// be periodic columns themselves. Instead, round `r` is the round `r % ROUNDS_PER_BLOCK` of the
+/// block `r / ROUNDS_PER_BLOCK`, whose flag is committed.
+pub(crate) fn round_flag_columns<F: Field>() -> Vec<Vec<F>> {
+    (0..ROUNDS_PER_BLOCK)
+        .map(|k| {
+            (0..ROUNDS_PER_BLOCK)
+                .map(|i| F::from_bool(i == k))
+                .collect()
+        })
+        .collect()
+}
 
 #[inline]
-pub(crate) fn eval_round_flags<AB: AirBuilder>(builder: &mut AB) {
+pub(crate) fn eval_round_flags<AB: PeriodicAirBuilder>(builder: &mut AB) {
     let main = builder.main();
     let (local, next) = (main.row_slice(0), main.row_slice(1));
     let local: &KeccakCols<AB::Var> = (*local).borrow();
     let next: &KeccakCols<AB::Var> = (*next).borrow();
+    let final_round_in_block: AB::Expr = builder.periodic_values()[ROUNDS_PER_BLOCK - 1].into();
 
-    // Initially, the first step flag should be 1 while the others should be 0.
-    builder.when_first_row().assert_one(local.step_flags[0]);
+    // Initially, the first block flag should be 1 while the others should be 0.
+    builder
+        .when_first_row()
+        .assert_one(local.round_block_flags[0]);
     builder
         .when_first_row()
-        .assert_zeros::<NUM_ROUNDS_MIN_1, _>(local.step_flags[1..].try_into().unwrap());
+        .assert_zeros::<NUM_ROUND_BLOCKS_MIN_1, _>(
+            local.round_block_flags[1..].try_into().unwrap(),
+        );
 
+    // The block flags rotate after the final round of each block, and are otherwise unchanged.
     builder
         .when_transition()
-        .assert_zeros::<NUM_ROUNDS, _>(array::from_fn(|i| {
-            local.step_flags[i] - next.step_flags[(i + 1) % NUM_ROUNDS]
+        .assert_zeros::<NUM_ROUND_BLOCKS, _>(array::from_fn(|j| {
+            let previous_block =
+                local.round_block_flags[(j + NUM_ROUND_BLOCKS - 1) % NUM_ROUND_BLOCKS];
+            let rotated = final_round_in_block.clone() * previous_block
+                + (AB::Expr::ONE - final_round_in_block.clone()) * local.round_block_flags[j];
+            next.round_block_flags[j] - rotated
         }));
 }
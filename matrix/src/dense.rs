@@ -53,6 +53,31 @@ impl<T: Clone + Send + Sync> DenseStorage<T> for Cow<'_, [T]> {
     }
 }
 
+/// Storage which owns its values, and which a `Vec` of values can be moved into, e.g. to keep them
+/// somewhere other than the heap.
+pub trait OwnedDenseStorage<T>: DenseStorage<T> + BorrowMut<[T]> {
+    /// Whether the values are kept on the heap, so that building them in a `Vec` first and moving
+    /// it in with [`from_vec`](Self::from_vec) costs no extra memory.
+    const ON_HEAP: bool;
+
+    fn from_vec(values: Vec<T>) -> Self;
+
+    /// Storage holding `len` copies of `value`, e.g. to be overwritten in place with values that
+    /// shouldn't be built on the heap first.
+    fn from_elem(len: usize, value: T) -> Self;
+}
+impl<T: Clone + Send + Sync> OwnedDenseStorage<T> for Vec<T> {
+    const ON_HEAP: bool = true;
+
+    fn from_vec(values: Vec<T>) -> Self {
+        values
+    }
+
+    fn from_elem(len: usize, value: T) -> Self {
+        vec![value; len]
+    }
+}
+
 impl<T: Clone + Send + Sync + Default> DenseMatrix<T> {
     /// Create a new dense matrix of the given dimensions, backed by a `Vec`, and filled with
     /// default values.
@@ -1158,4 +1183,35 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_vertically_packed_row_window() {
+        type Packed = FieldArray<BabyBear, 2>;
+
+        let matrix = RowMajorMatrix::new((1..17).map(BabyBear::new).collect::<Vec<_>>(), 4);
+
+        // A window of 2 is the same as a row pair.
+        assert_eq!(
+            matrix.vertically_packed_row_window::<Packed>(1, 2, 2),
+            matrix.vertically_packed_row_pair::<Packed>(1, 2),
+        );
+
+        // Packing rows 3-0, 0-1 and 1-2 together (starting at r = 3 with step = 1), wrapping around:
+        //
+        // Expected packed result:
+        // [
+        //   (13, 1), (14, 2), (15, 3), (16, 4), // Packed row (Row 3 & Row 0)
+        //   (1, 5), (2, 6), (3, 7), (4, 8),     // Packed row (Row 0 & Row 1)
+        //   (5, 9), (6, 10), (7, 11), (8, 12),  // Packed row (Row 1 & Row 2)
+        // ]
+        let packed = matrix.vertically_packed_row_window::<Packed>(3, 1, 3);
+        assert_eq!(
+            packed,
+            [(13, 1), (14, 2), (15, 3), (16, 4)]
+                .into_iter()
+                .chain((1..9).map(|i| (i, i + 4)))
+                .map(|(a, b)| [BabyBear::new(a), BabyBear::new(b)].into())
+                .collect::<Vec<Packed>>(),
+        );
+    }
 }
@@ -3,6 +3,8 @@
 #![no_std]
 
 extern crate alloc;
+#[cfg(feature = "mmap")]
+extern crate std;
 
 use alloc::vec::Vec;
 use core::fmt::{Debug, Display, Formatter};
@@ -23,6 +25,8 @@ pub mod bitrev;
 pub mod dense;
 pub mod extension;
 pub mod horizontally_truncated;
+#[cfg(feature = "mmap")]
+pub mod mmap;
 pub mod mul;
 pub mod row_index_mapped;
 pub mod sparse;
@@ -213,6 +217,45 @@ pub trait Matrix<T: Send + Sync>: Send + Sync {
             .collect_vec()
     }
 
+    /// Pack together a window of rows from the matrix, each `step` rows after the previous one.
+    ///
+    /// Returns a vector corresponding to `window` packed rows. The i'th element of the k'th
+    /// packed row contains the packing of the i'th element of the rows r + k * step through
+    /// r + k * step + P::WIDTH - 1. If at some point we exceed the height of the matrix, wrap
+    /// around and include initial rows.
+    ///
+    /// With a window of 2 this agrees with [`vertically_packed_row_pair`](Self::vertically_packed_row_pair).
+    #[inline]
+    fn vertically_packed_row_window<P>(&self, r: usize, step: usize, window: usize) -> Vec<P>
+    where
+        T: Copy,
+        P: PackedValue<Value = T>,
+    {
+        let mut buffer = Vec::with_capacity(window * self.width());
+        self.vertically_packed_row_window_into(r, step, window, &mut buffer);
+        buffer
+    }
+
+    /// Like [`vertically_packed_row_window`](Self::vertically_packed_row_window), but writing the
+    /// packed rows to `buffer`, which is cleared first. Reusing a buffer avoids allocating one
+    /// vector for each window.
+    #[inline]
+    fn vertically_packed_row_window_into<P>(
+        &self,
+        r: usize,
+        step: usize,
+        window: usize,
+        buffer: &mut Vec<P>,
+    ) where
+        T: Copy,
+        P: PackedValue<Value = T>,
+    {
+        buffer.clear();
+        for k in 0..window {
+            buffer.extend(self.vertically_packed_row::<P>(r + k * step));
+        }
+    }
+
     fn vertically_strided(self, stride: usize, offset: usize) -> VerticallyStridedMatrixView<Self>
     where
         Self: Sized,
@@ -0,0 +1,185 @@
+//! Matrices whose values are kept in memory-mapped temporary files rather than on the heap, so that
+//! the OS can page them out to disk when they don't fit in memory.
+
+use alloc::vec::Vec;
+use core::borrow::{Borrow, BorrowMut};
+use core::marker::PhantomData;
+use core::{mem, ptr, slice};
+use std::io;
+use std::path::Path;
+
+use memmap2::MmapMut;
+
+use crate::dense::{DenseMatrix, DenseStorage, OwnedDenseStorage, RowMajorMatrix};
+
+/// Values stored in a memory-mapped temporary file.
+///
+/// The file is created in a given directory, or in `std::env::temp_dir()` (which can be set with
+/// `TMPDIR`), and is removed as soon as the storage is dropped.
+#[derive(Debug)]
+pub struct MmapStorage<T> {
+    mmap: MmapMut,
+    len: usize,
+    _phantom: PhantomData<T>,
+}
+
+pub type MmapMatrix<T> = DenseMatrix<T, MmapStorage<T>>;
+
+impl<T: Copy> MmapStorage<T> {
+    /// Storage in a new file in `dir`, holding `len` copies of `value`.
+    pub fn new_in(dir: impl AsRef<Path>, len: usize, value: T) -> io::Result<Self> {
+        let mut storage = Self::uninit_in(dir.as_ref(), len)?;
+        let values = storage.mmap.as_mut_ptr().cast::<T>();
+        for i in 0..len {
+            // SAFETY: The mapping has room for `len` values of `T`, and is suitably aligned.
+            unsafe { values.add(i).write(value) };
+        }
+        Ok(storage)
+    }
+
+    /// Storage in a new file in `dir`, holding a copy of `values`.
+    pub fn from_slice_in(dir: impl AsRef<Path>, values: &[T]) -> io::Result<Self> {
+        let mut storage = Self::uninit_in(dir.as_ref(), values.len())?;
+        // SAFETY: The mapping has room for `values.len()` values of `T`, is suitably aligned, and
+        // doesn't overlap `values`.
+        unsafe {
+            ptr::copy_nonoverlapping(
+                values.as_ptr(),
+                storage.mmap.as_mut_ptr().cast::<T>(),
+                values.len(),
+            );
+        }
+        Ok(storage)
+    }
+
+    /// Storage in a new file in `std::env::temp_dir()`, holding a copy of `values`.
+    pub fn from_slice(values: &[T]) -> io::Result<Self> {
+        Self::from_slice_in(std::env::temp_dir(), values)
+    }
+
+    /// Maps a new file in `dir` with room for `len` values, all of which must be written before the
+    /// storage is borrowed.
+    fn uninit_in(dir: &Path, len: usize) -> io::Result<Self> {
+        assert_ne!(mem::size_of::<T>(), 0, "Zero-sized values can't be mapped");
+        let file = tempfile::tempfile_in(dir)?;
+        file.set_len((len * mem::size_of::<T>()) as u64)?;
+        // SAFETY: The file has no name, so no one else can modify or truncate it while it's mapped.
+        let mmap = unsafe { MmapMut::map_mut(&file)? };
+        // Mappings start at a page boundary, which is enough for any value we'd store.
+        assert!(
+            mmap.as_ptr().cast::<T>().is_aligned(),
+            "Values are too aligned to be mapped"
+        );
+        Ok(Self {
+            mmap,
+            len,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+impl<T> Borrow<[T]> for MmapStorage<T> {
+    fn borrow(&self) -> &[T] {
+        // SAFETY: The mapping is aligned, and holds `len` values of `T`, all written on creation.
+        unsafe { slice::from_raw_parts(self.mmap.as_ptr().cast::<T>(), self.len) }
+    }
+}
+
+impl<T> BorrowMut<[T]> for MmapStorage<T> {
+    fn borrow_mut(&mut self) -> &mut [T] {
+        // SAFETY: As above, and the mapping is borrowed mutably.
+        unsafe { slice::from_raw_parts_mut(self.mmap.as_mut_ptr().cast::<T>(), self.len) }
+    }
+}
+
+impl<T: Copy + Send + Sync> DenseStorage<T> for MmapStorage<T> {
+    fn to_vec(self) -> Vec<T> {
+        <[T]>::to_vec(self.borrow())
+    }
+}
+
+impl<T: Copy + Send + Sync> OwnedDenseStorage<T> for MmapStorage<T> {
+    const ON_HEAP: bool = false;
+
+    /// Moves `values` into a new file in `std::env::temp_dir()`.
+    ///
+    /// Panics if the file can't be created, e.g. because the disk is full.
+    fn from_vec(values: Vec<T>) -> Self {
+        Self::from_slice(&values).expect("failed to write values to a temporary file")
+    }
+
+    /// Fills a new file in `std::env::temp_dir()` with `len` copies of `value`.
+    ///
+    /// Panics if the file can't be created.
+    fn from_elem(len: usize, value: T) -> Self {
+        Self::new_in(std::env::temp_dir(), len, value)
+            .expect("failed to write values to a temporary file")
+    }
+}
+
+impl<T: Copy + Send + Sync> MmapMatrix<T> {
+    /// A `width` by `height` matrix in a new file in `dir`, filled with `value`.
+    pub fn new_in(
+        dir: impl AsRef<Path>,
+        width: usize,
+        height: usize,
+        value: T,
+    ) -> io::Result<Self> {
+        Ok(Self::new(
+            MmapStorage::new_in(dir, width * height, value)?,
+            width,
+        ))
+    }
+
+    /// A copy of `matrix` in a new file in `dir`.
+    pub fn from_matrix_in(dir: impl AsRef<Path>, matrix: &RowMajorMatrix<T>) -> io::Result<Self> {
+        Ok(Self::new(
+            MmapStorage::from_slice_in(dir, &matrix.values)?,
+            matrix.width,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::Matrix;
+
+    type F = BabyBear;
+
+    #[test]
+    fn matches_heap_matrix() {
+        let mut rng = SmallRng::seed_from_u64(0);
+        let matrix = RowMajorMatrix::<F>::rand(&mut rng, 100, 7);
+        let mapped = MmapMatrix::from_matrix_in(std::env::temp_dir(), &matrix).unwrap();
+        assert_eq!(mapped.dimensions(), matrix.dimensions());
+        assert!((0..100).all(|r| *mapped.row_slice(r) == *matrix.row_slice(r)));
+        assert_eq!(mapped.values.to_vec(), matrix.values);
+    }
+
+    #[test]
+    fn write_rows() {
+        let mut mapped = MmapMatrix::new_in(std::env::temp_dir(), 3, 4, F::ONE).unwrap();
+        mapped
+            .row_mut(2)
+            .copy_from_slice(&[F::ZERO, F::TWO, F::NEG_ONE]);
+        let mut expected = RowMajorMatrix::new(vec![F::ONE; 12], 3);
+        expected
+            .row_mut(2)
+            .copy_from_slice(&[F::ZERO, F::TWO, F::NEG_ONE]);
+        assert_eq!(mapped.values.to_vec(), expected.values);
+    }
+
+    #[test]
+    fn empty() {
+        let mapped = MmapStorage::<F>::from_vec(vec![]);
+        assert!(Borrow::<[F]>::borrow(&mapped).is_empty());
+    }
+}
@@ -6,14 +6,15 @@ use p3_blake3::Blake3;
 use p3_commit::Mmcs;
 use p3_field::{Field, PackedField, PackedValue};
 use p3_keccak::Keccak256Hash;
+use p3_koala_bear::{KoalaBear, Poseidon2KoalaBear};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_mds::integrated_coset_mds::IntegratedCosetMds;
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_rescue::Rescue;
 use p3_symmetric::{
-    CompressionFunctionFromHasher, CryptographicHasher, PaddingFreeSponge,
-    PseudoCompressionFunction, SerializingHasher32, TruncatedPermutation,
+    CompressionFunctionFromHasher, CryptographicHasher, CryptographicPermutation,
+    PaddingFreeSponge, PseudoCompressionFunction, SerializingHasher32, TruncatedPermutation,
 };
 use rand::SeedableRng;
 use rand::distr::{Distribution, StandardUniform};
@@ -22,31 +23,53 @@ use serde::Serialize;
 use serde::de::DeserializeOwned;
 
 fn bench_merkle_trees(criterion: &mut Criterion) {
-    bench_bb_poseidon2(criterion);
+    let mut rng = SmallRng::seed_from_u64(1);
+    bench_poseidon2::<BabyBear, _, _>(
+        criterion,
+        Poseidon2BabyBear::<16>::new_from_rng_128(&mut rng),
+        Poseidon2BabyBear::<24>::new_from_rng_128(&mut rng),
+    );
+    let mut rng = SmallRng::seed_from_u64(1);
+    bench_poseidon2::<KoalaBear, _, _>(
+        criterion,
+        Poseidon2KoalaBear::<16>::new_from_rng_128(&mut rng),
+        Poseidon2KoalaBear::<24>::new_from_rng_128(&mut rng),
+    );
     bench_bb_rescue(criterion);
     bench_bb_blake3(criterion);
     bench_bb_keccak(criterion);
 }
 
-fn bench_bb_poseidon2(criterion: &mut Criterion) {
-    type F = BabyBear;
+/// Benchmark trees over `F` hashed with a width 16 permutation `perm`, with arity 2, 4 and 8.
+///
+/// Higher arities compress their digests by absorbing them into a width 24 sponge over
+/// `wide_perm`, which takes 16 elements, or 2 digests, per permutation. So a node costs 2
+/// permutations in the 4-ary tree and 4 in the 8-ary tree, against 1 in the binary tree, while
+/// each layer of the tree is 2 or 3 binary layers. A single width 24 permutation could compress
+/// 3 digests, but `MerkleTreeMmcs` needs the arity to be a power of two.
+fn bench_poseidon2<F, Perm, WidePerm>(criterion: &mut Criterion, perm: Perm, wide_perm: WidePerm)
+where
+    F: Field,
+    Perm: CryptographicPermutation<[F; 16]> + CryptographicPermutation<[F::Packing; 16]>,
+    WidePerm: CryptographicPermutation<[F; 24]> + CryptographicPermutation<[F::Packing; 24]>,
+    [F; 8]: Serialize + DeserializeOwned,
+    StandardUniform: Distribution<F>,
+{
+    let h = PaddingFreeSponge::<Perm, 16, 8, 8>::new(perm.clone());
 
-    let mut rng = SmallRng::seed_from_u64(1);
-    type Perm = Poseidon2BabyBear<16>;
-    let perm = Perm::new_from_rng_128(&mut rng);
+    let c = TruncatedPermutation::<Perm, 2, 8, 16>::new(perm);
+    bench_mmcs::<F::Packing, F::Packing, _, _, 8, 2>(criterion, h.clone(), c.clone());
+    bench_merkle_tree::<F::Packing, F::Packing, _, _, 8, 2>(criterion, h.clone(), c);
 
-    type H = PaddingFreeSponge<Perm, 16, 8, 8>;
-    let h = H::new(perm.clone());
+    let wide_sponge = PaddingFreeSponge::<WidePerm, 24, 16, 8>::new(wide_perm);
 
-    type C = TruncatedPermutation<Perm, 2, 8, 16>;
-    let c = C::new(perm);
+    let c4 = CompressionFunctionFromHasher::<_, 4, 8>::new(wide_sponge.clone());
+    bench_mmcs::<F::Packing, F::Packing, _, _, 8, 4>(criterion, h.clone(), c4.clone());
+    bench_merkle_tree::<F::Packing, F::Packing, _, _, 8, 4>(criterion, h.clone(), c4);
 
-    bench_mmcs::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8>(
-        criterion,
-        h.clone(),
-        c.clone(),
-    );
-    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8>(criterion, h, c);
+    let c8 = CompressionFunctionFromHasher::<_, 8, 8>::new(wide_sponge);
+    bench_mmcs::<F::Packing, F::Packing, _, _, 8, 8>(criterion, h.clone(), c8.clone());
+    bench_merkle_tree::<F::Packing, F::Packing, _, _, 8, 8>(criterion, h, c8);
 }
 
 fn bench_bb_rescue(criterion: &mut Criterion) {
@@ -66,12 +89,12 @@ fn bench_bb_rescue(criterion: &mut Criterion) {
     type C = TruncatedPermutation<Perm, 2, 8, 16>;
     let c = C::new(perm);
 
-    bench_mmcs::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8>(
+    bench_mmcs::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8, 2>(
         criterion,
         h.clone(),
         c.clone(),
     );
-    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8>(criterion, h, c);
+    bench_merkle_tree::<<F as Field>::Packing, <F as Field>::Packing, H, C, 8, 2>(criterion, h, c);
 }
 
 fn bench_bb_blake3(criterion: &mut Criterion) {
@@ -84,8 +107,8 @@ fn bench_bb_blake3(criterion: &mut Criterion) {
     let b = Blake3 {};
     let c = C::new(b);
 
-    bench_mmcs::<F, u8, H, C, 32>(criterion, h, c.clone());
-    bench_merkle_tree::<F, u8, H, C, 32>(criterion, h, c);
+    bench_mmcs::<F, u8, H, C, 32, 2>(criterion, h, c.clone());
+    bench_merkle_tree::<F, u8, H, C, 32, 2>(criterion, h, c);
 }
 
 fn bench_bb_keccak(criterion: &mut Criterion) {
@@ -98,19 +121,22 @@ fn bench_bb_keccak(criterion: &mut Criterion) {
     type C = CompressionFunctionFromHasher<Keccak256Hash, 2, 32>;
     let c = C::new(k);
 
-    bench_mmcs::<F, u8, H, C, 32>(criterion, h, c.clone());
-    bench_merkle_tree::<F, u8, H, C, 32>(criterion, h, c);
+    bench_mmcs::<F, u8, H, C, 32, 2>(criterion, h, c.clone());
+    bench_merkle_tree::<F, u8, H, C, 32, 2>(criterion, h, c);
 }
 
-fn bench_merkle_tree<P, PW, H, C, const DIGEST_ELEMS: usize>(criterion: &mut Criterion, h: H, c: C)
-where
+fn bench_merkle_tree<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
+    criterion: &mut Criterion,
+    h: H,
+    c: C,
+) where
     P: PackedField,
     PW: PackedValue,
     H: CryptographicHasher<P::Scalar, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
         + Sync,
     [PW::Value; DIGEST_ELEMS]: Serialize + DeserializeOwned,
     StandardUniform: Distribution<P::Scalar>,
@@ -133,21 +159,24 @@ where
     let mut group = criterion.benchmark_group(name);
     group.sample_size(10);
 
-    let mmcs = MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS>::new(h, c);
+    let mmcs = MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS, ARITY>::new(h, c);
     group.bench_with_input(params, &leaves, |b, input| {
         b.iter(|| mmcs.commit(input.clone()))
     });
 }
 
-fn bench_mmcs<P, PW, H, C, const DIGEST_ELEMS: usize>(criterion: &mut Criterion, h: H, c: C)
-where
+fn bench_mmcs<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
+    criterion: &mut Criterion,
+    h: H,
+    c: C,
+) where
     P: PackedField,
     PW: PackedValue,
     H: CryptographicHasher<P::Scalar, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
         + Sync,
     [PW::Value; DIGEST_ELEMS]: Serialize + DeserializeOwned,
     StandardUniform: Distribution<P::Scalar>,
@@ -171,7 +200,7 @@ where
     let mut group = criterion.benchmark_group(name);
     group.sample_size(10);
 
-    let mmcs = MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS>::new(h, c);
+    let mmcs = MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS, ARITY>::new(h, c);
     group.bench_with_input(params, &leaves, |b, input| {
         b.iter(|| mmcs.commit(input.clone()))
     });
@@ -2,12 +2,12 @@ use alloc::vec::Vec;
 use core::cell::RefCell;
 
 use itertools::Itertools;
-use p3_commit::Mmcs;
+use p3_commit::{Mmcs, MmcsShape, MultiMmcs};
 use p3_field::PackedValue;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::stack::HorizontalPair;
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
 use p3_util::zip_eq::zip_eq;
 use rand::Rng;
 use rand::distr::{Distribution, StandardUniform};
@@ -35,18 +35,33 @@ use crate::{MerkleTree, MerkleTreeError, MerkleTreeMmcs};
 /// - `H`: the leaf hasher
 /// - `C`: the digest compression function
 /// - `R`: a random number generator for blinding leaves
+/// - `ARITY`: the number of children of each node, which must be a power of two
 #[derive(Clone, Debug)]
-pub struct MerkleTreeHidingMmcs<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-{
-    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>,
+pub struct MerkleTreeHidingMmcs<
+    P,
+    PW,
+    H,
+    C,
+    R,
+    const DIGEST_ELEMS: usize,
+    const SALT_ELEMS: usize,
+    const ARITY: usize = 2,
+> {
+    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>,
     rng: RefCell<R>,
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
 {
     pub fn new(hash: H, compress: C, rng: R) -> Self {
-        let inner = MerkleTreeMmcs::new(hash, compress);
+        Self::new_with_cap_height(hash, compress, 0, rng)
+    }
+
+    /// A hiding MMCS which commits to the `ARITY^cap_height` digests of each tree at `cap_height`
+    /// layers below the root; see [`MerkleTreeMmcs::new_with_cap_height`].
+    pub fn new_with_cap_height(hash: H, compress: C, cap_height: usize, rng: R) -> Self {
+        let inner = MerkleTreeMmcs::new_with_cap_height(hash, compress, cap_height);
         Self {
             inner,
             rng: rng.into(),
@@ -54,8 +69,8 @@ impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
     }
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    Mmcs<P::Value> for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
 where
     P: PackedValue,
     P::Value: Serialize + DeserializeOwned,
@@ -63,17 +78,22 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
         + Sync,
     R: Rng + Clone,
     PW::Value: Eq,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
     StandardUniform: Distribution<P::Value>,
 {
-    type ProverData<M> =
-        MerkleTree<P::Value, PW::Value, HorizontalPair<M, RowMajorMatrix<P::Value>>, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<
+        P::Value,
+        PW::Value,
+        HorizontalPair<M, RowMajorMatrix<P::Value>>,
+        DIGEST_ELEMS,
+        ARITY,
+    >;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
     /// The first item is salts; the second is the usual Merkle proof (sibling digests).
     type Proof = (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>);
     type Error = MerkleTreeError;
@@ -99,13 +119,7 @@ where
         prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<P::Value>>, Self::Proof) {
         let (salted_openings, siblings) = self.inner.open_batch(index, prover_data);
-        let (openings, salts): (Vec<_>, Vec<_>) = salted_openings
-            .into_iter()
-            .map(|row| {
-                let (a, b) = row.split_at(row.len() - SALT_ELEMS);
-                (a.to_vec(), b.to_vec())
-            })
-            .unzip();
+        let (openings, salts) = split_salts::<_, SALT_ELEMS>(salted_openings);
         (openings, (salts, siblings))
     }
 
@@ -116,6 +130,10 @@ where
         prover_data.leaves.iter().map(|mat| &mat.first).collect()
     }
 
+    fn shape(&self) -> MmcsShape {
+        self.inner.shape()
+    }
+
     fn verify_batch(
         &self,
         commit: &Self::Commitment,
@@ -125,23 +143,119 @@ where
         proof: &Self::Proof,
     ) -> Result<(), Self::Error> {
         let (salts, siblings) = proof;
+        if salts.iter().any(|salt| salt.len() != SALT_ELEMS) {
+            return Err(MerkleTreeError::WrongWidth);
+        }
 
         let opened_salted_values = zip_eq(opened_values, salts, MerkleTreeError::WrongBatchSize)?
             .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
             .collect_vec();
 
-        self.inner
-            .verify_batch(commit, dimensions, index, &opened_salted_values, siblings)
+        self.inner.verify_batch(
+            commit,
+            &salted_dimensions::<SALT_ELEMS>(dimensions),
+            index,
+            &opened_salted_values,
+            siblings,
+        )
+    }
+}
+
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    MultiMmcs<P::Value> for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
+where
+    P: PackedValue,
+    P::Value: Serialize + DeserializeOwned,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
+        + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
+        + Sync,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
+        + Sync,
+    R: Rng + Clone,
+    PW::Value: Eq,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+    StandardUniform: Distribution<P::Value>,
+{
+    /// The first item is the salts at each index; the second is the usual Merkle multi-proof.
+    type MultiProof = (Vec<Vec<Vec<P::Value>>>, Vec<[PW::Value; DIGEST_ELEMS]>);
+
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<P::Value>>>, Self::MultiProof) {
+        let (salted_openings, siblings) = self.inner.open_multi_batch(indices, prover_data);
+        let (openings, salts) = salted_openings
+            .into_iter()
+            .map(split_salts::<_, SALT_ELEMS>)
+            .unzip();
+        (openings, (salts, siblings))
+    }
+
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        let (salts, siblings) = proof;
+        if salts.iter().flatten().any(|salt| salt.len() != SALT_ELEMS) {
+            return Err(MerkleTreeError::WrongWidth);
+        }
+
+        let opened_salted_values = zip_eq(opened_values, salts, MerkleTreeError::WrongBatchSize)?
+            .map(|(opened, salts)| {
+                Ok(zip_eq(opened, salts, MerkleTreeError::WrongBatchSize)?
+                    .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
+                    .collect_vec())
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.inner.verify_multi_batch(
+            commit,
+            &salted_dimensions::<SALT_ELEMS>(dimensions),
+            indices,
+            &opened_salted_values,
+            siblings,
+        )
     }
 }
 
+/// The dimensions of the salted matrices, which hold the salts in extra columns.
+fn salted_dimensions<const SALT_ELEMS: usize>(dimensions: &[Dimensions]) -> Vec<Dimensions> {
+    dimensions
+        .iter()
+        .map(|dims| Dimensions {
+            width: dims.width + SALT_ELEMS,
+            height: dims.height,
+        })
+        .collect()
+}
+
+/// Split the salts off the end of each salted row.
+fn split_salts<T: Clone, const SALT_ELEMS: usize>(
+    salted_rows: Vec<Vec<T>>,
+) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+    salted_rows
+        .into_iter()
+        .map(|row| {
+            let (a, b) = row.split_at(row.len() - SALT_ELEMS);
+            (a.to_vec(), b.to_vec())
+        })
+        .unzip()
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
 
     use itertools::Itertools;
     use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
-    use p3_commit::Mmcs;
+    use p3_commit::{Mmcs, MultiMmcs};
     use p3_field::{Field, PrimeCharacteristicRing};
     use p3_matrix::Matrix;
     use p3_matrix::dense::RowMajorMatrix;
@@ -199,6 +313,26 @@ mod tests {
 
         let (commit, prover_data) = mmcs.commit(mats);
         let (opened_values, proof) = mmcs.open_batch(17, &prover_data);
-        mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
+        mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)?;
+
+        // A value moved from one row to the next, or from a salt to the end of its row.
+        let mut tampered = opened_values.clone();
+        let value = tampered[3].pop().unwrap();
+        tampered[4].insert(0, value);
+        assert!(matches!(
+            mmcs.verify_batch(&commit, &dims, 17, &tampered, &proof),
+            Err(MerkleTreeError::WrongWidth)
+        ));
+        let mut tampered = opened_values;
+        let mut tampered_proof = proof.clone();
+        tampered[3].push(tampered_proof.0[3].remove(0));
+        assert!(matches!(
+            mmcs.verify_batch(&commit, &dims, 17, &tampered, &tampered_proof),
+            Err(MerkleTreeError::WrongWidth)
+        ));
+
+        let indices = [17, 3, 17, 30];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
     }
 }
@@ -3,62 +3,163 @@ use alloc::vec::Vec;
 use core::array;
 use core::cmp::Reverse;
 use core::marker::PhantomData;
+#[cfg(feature = "mmap")]
+use core::mem;
+use core::ops::Deref;
 
 use itertools::Itertools;
 use p3_field::PackedValue;
 use p3_matrix::Matrix;
+#[cfg(feature = "mmap")]
+use p3_matrix::dense::OwnedDenseStorage;
+#[cfg(feature = "mmap")]
+use p3_matrix::mmap::MmapStorage;
 use p3_maybe_rayon::prelude::*;
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
-use serde::{Deserialize, Serialize};
+use p3_symmetric::{CryptographicHasher, Hash, MerkleCap, PseudoCompressionFunction};
+use p3_util::{log2_ceil_usize, log2_strict_usize};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use tracing::instrument;
 
-/// A binary Merkle tree for packed data. It has leaves of type `F` and digests of type
-/// `[W; DIGEST_ELEMS]`.
+/// A Merkle tree for packed data, in which each node has `ARITY` children. It has leaves of type
+/// `F` and digests of type `[W; DIGEST_ELEMS]`.
 ///
 /// This generally shouldn't be used directly. If you're using a Merkle tree as an MMCS,
 /// see `MerkleTreeMmcs`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
+pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize, const ARITY: usize = 2> {
     pub(crate) leaves: Vec<M>,
     // Enable serialization for this type whenever the underlying array type supports it (len 1-32).
     #[serde(bound(serialize = "[W; DIGEST_ELEMS]: Serialize"))]
     // Enable deserialization for this type whenever the underlying array type supports it (len 1-32).
     #[serde(bound(deserialize = "[W; DIGEST_ELEMS]: Deserialize<'de>"))]
-    pub(crate) digest_layers: Vec<Vec<[W; DIGEST_ELEMS]>>,
+    pub(crate) digest_layers: Vec<DigestLayer<[W; DIGEST_ELEMS]>>,
     _phantom: PhantomData<F>,
 }
 
-impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
-    MerkleTree<F, W, M, DIGEST_ELEMS>
+/// Where a [`MerkleTree`] keeps its digest layers.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum DigestStorage {
+    /// On the heap.
+    #[default]
+    Memory,
+    /// In memory-mapped temporary files in `std::env::temp_dir()`, so that the OS can page them out
+    /// to disk. Each layer is moved there once it's complete, except for the root.
+    #[cfg(feature = "mmap")]
+    Disk,
+}
+
+/// A layer of digests, kept as given by a [`DigestStorage`]. It's serialized as a sequence of
+/// digests, and deserialized into memory.
+#[derive(Debug)]
+pub(crate) enum DigestLayer<T> {
+    Memory(Vec<T>),
+    #[cfg(feature = "mmap")]
+    Disk(MmapStorage<T>),
+}
+
+impl<T: Copy + Send + Sync> DigestLayer<T> {
+    /// Move the digests to `storage`, if they aren't there already.
+    fn store(&mut self, storage: DigestStorage) {
+        match storage {
+            DigestStorage::Memory => {}
+            #[cfg(feature = "mmap")]
+            DigestStorage::Disk => {
+                if let Self::Memory(digests) = self {
+                    *self = Self::Disk(MmapStorage::from_vec(mem::take(digests)));
+                }
+            }
+        }
+    }
+}
+
+impl<T> Deref for DigestLayer<T> {
+    type Target = [T];
+
+    fn deref(&self) -> &[T] {
+        match self {
+            Self::Memory(digests) => digests,
+            #[cfg(feature = "mmap")]
+            Self::Disk(digests) => core::borrow::Borrow::borrow(digests),
+        }
+    }
+}
+
+impl<T: Serialize> Serialize for DigestLayer<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        (**self).serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for DigestLayer<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        Vec::deserialize(deserializer).map(Self::Memory)
+    }
+}
+
+impl<
+    F: Clone + Send + Sync,
+    W: Copy + Send + Sync,
+    M: Matrix<F>,
+    const DIGEST_ELEMS: usize,
+    const ARITY: usize,
+> MerkleTree<F, W, M, DIGEST_ELEMS, ARITY>
 {
     /// Matrix heights need not be powers of two. However, if the heights of two given matrices
     /// round up to the same power of two, they must be equal.
+    ///
+    /// `ARITY` must be a power of two. The rows of a shorter matrix are mixed into the layer given
+    /// by [`injection_layer`].
+    pub fn new<P, PW, H, C>(h: &H, c: &C, leaves: Vec<M>) -> Self
+    where
+        P: PackedValue<Value = F>,
+        PW: PackedValue<Value = W>,
+        H: CryptographicHasher<F, [W; DIGEST_ELEMS]>
+            + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
+            + Sync,
+        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], ARITY>
+            + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
+            + Sync,
+    {
+        Self::new_with_digest_storage::<P, PW, H, C>(h, c, leaves, DigestStorage::Memory)
+    }
+
+    /// A tree as given by [`MerkleTree::new`], whose digest layers are kept in `digest_storage`.
     #[instrument(name = "build merkle tree", level = "debug", skip_all,
                  fields(dimensions = alloc::format!("{:?}", leaves.iter().map(|l| l.dimensions()).collect::<Vec<_>>())))]
-    pub fn new<P, PW, H, C>(h: &H, c: &C, leaves: Vec<M>) -> Self
+    pub fn new_with_digest_storage<P, PW, H, C>(
+        h: &H,
+        c: &C,
+        leaves: Vec<M>,
+        digest_storage: DigestStorage,
+    ) -> Self
     where
         P: PackedValue<Value = F>,
         PW: PackedValue<Value = W>,
         H: CryptographicHasher<F, [W; DIGEST_ELEMS]>
             + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
             + Sync,
-        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], 2>
-            + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], ARITY>
+            + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
             + Sync,
     {
         assert!(!leaves.is_empty(), "No matrices given?");
 
+        assert!(
+            ARITY >= 2 && ARITY.is_power_of_two(),
+            "Arity must be a power of two"
+        );
+
         assert_eq!(P::WIDTH, PW::WIDTH, "Packing widths must match");
 
-        let mut leaves_largest_first = leaves
+        let leaves_largest_first = leaves
             .iter()
             .sorted_by_key(|l| Reverse(l.height()))
-            .peekable();
+            .collect_vec();
 
         // check height property
         assert!(
             leaves_largest_first
-                .clone()
+                .iter()
                 .map(|m| m.height())
                 .tuple_windows()
                 .all(|(curr, next)| curr == next
@@ -66,34 +167,50 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
             "matrix heights that round up to the same power of two must be equal"
         );
 
-        let max_height = leaves_largest_first.peek().unwrap().height();
-        let tallest_matrices = leaves_largest_first
-            .peeking_take_while(|m| m.height() == max_height)
-            .collect_vec();
+        let max_height = leaves_largest_first[0].height();
+        let log_max_height = log2_ceil_usize(max_height);
+        let log_arity = log2_strict_usize(ARITY);
+        let mut matrices_by_height = leaves_largest_first
+            .into_iter()
+            .chunk_by(|m| m.height())
+            .into_iter()
+            .map(|(height, group)| (height, group.collect_vec()))
+            .collect_vec()
+            .into_iter()
+            .peekable();
 
-        let mut digest_layers = vec![first_digest_layer::<P, _, _, _, DIGEST_ELEMS>(
-            h,
-            tallest_matrices,
-        )];
+        let (_, tallest_matrices) = matrices_by_height.next().unwrap();
+        let first_layer =
+            first_digest_layer::<P, PW, H, M, DIGEST_ELEMS, ARITY>(h, &tallest_matrices);
+        let mut digest_layers = vec![DigestLayer::Memory(first_layer)];
         loop {
-            let prev_layer = digest_layers.last().unwrap().as_slice();
+            // Mix in the rows of all matrices which get injected at this layer. Only the nodes
+            // computed from the previous layer are changed, and not the padding.
+            let layer_index = digest_layers.len() - 1;
+            let num_nodes = max_height.div_ceil(1 << (layer_index * log_arity));
+            while let Some((height, matrices)) = matrices_by_height.next_if(|&(height, _)| {
+                injection_layer::<ARITY>(log_max_height, height).0 == layer_index
+            }) {
+                let (_, shift) = injection_layer::<ARITY>(log_max_height, height);
+                let row_digests = hash_rows::<P, PW, H, M, DIGEST_ELEMS>(h, &matrices);
+                let Some(DigestLayer::Memory(layer)) = digest_layers.last_mut() else {
+                    unreachable!("layers are only stored once complete")
+                };
+                inject::<PW, C, DIGEST_ELEMS, ARITY>(
+                    &mut layer[..num_nodes],
+                    &row_digests,
+                    shift,
+                    c,
+                );
+            }
+
+            let prev_layer = digest_layers.last_mut().unwrap();
             if prev_layer.len() == 1 {
                 break;
             }
-            let next_layer_len = (prev_layer.len() / 2).next_power_of_two();
-
-            // The matrices that get injected at this layer.
-            let matrices_to_inject = leaves_largest_first
-                .peeking_take_while(|m| m.height().next_power_of_two() == next_layer_len)
-                .collect_vec();
-
-            let next_digests = compress_and_inject::<P, _, _, _, _, DIGEST_ELEMS>(
-                prev_layer,
-                matrices_to_inject,
-                h,
-                c,
-            );
-            digest_layers.push(next_digests);
+            prev_layer.store(digest_storage);
+            let next_digests = compress::<PW, C, DIGEST_ELEMS, ARITY>(prev_layer, c);
+            digest_layers.push(DigestLayer::Memory(next_digests));
         }
 
         Self {
@@ -104,18 +221,108 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
     }
 
     #[must_use]
-    pub fn root(&self) -> Hash<F, W, DIGEST_ELEMS>
-    where
-        W: Copy,
-    {
+    pub fn root(&self) -> Hash<F, W, DIGEST_ELEMS> {
         self.digest_layers.last().unwrap()[0].into()
     }
+
+    /// The digests of the layer `cap_height` below the root.
+    ///
+    /// The cap is taken closer to the root if a matrix is injected above that layer, so that it
+    /// still commits to every matrix; see [`cap_height_for`].
+    #[must_use]
+    pub fn cap(&self, cap_height: usize) -> MerkleCap<F, W, DIGEST_ELEMS> {
+        let heights = self.leaves.iter().map(|m| m.height());
+        let cap_height = cap_height_for::<ARITY>(
+            cap_height,
+            heights.clone().max().unwrap(),
+            heights.min().unwrap(),
+        );
+        self.digest_layers[self.digest_layers.len() - 1 - cap_height]
+            .to_vec()
+            .into()
+    }
+}
+
+/// The number of layers above the leaves of a tree with `ARITY` children per node, whose tallest
+/// matrix has height at most `2^log_max_height`.
+#[must_use]
+pub fn tree_height<const ARITY: usize>(log_max_height: usize) -> usize {
+    log_max_height.div_ceil(log2_strict_usize(ARITY))
+}
+
+/// The layer, counted from the leaves, at which the rows of a matrix of height `height` are mixed
+/// into a tree with `ARITY` children per node, whose tallest matrix has height at most
+/// `2^log_max_height`.
+///
+/// This is the highest layer in which each node lies above leaves of a single row of the matrix.
+/// Returns the layer along with a `shift`, such that node `i` of the layer is mixed with row
+/// `i >> shift`; the shift is always zero for binary trees.
+#[must_use]
+pub fn injection_layer<const ARITY: usize>(log_max_height: usize, height: usize) -> (usize, usize) {
+    let log_arity = log2_strict_usize(ARITY);
+    let bits_reduced = log_max_height - log2_ceil_usize(height);
+    (bits_reduced / log_arity, bits_reduced % log_arity)
+}
+
+/// The height of the cap actually used for a tree with `ARITY` children per node, whose tallest
+/// and shortest matrices have `max_height` and `min_height` rows.
+///
+/// Rows of shorter matrices are injected closer to the root, and a cap below them would not commit
+/// to them, so the cap is at most as low as the layer at which the shortest matrix is injected.
+#[must_use]
+pub fn cap_height_for<const ARITY: usize>(
+    cap_height: usize,
+    max_height: usize,
+    min_height: usize,
+) -> usize {
+    let log_max_height = log2_ceil_usize(max_height);
+    let (layer, _) = injection_layer::<ARITY>(log_max_height, min_height);
+    cap_height.min(tree_height::<ARITY>(log_max_height) - layer)
 }
 
+/// The inputs of the compression which mixes an injected digest into a node: the node, then the
+/// injected digest, then default digests up to the arity.
+pub(crate) fn injection_input<T: Copy, const ARITY: usize>(
+    node: T,
+    injected: T,
+    default: T,
+) -> [T; ARITY] {
+    array::from_fn(|i| match i {
+        0 => node,
+        1 => injected,
+        _ => default,
+    })
+}
+
+/// Hash the rows of the tallest matrices, padded to a multiple of the arity of digests.
 #[instrument(name = "first digest layer", level = "debug", skip_all)]
-fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize>(
+fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize, const ARITY: usize>(
     h: &H,
-    tallest_matrices: Vec<&M>,
+    tallest_matrices: &[&M],
+) -> Vec<[PW::Value; DIGEST_ELEMS]>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
+        + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
+        + Sync,
+    M: Matrix<P::Value>,
+{
+    let mut digests = hash_rows::<P, PW, H, M, DIGEST_ELEMS>(h, tallest_matrices);
+    // We always want a multiple of the arity of digests, except when it's the root.
+    if digests.len() > 1 {
+        digests.resize(
+            digests.len().next_multiple_of(ARITY),
+            [PW::Value::default(); DIGEST_ELEMS],
+        );
+    }
+    digests
+}
+
+/// Hash the rows of some matrices of the same height, giving one digest per row.
+fn hash_rows<P, PW, H, M, const DIGEST_ELEMS: usize>(
+    h: &H,
+    matrices: &[&M],
 ) -> Vec<[PW::Value; DIGEST_ELEMS]>
 where
     P: PackedValue,
@@ -126,24 +333,18 @@ where
     M: Matrix<P::Value>,
 {
     let width = PW::WIDTH;
-    let max_height = tallest_matrices[0].height();
-    // we always want to return an even number of digests, except when it's the root.
-    let max_height_padded = if max_height == 1 {
-        1
-    } else {
-        max_height + max_height % 2
-    };
+    let height = matrices[0].height();
 
     let default_digest = [PW::Value::default(); DIGEST_ELEMS];
-    let mut digests = vec![default_digest; max_height_padded];
+    let mut digests = vec![default_digest; height];
 
-    digests[0..max_height]
+    digests
         .par_chunks_exact_mut(width)
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
             let packed_digest: [PW; DIGEST_ELEMS] = h.hash_iter(
-                tallest_matrices
+                matrices
                     .iter()
                     .flat_map(|m| m.vertically_packed_row(first_row)),
             );
@@ -152,114 +353,80 @@ where
             }
         });
 
-    // If our packing width did not divide max_height, fall back to single-threaded scalar code
+    // If our packing width did not divide height, fall back to single-threaded scalar code
     // for the last bit.
     #[allow(clippy::needless_range_loop)]
-    for i in (max_height / width * width)..max_height {
-        digests[i] = h.hash_iter(tallest_matrices.iter().flat_map(|m| m.row(i)));
+    for i in (height / width * width)..height {
+        digests[i] = h.hash_iter(matrices.iter().flat_map(|m| m.row(i)));
     }
 
-    // Everything has been initialized so we can safely cast.
     digests
 }
 
-/// Compress `n` digests from the previous layer into `n/2` digests, while potentially mixing in
-/// some leaf data, if there are input matrices with (padded) height `n/2`.
-fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize>(
-    prev_layer: &[[PW::Value; DIGEST_ELEMS]],
-    matrices_to_inject: Vec<&M>,
-    h: &H,
+/// Mix the digests of the rows of some matrices into the nodes of a layer, where node `i` receives
+/// the digest of row `i >> shift`. Nodes past the last row receive the default digest instead.
+fn inject<P, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
+    layer: &mut [[P::Value; DIGEST_ELEMS]],
+    row_digests: &[[P::Value; DIGEST_ELEMS]],
+    shift: usize,
     c: &C,
-) -> Vec<[PW::Value; DIGEST_ELEMS]>
-where
+) where
     P: PackedValue,
-    PW: PackedValue,
-    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
-        + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
-        + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[P; DIGEST_ELEMS], ARITY>
         + Sync,
-    M: Matrix<P::Value>,
 {
-    if matrices_to_inject.is_empty() {
-        return compress::<PW, _, DIGEST_ELEMS>(prev_layer, c);
-    }
-
-    let width = PW::WIDTH;
-    let next_len = matrices_to_inject[0].height();
-    // We always want to return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        // Round prev_layer.len() / 2 up to the next even integer.
-        (prev_layer.len() / 2 + 1) & !1
+    let width = P::WIDTH;
+    let len = layer.len();
+    let default_digest = [P::Value::default(); DIGEST_ELEMS];
+    let packed_default_digest = [P::from_fn(|_| P::Value::default()); DIGEST_ELEMS];
+    let injected = |i: usize| {
+        row_digests
+            .get(i >> shift)
+            .copied()
+            .unwrap_or(default_digest)
     };
 
-    let default_digest = [PW::Value::default(); DIGEST_ELEMS];
-    let mut next_digests = vec![default_digest; next_len_padded];
-    next_digests[0..next_len]
+    layer[..len / width * width]
         .par_chunks_exact_mut(width)
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let mut packed_digest = c.compress([left, right]);
-            let tallest_digest = h.hash_iter(
-                matrices_to_inject
-                    .iter()
-                    .flat_map(|m| m.vertically_packed_row(first_row)),
-            );
-            packed_digest = c.compress([packed_digest, tallest_digest]);
+            let node = array::from_fn(|j| P::from_fn(|k| digests_chunk[k][j]));
+            let rows_digest = array::from_fn(|j| P::from_fn(|k| injected(first_row + k)[j]));
+            let packed_digest =
+                c.compress(injection_input(node, rows_digest, packed_default_digest));
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
         });
 
-    // If our packing width did not divide next_len, fall back to single-threaded scalar code
+    // If our packing width did not divide len, fall back to single-threaded scalar code
     // for the last bit.
-    for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
-        let rows_digest = h.hash_iter(matrices_to_inject.iter().flat_map(|m| m.row(i)));
-        next_digests[i] = c.compress([digest, rows_digest]);
-    }
-
-    // At this point, we've exceeded the height of the matrices to inject, so we continue the
-    // process above except with default_digest in place of an input digest.
-    // We only need go as far as half the length of the previous layer.
-    for i in next_len..(prev_layer.len() / 2) {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
-        next_digests[i] = c.compress([digest, default_digest]);
+    for (i, node) in layer.iter_mut().enumerate().skip(len / width * width) {
+        *node = c.compress(injection_input(*node, injected(i), default_digest));
     }
-
-    next_digests
 }
 
-/// Compress `n` digests from the previous layer into `n/2` digests.
-fn compress<P, C, const DIGEST_ELEMS: usize>(
+/// Compress `n` digests from the previous layer into `n / ARITY` digests.
+fn compress<P, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
     prev_layer: &[[P::Value; DIGEST_ELEMS]],
     c: &C,
 ) -> Vec<[P::Value; DIGEST_ELEMS]>
 where
     P: PackedValue,
-    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[P; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[P; DIGEST_ELEMS], ARITY>
         + Sync,
 {
     let width = P::WIDTH;
-    // Always return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
+    let next_len = prev_layer.len() / ARITY;
+    // Always return a multiple of the arity of digests, except when it's the root.
+    let next_len_padded = if prev_layer.len() == ARITY {
         1
     } else {
-        // Round prev_layer.len() / 2 up to the next even integer.
-        (prev_layer.len() / 2 + 1) & !1
+        next_len.next_multiple_of(ARITY)
     };
-    let next_len = prev_layer.len() / 2;
 
     let default_digest = [P::Value::default(); DIGEST_ELEMS];
     let mut next_digests = vec![default_digest; next_len_padded];
@@ -269,9 +436,10 @@ where
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| P::from_fn(|k| prev_layer[ARITY * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -280,9 +448,7 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        next_digests[i] = c.compress([left, right]);
+        next_digests[i] = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
     }
 
     // Everything has been initialized so we can safely cast.
@@ -327,7 +493,7 @@ mod tests {
             [0x03; 32], // 0x01 ^ 0x02
             [0x07; 32], // 0x03 ^ 0x04
         ];
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 
@@ -339,7 +505,7 @@ mod tests {
             [0x03; 32], // 0x05 ^ 0x06
             [0x00; 32],
         ];
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 
@@ -358,7 +524,7 @@ mod tests {
                 result
             })
             .collect();
-        let result = compress::<u8, DummyCompressionFunction, 32>(&prev_layer, &compressor);
+        let result = compress::<u8, DummyCompressionFunction, 32, 2>(&prev_layer, &compressor);
         assert_eq!(result, expected);
     }
 }
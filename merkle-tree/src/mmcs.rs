@@ -18,22 +18,36 @@
 //! E.g. we start by making a standard MerkleTree commitment for each row of M and then add in the rows of N when we
 //! get to the correct level. A proof for the values of say `M[5]` and `N[1]` consists of the siblings `H(M[4]), c23, c10`.
 //!
+//! With a cap height of 1, the commitment is instead the cap `[c10, c11]`, and the same proof consists of the siblings
+//! `H(M[4]), c23`.
+//!
+//! Trees may also have a higher arity, a power of two, in which case each node is the compression of all its children,
+//! and a proof holds all siblings of each node on the path. The rows of a shorter matrix are then mixed in, as
+//! `C(node, H(rows), default, ...)`, at the highest layer in which each node lies above a single row of the matrix.
+//! E.g. in a 4-ary tree committing to M with 16 rows, the 4 rows of N are mixed into the 4 nodes one layer above the
+//! leaves, while the 8 rows of a matrix N' would be mixed into the leaves, with leaf `i` getting the row `N'[i >> 1]`.
+//!
 
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
 use core::cmp::Reverse;
 use core::marker::PhantomData;
+use core::mem::size_of;
 
 use itertools::Itertools;
-use p3_commit::Mmcs;
+use p3_commit::{Mmcs, MmcsShape, MultiMmcs};
 use p3_field::PackedValue;
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
 use p3_util::{log2_ceil_usize, log2_strict_usize};
 use serde::{Deserialize, Serialize};
 
-use crate::MerkleTree;
 use crate::MerkleTreeError::{
-    EmptyBatch, IncompatibleHeights, RootMismatch, WrongBatchSize, WrongHeight,
+    EmptyBatch, IncompatibleHeights, RootMismatch, WrongBatchSize, WrongCapSize, WrongHeight,
+    WrongWidth,
+};
+use crate::{
+    DigestStorage, MerkleTree, cap_height_for, injection_input, injection_layer, tree_height,
 };
 
 /// A vector commitment scheme backed by a `MerkleTree`.
@@ -43,10 +57,21 @@ use crate::MerkleTreeError::{
 /// - `PW`: an element of a digest
 /// - `H`: the leaf hasher
 /// - `C`: the digest compression function
+/// - `ARITY`: the number of children of each node, which must be a power of two
+///
+/// As the layers of the tree have power of two heights, a compression of 3 digests, such as a
+/// width 24 permutation over 8-element digests, can't be used. A 4-ary or 8-ary tree instead
+/// needs a compression of 4 or 8 digests, e.g. absorbing them into a sponge, which takes more than
+/// one permutation per node.
+///
+/// The commitment is the cap of the tree at `cap_height` layers below the root, so that opening
+/// proofs stop there. The digest layers of committed trees are kept as given by `digest_storage`.
 #[derive(Copy, Clone, Debug)]
-pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize> {
+pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize = 2> {
     hash: H,
     compress: C,
+    cap_height: usize,
+    digest_storage: DigestStorage,
     _phantom: PhantomData<(P, PW)>,
 }
 
@@ -59,36 +84,129 @@ pub enum MerkleTreeError {
         num_siblings: usize,
     },
     IncompatibleHeights,
+    /// The commitment doesn't have the `ARITY^cap_height` digests of a cap of this MMCS's height,
+    /// reduced as in [`cap_height_for`] for short trees.
+    WrongCapSize {
+        expected: usize,
+        actual: usize,
+    },
     RootMismatch,
     EmptyBatch,
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS> {
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
+{
+    /// An MMCS which commits to the root of each tree.
     pub const fn new(hash: H, compress: C) -> Self {
+        Self::new_with_cap_height(hash, compress, 0)
+    }
+
+    /// An MMCS which commits to the `ARITY^cap_height` digests of each tree at `cap_height` layers
+    /// below the root.
+    pub const fn new_with_cap_height(hash: H, compress: C, cap_height: usize) -> Self {
+        Self::new_with_digest_storage(hash, compress, cap_height, DigestStorage::Memory)
+    }
+
+    /// An MMCS as given by [`MerkleTreeMmcs::new_with_cap_height`], which keeps the digest layers
+    /// of committed trees in `digest_storage`, e.g. on disk for trees too large for memory.
+    pub const fn new_with_digest_storage(
+        hash: H,
+        compress: C,
+        cap_height: usize,
+        digest_storage: DigestStorage,
+    ) -> Self {
         Self {
             hash,
             compress,
+            cap_height,
+            digest_storage,
             _phantom: PhantomData,
         }
     }
+
+    /// The number of layers between the cap and the leaves of a tree committing to matrices of
+    /// the given heights, which is the number of layers of siblings in an opening proof.
+    fn path_len(&self, heights: impl Iterator<Item = usize> + Clone) -> usize {
+        let max_height = heights.clone().max().unwrap();
+        let cap_height =
+            cap_height_for::<ARITY>(self.cap_height, max_height, heights.min().unwrap());
+        tree_height::<ARITY>(log2_ceil_usize(max_height)) - cap_height
+    }
+
+    /// Check that `commit` has as many digests as the cap of a tree committing to matrices of the
+    /// given heights.
+    fn check_cap_len(
+        &self,
+        commit: &MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>,
+        heights: impl Iterator<Item = usize> + Clone,
+    ) -> Result<(), MerkleTreeError>
+    where
+        P: PackedValue,
+        PW: PackedValue,
+    {
+        let max_height = heights.clone().max().unwrap();
+        let cap_height =
+            cap_height_for::<ARITY>(self.cap_height, max_height, heights.min().unwrap());
+        let expected = ARITY.pow(cap_height as u32);
+        if commit.len() != expected {
+            return Err(WrongCapSize {
+                expected,
+                actual: commit.len(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The rows of each committed matrix at `index`, reduced to the height of each matrix.
+    fn open_rows<M: Matrix<P::Value>>(
+        &self,
+        index: usize,
+        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY>,
+    ) -> Vec<Vec<P::Value>>
+    where
+        P: PackedValue,
+        PW: PackedValue,
+    {
+        let log_max_height = log2_ceil_usize(
+            prover_data
+                .leaves
+                .iter()
+                .map(|matrix| matrix.height())
+                .max()
+                .unwrap_or_else(|| panic!("No committed matrices?")),
+        );
+        prover_data
+            .leaves
+            .iter()
+            .map(|matrix| {
+                let log2_height = log2_ceil_usize(matrix.height());
+                let bits_reduced = log_max_height - log2_height;
+                let reduced_index = index >> bits_reduced;
+                matrix.row(reduced_index).collect()
+            })
+            .collect_vec()
+    }
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize> Mmcs<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
 where
     P: PackedValue,
     PW: PackedValue,
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
         + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
         + Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>
-        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
         + Sync,
     PW::Value: Eq,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
 {
-    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY>;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
+    /// The `ARITY - 1` siblings of each node on the path from the leaf to the cap, in increasing
+    /// order of index within a layer.
     type Proof = Vec<[PW::Value; DIGEST_ELEMS]>;
     type Error = MerkleTreeError;
 
@@ -96,9 +214,14 @@ where
         &self,
         inputs: Vec<M>,
     ) -> (Self::Commitment, Self::ProverData<M>) {
-        let tree = MerkleTree::new::<P, PW, H, C>(&self.hash, &self.compress, inputs);
-        let root = tree.root();
-        (root, tree)
+        let tree = MerkleTree::new_with_digest_storage::<P, PW, H, C>(
+            &self.hash,
+            &self.compress,
+            inputs,
+            self.digest_storage,
+        );
+        let cap = tree.cap(self.cap_height);
+        (cap, tree)
     }
 
     /// Opens a batch of rows from committed matrices.
@@ -107,30 +230,29 @@ where
     /// the `j`th row of the ith matrix `M[i]`, with
     ///     `j == index >> (log2_ceil(max_height) - log2_ceil(M[i].height))`
     /// and `proof` is the vector of sibling Merkle tree nodes allowing the verifier to
-    /// reconstruct the committed root.
+    /// reconstruct the committed node of the cap.
     fn open_batch<M: Matrix<P::Value>>(
         &self,
         index: usize,
-        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>,
+        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY>,
     ) -> (Vec<Vec<P::Value>>, Self::Proof) {
-        let max_height = self.get_max_height(prover_data);
-        let log_max_height = log2_ceil_usize(max_height);
+        let path_len = self.path_len(prover_data.leaves.iter().map(|m| m.height()));
+        let log_arity = log2_strict_usize(ARITY);
 
         // Get the matrix rows encountered along the path from the root to the given leaf index.
-        let openings = prover_data
-            .leaves
+        let openings = self.open_rows(index, prover_data);
+
+        // Get all the siblings nodes corresponding to the path from the cap to the given leaf index.
+        let proof = prover_data.digest_layers[..path_len]
             .iter()
-            .map(|matrix| {
-                let log2_height = log2_ceil_usize(matrix.height());
-                let bits_reduced = log_max_height - log2_height;
-                let reduced_index = index >> bits_reduced;
-                matrix.row(reduced_index).collect()
+            .enumerate()
+            .flat_map(|(i, layer)| {
+                let index = index >> (i * log_arity);
+                let first_child = index & !(ARITY - 1);
+                (first_child..first_child + ARITY)
+                    .filter(move |&child| child != index)
+                    .map(|child| layer[child])
             })
-            .collect_vec();
-
-        // Get all the siblings nodes corresponding to the path from the root to the given leaf index.
-        let proof = (0..log_max_height)
-            .map(|i| prover_data.digest_layers[i][(index >> i) ^ 1])
             .collect();
 
         (openings, proof)
@@ -145,16 +267,25 @@ where
 
     /// Verifies an opened batch of rows with respect to a given commitment.
     ///
-    /// - `commit`: The merkle root of the tree.
+    /// - `commit`: The merkle cap of the tree.
     /// - `dimensions`: A vector of the dimensions of the matrices committed to.
     /// - `index`: The index of a leaf in the tree.
     /// - `opened_values`: A vector of matrix rows. Assume that the tallest matrix committed
     ///   to has height `2^n >= M_tall.height() > 2^{n - 1}` and the `j`th matrix has height
-    ///   `2^m >= Mj.height() > 2^{m - 1}`. Then `j`'th value of opened values must be the row `Mj[index >> (m - n)]`.
-    /// - `proof`: A vector of sibling nodes. The `i`th element should be the node at level `i`
-    ///   with index `(index << i) ^ 1`.
+    ///   `2^m >= Mj.height() > 2^{m - 1}`. Then `j`'th value of opened values must be the row `Mj[index >> (m - n)]`,
+    ///   of the width given in `dimensions`.
+    /// - `proof`: A vector of sibling nodes. For the `i`th layer, it should hold the `ARITY - 1`
+    ///   nodes other than `index >> (i * log2(ARITY))` with the same parent, up to the cap.
     ///
     /// Returns nothing if the verification is successful, otherwise returns an error.
+    fn shape(&self) -> MmcsShape {
+        MmcsShape {
+            arity: ARITY,
+            cap_height: self.cap_height,
+            digest_bytes: size_of::<[PW::Value; DIGEST_ELEMS]>(),
+        }
+    }
+
     fn verify_batch(
         &self,
         commit: &Self::Commitment,
@@ -168,12 +299,13 @@ where
             return Err(WrongBatchSize);
         }
 
-        // TODO: Disabled for now since TwoAdicFriPcs and CirclePcs currently pass 0 for width.
-        // for (dims, opened_vals) in zip_eq(dimensions.iter(), opened_values) {
-        //     if opened_vals.len() != dims.width {
-        //         return Err(WrongWidth);
-        //     }
-        // }
+        if dimensions
+            .iter()
+            .zip(opened_values)
+            .any(|(dims, opened_vals)| opened_vals.len() != dims.width)
+        {
+            return Err(WrongWidth);
+        }
 
         let mut heights_tallest_first = dimensions
             .iter()
@@ -196,51 +328,58 @@ where
         // Get the initial height padded to a power of two. As heights_tallest_first is sorted,
         // the initial height will be the maximum height.
         // Returns an error if either:
-        //              1. proof.len() != log_max_height
+        //              1. proof.len() != (ARITY - 1) * (layers between the leaves and the cap)
         //              2. heights_tallest_first is empty.
-        let mut curr_height_padded = match heights_tallest_first.peek() {
+        let (max_height, log_max_height, path_len) = match heights_tallest_first.peek() {
             Some((_, dims)) => {
-                let max_height = dims.height.next_power_of_two();
-                let log_max_height = log2_strict_usize(max_height);
-                if proof.len() != log_max_height {
+                let log_max_height = log2_ceil_usize(dims.height);
+                let path_len = self.path_len(dimensions.iter().map(|dims| dims.height));
+                if proof.len() != path_len * (ARITY - 1) {
                     return Err(WrongHeight {
                         log_max_height,
                         num_siblings: proof.len(),
                     });
                 }
-                max_height
+                (dims.height, log_max_height, path_len)
             }
             None => return Err(EmptyBatch),
         };
+        self.check_cap_len(commit, dimensions.iter().map(|dims| dims.height))?;
+        let log_arity = log2_strict_usize(ARITY);
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
 
         // Hash all matrix openings at the current height.
         let mut root = self.hash.hash_iter_slices(
             heights_tallest_first
-                .peeking_take_while(|(_, dims)| {
-                    dims.height.next_power_of_two() == curr_height_padded
-                })
+                .peeking_take_while(|(_, dims)| dims.height == max_height)
                 .map(|(i, _)| opened_values[i].as_slice()),
         );
+        let mut siblings = proof.chunks_exact(ARITY - 1);
+
+        for layer in 0..=path_len {
+            if layer > 0 {
+                // The last bits of index give the position of the current node among its siblings.
+                let position = index & (ARITY - 1);
+                let mut siblings = siblings.next().unwrap().iter();
+                let children = core::array::from_fn(|i| {
+                    if i == position {
+                        root
+                    } else {
+                        *siblings.next().unwrap()
+                    }
+                });
+
+                // Combine the current node with the sibling nodes to get the parent node.
+                root = self.compress.compress(children);
+                index >>= log_arity;
+            }
 
-        for &sibling in proof {
-            // The last bit of index informs us whether the current node is on the left or right.
-            let (left, right) = if index & 1 == 0 {
-                (root, sibling)
-            } else {
-                (sibling, root)
-            };
-
-            // Combine the current node with the sibling node to get the parent node.
-            root = self.compress.compress([left, right]);
-            index >>= 1;
-            curr_height_padded >>= 1;
-
-            // Check if there are any new matrix rows to inject at the next height.
-            let next_height = heights_tallest_first
+            // Check if there are any new matrix rows to inject at this layer.
+            while let Some(next_height) = heights_tallest_first
                 .peek()
                 .map(|(_, dims)| dims.height)
-                .filter(|h| h.next_power_of_two() == curr_height_padded);
-            if let Some(next_height) = next_height {
+                .filter(|&h| injection_layer::<ARITY>(log_max_height, h).0 == layer)
+            {
                 // If there are new matrix rows, hash the rows together and then combine with the current root.
                 let next_height_openings_digest = self.hash.hash_iter_slices(
                     heights_tallest_first
@@ -248,12 +387,210 @@ where
                         .map(|(i, _)| opened_values[i].as_slice()),
                 );
 
-                root = self.compress.compress([root, next_height_openings_digest]);
+                root = self.compress.compress(injection_input(
+                    root,
+                    next_height_openings_digest,
+                    default_digest,
+                ));
+            }
+        }
+
+        // The computed node should equal the committed one in the cap.
+        if commit.get(index) == Some(&root) {
+            Ok(())
+        } else {
+            Err(RootMismatch)
+        }
+    }
+}
+
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize> MultiMmcs<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>
+        + CryptographicHasher<P, [PW; DIGEST_ELEMS]>
+        + Sync,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>
+        + PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>
+        + Sync,
+    PW::Value: Eq,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+{
+    /// The siblings of the paths of all opened indices which cannot be computed from the opened
+    /// rows, layer by layer from the leaves, and in increasing order of index within a layer.
+    type MultiProof = Vec<[PW::Value; DIGEST_ELEMS]>;
+
+    /// Opens a batch of rows from committed matrices at each of `indices`.
+    ///
+    /// Returns `(openings, proof)` where `openings[q]` is the opening of `open_batch` at
+    /// `indices[q]`, and `proof` holds the sibling nodes of all their paths, except the ones the
+    /// verifier can compute from the opened rows. Paths which meet share the nodes above.
+    fn open_multi_batch<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY>,
+    ) -> (Vec<Vec<Vec<P::Value>>>, Self::MultiProof) {
+        let path_len = self.path_len(prover_data.leaves.iter().map(|m| m.height()));
+        let log_arity = log2_strict_usize(ARITY);
+
+        let openings = indices
+            .iter()
+            .map(|&index| self.open_rows(index, prover_data))
+            .collect();
+
+        // The nodes of the current layer which the verifier knows, from the layer below or the
+        // opened rows.
+        let mut known: BTreeSet<usize> = indices.iter().copied().collect();
+        let mut proof = Vec::new();
+        for layer in &prover_data.digest_layers[..path_len] {
+            proof.extend(
+                known
+                    .iter()
+                    .map(|&index| index >> log_arity)
+                    .dedup()
+                    .flat_map(|parent| parent * ARITY..(parent + 1) * ARITY)
+                    .filter(|child| !known.contains(child))
+                    .map(|child| layer[child]),
+            );
+            known = known.iter().map(|&index| index >> log_arity).collect();
+        }
+
+        (openings, proof)
+    }
+
+    /// Verifies openings at several indices with respect to a given commitment.
+    ///
+    /// - `commit`: The merkle cap of the tree.
+    /// - `dimensions`: A vector of the dimensions of the matrices committed to.
+    /// - `indices`: The indices of the opened leaves.
+    /// - `opened_values`: For each index, the matrix rows at that index, as in `verify_batch`.
+    /// - `proof`: The siblings of all paths which cannot be computed from the opened rows, in the
+    ///   order given by `open_multi_batch`.
+    ///
+    /// Returns nothing if the verification is successful, otherwise returns an error.
+    fn verify_multi_batch(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        // Check that the openings have the correct shape.
+        if indices.len() != opened_values.len()
+            || opened_values
+                .iter()
+                .any(|opened| opened.len() != dimensions.len())
+        {
+            return Err(WrongBatchSize);
+        }
+        if opened_values
+            .iter()
+            .flat_map(|opened| dimensions.iter().zip(opened))
+            .any(|(dims, opened_vals)| opened_vals.len() != dims.width)
+        {
+            return Err(WrongWidth);
+        }
+        if dimensions.is_empty() {
+            return Err(EmptyBatch);
+        }
+
+        // The matrices of each padded height, tallest first.
+        let matrices_by_height = dimensions
+            .iter()
+            .enumerate()
+            .sorted_by_key(|(_, dims)| Reverse(dims.height))
+            .chunk_by(|(_, dims)| dims.height)
+            .into_iter()
+            .map(|(height, group)| (height, group.map(|(i, _)| i).collect_vec()))
+            .collect_vec();
+
+        // Matrix heights that round up to the same power of two must be equal
+        if !matrices_by_height
+            .iter()
+            .tuple_windows()
+            .all(|((curr, _), (next, _))| curr.next_power_of_two() != next.next_power_of_two())
+        {
+            return Err(IncompatibleHeights);
+        }
+        self.check_cap_len(commit, dimensions.iter().map(|dims| dims.height))?;
+        let log_max_height = log2_ceil_usize(matrices_by_height[0].0);
+        let path_len = self.path_len(dimensions.iter().map(|dims| dims.height));
+        let log_arity = log2_strict_usize(ARITY);
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+        let wrong_height = || WrongHeight {
+            log_max_height,
+            num_siblings: proof.len(),
+        };
+
+        // Hash the rows of the given matrices at each index, checking that indices which meet at
+        // the same row agree.
+        let hash_rows = |matrices: &[usize], height: usize| {
+            let bits_reduced = log_max_height - log2_ceil_usize(height);
+            let mut digests = BTreeMap::new();
+            for (&index, opened) in indices.iter().zip(opened_values) {
+                let digest = self
+                    .hash
+                    .hash_iter_slices(matrices.iter().map(|&i| opened[i].as_slice()));
+                if *digests.entry(index >> bits_reduced).or_insert(digest) != digest {
+                    return Err(RootMismatch);
+                }
+            }
+            Ok(digests)
+        };
+
+        let mut matrices_by_height = matrices_by_height.into_iter().peekable();
+        let (max_height, tallest) = matrices_by_height.next().unwrap();
+        let mut layer = hash_rows(&tallest, max_height)?;
+        let mut siblings = proof.iter();
+
+        for layer_index in 0..=path_len {
+            if layer_index > 0 {
+                let mut next_layer = BTreeMap::new();
+                for (parent, children) in
+                    &layer.into_iter().chunk_by(|&(index, _)| index >> log_arity)
+                {
+                    // Take each child from the known nodes if we can, and from the proof otherwise.
+                    let mut children = children.peekable();
+                    let mut inputs = [default_digest; ARITY];
+                    for (i, input) in inputs.iter_mut().enumerate() {
+                        *input = match children.next_if(|&(child, _)| child == parent * ARITY + i) {
+                            Some((_, node)) => node,
+                            None => *siblings.next().ok_or_else(wrong_height)?,
+                        };
+                    }
+                    next_layer.insert(parent, self.compress.compress(inputs));
+                }
+                layer = next_layer;
+            }
+
+            // Check if there are any new matrix rows to inject at this layer.
+            while let Some((height, matrices)) = matrices_by_height.next_if(|&(height, _)| {
+                injection_layer::<ARITY>(log_max_height, height).0 == layer_index
+            }) {
+                let (_, shift) = injection_layer::<ARITY>(log_max_height, height);
+                let injected = hash_rows(&matrices, height)?;
+                for (index, node) in &mut layer {
+                    *node = self.compress.compress(injection_input(
+                        *node,
+                        injected[&(*index >> shift)],
+                        default_digest,
+                    ));
+                }
             }
         }
 
-        // The computed root should equal the committed one.
-        if commit == &root {
+        if siblings.next().is_some() {
+            return Err(wrong_height());
+        }
+
+        // The computed nodes should equal the committed ones in the cap.
+        if layer
+            .into_iter()
+            .all(|(index, node)| commit.get(index) == Some(&node))
+        {
             Ok(())
         } else {
             Err(RootMismatch)
@@ -264,20 +601,22 @@ where
 #[cfg(test)]
 mod tests {
     use alloc::vec;
+    use alloc::vec::Vec;
 
     use itertools::Itertools;
     use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
-    use p3_commit::Mmcs;
+    use p3_commit::{Mmcs, MultiMmcs, open_multi_batch_per_index, verify_multi_batch_per_index};
     use p3_field::{Field, PrimeCharacteristicRing};
     use p3_matrix::dense::RowMajorMatrix;
     use p3_matrix::{Dimensions, Matrix};
     use p3_symmetric::{
-        CryptographicHasher, PaddingFreeSponge, PseudoCompressionFunction, TruncatedPermutation,
+        CompressionFunctionFromHasher, CryptographicHasher, PaddingFreeSponge,
+        PseudoCompressionFunction, TruncatedPermutation,
     };
     use rand::SeedableRng;
     use rand::rngs::SmallRng;
 
-    use super::MerkleTreeMmcs;
+    use super::{MerkleTreeError, MerkleTreeMmcs};
 
     type F = BabyBear;
 
@@ -286,6 +625,9 @@ mod tests {
     type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
     type MyMmcs =
         MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress, 8>;
+    type MyCompress4 = CompressionFunctionFromHasher<MyHash, 4, 8>;
+    type MyMmcs4 =
+        MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress4, 8, 4>;
 
     #[test]
     fn commit_single_1x8() {
@@ -318,7 +660,7 @@ mod tests {
                 compress.compress([hash.hash_item(v[6]), hash.hash_item(v[7])]),
             ]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -333,7 +675,7 @@ mod tests {
         let (commit, _) = mmcs.commit(vec![mat.clone()]);
 
         let expected_result = hash.hash_iter(mat.vertically_packed_row(0));
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -356,7 +698,7 @@ mod tests {
             hash.hash_slice(&[F::ZERO, F::ONE]),
             hash.hash_slice(&[F::TWO, F::ONE]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -384,7 +726,7 @@ mod tests {
             ]),
             compress.compress([hash.hash_slice(&[F::TWO, F::TWO]), default_digest]),
         ]);
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
     }
 
     #[test]
@@ -473,7 +815,7 @@ mod tests {
             ]),
         ]);
 
-        assert_eq!(commit, expected_result);
+        assert_eq!(commit.as_ref(), [expected_result]);
 
         let (opened_values, _proof) = mmcs.open_batch(2, &prover_data);
         assert_eq!(
@@ -606,6 +948,339 @@ mod tests {
         .expect("expected verification to succeed");
     }
 
+    #[test]
+    fn multi_batch_mixed_heights() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = [1000, 1000, 70, 8, 1]
+            .map(|height| RowMajorMatrix::<F>::rand(&mut rng, height, 8))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // Repeated indices, and indices which share a parent, are all allowed.
+        let indices = [6, 555, 17, 6, 0, 1, 512];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        for (&index, opened) in indices.iter().zip(&opened_values) {
+            assert_eq!(opened, &mmcs.open_batch(index, &prover_data).0);
+        }
+        let num_single_siblings: usize = indices
+            .iter()
+            .map(|&index| mmcs.open_batch(index, &prover_data).1.len())
+            .sum();
+        assert!(proof.len() < num_single_siblings);
+
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // Opening each index on its own gives the same values, with a proof per index.
+        let (single_values, single_proofs) =
+            open_multi_batch_per_index(&mmcs, &indices, &prover_data);
+        assert_eq!(single_values, opened_values);
+        assert_eq!(
+            single_proofs.iter().map(Vec::len).sum::<usize>(),
+            num_single_siblings
+        );
+        verify_multi_batch_per_index(
+            &mmcs,
+            &commit,
+            &dims,
+            &indices,
+            &single_values,
+            &single_proofs,
+        )
+        .expect("expected verification to succeed");
+    }
+
+    #[test]
+    fn multi_batch_tampered_fails() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = [64, 16]
+            .map(|height| RowMajorMatrix::<F>::rand(&mut rng, height, 4))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        let indices = [3, 40, 41, 3];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // A wrong value at a single index.
+        let mut tampered = opened_values.clone();
+        tampered[1][0][0] += F::ONE;
+        assert!(
+            mmcs.verify_multi_batch(&commit, &dims, &indices, &tampered, &proof)
+                .is_err()
+        );
+
+        // Different values for the same row of a shorter matrix, from indices 40 and 41.
+        let mut tampered = opened_values.clone();
+        tampered[2][1][0] += F::ONE;
+        assert!(
+            mmcs.verify_multi_batch(&commit, &dims, &indices, &tampered, &proof)
+                .is_err()
+        );
+
+        // A wrong sibling, and a missing one.
+        let mut tampered_proof = proof.clone();
+        tampered_proof[0][0] += F::ONE;
+        assert!(
+            mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &tampered_proof)
+                .is_err()
+        );
+        assert!(
+            mmcs.verify_multi_batch(
+                &commit,
+                &dims,
+                &indices,
+                &opened_values,
+                &proof[1..].to_vec()
+            )
+            .is_err()
+        );
+
+        // The openings of the wrong indices.
+        assert!(
+            mmcs.verify_multi_batch(&commit, &dims, &[3, 40, 42, 3], &opened_values, &proof)
+                .is_err()
+        );
+    }
+
+    #[test]
+    fn wrong_widths_fail() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = [(32, 3), (32, 5), (8, 2)]
+            .map(|(height, width)| RowMajorMatrix::<F>::rand(&mut rng, height, width))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // Moving a value from the end of one row to the start of the next leaves their
+        // concatenation, and so its hash, unchanged.
+        fn shift_value(rows: &mut [Vec<F>]) {
+            let value = rows[0].pop().unwrap();
+            rows[1].insert(0, value);
+        }
+        fn extra_value(rows: &mut [Vec<F>]) {
+            rows[2].push(F::ZERO);
+        }
+        fn missing_value(rows: &mut [Vec<F>]) {
+            rows[2].pop();
+        }
+        let tamperings = [shift_value, extra_value, missing_value];
+
+        let (opened_values, proof) = mmcs.open_batch(17, &prover_data);
+        mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
+            .expect("expected verification to succeed");
+        for tamper in tamperings {
+            let mut tampered = opened_values.clone();
+            tamper(&mut tampered);
+            assert!(matches!(
+                mmcs.verify_batch(&commit, &dims, 17, &tampered, &proof),
+                Err(MerkleTreeError::WrongWidth)
+            ));
+        }
+
+        let indices = [17, 3, 30];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        for tamper in tamperings {
+            let mut tampered = opened_values.clone();
+            tamper(&mut tampered[1]);
+            assert!(matches!(
+                mmcs.verify_multi_batch(&commit, &dims, &indices, &tampered, &proof),
+                Err(MerkleTreeError::WrongWidth)
+            ));
+        }
+    }
+
+    #[test]
+    fn cap_roundtrip() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash.clone(), compress.clone());
+        let capped_mmcs = MyMmcs::new_with_cap_height(hash, compress, 3);
+
+        let mats = [1000, 1000, 70, 24]
+            .map(|height| RowMajorMatrix::<F>::rand(&mut rng, height, 4))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let (root, _) = mmcs.commit(mats.clone());
+        let (commit, prover_data) = capped_mmcs.commit(mats);
+        // 1000 rows are padded to 1024, so the cap is the 8 nodes of the third layer.
+        assert_eq!(root.len(), 1);
+        assert_eq!(commit.len(), 8);
+
+        let (opened_values, proof) = capped_mmcs.open_batch(555, &prover_data);
+        assert_eq!(proof.len(), 7);
+        capped_mmcs
+            .verify_batch(&commit, &dims, 555, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // A proof against a different cap entry is rejected.
+        let mut tampered = commit.clone().into_iter().collect_vec();
+        tampered[555 >> 7][0] += F::ONE;
+        assert!(
+            capped_mmcs
+                .verify_batch(&tampered.into(), &dims, 555, &opened_values, &proof)
+                .is_err()
+        );
+
+        // So is a cap of the wrong size, even if it holds the right digest at the proof's index.
+        let mut doubled = commit.clone().into_iter().collect_vec();
+        doubled.extend(doubled.clone());
+        let doubled = doubled.into();
+        assert!(matches!(
+            capped_mmcs.verify_batch(&doubled, &dims, 555, &opened_values, &proof),
+            Err(MerkleTreeError::WrongCapSize {
+                expected: 8,
+                actual: 16
+            })
+        ));
+
+        let indices = [6, 555, 17, 500];
+        let (opened_values, proof) = capped_mmcs.open_multi_batch(&indices, &prover_data);
+        capped_mmcs
+            .verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+        assert!(matches!(
+            capped_mmcs.verify_multi_batch(&doubled, &dims, &indices, &opened_values, &proof),
+            Err(MerkleTreeError::WrongCapSize {
+                expected: 8,
+                actual: 16
+            })
+        ));
+    }
+
+    #[test]
+    fn cap_clamped_by_shortest_matrix() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new_with_cap_height(hash, compress, 5);
+
+        // The matrix of height 4 is injected two layers below the root, so the cap can't be higher.
+        let mats = [64, 4]
+            .map(|height| RowMajorMatrix::<F>::rand(&mut rng, height, 2))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+        assert_eq!(commit.len(), 4);
+
+        let (opened_values, proof) = mmcs.open_batch(37, &prover_data);
+        assert_eq!(proof.len(), 4);
+        mmcs.verify_batch(&commit, &dims, 37, &opened_values, &proof)
+            .expect("expected verification to succeed");
+    }
+
+    #[test]
+    fn commit_mixed_arity_4() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm);
+        let compress = MyCompress4::new(hash.clone());
+        let mmcs = MyMmcs4::new(hash.clone(), compress.clone());
+
+        let m = RowMajorMatrix::<F>::rand(&mut rng, 16, 2);
+        let n = RowMajorMatrix::<F>::rand(&mut rng, 8, 3);
+        let k = RowMajorMatrix::<F>::rand(&mut rng, 4, 1);
+        let l = RowMajorMatrix::<F>::rand(&mut rng, 2, 5);
+        let (commit, _) = mmcs.commit(vec![l.clone(), m.clone(), k.clone(), n.clone()]);
+
+        let default_digest = [F::ZERO; 8];
+        let inject = |node, row: Vec<F>| {
+            compress.compress([node, hash.hash_iter(row), default_digest, default_digest])
+        };
+        // N is mixed into the leaves, two of which share each of its rows.
+        let leaves = (0..16)
+            .map(|i| inject(hash.hash_iter(m.row(i)), n.row(i >> 1).collect()))
+            .collect_vec();
+        // K and L are mixed into the layer above.
+        let layer = (0..4)
+            .map(|i| {
+                let node = compress.compress(leaves[4 * i..4 * i + 4].try_into().unwrap());
+                let node = inject(node, k.row(i).collect());
+                inject(node, l.row(i >> 1).collect())
+            })
+            .collect_vec();
+        let expected_result = compress.compress(layer.try_into().unwrap());
+        assert_eq!(commit.as_ref(), [expected_result]);
+    }
+
+    #[test]
+    fn arity_4_mixed_heights() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm);
+        let compress = MyCompress4::new(hash.clone());
+
+        let mats = [1000, 1000, 500, 70, 8, 3, 1]
+            .map(|height| RowMajorMatrix::<F>::rand(&mut rng, height, 4))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let indices = [6, 555, 17, 6, 0, 1, 512, 300];
+
+        for cap_height in [0, 2] {
+            let mmcs = MyMmcs4::new_with_cap_height(hash.clone(), compress.clone(), cap_height);
+            let (commit, prover_data) = mmcs.commit(mats.clone());
+            // The matrix of height 1 is mixed into the root, so the cap is always the root.
+            assert_eq!(commit.len(), 1);
+
+            for &index in &indices {
+                let (opened_values, proof) = mmcs.open_batch(index, &prover_data);
+                // 1024 leaves are 5 layers below the root, and each layer has 3 siblings.
+                assert_eq!(proof.len(), 15);
+                mmcs.verify_batch(&commit, &dims, index, &opened_values, &proof)
+                    .expect("expected verification to succeed");
+
+                let mut tampered = opened_values.clone();
+                tampered[5][0] += F::ONE;
+                assert!(
+                    mmcs.verify_batch(&commit, &dims, index, &tampered, &proof)
+                        .is_err()
+                );
+            }
+
+            let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+            mmcs.verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+                .expect("expected verification to succeed");
+
+            // Different values for the same row of a matrix mixed into the leaves, from indices 0
+            // and 1.
+            let mut tampered = opened_values.clone();
+            tampered[5][2][0] += F::ONE;
+            assert!(
+                mmcs.verify_multi_batch(&commit, &dims, &indices, &tampered, &proof)
+                    .is_err()
+            );
+        }
+
+        // Without the short matrices, a cap of height 2 holds 16 nodes.
+        let mmcs = MyMmcs4::new_with_cap_height(hash, compress, 2);
+        let (commit, prover_data) = mmcs.commit(mats[..4].to_vec());
+        assert_eq!(commit.len(), 16);
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        mmcs.verify_multi_batch(&commit, &dims[..4], &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+    }
+
     #[test]
     fn different_widths() {
         let mut rng = SmallRng::seed_from_u64(1);
@@ -625,4 +1300,34 @@ mod tests {
         mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
             .expect("expected verification to succeed");
     }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn digests_on_disk() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new_with_cap_height(hash.clone(), compress.clone(), 2);
+        let disk_mmcs =
+            MyMmcs::new_with_digest_storage(hash, compress, 2, crate::DigestStorage::Disk);
+
+        let mats = [1000, 1000, 70, 24]
+            .map(|height| RowMajorMatrix::<F>::rand(&mut rng, height, 4))
+            .to_vec();
+        let dims = mats.iter().map(Matrix::dimensions).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats.clone());
+        let (disk_commit, disk_prover_data) = disk_mmcs.commit(mats);
+        assert_eq!(disk_commit, commit);
+
+        let indices = [6, 555, 17, 500];
+        let (opened_values, proof) = mmcs.open_multi_batch(&indices, &prover_data);
+        let (disk_opened_values, disk_proof) =
+            disk_mmcs.open_multi_batch(&indices, &disk_prover_data);
+        assert_eq!(disk_opened_values, opened_values);
+        assert_eq!(disk_proof, proof);
+        disk_mmcs
+            .verify_multi_batch(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+    }
 }
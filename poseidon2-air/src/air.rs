@@ -1,7 +1,7 @@
 use core::borrow::Borrow;
 use core::marker::PhantomData;
 
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, TracePadding};
 use p3_field::{Field, PrimeCharacteristicRing};
 use p3_matrix::Matrix;
 use p3_poseidon2::GenericPoseidon2LinearLayers;
@@ -76,6 +76,11 @@ impl<
     fn width(&self) -> usize {
         num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS>()
     }
+
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        // Each row is an independent permutation, so a copy of the last one is valid padding.
+        Some(TracePadding::RepeatLastRow)
+    }
 }
 
 pub(crate) fn eval<
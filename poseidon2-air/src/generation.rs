@@ -27,14 +27,15 @@ pub fn generate_vectorized_trace_rows<
 ) -> RowMajorMatrix<F> {
     let n = inputs.len();
     assert!(
-        n % VECTOR_LEN == 0 && (n / VECTOR_LEN).is_power_of_two(),
-        "Callers expected to pad inputs to VECTOR_LEN times a power of two"
+        n % VECTOR_LEN == 0,
+        "Callers expected to pad inputs to a multiple of VECTOR_LEN"
     );
 
     let nrows = n.div_ceil(VECTOR_LEN);
     let ncols = num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS>()
         * VECTOR_LEN;
-    let mut vec = Vec::with_capacity((nrows * ncols) << extra_capacity_bits);
+    // The prover pads the trace to a power of two height, so reserve room for that as well.
+    let mut vec = Vec::with_capacity((nrows.next_power_of_two() * ncols) << extra_capacity_bits);
     let trace = &mut vec.spare_capacity_mut()[..nrows * ncols];
     let trace = RowMajorMatrixViewMut::new(trace, ncols);
 
@@ -86,13 +87,8 @@ pub fn generate_trace_rows<
     constants: &RoundConstants<F, WIDTH, HALF_FULL_ROUNDS, PARTIAL_ROUNDS>,
 ) -> RowMajorMatrix<F> {
     let n = inputs.len();
-    assert!(
-        n.is_power_of_two(),
-        "Callers expected to pad inputs to a power of two"
-    );
-
     let ncols = num_cols::<WIDTH, SBOX_DEGREE, SBOX_REGISTERS, HALF_FULL_ROUNDS, PARTIAL_ROUNDS>();
-    let mut vec = Vec::with_capacity(n * ncols * 2);
+    let mut vec = Vec::with_capacity(n.next_power_of_two() * ncols * 2);
     let trace = &mut vec.spare_capacity_mut()[..n * ncols];
     let trace = RowMajorMatrixViewMut::new(trace, ncols);
 
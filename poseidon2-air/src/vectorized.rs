@@ -1,6 +1,6 @@
 use core::borrow::{Borrow, BorrowMut};
 
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, TracePadding};
 use p3_field::{Field, PrimeField};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
@@ -232,6 +232,10 @@ impl<
     fn width(&self) -> usize {
         self.air.width() * VECTOR_LEN
     }
+
+    fn trace_padding(&self) -> Option<TracePadding<F>> {
+        self.air.trace_padding()
+    }
 }
 
 impl<
@@ -0,0 +1,149 @@
+//! Compares STIR with FRI at the same conjectured security, printing the size of each proof and
+//! benchmarking the time taken to open.
+
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use itertools::Itertools;
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{CanObserve, DuplexChallenger, FieldChallenger};
+use p3_commit::{ExtensionMmcs, Pcs, PolynomialSpace};
+use p3_dft::Radix2DitParallel;
+use p3_field::Field;
+use p3_field::extension::BinomialExtensionField;
+use p3_fri::{FriConfig, TwoAdicFriPcs};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_stir::{StirConfig, StirPcs};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+type Val = BabyBear;
+type Challenge = BinomialExtensionField<Val, 4>;
+
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+type Dft = Radix2DitParallel<Val>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+const LOG_BLOWUP: usize = 1;
+const SECURITY_BITS: usize = 100;
+const PROOF_OF_WORK_BITS: usize = 16;
+const WIDTH: usize = 16;
+
+fn mmcs() -> (ValMmcs, ChallengeMmcs, Challenger) {
+    let perm = Perm::new_from_rng_128(&mut SmallRng::seed_from_u64(0));
+    let val_mmcs = ValMmcs::new(MyHash::new(perm.clone()), MyCompress::new(perm.clone()));
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    (val_mmcs, challenge_mmcs, Challenger::new(perm))
+}
+
+fn fri_pcs() -> (TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>, Challenger) {
+    let (val_mmcs, challenge_mmcs, challenger) = mmcs();
+    let fri_config = FriConfig {
+        log_blowup: LOG_BLOWUP,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        // Each query is conjectured to give `log_blowup` bits of security.
+        num_queries: (SECURITY_BITS - PROOF_OF_WORK_BITS).div_ceil(LOG_BLOWUP),
+        proof_of_work_bits: PROOF_OF_WORK_BITS,
+        mmcs: challenge_mmcs,
+    };
+    (
+        TwoAdicFriPcs::new(Dft::default(), val_mmcs, fri_config),
+        challenger,
+    )
+}
+
+fn stir_pcs(log_folding_factor: usize) -> (StirPcs<Val, Dft, ValMmcs, ChallengeMmcs>, Challenger) {
+    let (val_mmcs, challenge_mmcs, challenger) = mmcs();
+    let stir_config = StirConfig {
+        log_blowup: LOG_BLOWUP,
+        log_folding_factor,
+        log_final_poly_len: 0,
+        security_bits: SECURITY_BITS,
+        proof_of_work_bits: PROOF_OF_WORK_BITS,
+        mmcs: challenge_mmcs,
+    };
+    (
+        StirPcs::new(Dft::default(), val_mmcs, stir_config),
+        challenger,
+    )
+}
+
+/// Commit to a random matrix of height `2^log_degree`, and open it at a random point.
+fn open<P>((pcs, challenger): &(P, Challenger), log_degree: usize) -> (P::Commitment, P::Proof)
+where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Challenger: CanObserve<P::Commitment>,
+{
+    let mut rng = SmallRng::seed_from_u64(1);
+    let domain = pcs.natural_domain_for_degree(1 << log_degree);
+    let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << log_degree, WIDTH);
+    let (commitment, data) = pcs.commit(vec![(domain, evals)]);
+
+    let mut challenger = challenger.clone();
+    challenger.observe(commitment.clone());
+    let zeta: Challenge = FieldChallenger::<Val>::sample_algebra_element(&mut challenger);
+    let (_, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut challenger);
+    (commitment, proof)
+}
+
+fn proof_size<P>(pcs: &(P, Challenger), log_degree: usize) -> usize
+where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Challenger: CanObserve<P::Commitment>,
+{
+    let (_, proof) = open(pcs, log_degree);
+    postcard::to_allocvec(&proof)
+        .expect("unable to serialize proof")
+        .len()
+}
+
+fn bench_proof_size(c: &mut Criterion) {
+    let log_degrees = [10, 14, 18];
+    let log_folding_factors = [2, 4];
+
+    for &log_degree in &log_degrees {
+        let sizes = log_folding_factors
+            .iter()
+            .map(|&lf| {
+                format!(
+                    "STIR (folding factor {}): {} bytes",
+                    1 << lf,
+                    proof_size(&stir_pcs(lf), log_degree)
+                )
+            })
+            .join(", ");
+        println!(
+            "degree 2^{log_degree}: FRI: {} bytes, {sizes}",
+            proof_size(&fri_pcs(), log_degree)
+        );
+    }
+
+    let mut group = c.benchmark_group("open");
+    group.sample_size(10);
+    for &log_degree in &log_degrees {
+        let fri = fri_pcs();
+        group.bench_function(BenchmarkId::new("FRI", log_degree), |b| {
+            b.iter(|| open(&fri, log_degree))
+        });
+        for &lf in &log_folding_factors {
+            let stir = stir_pcs(lf);
+            group.bench_function(
+                BenchmarkId::new(format!("STIR (folding factor {})", 1 << lf), log_degree),
+                |b| b.iter(|| open(&stir, log_degree)),
+            );
+        }
+    }
+}
+
+criterion_group!(benches, bench_proof_size);
+criterion_main!(benches);
@@ -0,0 +1,128 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_field::TwoAdicField;
+use p3_util::reverse_bits_len;
+
+#[derive(Debug)]
+pub struct StirConfig<M> {
+    pub log_blowup: usize,
+    /// The log of the folding factor `k`. Each round folds the degree by `k`, while the next
+    /// domain is only half the size of the current one, so the rate improves by `k / 2`.
+    pub log_folding_factor: usize,
+    /// The log of the length of the final polynomial, at which STIR stops folding.
+    pub log_final_poly_len: usize,
+    /// The conjectured number of bits of security of each round, which determines its number of
+    /// queries.
+    pub security_bits: usize,
+    /// The number of bits of proof of work ground before the queries of each round.
+    pub proof_of_work_bits: usize,
+    pub mmcs: M,
+}
+
+/// The shape of one round of STIR, which folds a function over a domain of size
+/// `2^log_domain_size` and degree less than `2^log_degree`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StirRound {
+    pub log_domain_size: usize,
+    pub log_degree: usize,
+    pub log_folding_factor: usize,
+    pub num_queries: usize,
+}
+
+impl<M> StirConfig<M> {
+    pub const fn blowup(&self) -> usize {
+        1 << self.log_blowup
+    }
+
+    pub const fn final_poly_len(&self) -> usize {
+        1 << self.log_final_poly_len
+    }
+
+    /// Returns parameters which affect soundness, to be bound into the Fiat-Shamir transcript.
+    pub fn parameters(&self) -> Vec<usize> {
+        vec![
+            self.log_blowup,
+            self.log_folding_factor,
+            self.log_final_poly_len,
+            self.security_bits,
+            self.proof_of_work_bits,
+        ]
+    }
+
+    /// The rounds of STIR on a codeword of height `2^log_height`.
+    ///
+    /// Every round but the last commits to its folded function over the next domain. The last one
+    /// folds by less if that is enough to reach the final polynomial, whose coefficients are then
+    /// sent in the clear.
+    pub fn rounds(&self, log_height: usize) -> Vec<StirRound> {
+        assert!(self.log_folding_factor > 0, "STIR must fold by at least 2");
+        let mut rounds = vec![];
+        let mut log_domain_size = log_height;
+        let mut log_degree = log_height.saturating_sub(self.log_blowup);
+        loop {
+            let log_folding_factor = self
+                .log_folding_factor
+                .min(log_degree.saturating_sub(self.log_final_poly_len));
+            // Each query of a round at rate `rho` is conjectured to give `log(1/rho)` bits.
+            let log_inv_rate = (log_domain_size - log_degree).max(1);
+            let num_queries = self
+                .security_bits
+                .saturating_sub(self.proof_of_work_bits)
+                .div_ceil(log_inv_rate);
+            rounds.push(StirRound {
+                log_domain_size,
+                log_degree,
+                log_folding_factor,
+                num_queries,
+            });
+            if log_degree - log_folding_factor <= self.log_final_poly_len {
+                return rounds;
+            }
+            log_domain_size -= 1;
+            log_degree -= log_folding_factor;
+        }
+    }
+}
+
+impl StirRound {
+    pub const fn folding_factor(&self) -> usize {
+        1 << self.log_folding_factor
+    }
+
+    /// The log of the number of rows of the committed codeword, each holding a coset of size
+    /// `2^log_folding_factor`.
+    pub const fn log_num_rows(&self) -> usize {
+        self.log_domain_size - self.log_folding_factor
+    }
+}
+
+/// The shift of the domain of the function folded in round `round`.
+///
+/// The first function is defined over the subgroup of order `2^log_domain_size`. Later ones are
+/// defined over cosets of the multiplicative generator, which do not intersect the folded domains
+/// of the rounds before them.
+pub(crate) fn domain_shift<F: TwoAdicField>(round: usize) -> F {
+    if round == 0 { F::ONE } else { F::GENERATOR }
+}
+
+/// The point of row `row` of round `round`: the committed row holds the evaluations over `y H`,
+/// where `H` is the subgroup of order `2^log_folding_factor`, in bit-reversed order.
+pub(crate) fn row_point<F: TwoAdicField>(round: usize, shape: &StirRound, row: usize) -> F {
+    domain_shift::<F>(round)
+        * F::two_adic_generator(shape.log_domain_size)
+            .exp_u64(reverse_bits_len(row, shape.log_num_rows()) as u64)
+}
+
+/// Creates a minimal `StirConfig` for testing purposes.
+/// This configuration is designed to reduce computational cost during tests.
+pub const fn create_test_stir_config<Mmcs>(mmcs: Mmcs) -> StirConfig<Mmcs> {
+    StirConfig {
+        log_blowup: 1,
+        log_folding_factor: 2,
+        log_final_poly_len: 0,
+        security_bits: 8,
+        proof_of_work_bits: 1,
+        mmcs,
+    }
+}
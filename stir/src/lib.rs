@@ -0,0 +1,18 @@
+//! An implementation of the STIR low-degree test, following
+//! [STIR: Reed–Solomon Proximity Testing with Fewer Queries](https://eprint.iacr.org/2024/390)
+//! by Arnon, Chiesa, Fenzi and Yogev.
+
+#![no_std]
+
+extern crate alloc;
+
+mod config;
+mod pcs;
+mod polynomial;
+mod proof;
+pub mod prover;
+pub mod verifier;
+
+pub use config::*;
+pub use pcs::*;
+pub use proof::*;
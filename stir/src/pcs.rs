@@ -0,0 +1,222 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use itertools::Itertools;
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::{CommitError, MultiMmcs, OpenedValues, Pcs};
+use p3_dft::TwoAdicSubgroupDft;
+use p3_field::coset::TwoAdicMultiplicativeCoset;
+use p3_field::{ExtensionField, TwoAdicField};
+use p3_fri::verifier::FriError;
+use p3_fri::{
+    BatchOpening, log_global_max_height, open_and_reduce, open_input, verify_and_reduce_input,
+};
+use p3_matrix::Matrix;
+use p3_matrix::bitrev::{BitReversedMatrixView, BitReversibleMatrix};
+use p3_matrix::dense::{DenseMatrix, RowMajorMatrix};
+use p3_util::log2_strict_usize;
+
+use crate::{StirConfig, StirProof, prover, verifier};
+
+/// A PCS over two-adic cosets, which batches openings into DEEP quotients like
+/// [`p3_fri::TwoAdicFriPcs`], and proves them low-degree with STIR rather than FRI.
+#[derive(Debug)]
+pub struct StirPcs<Val, Dft, InputMmcs, StirMmcs> {
+    dft: Dft,
+    mmcs: InputMmcs,
+    stir: StirConfig<StirMmcs>,
+    _phantom: PhantomData<Val>,
+}
+
+impl<Val, Dft, InputMmcs, StirMmcs> StirPcs<Val, Dft, InputMmcs, StirMmcs> {
+    pub const fn new(dft: Dft, mmcs: InputMmcs, stir: StirConfig<StirMmcs>) -> Self {
+        Self {
+            dft,
+            mmcs,
+            stir,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<Val, Dft, InputMmcs, StirMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
+    for StirPcs<Val, Dft, InputMmcs, StirMmcs>
+where
+    Val: TwoAdicField,
+    Dft: TwoAdicSubgroupDft<Val>,
+    InputMmcs: MultiMmcs<Val>,
+    StirMmcs: MultiMmcs<Challenge>,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    Challenger:
+        FieldChallenger<Val> + CanObserve<StirMmcs::Commitment> + GrindingChallenger<Witness = Val>,
+{
+    type Domain = TwoAdicMultiplicativeCoset<Val>;
+    type Commitment = InputMmcs::Commitment;
+    type ProverData = InputMmcs::ProverData<RowMajorMatrix<Val>>;
+    type EvaluationsOnDomain<'a> = BitReversedMatrixView<DenseMatrix<Val, &'a [Val]>>;
+    type Proof = StirProof<Challenge, StirMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>;
+    type Error = FriError<StirMmcs::Error, InputMmcs::Error>;
+
+    fn parameters(&self) -> Vec<usize> {
+        self.stir.parameters()
+    }
+
+    fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
+        // This panics if (and only if) `degree` is not a power of 2 or `degree`
+        // > `1 << Val::TWO_ADICITY`.
+        TwoAdicMultiplicativeCoset::new(Val::ONE, log2_strict_usize(degree)).unwrap()
+    }
+
+    fn try_commit(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
+    ) -> Result<(Self::Commitment, Self::ProverData), CommitError> {
+        for (index, (domain, evals)) in evaluations.iter().enumerate() {
+            if domain.log_size() + self.stir.log_blowup > Val::TWO_ADICITY {
+                return Err(CommitError::UnsupportedDomainSize {
+                    index,
+                    log_size: domain.log_size(),
+                });
+            }
+            if domain.size() != evals.height() {
+                return Err(CommitError::HeightMismatch {
+                    index,
+                    domain_size: domain.size(),
+                    height: evals.height(),
+                });
+            }
+        }
+
+        let ldes: Vec<_> = evaluations
+            .into_iter()
+            .map(|(domain, evals)| {
+                let shift = Val::GENERATOR / domain.shift();
+                // Commit to the bit-reversed LDE.
+                self.dft
+                    .coset_lde_batch(evals, self.stir.log_blowup, shift)
+                    .bit_reverse_rows()
+                    .to_row_major_matrix()
+            })
+            .collect();
+
+        Ok(self.mmcs.commit(ldes))
+    }
+
+    fn get_evaluations_on_domain<'a>(
+        &self,
+        prover_data: &'a Self::ProverData,
+        idx: usize,
+        domain: Self::Domain,
+    ) -> Self::EvaluationsOnDomain<'a> {
+        assert_eq!(domain.shift(), Val::GENERATOR);
+        let lde = self.mmcs.get_matrices(prover_data)[idx];
+        assert!(lde.height() >= domain.size());
+        lde.split_rows(domain.size()).0.bit_reverse_rows()
+    }
+
+    fn open(
+        &self,
+        // For each round,
+        rounds: Vec<(
+            &Self::ProverData,
+            // for each matrix,
+            Vec<
+                // points to open
+                Vec<Challenge>,
+            >,
+        )>,
+        challenger: &mut Challenger,
+    ) -> (OpenedValues<Challenge>, Self::Proof) {
+        let (all_opened_values, reduced_openings) =
+            open_and_reduce(&self.mmcs, self.stir.log_blowup, &rounds, challenger);
+
+        // STIR tests a single function, so combine the quotients of every height, each lifted to
+        // the tallest domain, with a new challenge.
+        let gamma: Challenge = challenger.sample_algebra_element();
+        let log_global_max_height = log2_strict_usize(
+            reduced_openings
+                .first()
+                .expect("No Matrices Supplied?")
+                .len(),
+        );
+        let mut combined = Challenge::zero_vec(1 << log_global_max_height);
+        for ro in &reduced_openings {
+            let bits_reduced = log_global_max_height - log2_strict_usize(ro.len());
+            for (index, c) in combined.iter_mut().enumerate() {
+                *c = *c * gamma + ro[index >> bits_reduced];
+            }
+        }
+
+        let data = rounds.iter().map(|(data, _)| *data).collect_vec();
+        let stir_proof = prover::prove(&self.stir, combined, challenger, |indices| {
+            open_input(&self.mmcs, log_global_max_height, &data, indices)
+        });
+
+        (all_opened_values, stir_proof)
+    }
+
+    fn verify(
+        &self,
+        // For each round:
+        rounds: Vec<(
+            Self::Commitment,
+            // for each matrix:
+            Vec<(
+                // its domain,
+                Self::Domain,
+                // for each point:
+                Vec<(
+                    // the point,
+                    Challenge,
+                    // values at the point
+                    Vec<Challenge>,
+                )>,
+            )>,
+        )>,
+        proof: &Self::Proof,
+        challenger: &mut Challenger,
+    ) -> Result<(), Self::Error> {
+        // Write evaluations to challenger
+        for (_, round) in &rounds {
+            for (_, mat) in round {
+                for (_, point) in mat {
+                    point
+                        .iter()
+                        .for_each(|&opening| challenger.observe_algebra_element(opening));
+                }
+            }
+        }
+
+        // Batch combination challenges
+        let alpha: Challenge = challenger.sample_algebra_element();
+        let gamma: Challenge = challenger.sample_algebra_element();
+
+        let log_global_max_height = log_global_max_height(&rounds, self.stir.log_blowup);
+
+        verifier::verify(
+            &self.stir,
+            proof,
+            log_global_max_height,
+            challenger,
+            |indices, input_proof| {
+                let reduced_openings = verify_and_reduce_input(
+                    &self.mmcs,
+                    self.stir.log_blowup,
+                    &rounds,
+                    alpha,
+                    log_global_max_height,
+                    indices,
+                    input_proof,
+                )?;
+                Ok(reduced_openings
+                    .into_iter()
+                    .map(|reduced_openings| {
+                        reduced_openings
+                            .into_iter()
+                            .fold(Challenge::ZERO, |acc, (_, ro)| acc * gamma + ro)
+                    })
+                    .collect())
+            },
+        )
+    }
+}
@@ -0,0 +1,125 @@
+//! Operations on polynomials in coefficient form, lowest degree first.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_field::{ExtensionField, Field};
+
+/// Evaluate `coeffs` at `x` with Horner's rule.
+pub(crate) fn eval_poly<F: Field, EF: ExtensionField<F>>(coeffs: &[EF], x: F) -> EF {
+    coeffs
+        .iter()
+        .rev()
+        .fold(EF::ZERO, |acc, &coeff| acc * x + coeff)
+}
+
+/// Fold `coeffs` by `2^log_folding_factor`: writing `f(X) = sum_i X^i f_i(X^k)` for
+/// `k = 2^log_folding_factor`, returns the coefficients of `sum_i r^i f_i(X)`.
+pub(crate) fn fold_poly<F: Field>(coeffs: &[F], r: F, log_folding_factor: usize) -> Vec<F> {
+    coeffs
+        .chunks_exact(1 << log_folding_factor)
+        .map(|chunk| eval_poly(chunk, r))
+        .collect()
+}
+
+/// The quotient of `coeffs` by the vanishing polynomial of `points`, dropping the remainder.
+pub(crate) fn divide_by_vanishing<F: Field>(coeffs: &[F], points: &[F]) -> Vec<F> {
+    // The vanishing polynomial is monic, with degree `points.len()`.
+    let mut vanishing = vec![F::ONE];
+    for &point in points {
+        vanishing.push(F::ZERO);
+        for i in (1..vanishing.len()).rev() {
+            vanishing[i] = vanishing[i - 1] - point * vanishing[i];
+        }
+        vanishing[0] = -point * vanishing[0];
+    }
+
+    let degree = points.len();
+    let mut remainder = coeffs.to_vec();
+    let mut quotient = F::zero_vec(coeffs.len().saturating_sub(degree));
+    for i in (0..quotient.len()).rev() {
+        quotient[i] = remainder[i + degree];
+        for (r, &v) in remainder[i..i + degree].iter_mut().zip(&vanishing) {
+            *r -= quotient[i] * v;
+        }
+    }
+    quotient
+}
+
+/// Multiply `coeffs` by `sum_{i <= degree} (r X)^i`, which raises its degree by `degree`.
+pub(crate) fn correct_degree<F: Field>(coeffs: &[F], r: F, degree: usize) -> Vec<F> {
+    // Each coefficient is a sliding window sum, `c_j = sum_{i <= degree} r^i q_{j - i}`, so
+    // `c_j = r c_{j - 1} + q_j - r^{degree + 1} q_{j - degree - 1}`.
+    let r_pow = r.exp_u64(degree as u64 + 1);
+    let mut result = Vec::with_capacity(coeffs.len() + degree);
+    let mut acc = F::ZERO;
+    for j in 0..coeffs.len() + degree {
+        acc *= r;
+        if let Some(&q) = coeffs.get(j) {
+            acc += q;
+        }
+        if let Some(&q) = j.checked_sub(degree + 1).and_then(|k| coeffs.get(k)) {
+            acc -= r_pow * q;
+        }
+        result.push(acc);
+    }
+    result
+}
+
+/// Evaluate `sum_{i <= degree} x^i`.
+pub(crate) fn eval_degree_correction<F: Field>(x: F, degree: usize) -> F {
+    if x == F::ONE {
+        F::from_usize(degree + 1)
+    } else {
+        (x.exp_u64(degree as u64 + 1) - F::ONE) / (x - F::ONE)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+    use rand::rngs::SmallRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+
+    type F = BabyBear;
+
+    #[test]
+    fn test_quotient_and_degree_correction() {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let coeffs: Vec<F> = (0..32).map(|_| rng.random()).collect();
+        let points: Vec<F> = (0..5).map(|_| rng.random()).collect();
+        let r: F = rng.random();
+
+        let quotient = divide_by_vanishing(&coeffs, &points);
+        let corrected = correct_degree(&quotient, r, points.len());
+        assert_eq!(corrected.len(), coeffs.len());
+
+        // Away from the points, the quotient interpolates `(f(x) - f_S(x)) / V_S(x)`, where `f_S`
+        // interpolates `f` on the points.
+        let x: F = rng.random();
+        let vanishing: F = points.iter().map(|&p| x - p).product();
+        let interpolant: F = points
+            .iter()
+            .enumerate()
+            .map(|(i, &p)| {
+                let basis: F = points
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, &q)| (x - q) / (p - q))
+                    .product();
+                eval_poly(&coeffs, p) * basis
+            })
+            .sum();
+        let expected_quotient = (eval_poly(&coeffs, x) - interpolant) / vanishing;
+        assert_eq!(eval_poly(&quotient, x), expected_quotient);
+        assert_eq!(
+            eval_poly(&corrected, x),
+            expected_quotient * eval_degree_correction(r * x, points.len())
+        );
+        assert_eq!(eval_degree_correction(F::ONE, 3), F::from_u8(4));
+    }
+}
@@ -0,0 +1,47 @@
+use alloc::vec::Vec;
+
+use p3_commit::MultiMmcs;
+use p3_field::Field;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize, InputProof: Serialize",
+    deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
+))]
+pub struct StirProof<F: Field, M: MultiMmcs<F>, Witness, InputProof> {
+    /// A commitment to the first function, the DEEP quotients of the inputs batched together.
+    pub initial_commitment: M::Commitment,
+    pub round_proofs: Vec<StirRoundProof<F, M, Witness>>,
+    pub final_poly: Vec<F>,
+    pub final_pow_witness: Witness,
+    /// Openings of the last function at the queries of the final round.
+    pub final_query_openings: StirQueryOpenings<F, M>,
+    /// Openings of the inputs at the queries of the first round.
+    pub input_proof: InputProof,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize",
+    deserialize = "Witness: Deserialize<'de>"
+))]
+pub struct StirRoundProof<F: Field, M: MultiMmcs<F>, Witness> {
+    /// A commitment to the folded function, evaluated over the next domain.
+    pub commitment: M::Commitment,
+    /// The value of the folded function at the out-of-domain point.
+    pub ood_answer: F,
+    pub pow_witness: Witness,
+    /// Openings of the current function at the queries of this round.
+    pub query_openings: StirQueryOpenings<F, M>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(bound = "")]
+pub struct StirQueryOpenings<F: Field, M: MultiMmcs<F>> {
+    /// For each query, the queried row, a coset of the folding factor's size, in bit-reversed
+    /// order.
+    pub values: Vec<Vec<F>>,
+    /// A proof of the openings of all queries.
+    pub opening_proof: M::MultiProof,
+}
@@ -0,0 +1,177 @@
+use alloc::collections::BTreeSet;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::MultiMmcs;
+use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
+use p3_field::{ExtensionField, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::{log2_strict_usize, reverse_slice_index_bits};
+use tracing::{debug_span, info_span, instrument};
+
+use crate::polynomial::{correct_degree, divide_by_vanishing, eval_poly, fold_poly};
+use crate::{
+    StirConfig, StirProof, StirQueryOpenings, StirRound, StirRoundProof, domain_shift, row_point,
+};
+
+/// Prove that `evals`, the evaluations in bit-reversed order of a function over the subgroup of
+/// order `evals.len()`, are close to a polynomial of degree less than `evals.len() / blowup`.
+///
+/// `open_input` is called at the indices of the queries of the first round, so that the verifier
+/// can check the committed evaluations against the inputs they were computed from.
+#[instrument(name = "STIR prover", skip_all)]
+pub fn prove<Val, Challenge, M, Challenger, InputProof>(
+    config: &StirConfig<M>,
+    evals: Vec<Challenge>,
+    challenger: &mut Challenger,
+    open_input: impl Fn(&[usize]) -> InputProof,
+) -> StirProof<Challenge, M, Challenger::Witness, InputProof>
+where
+    Val: TwoAdicField,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    M: MultiMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let dft = Radix2Dit::default();
+    let rounds = config.rounds(log2_strict_usize(evals.len()));
+    let (final_round, commit_rounds) = rounds.split_last().unwrap();
+
+    let mut coeffs = debug_span!("idft first function").in_scope(|| {
+        let mut evals = evals.clone();
+        reverse_slice_index_bits(&mut evals);
+        dft.idft(evals)
+    });
+    // The evaluation domain is "blown-up" relative to the degree of the first function, so all
+    // coefficients beyond its degree bound should be zero.
+    debug_assert!(
+        coeffs[1 << rounds[0].log_degree..]
+            .iter()
+            .all(|c| c.is_zero()),
+        "The first function must have degree less than its degree bound"
+    );
+    coeffs.truncate(1 << rounds[0].log_degree);
+
+    let (initial_commitment, mut data) = config
+        .mmcs
+        .commit_matrix(RowMajorMatrix::new(evals, rounds[0].folding_factor()));
+    challenger.observe(initial_commitment.clone());
+    let mut folding_randomness: Challenge = challenger.sample_algebra_element();
+
+    let mut round_proofs = vec![];
+    let mut input_indices = None;
+    for (i, round) in commit_rounds.iter().enumerate() {
+        let next_round = &rounds[i + 1];
+        let _span = info_span!("STIR round", round = i).entered();
+
+        // Fold, and commit to the folded polynomial over the next domain.
+        let folded = fold_poly(&coeffs, folding_randomness, round.log_folding_factor);
+        let mut next_evals = folded.clone();
+        next_evals.resize(1 << next_round.log_domain_size, Challenge::ZERO);
+        let mut next_evals = dft.coset_dft(next_evals, Challenge::from(domain_shift::<Val>(i + 1)));
+        reverse_slice_index_bits(&mut next_evals);
+        let (commitment, next_data) = config
+            .mmcs
+            .commit_matrix(RowMajorMatrix::new(next_evals, next_round.folding_factor()));
+        challenger.observe(commitment.clone());
+
+        let ood_point: Challenge = challenger.sample_algebra_element();
+        let ood_answer = eval_poly::<Challenge, _>(&folded, ood_point);
+        challenger.observe_algebra_element(ood_answer);
+        let comb_randomness: Challenge = challenger.sample_algebra_element();
+
+        let pow_witness = challenger.grind(config.proof_of_work_bits);
+        let (indices, query_openings) = answer_queries(config, round, &data, challenger);
+        input_indices.get_or_insert_with(|| indices.clone());
+
+        // The next function is the quotient of the folded polynomial by its values at the
+        // out-of-domain point and the queried points, with its degree corrected back up.
+        let points = iter::once(ood_point)
+            .chain(queried_points::<Val, _>(i, round, &indices))
+            .collect::<Vec<_>>();
+        let quotient = divide_by_vanishing(&folded, &points);
+        coeffs = correct_degree(&quotient, comb_randomness, points.len());
+        coeffs.resize(1 << next_round.log_degree, Challenge::ZERO);
+
+        round_proofs.push(StirRoundProof {
+            commitment,
+            ood_answer,
+            pow_witness,
+            query_openings,
+        });
+        data = next_data;
+        folding_randomness = challenger.sample_algebra_element();
+    }
+
+    let final_poly = fold_poly(&coeffs, folding_randomness, final_round.log_folding_factor);
+    for &coeff in &final_poly {
+        challenger.observe_algebra_element(coeff);
+    }
+    let final_pow_witness = challenger.grind(config.proof_of_work_bits);
+    let (indices, final_query_openings) = answer_queries(config, final_round, &data, challenger);
+    let input_proof = open_input(input_indices.as_deref().unwrap_or(&indices));
+
+    StirProof {
+        initial_commitment,
+        round_proofs,
+        final_poly,
+        final_pow_witness,
+        final_query_openings,
+        input_proof,
+    }
+}
+
+/// Sample the queries of `round`, and open its committed rows at all of them.
+///
+/// Each query is an index into the round's domain, whose row is opened. Only the first round uses
+/// the rest of the index, to check the inputs at that point.
+fn answer_queries<Val, Challenge, M, Challenger>(
+    config: &StirConfig<M>,
+    round: &StirRound,
+    data: &M::ProverData<RowMajorMatrix<Challenge>>,
+    challenger: &mut Challenger,
+) -> (Vec<usize>, StirQueryOpenings<Challenge, M>)
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val>,
+    M: MultiMmcs<Challenge>,
+    Challenger: FieldChallenger<Val>,
+{
+    let indices = (0..round.num_queries)
+        .map(|_| challenger.sample_bits(round.log_domain_size))
+        .collect::<Vec<_>>();
+    let rows = indices
+        .iter()
+        .map(|&index| index >> round.log_folding_factor)
+        .collect::<Vec<_>>();
+    let (opened_rows, opening_proof) = config.mmcs.open_multi_batch(&rows, data);
+    let values = opened_rows
+        .into_iter()
+        .map(|mut opened_rows| opened_rows.pop().unwrap())
+        .collect();
+    (
+        indices,
+        StirQueryOpenings {
+            values,
+            opening_proof,
+        },
+    )
+}
+
+/// The distinct points of the folded domain reached by the queries at `indices` of `round`.
+pub(crate) fn queried_points<Val: TwoAdicField, Challenge: ExtensionField<Val>>(
+    round_index: usize,
+    round: &StirRound,
+    indices: &[usize],
+) -> impl Iterator<Item = Challenge> {
+    let rows = indices
+        .iter()
+        .map(|&index| index >> round.log_folding_factor)
+        .collect::<BTreeSet<_>>();
+    rows.into_iter().map(move |row| {
+        Challenge::from(
+            row_point::<Val>(round_index, round, row).exp_power_of_2(round.log_folding_factor),
+        )
+    })
+}
@@ -0,0 +1,262 @@
+use alloc::collections::BTreeMap;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::MultiMmcs;
+use p3_field::{ExtensionField, Field, TwoAdicField, batch_multiplicative_inverse};
+use p3_fri::verifier::FriError;
+use p3_interpolation::interpolate_coset;
+use p3_matrix::Dimensions;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::reverse_slice_index_bits;
+
+use crate::polynomial::{eval_degree_correction, eval_poly};
+use crate::{StirConfig, StirProof, StirQueryOpenings, StirRound, row_point};
+
+/// Verify a proof that the function committed in `proof`, over the subgroup of order
+/// `2^log_height`, is close to a polynomial of degree less than `2^log_height / blowup`.
+///
+/// `open_input` checks an input proof at indices of that subgroup, and returns the values of the
+/// function there.
+pub fn verify<Val, Challenge, M, Challenger, InputProof, InputError>(
+    config: &StirConfig<M>,
+    proof: &StirProof<Challenge, M, Challenger::Witness, InputProof>,
+    log_height: usize,
+    challenger: &mut Challenger,
+    open_input: impl Fn(&[usize], &InputProof) -> Result<Vec<Challenge>, FriError<M::Error, InputError>>,
+) -> Result<(), FriError<M::Error, InputError>>
+where
+    Val: TwoAdicField,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    M: MultiMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let rounds = config.rounds(log_height);
+    let (final_round, commit_rounds) = rounds.split_last().unwrap();
+    if proof.round_proofs.len() != commit_rounds.len()
+        || proof.final_poly.len() != 1 << (final_round.log_degree - final_round.log_folding_factor)
+    {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    challenger.observe(proof.initial_commitment.clone());
+    let mut folding_randomness: Challenge = challenger.sample_algebra_element();
+
+    let mut commitment = &proof.initial_commitment;
+    // The function of each round after the first is derived from the committed folded function.
+    let mut quotient = None;
+    for (i, (round, round_proof)) in commit_rounds.iter().zip(&proof.round_proofs).enumerate() {
+        challenger.observe(round_proof.commitment.clone());
+        let ood_point: Challenge = challenger.sample_algebra_element();
+        challenger.observe_algebra_element(round_proof.ood_answer);
+        let comb_randomness: Challenge = challenger.sample_algebra_element();
+
+        if !challenger.check_witness(config.proof_of_work_bits, round_proof.pow_witness) {
+            return Err(FriError::InvalidPowWitness);
+        }
+
+        let folded = verify_queries(
+            config,
+            i,
+            round,
+            commitment,
+            &round_proof.query_openings,
+            (i == 0).then_some(&proof.input_proof),
+            quotient.as_ref(),
+            folding_randomness,
+            challenger,
+            &open_input,
+        )?;
+
+        // The next function is the quotient of the folded function by its values at the
+        // out-of-domain point and the queried points, with its degree corrected back up.
+        let (points, answers) = folded.into_values().unzip::<_, _, Vec<_>, Vec<_>>();
+        quotient = Some(Quotient::new(
+            [vec![ood_point], points].concat(),
+            [vec![round_proof.ood_answer], answers].concat(),
+            comb_randomness,
+        ));
+        commitment = &round_proof.commitment;
+        folding_randomness = challenger.sample_algebra_element();
+    }
+
+    // Observe all coefficients of the final polynomial.
+    for &coeff in &proof.final_poly {
+        challenger.observe_algebra_element(coeff);
+    }
+    if !challenger.check_witness(config.proof_of_work_bits, proof.final_pow_witness) {
+        return Err(FriError::InvalidPowWitness);
+    }
+
+    let folded = verify_queries(
+        config,
+        commit_rounds.len(),
+        final_round,
+        commitment,
+        &proof.final_query_openings,
+        commit_rounds.is_empty().then_some(&proof.input_proof),
+        quotient.as_ref(),
+        folding_randomness,
+        challenger,
+        &open_input,
+    )?;
+    for (point, eval) in folded.into_values() {
+        if eval_poly::<Challenge, _>(&proof.final_poly, point) != eval {
+            return Err(FriError::FinalPolyMismatch);
+        }
+    }
+
+    Ok(())
+}
+
+/// Sample the queries of round `round_index`, fold them, and check their openings against
+/// `commitment`.
+///
+/// Returns, for each distinct queried row, the point of the folded domain it reaches and the value
+/// of the folded function there. In the first round, the queried value of each row is replaced by
+/// the value computed from the inputs, so the opening only verifies if they agree.
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn verify_queries<Val, Challenge, M, Challenger, InputProof, InputError>(
+    config: &StirConfig<M>,
+    round_index: usize,
+    round: &StirRound,
+    commitment: &M::Commitment,
+    openings: &StirQueryOpenings<Challenge, M>,
+    input_proof: Option<&InputProof>,
+    quotient: Option<&Quotient<Challenge>>,
+    folding_randomness: Challenge,
+    challenger: &mut Challenger,
+    open_input: &impl Fn(
+        &[usize],
+        &InputProof,
+    ) -> Result<Vec<Challenge>, FriError<M::Error, InputError>>,
+) -> Result<BTreeMap<usize, (Challenge, Challenge)>, FriError<M::Error, InputError>>
+where
+    Val: TwoAdicField,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    M: MultiMmcs<Challenge>,
+    Challenger: FieldChallenger<Val>,
+{
+    if openings.values.len() != round.num_queries {
+        return Err(FriError::InvalidProofShape);
+    }
+    let indices = (0..round.num_queries)
+        .map(|_| challenger.sample_bits(round.log_domain_size))
+        .collect::<Vec<_>>();
+    let input_values = input_proof
+        .map(|input_proof| open_input(&indices, input_proof))
+        .transpose()?;
+    if input_values
+        .as_ref()
+        .is_some_and(|input_values| input_values.len() != indices.len())
+    {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    let mut folded = BTreeMap::new();
+    let mut rows = Vec::with_capacity(indices.len());
+    let mut opened_values = Vec::with_capacity(indices.len());
+    for (q, (&index, values)) in indices.iter().zip(&openings.values).enumerate() {
+        let row = index >> round.log_folding_factor;
+        if values.len() != round.folding_factor() {
+            return Err(FriError::InvalidProofShape);
+        }
+
+        let mut values = values.clone();
+        if let Some(input_values) = &input_values {
+            values[index % round.folding_factor()] = input_values[q];
+        }
+        rows.push(row);
+        opened_values.push(vec![values.clone()]);
+
+        // The row holds evaluations over the coset `y H` in bit-reversed order.
+        let y = row_point::<Val>(round_index, round, row);
+        if let Some(quotient) = quotient {
+            let mut xs = Val::two_adic_generator(round.log_folding_factor)
+                .shifted_powers(y)
+                .take(round.folding_factor())
+                .collect::<Vec<_>>();
+            reverse_slice_index_bits(&mut xs);
+            for (value, x) in values.iter_mut().zip(xs) {
+                *value = quotient.eval(*value, Challenge::from(x));
+            }
+        }
+        reverse_slice_index_bits(&mut values);
+        let folded_eval = interpolate_coset(
+            &RowMajorMatrix::new_col(values),
+            Challenge::from(y),
+            folding_randomness,
+            None,
+        )[0];
+        let point = Challenge::from(y.exp_power_of_2(round.log_folding_factor));
+        folded.insert(row, (point, folded_eval));
+    }
+
+    // Check the openings of all queries at once.
+    let dims = &[Dimensions {
+        width: round.folding_factor(),
+        height: 1 << round.log_num_rows(),
+    }];
+    config
+        .mmcs
+        .verify_multi_batch(
+            commitment,
+            dims,
+            &rows,
+            &opened_values,
+            &openings.opening_proof,
+        )
+        .map_err(FriError::CommitPhaseMmcsError)?;
+    Ok(folded)
+}
+
+/// The function `(g(x) - a(x)) / V(x) * sum_{i <= |S|} (r x)^i`, where `g` is a committed folded
+/// function, `V` is the vanishing polynomial of a set of points `S` and `a` interpolates the values
+/// of `g` on `S`.
+struct Quotient<F> {
+    points: Vec<F>,
+    /// The values of `g` at `points`, times their barycentric weights.
+    weighted_answers: Vec<F>,
+    comb_randomness: F,
+}
+
+impl<F: Field> Quotient<F> {
+    fn new(points: Vec<F>, answers: Vec<F>, comb_randomness: F) -> Self {
+        let weights = batch_multiplicative_inverse(
+            &points
+                .iter()
+                .enumerate()
+                .map(|(i, &p)| {
+                    points
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, &q)| p - q)
+                        .product()
+                })
+                .collect::<Vec<F>>(),
+        );
+        let weighted_answers = answers.iter().zip(weights).map(|(&a, w)| a * w).collect();
+        Self {
+            points,
+            weighted_answers,
+            comb_randomness,
+        }
+    }
+
+    /// Evaluate the function at `x`, given the value `g(x)` of the folded function.
+    fn eval(&self, g_at_x: F, x: F) -> F {
+        // With `d_i = 1 / (x - s_i)`, `a(x) / V(x) = sum_i w_i a_i d_i` and `1 / V(x) = prod_i d_i`.
+        let inv_diffs =
+            batch_multiplicative_inverse(&self.points.iter().map(|&p| x - p).collect::<Vec<_>>());
+        let inv_vanishing: F = inv_diffs.iter().copied().product();
+        let interpolant: F = inv_diffs
+            .iter()
+            .zip(&self.weighted_answers)
+            .map(|(&d, &a)| a * d)
+            .sum();
+        (g_at_x * inv_vanishing - interpolant)
+            * eval_degree_correction(self.comb_randomness * x, self.points.len())
+    }
+}
@@ -0,0 +1,352 @@
+use itertools::{Itertools, izip};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{CanObserve, DuplexChallenger, FieldChallenger};
+use p3_commit::{CommitError, ExtensionMmcs, Pcs, PolynomialSpace};
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing};
+use p3_fri::verifier::FriError;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_stir::{StirConfig, StirPcs};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::distr::{Distribution, StandardUniform};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+fn seeded_rng() -> impl Rng {
+    SmallRng::seed_from_u64(0)
+}
+
+fn do_test_stir_pcs<Val, Challenge, Challenger, P>(
+    (pcs, challenger): &(P, Challenger),
+    log_degrees_by_round: &[&[usize]],
+) where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Val: Field,
+    StandardUniform: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+    Challenger: Clone + CanObserve<P::Commitment> + FieldChallenger<Val>,
+{
+    let num_rounds = log_degrees_by_round.len();
+    let mut rng = seeded_rng();
+
+    let mut p_challenger = challenger.clone();
+
+    let domains_and_polys_by_round = log_degrees_by_round
+        .iter()
+        .map(|log_degrees| {
+            log_degrees
+                .iter()
+                .map(|&log_degree| {
+                    let d = 1 << log_degree;
+                    // random width 5-15
+                    let width = 5 + rng.random_range(0..=10);
+                    (
+                        pcs.natural_domain_for_degree(d),
+                        RowMajorMatrix::<Val>::rand(&mut rng, d, width),
+                    )
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let (commits_by_round, data_by_round): (Vec<_>, Vec<_>) = domains_and_polys_by_round
+        .iter()
+        .map(|domains_and_polys| pcs.commit(domains_and_polys.clone()))
+        .unzip();
+    assert_eq!(commits_by_round.len(), num_rounds);
+    assert_eq!(data_by_round.len(), num_rounds);
+    p_challenger.observe_slice(&commits_by_round);
+
+    let zeta: Challenge = p_challenger.sample_algebra_element();
+
+    let points_by_round = log_degrees_by_round
+        .iter()
+        .map(|log_degrees| vec![vec![zeta]; log_degrees.len()])
+        .collect_vec();
+    let data_and_points = data_by_round.iter().zip(points_by_round).collect();
+    let (opening_by_round, proof) = pcs.open(data_and_points, &mut p_challenger);
+    assert_eq!(opening_by_round.len(), num_rounds);
+
+    // Verify the proof.
+    let mut v_challenger = challenger.clone();
+    v_challenger.observe_slice(&commits_by_round);
+    let verifier_zeta: Challenge = v_challenger.sample_algebra_element();
+    assert_eq!(verifier_zeta, zeta);
+
+    let commits_and_claims_by_round = izip!(
+        commits_by_round,
+        domains_and_polys_by_round,
+        opening_by_round
+    )
+    .map(|(commit, domains_and_polys, openings)| {
+        let claims = domains_and_polys
+            .iter()
+            .zip(openings)
+            .map(|((domain, _), mat_openings)| (*domain, vec![(zeta, mat_openings[0].clone())]))
+            .collect_vec();
+        (commit, claims)
+    })
+    .collect_vec();
+    assert_eq!(commits_and_claims_by_round.len(), num_rounds);
+
+    pcs.verify(commits_and_claims_by_round, &proof, &mut v_challenger)
+        .unwrap()
+}
+
+fn do_test_commit_errors<Val, Challenge, Challenger, P>((pcs, _): &(P, Challenger))
+where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Val: Field,
+    StandardUniform: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+{
+    let mut rng = seeded_rng();
+    let valid = (
+        pcs.natural_domain_for_degree(1 << 3),
+        RowMajorMatrix::<Val>::rand(&mut rng, 1 << 3, 5),
+    );
+
+    let short = (
+        pcs.natural_domain_for_degree(1 << 4),
+        RowMajorMatrix::<Val>::rand(&mut rng, 1 << 3, 5),
+    );
+    assert_eq!(
+        pcs.try_commit(vec![valid.clone(), short]).err(),
+        Some(CommitError::HeightMismatch {
+            index: 1,
+            domain_size: 1 << 4,
+            height: 1 << 3
+        })
+    );
+
+    // The domain's low-degree extension would be larger than any domain.
+    let log_size = P::Domain::MAX_LOG_SIZE;
+    let large = (
+        pcs.natural_domain_for_degree(1 << log_size),
+        RowMajorMatrix::<Val>::rand(&mut rng, 1, 5),
+    );
+    assert_eq!(
+        pcs.try_commit(vec![valid, large]).err(),
+        Some(CommitError::UnsupportedDomainSize { index: 1, log_size })
+    );
+}
+
+// Set it up so we create tests inside a module for each pcs, so we get nice error reports
+// specific to a failing PCS.
+macro_rules! make_tests_for_pcs {
+    ($p:expr) => {
+        #[test]
+        fn single() {
+            let p = $p;
+            for i in 3..6 {
+                $crate::do_test_stir_pcs(&p, &[&[i]]);
+            }
+        }
+
+        #[test]
+        fn many_equal() {
+            let p = $p;
+            for i in 5..8 {
+                $crate::do_test_stir_pcs(&p, &[&[i; 5]]);
+                println!("{i} ok");
+            }
+        }
+
+        #[test]
+        fn many_different() {
+            let p = $p;
+            for i in 3..8 {
+                let degrees = (3..3 + i).collect::<Vec<_>>();
+                $crate::do_test_stir_pcs(&p, &[&degrees]);
+            }
+        }
+
+        #[test]
+        fn many_different_rev() {
+            let p = $p;
+            for i in 3..8 {
+                let degrees = (3..3 + i).rev().collect::<Vec<_>>();
+                $crate::do_test_stir_pcs(&p, &[&degrees]);
+            }
+        }
+
+        #[test]
+        fn commit_errors() {
+            let p = $p;
+            $crate::do_test_commit_errors(&p);
+        }
+
+        #[test]
+        fn multiple_rounds() {
+            let p = $p;
+            $crate::do_test_stir_pcs(&p, &[&[3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3], &[3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3], &[2]]);
+            $crate::do_test_stir_pcs(&p, &[&[2], &[3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3, 4], &[3, 4]]);
+            $crate::do_test_stir_pcs(&p, &[&[4, 2], &[4, 2]]);
+            $crate::do_test_stir_pcs(&p, &[&[2, 2], &[3, 3]]);
+            $crate::do_test_stir_pcs(&p, &[&[3, 3], &[2, 2]]);
+            $crate::do_test_stir_pcs(&p, &[&[2], &[3, 3]]);
+        }
+    };
+}
+
+mod babybear_stir_pcs {
+    use super::*;
+
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type MyPcs = StirPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+
+    fn get_pcs(
+        log_blowup: usize,
+        log_folding_factor: usize,
+        log_final_poly_len: usize,
+    ) -> (MyPcs, Challenger) {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = ValMmcs::new(hash, compress);
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let stir_config = StirConfig {
+            log_blowup,
+            log_folding_factor,
+            log_final_poly_len,
+            security_bits: 20,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, stir_config);
+        (pcs, Challenger::new(perm))
+    }
+
+    mod blowup_1 {
+        make_tests_for_pcs!(super::get_pcs(1, 2, 0));
+    }
+    mod blowup_2 {
+        make_tests_for_pcs!(super::get_pcs(2, 2, 0));
+    }
+    mod folding_2 {
+        make_tests_for_pcs!(super::get_pcs(1, 1, 0));
+    }
+    mod folding_16 {
+        make_tests_for_pcs!(super::get_pcs(2, 4, 0));
+    }
+    mod final_poly_4 {
+        make_tests_for_pcs!(super::get_pcs(1, 2, 2));
+    }
+
+    type MyProof = <MyPcs as Pcs<Challenge, Challenger>>::Proof;
+    type MyError = <MyPcs as Pcs<Challenge, Challenger>>::Error;
+
+    /// Open a random polynomial of degree `2^6` at a random point, and verify the opened values and
+    /// the proof after `tamper` modified them.
+    fn verify_tampered(
+        tamper: impl FnOnce(&mut Vec<Challenge>, &mut MyProof),
+    ) -> Result<(), MyError> {
+        let (pcs, challenger) = get_pcs(1, 2, 0);
+        let mut rng = seeded_rng();
+        let domain = <MyPcs as Pcs<Challenge, Challenger>>::natural_domain_for_degree(&pcs, 1 << 6);
+        let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << 6, 5);
+        let (commit, data) =
+            <MyPcs as Pcs<Challenge, Challenger>>::commit(&pcs, vec![(domain, evals)]);
+
+        let mut p_challenger = challenger.clone();
+        p_challenger.observe(commit.clone());
+        let zeta: Challenge = p_challenger.sample_algebra_element();
+        let (opened_values, mut proof) =
+            pcs.open(vec![(&data, vec![vec![zeta]])], &mut p_challenger);
+        let mut values = opened_values[0][0][0].clone();
+        tamper(&mut values, &mut proof);
+
+        let mut v_challenger = challenger;
+        v_challenger.observe(commit.clone());
+        let _: Challenge = v_challenger.sample_algebra_element();
+        pcs.verify(
+            vec![(commit, vec![(domain, vec![(zeta, values)])])],
+            &proof,
+            &mut v_challenger,
+        )
+    }
+
+    #[test]
+    fn valid_opening_accepted() {
+        verify_tampered(|_, proof| assert_eq!(proof.round_proofs.len(), 2)).unwrap();
+    }
+
+    #[test]
+    fn wrong_opening_rejected() {
+        // Claim a different value for the first column.
+        assert!(verify_tampered(|values, _| values[0] += Challenge::ONE).is_err());
+    }
+
+    #[test]
+    fn tampered_proof_rejected() {
+        let tamper_round_commitment = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.round_proofs[0].commitment = proof.round_proofs[1].commitment.clone();
+        };
+        let tamper_ood_answer = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.round_proofs[1].ood_answer += Challenge::ONE;
+        };
+        let tamper_final_poly = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.final_poly[0] += Challenge::ONE;
+        };
+        let tamper_query_opening = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.round_proofs[1].query_openings.values[0][0] += Challenge::ONE;
+        };
+        let tamper_final_query_opening = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.final_query_openings.values[0][0] += Challenge::ONE;
+        };
+        let tamper_input_opening = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.input_proof[0].opened_values[0][0][0] += Val::ONE;
+        };
+        let tamper_merkle_path = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.round_proofs[0].query_openings.opening_proof[0][0] += Val::ONE;
+        };
+        let tamper_input_merkle_path = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.input_proof[0].opening_proof[0][0] += Val::ONE;
+        };
+        let tamper_pow_witness = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.round_proofs[0].pow_witness += Val::ONE;
+        };
+        let tamper_final_pow_witness = |_: &mut Vec<Challenge>, proof: &mut MyProof| {
+            proof.final_pow_witness += Val::ONE;
+        };
+
+        assert!(verify_tampered(tamper_round_commitment).is_err());
+        assert!(verify_tampered(tamper_ood_answer).is_err());
+        assert!(verify_tampered(tamper_final_poly).is_err());
+        assert!(verify_tampered(tamper_query_opening).is_err());
+        assert!(verify_tampered(tamper_final_query_opening).is_err());
+        assert!(verify_tampered(tamper_input_opening).is_err());
+        assert!(verify_tampered(tamper_merkle_path).is_err());
+        assert!(verify_tampered(tamper_input_merkle_path).is_err());
+        assert!(matches!(
+            verify_tampered(tamper_pow_witness),
+            Err(FriError::InvalidPowWitness)
+        ));
+        assert!(matches!(
+            verify_tampered(tamper_final_pow_witness),
+            Err(FriError::InvalidPowWitness)
+        ));
+    }
+}
@@ -0,0 +1,70 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Hash;
+
+/// The digests of all nodes of a Merkle tree at some layer, which together commit to the tree.
+///
+/// A cap with a single digest is the root of the tree. A cap of `2^k` digests lets opening proofs
+/// stop `k` layers below the root, at the cost of a larger commitment.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "[W; DIGEST_ELEMS]: Serialize"))]
+#[serde(bound(deserialize = "[W; DIGEST_ELEMS]: Deserialize<'de>"))]
+pub struct MerkleCap<F, W, const DIGEST_ELEMS: usize> {
+    digests: Vec<[W; DIGEST_ELEMS]>,
+    _marker: PhantomData<F>,
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> MerkleCap<F, W, DIGEST_ELEMS> {
+    #[must_use]
+    pub const fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    #[must_use]
+    pub const fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+
+    /// The digest of the node at `index` in the cap's layer.
+    #[must_use]
+    pub fn get(&self, index: usize) -> Option<&[W; DIGEST_ELEMS]> {
+        self.digests.get(index)
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> From<Vec<[W; DIGEST_ELEMS]>>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn from(digests: Vec<[W; DIGEST_ELEMS]>) -> Self {
+        Self {
+            digests,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> From<Hash<F, W, DIGEST_ELEMS>>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn from(root: Hash<F, W, DIGEST_ELEMS>) -> Self {
+        Self::from(alloc::vec![root.into()])
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> IntoIterator for MerkleCap<F, W, DIGEST_ELEMS> {
+    type Item = [W; DIGEST_ELEMS];
+    type IntoIter = alloc::vec::IntoIter<[W; DIGEST_ELEMS]>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.digests.into_iter()
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> AsRef<[[W; DIGEST_ELEMS]]> for MerkleCap<F, W, DIGEST_ELEMS> {
+    fn as_ref(&self) -> &[[W; DIGEST_ELEMS]] {
+        &self.digests
+    }
+}
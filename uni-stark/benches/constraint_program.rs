@@ -0,0 +1,154 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{BasedVectorSpace, Field, PrimeCharacteristicRing};
+use p3_fri::TwoAdicFriPcs;
+use p3_keccak_air::KeccakAir;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrixView;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    ConstraintProgram, PackedChallenge, PackedVal, ProverConstraintFolder, StarkConfig,
+    SymbolicAirBuilder, get_symbolic_constraints,
+};
+use rand::distr::{Distribution, StandardUniform};
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+const D: usize = 4;
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, D>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+const WIDTH: usize = 128;
+const NUM_ROWS: usize = 1 << 10;
+
+/// An AIR with degree three constraints between neighbouring columns and rows, sharing many
+/// subexpressions between its constraints.
+struct ChainAir;
+
+impl<F> BaseAir<F> for ChainAir {
+    fn width(&self) -> usize {
+        WIDTH
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for ChainAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        for i in 0..WIDTH - 2 {
+            let product = local[i] * local[i + 1];
+            builder.assert_zero(product.clone() * local[i + 2] - next[i]);
+            builder
+                .when_transition()
+                .assert_eq(product + local[i + 2], next[i + 1]);
+        }
+    }
+}
+
+fn random_packed<T>(rng: &mut SmallRng, n: usize) -> Vec<T>
+where
+    StandardUniform: Distribution<T>,
+{
+    (0..n).map(|_| rng.random()).collect()
+}
+
+/// Fold the constraints of `air` over [`NUM_ROWS`] random packed rows, either by evaluating the AIR
+/// or its compiled constraint program, as `quotient_values` does.
+fn bench_air<A>(c: &mut Criterion, name: &str, air: &A)
+where
+    A: BaseAir<Val>
+        + Air<SymbolicAirBuilder<Val>>
+        + for<'a> Air<ProverConstraintFolder<'a, MyConfig>>,
+{
+    let width = air.width();
+    let num_periodic = air.periodic_columns().len();
+    let mut rng = SmallRng::seed_from_u64(1);
+    let windows: Vec<Vec<PackedVal<MyConfig>>> = (0..NUM_ROWS)
+        .map(|_| random_packed(&mut rng, 2 * width))
+        .collect();
+    let periodic_values: Vec<Vec<PackedVal<MyConfig>>> = (0..NUM_ROWS)
+        .map(|_| random_packed(&mut rng, num_periodic))
+        .collect();
+    let is_transition = random_packed::<PackedVal<MyConfig>>(&mut rng, NUM_ROWS);
+
+    let constraints = get_symbolic_constraints::<Val, _>(air, 0, 0);
+    let program = ConstraintProgram::compile(&constraints);
+    let alpha: Challenge = rng.random();
+    let alpha_powers = alpha.powers().take(constraints.len()).collect::<Vec<_>>();
+    let decomposed_alpha_powers: Vec<Vec<Val>> = (0..D)
+        .map(|i| {
+            alpha_powers
+                .iter()
+                .map(|x| BasedVectorSpace::<Val>::as_basis_coefficients_slice(x)[i])
+                .collect()
+        })
+        .collect();
+    let public_values = vec![];
+    let mut program_base = Vec::new();
+    let mut program_ext = Vec::new();
+
+    let mut fold = |eval: &dyn Fn(&mut ProverConstraintFolder<'_, MyConfig>)| {
+        let mut sum = PackedChallenge::<MyConfig>::ZERO;
+        for ((window, periodic_values), &is_transition) in
+            windows.iter().zip(&periodic_values).zip(&is_transition)
+        {
+            let is_transition_windows = [is_transition];
+            let mut folder = ProverConstraintFolder {
+                preprocessed: RowMajorMatrixView::new(&[], 0),
+                main: RowMajorMatrixView::new(window, width),
+                main_ext: RowMajorMatrixView::new(&[], 0),
+                permutation: RowMajorMatrixView::new(&[], 0),
+                permutation_challenges: &[],
+                public_values: &public_values,
+                periodic_values,
+                is_first_row: PackedVal::<MyConfig>::ZERO,
+                is_last_row: PackedVal::<MyConfig>::ZERO,
+                is_transition_windows: &is_transition_windows,
+                alpha_powers: &alpha_powers,
+                decomposed_alpha_powers: &decomposed_alpha_powers,
+                accumulator: PackedChallenge::<MyConfig>::ZERO,
+                constraint_index: 0,
+                program_base: &mut program_base,
+                program_ext: &mut program_ext,
+            };
+            eval(&mut folder);
+            sum += folder.accumulator;
+        }
+        sum
+    };
+
+    let mut group = c.benchmark_group(format!("fold_constraints::<{name}>"));
+    group.sample_size(10);
+    group.bench_function("air_eval", |b| {
+        b.iter(|| fold(&|folder| air.eval(folder)));
+    });
+    group.bench_function("eval_program", |b| {
+        b.iter(|| fold(&|folder| folder.eval_program(&program)));
+    });
+    group.finish();
+}
+
+fn bench_constraint_program(c: &mut Criterion) {
+    bench_air(c, "ChainAir", &ChainAir);
+    bench_air(c, "KeccakAir", &KeccakAir {});
+}
+
+criterion_group!(benches, bench_constraint_program);
+criterion_main!(benches);
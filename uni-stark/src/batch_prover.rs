@@ -0,0 +1,331 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{Itertools, izip};
+use p3_air::{Air, InteractionAir, get_interactions};
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::PrimeCharacteristicRing;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::log2_strict_usize;
+use tracing::{info_span, instrument};
+
+use crate::fingerprint::{observe_air, observe_config};
+use crate::prover::quotient_values;
+use crate::verifier::{main_trace_width, window_points};
+use crate::{
+    BatchProof, Commitments, ConstraintProgram, NUM_LOOKUP_CHALLENGES, OpenedValues,
+    ProverConstraintFolder, StarkGenericConfig, SymbolicAirBuilder, Val, eval_logup_constraints,
+    generate_logup_trace, get_air_fingerprint_with_interactions,
+    get_log_quotient_degree_with_interactions, get_symbolic_constraints, is_zk,
+    num_logup_constraints, permutation_width,
+};
+
+/// A single AIR to be proven as part of a batch, together with its trace and public values.
+#[derive(Debug)]
+pub struct StarkInstance<'a, SC: StarkGenericConfig, A> {
+    pub air: &'a A,
+    pub trace: RowMajorMatrix<Val<SC>>,
+    pub public_values: Vec<Val<SC>>,
+}
+
+/// Prove several AIRs at once, producing a single [`BatchProof`].
+///
+/// The traces may have different heights, and are padded as described by
+/// [`BaseAir::trace_padding`](p3_air::BaseAir::trace_padding). All traces are committed in a
+/// single round, the quotient chunks of every AIR are committed in a second round, and everything
+/// is opened in one opening proof.
+///
+/// AIRs may interact with each other over shared buses, as described by their
+/// [`InteractionAir`] implementations. If any AIR has interactions, a LogUp permutation trace is
+/// committed for each such AIR in an additional round after the main traces, and the proof
+/// carries the cumulative sum of every AIR so that the verifier can check that all buses balance.
+///
+/// AIRs of different Rust types can be proven together by wrapping them in an enum which
+/// dispatches `eval` to the contained AIR. Preprocessed traces and AIR-defined permutation traces
+/// (see [`BaseAir::permutation_width`](p3_air::BaseAir::permutation_width)) are not supported in
+/// batch mode.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_batch<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    mut instances: Vec<StarkInstance<'_, SC, A>>,
+    challenger: &mut SC::Challenger,
+) -> BatchProof<SC>
+where
+    SC: StarkGenericConfig,
+    A: InteractionAir<Val<SC>> + Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    assert!(!instances.is_empty(), "batch must contain at least one AIR");
+    assert!(
+        instances
+            .iter()
+            .all(|instance| instance.air.permutation_width() == 0),
+        "AIR-defined permutation traces are not supported in batch mode"
+    );
+
+    #[cfg(debug_assertions)]
+    let unpadded_heights = instances
+        .iter()
+        .map(|instance| instance.trace.height())
+        .collect_vec();
+    for instance in &mut instances {
+        if let Some(padding) = instance.air.trace_padding() {
+            padding.pad(&mut instance.trace);
+        }
+        let height = instance.trace.height();
+        assert!(
+            instance
+                .air
+                .periodic_columns()
+                .iter()
+                .all(|c| c.len().is_power_of_two() && c.len() <= height),
+            "periodic column lengths must be powers of two dividing the trace height"
+        );
+    }
+
+    #[cfg(debug_assertions)]
+    for (instance, unpadded_height) in instances.iter().zip(unpadded_heights) {
+        crate::check_constraints::assert_constraints::<_, SC::Challenge, _>(
+            instance.air,
+            None,
+            &instance.trace,
+            None,
+            &[],
+            &instance.public_values,
+            unpadded_height,
+        );
+    }
+
+    let pcs = config.pcs();
+
+    let (airs, traces, public_values): (Vec<_>, Vec<_>, Vec<_>) = instances
+        .into_iter()
+        .map(|instance| (instance.air, instance.trace, instance.public_values))
+        .multiunzip();
+
+    let degree_bits = traces
+        .iter()
+        .map(|trace| log2_strict_usize(trace.height()))
+        .collect_vec();
+    let trace_domains = traces
+        .iter()
+        .map(|trace| pcs.natural_domain_for_degree(trace.height()))
+        .collect_vec();
+
+    let interactions = airs.iter().map(|air| get_interactions(*air)).collect_vec();
+    assert!(
+        izip!(&airs, &interactions)
+            .all(|(air, interactions)| interactions.is_empty() || air.window_size() >= 2),
+        "AIRs with interactions need a window of at least two rows"
+    );
+    // The commitment consumes the traces, so keep the ones needed for the permutation traces.
+    let lookup_traces = izip!(&interactions, &traces)
+        .filter(|(interactions, _)| !interactions.is_empty())
+        .map(|(_, trace)| trace.clone())
+        .collect_vec();
+
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.commit(izip!(trace_domains.iter().copied(), traces).collect_vec()));
+
+    // Observe the instance.
+    challenger.observe(Val::<SC>::from_usize(airs.len()));
+    for &bits in &degree_bits {
+        // degree < 2^255 so we can safely cast log_degree to a u8.
+        challenger.observe(Val::<SC>::from_u8(bits as u8));
+    }
+    observe_config(config, challenger);
+    for (air, interactions, pis) in izip!(&airs, &interactions, &public_values) {
+        observe_air::<SC>(
+            challenger,
+            main_trace_width::<SC, _>(*air),
+            0,
+            permutation_width(interactions),
+            air.window_size(),
+            &air.periodic_columns(),
+            &get_air_fingerprint_with_interactions(*air, interactions, pis.len()),
+        );
+    }
+
+    challenger.observe(trace_commit.clone());
+    for pis in &public_values {
+        challenger.observe_slice(pis);
+    }
+
+    // The index of each AIR's permutation trace within the permutation commitment, if it has one.
+    let mut num_permutation_traces = 0;
+    let permutation_indices = interactions
+        .iter()
+        .map(|interactions| {
+            (!interactions.is_empty()).then(|| {
+                num_permutation_traces += 1;
+                num_permutation_traces - 1
+            })
+        })
+        .collect_vec();
+
+    let mut permutation_challenges = vec![];
+    let mut cumulative_sums = vec![SC::Challenge::ZERO; airs.len()];
+    let mut permutation = None;
+    if num_permutation_traces > 0 {
+        permutation_challenges = (0..NUM_LOOKUP_CHALLENGES)
+            .map(|_| challenger.sample_algebra_element())
+            .collect_vec();
+
+        let mut lookup_traces = lookup_traces.iter();
+        let mut permutation_traces = Vec::with_capacity(num_permutation_traces);
+        for (interactions, &trace_domain, cumulative_sum) in
+            izip!(&interactions, &trace_domains, &mut cumulative_sums)
+        {
+            if interactions.is_empty() {
+                continue;
+            }
+            let trace = lookup_traces.next().unwrap();
+            let (permutation_trace, sum) =
+                generate_logup_trace(interactions, None, trace, &permutation_challenges);
+            *cumulative_sum = sum;
+            permutation_traces.push((trace_domain, permutation_trace.flatten_to_base()));
+        }
+
+        let (permutation_commit, permutation_data) =
+            info_span!("commit to permutation traces").in_scope(|| pcs.commit(permutation_traces));
+        challenger.observe(permutation_commit.clone());
+        for &sum in &cumulative_sums {
+            challenger.observe_algebra_element(sum);
+        }
+        permutation = Some((permutation_commit, permutation_data));
+    }
+
+    let alpha: SC::Challenge = challenger.sample_algebra_element();
+
+    // In zero-knowledge mode each quotient has twice as many chunks.
+    let is_zk = is_zk::<SC>() as usize;
+    let log_quotient_degrees = izip!(&airs, &interactions, &public_values)
+        .map(|(air, interactions, pis)| {
+            get_log_quotient_degree_with_interactions(*air, interactions, pis.len(), is_zk) + is_zk
+        })
+        .collect_vec();
+
+    let mut quotient_chunk_counts = Vec::with_capacity(airs.len());
+    let mut quotients = Vec::with_capacity(airs.len());
+    for (i, (air, pis, interactions, &trace_domain, &bits, &log_quotient_degree)) in izip!(
+        &airs,
+        &public_values,
+        &interactions,
+        &trace_domains,
+        &degree_bits,
+        &log_quotient_degrees
+    )
+    .enumerate()
+    {
+        let quotient_degree = 1 << log_quotient_degree;
+        let quotient_domain =
+            trace_domain.create_disjoint_domain(1 << (bits + log_quotient_degree));
+        let trace_on_quotient_domain =
+            pcs.get_evaluations_on_domain(&trace_data, i, quotient_domain);
+
+        let permutation_on_quotient_domain = permutation
+            .as_ref()
+            .zip(permutation_indices[i])
+            .map(|((_, data), j)| pcs.get_evaluations_on_domain(data, j, quotient_domain));
+        let cumulative_sum = cumulative_sums[i];
+
+        let symbolic_constraints = get_symbolic_constraints::<Val<SC>, A>(*air, 0, pis.len());
+        let constraint_count = symbolic_constraints.len() + num_logup_constraints(interactions);
+        let program = ConstraintProgram::compile(&symbolic_constraints);
+        let quotient_values = quotient_values(
+            |folder: &mut ProverConstraintFolder<'_, SC>| {
+                folder.eval_program(&program);
+                eval_logup_constraints(folder, interactions, cumulative_sum);
+            },
+            pis,
+            &air.periodic_columns(),
+            air.ext_width(),
+            trace_domain,
+            quotient_domain,
+            None,
+            trace_on_quotient_domain,
+            permutation_on_quotient_domain,
+            &permutation_challenges,
+            air.window_size(),
+            alpha,
+            constraint_count,
+        );
+        let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
+
+        quotient_chunk_counts.push(quotient_degree);
+        quotients.push((quotient_domain, quotient_flat, quotient_degree));
+    }
+
+    let (quotient_commit, quotient_data) =
+        info_span!("commit to quotient poly chunks").in_scope(|| pcs.commit_quotients(quotients));
+    challenger.observe(quotient_commit.clone());
+
+    let (permutation_commit, permutation_data) = permutation.unzip();
+    let commitments = Commitments {
+        trace: trace_commit,
+        permutation: permutation_commit,
+        quotient_chunks: quotient_commit,
+    };
+
+    let zeta: SC::Challenge = challenger.sample();
+
+    let trace_points = izip!(&airs, &trace_domains)
+        .map(|(air, domain)| window_points::<SC>(*domain, zeta, air.window_size()))
+        .collect_vec();
+
+    let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
+        let quotient_points = quotient_chunk_counts
+            .iter()
+            .flat_map(|&count| (0..count).map(|_| vec![zeta]))
+            .collect_vec();
+        let mut rounds = vec![
+            (&trace_data, trace_points.clone()),
+            (&quotient_data, quotient_points),
+        ];
+        if let Some(permutation_data) = &permutation_data {
+            let permutation_points = izip!(&trace_points, &permutation_indices)
+                .filter(|(_, index)| index.is_some())
+                .map(|(points, _)| points.clone())
+                .collect_vec();
+            rounds.push((permutation_data, permutation_points));
+        }
+        pcs.open(rounds, challenger)
+    });
+
+    let mut quotient_openings = opened_values[1].iter();
+    let opened_values = izip!(
+        &airs,
+        &opened_values[0],
+        permutation_indices,
+        quotient_chunk_counts
+    )
+    .map(|(air, trace_window, permutation_index, count)| {
+        let window_size = air.window_size();
+        OpenedValues {
+            preprocessed_window: vec![vec![]; window_size],
+            trace_window: trace_window.clone(),
+            permutation_window: permutation_index
+                .map_or(vec![vec![]; window_size], |j| opened_values[2][j].clone()),
+            quotient_chunks: quotient_openings
+                .by_ref()
+                .take(count)
+                .map(|v| v[0].clone())
+                .collect(),
+        }
+    })
+    .collect();
+
+    BatchProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+        cumulative_sums,
+    }
+}
@@ -0,0 +1,249 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{Itertools, izip};
+use p3_air::{Air, BaseAir, InteractionAir, get_interactions};
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{Field, PrimeCharacteristicRing};
+use tracing::instrument;
+
+use crate::fingerprint::{observe_air, observe_config};
+use crate::symbolic_builder::SymbolicAirBuilder;
+use crate::verifier::{
+    has_valid_periodic_columns, has_valid_shape, main_trace_width, verify_constraints,
+    window_points, zip_window,
+};
+use crate::{
+    BatchProof, NUM_LOOKUP_CHALLENGES, PcsError, StarkGenericConfig, Val, VerificationError,
+    VerifierConstraintFolder, eval_logup_constraints, get_air_fingerprint_with_interactions,
+    get_log_quotient_degree_with_interactions, is_zk, permutation_width,
+};
+
+/// Verify a [`BatchProof`] produced by [`prove_batch`](crate::prove_batch).
+///
+/// `airs` and `public_values` must be given in the same order as the instances passed to the
+/// prover. Besides checking each AIR's constraints, this checks that the LogUp cumulative sums of
+/// all AIRs add up to zero, i.e. that every bus is balanced.
+#[instrument(skip_all)]
+pub fn verify_batch<SC, A>(
+    config: &SC,
+    airs: &[&A],
+    challenger: &mut SC::Challenger,
+    proof: &BatchProof<SC>,
+    public_values: &[Vec<Val<SC>>],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: InteractionAir<Val<SC>>
+        + Air<SymbolicAirBuilder<Val<SC>>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let BatchProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+        cumulative_sums,
+    } = proof;
+
+    let num_airs = airs.len();
+    if num_airs == 0
+        || public_values.len() != num_airs
+        || opened_values.len() != num_airs
+        || degree_bits.len() != num_airs
+        || cumulative_sums.len() != num_airs
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    if airs.iter().any(|air| air.permutation_width() > 0) {
+        // AIR-defined permutation traces are not supported in batch mode.
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    let interactions = airs.iter().map(|air| get_interactions(*air)).collect_vec();
+    let has_interactions = interactions.iter().any(|i| !i.is_empty());
+    // AIRs without interactions contribute nothing to any bus.
+    let valid_cumulative_sums = izip!(&interactions, cumulative_sums)
+        .all(|(interactions, sum)| !interactions.is_empty() || sum.is_zero());
+    // The running sum of an AIR with interactions relates each row to the next.
+    let valid_windows = izip!(airs, &interactions).all(|(air, interactions)| {
+        interactions.is_empty() || <A as BaseAir<Val<SC>>>::window_size(*air) >= 2
+    });
+    if has_interactions != commitments.permutation.is_some()
+        || !valid_cumulative_sums
+        || !valid_windows
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    let pcs = config.pcs();
+
+    let trace_domains = degree_bits
+        .iter()
+        .map(|&bits| pcs.natural_domain_for_degree(1 << bits))
+        .collect_vec();
+    let is_zk = is_zk::<SC>() as usize;
+    let quotient_chunks_domains = izip!(
+        airs,
+        &interactions,
+        public_values,
+        &trace_domains,
+        degree_bits
+    )
+    .map(|(air, interactions, pis, trace_domain, &bits)| {
+        let log_quotient_degree =
+            get_log_quotient_degree_with_interactions(*air, interactions, pis.len(), is_zk) + is_zk;
+        trace_domain
+            .create_disjoint_domain(1 << (bits + log_quotient_degree))
+            .split_domains(1 << log_quotient_degree)
+    })
+    .collect_vec();
+
+    let periodic_columns = airs
+        .iter()
+        .map(|air| <A as BaseAir<Val<SC>>>::periodic_columns(*air))
+        .collect_vec();
+    let valid_shape = izip!(
+        airs,
+        &interactions,
+        opened_values,
+        &quotient_chunks_domains,
+        &periodic_columns,
+        degree_bits
+    )
+    .all(
+        |(air, interactions, opened_values, domains, periodic_columns, &bits)| {
+            has_valid_shape::<Val<SC>, _>(
+                opened_values,
+                0,
+                main_trace_width::<SC, _>(*air),
+                permutation_width(interactions),
+                <A as BaseAir<Val<SC>>>::window_size(*air),
+                domains.len(),
+            ) && has_valid_periodic_columns(periodic_columns, 1 << bits)
+        },
+    );
+    if !valid_shape {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    // Observe the instance.
+    challenger.observe(Val::<SC>::from_usize(num_airs));
+    for &bits in degree_bits {
+        challenger.observe(Val::<SC>::from_usize(bits));
+    }
+    observe_config(config, challenger);
+    for (air, interactions, pis, periodic_columns) in
+        izip!(airs, &interactions, public_values, &periodic_columns)
+    {
+        observe_air::<SC>(
+            challenger,
+            main_trace_width::<SC, _>(*air),
+            0,
+            permutation_width(interactions),
+            air.window_size(),
+            periodic_columns,
+            &get_air_fingerprint_with_interactions(*air, interactions, pis.len()),
+        );
+    }
+
+    challenger.observe(commitments.trace.clone());
+    for pis in public_values {
+        challenger.observe_slice(pis);
+    }
+
+    let mut permutation_challenges = vec![];
+    if let Some(permutation_commit) = &commitments.permutation {
+        permutation_challenges = (0..NUM_LOOKUP_CHALLENGES)
+            .map(|_| challenger.sample_algebra_element())
+            .collect_vec();
+        challenger.observe(permutation_commit.clone());
+        for &sum in cumulative_sums {
+            challenger.observe_algebra_element(sum);
+        }
+    }
+
+    let alpha: SC::Challenge = challenger.sample_algebra_element();
+    challenger.observe(commitments.quotient_chunks.clone());
+
+    let zeta: SC::Challenge = challenger.sample();
+
+    let points = izip!(airs, &trace_domains)
+        .map(|(air, domain)| window_points::<SC>(*domain, zeta, air.window_size()))
+        .collect_vec();
+    let trace_round = izip!(&trace_domains, &points, opened_values)
+        .map(|(domain, points, values)| (*domain, zip_window(points, &values.trace_window)))
+        .collect_vec();
+    let quotient_round = izip!(&quotient_chunks_domains, opened_values)
+        .flat_map(|(domains, values)| {
+            izip!(domains, &values.quotient_chunks)
+                .map(|(domain, chunk)| (*domain, vec![(zeta, chunk.clone())]))
+        })
+        .collect_vec();
+
+    let mut rounds = vec![
+        (commitments.trace.clone(), trace_round),
+        (commitments.quotient_chunks.clone(), quotient_round),
+    ];
+    if let Some(permutation_commit) = &commitments.permutation {
+        let permutation_round = izip!(&trace_domains, &points, &interactions, opened_values)
+            .filter(|(_, _, interactions, _)| !interactions.is_empty())
+            .map(|(domain, points, _, values)| {
+                (*domain, zip_window(points, &values.permutation_window))
+            })
+            .collect_vec();
+        rounds.push((permutation_commit.clone(), permutation_round));
+    }
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    for (
+        air,
+        interactions,
+        opened_values,
+        trace_domain,
+        domains,
+        pis,
+        &cumulative_sum,
+        periodic_columns,
+    ) in izip!(
+        airs,
+        &interactions,
+        opened_values,
+        &trace_domains,
+        &quotient_chunks_domains,
+        public_values,
+        cumulative_sums,
+        &periodic_columns
+    ) {
+        verify_constraints::<SC, _>(
+            |folder: &mut VerifierConstraintFolder<'_, SC>| {
+                air.eval(folder);
+                eval_logup_constraints(folder, interactions, cumulative_sum);
+            },
+            opened_values,
+            air.ext_width(),
+            *trace_domain,
+            domains,
+            zeta,
+            alpha,
+            &permutation_challenges,
+            pis,
+            periodic_columns,
+        )?;
+    }
+
+    if !cumulative_sums
+        .iter()
+        .copied()
+        .sum::<SC::Challenge>()
+        .is_zero()
+    {
+        return Err(VerificationError::UnbalancedBuses);
+    }
+
+    Ok(())
+}
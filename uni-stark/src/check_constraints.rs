@@ -1,63 +1,358 @@
+use alloc::format;
+use alloc::string::String;
 use alloc::vec::Vec;
+use core::fmt::{Display, Formatter};
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues};
-use p3_field::Field;
+use itertools::Itertools;
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, ExtensionMainBuilder,
+    PairBuilder, PeriodicAirBuilder, PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field};
 use p3_matrix::Matrix;
 use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
-use p3_matrix::stack::VerticalPair;
 use tracing::instrument;
 
+use crate::folder::transition_window_selector;
+use crate::{Entry, SymbolicExpression, SymbolicVariable};
+
+/// A constraint which did not evaluate to zero on some row.
+#[derive(Clone, Debug)]
+pub struct ConstraintFailure<F, EF> {
+    /// The index of the row the constraint was evaluated on.
+    pub row: usize,
+    /// The index of the constraint, in the order the AIR asserts its constraints.
+    pub constraint_index: usize,
+    /// The value the constraint evaluated to.
+    pub value: EF,
+    /// The failing constraint, rendered symbolically, if symbolic constraints were supplied.
+    pub expression: Option<String>,
+    /// The trace cells the failing constraint refers to, with their values on this row. Empty
+    /// unless symbolic constraints were supplied.
+    pub columns: Vec<(SymbolicVariable<F>, EF)>,
+}
+
+impl<F, EF: Display> Display for ConstraintFailure<F, EF> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        write!(
+            f,
+            "constraint {} had nonzero value {} on row {}",
+            self.constraint_index, self.value, self.row
+        )?;
+        if let Some(expression) = &self.expression {
+            write!(f, ": {expression}")?;
+        }
+        for (variable, value) in &self.columns {
+            write!(f, "\n    {variable} = {value}")?;
+        }
+        Ok(())
+    }
+}
+
+/// The result of [`check_constraints`]: every constraint which failed, on every row.
+#[derive(Clone, Debug)]
+pub struct ConstraintReport<F, EF> {
+    pub failures: Vec<ConstraintFailure<F, EF>>,
+}
+
+impl<F, EF> ConstraintReport<F, EF> {
+    /// Whether every constraint was satisfied on every row.
+    pub fn is_ok(&self) -> bool {
+        self.failures.is_empty()
+    }
+}
+
+impl<F, EF: Display> Display for ConstraintReport<F, EF> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        if self.failures.is_empty() {
+            return write!(f, "all constraints satisfied");
+        }
+        write!(f, "{} failing constraints", self.failures.len())?;
+        for failure in &self.failures {
+            write!(f, "\n{failure}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Evaluate the constraints of `air` on every row of the given traces, and report each constraint
+/// which does not evaluate to zero.
+///
+/// Unlike the checks `prove` runs in debug builds, this doesn't stop at the first failure and is
+/// available in release builds. If `symbolic_constraints` is given, e.g. by
+/// [`get_symbolic_constraints`](crate::get_symbolic_constraints), each failure also carries a
+/// rendering of the failing constraint and the values of the trace cells it refers to.
+///
+/// # Panics
+/// Panics if the preprocessed or permutation trace doesn't have the same height as `main`.
 #[instrument(name = "check constraints", skip_all)]
-pub(crate) fn check_constraints<F, A>(air: &A, main: &RowMajorMatrix<F>, public_values: &Vec<F>)
+pub fn check_constraints<F, EF, A>(
+    air: &A,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    main: &RowMajorMatrix<F>,
+    permutation: Option<&RowMajorMatrix<EF>>,
+    permutation_challenges: &[EF],
+    public_values: &[F],
+    symbolic_constraints: Option<&[SymbolicExpression<F>]>,
+) -> ConstraintReport<F, EF>
 where
     F: Field,
-    A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
 {
     let height = main.height();
+    if let Some(preprocessed) = preprocessed {
+        assert_eq!(
+            preprocessed.height(),
+            height,
+            "preprocessed trace height must match the main trace height"
+        );
+    }
+    if let Some(permutation) = permutation {
+        assert_eq!(
+            permutation.height(),
+            height,
+            "permutation trace height must match the main trace height"
+        );
+    }
 
+    let window_size = air.window_size();
+    let ext_width = air.ext_width();
+    let periodic_columns = air.periodic_columns();
+    let mut failures = Vec::new();
     (0..height).for_each(|i| {
-        let i_next = (i + 1) % height;
-
-        let local = main.row_slice(i);
-        let next = main.row_slice(i_next);
-        let main = VerticalPair::new(
-            RowMajorMatrixView::new_row(&*local),
-            RowMajorMatrixView::new_row(&*next),
-        );
+        let (main_window, main_ext_window) =
+            split_ext_columns::<F, EF>(window_rows(main, i, window_size), ext_width);
+        let preprocessed_window = preprocessed.map(|p| window_rows(p, i, window_size));
+        let permutation_window = permutation.map(|p| window_rows(p, i, window_size));
+        let is_transition_windows = (2..=window_size)
+            .map(|size| F::from_bool(i + size <= height))
+            .collect();
+        let periodic_values = periodic_columns
+            .iter()
+            .map(|column| column[i % column.len()])
+            .collect();
 
         let mut builder = DebugConstraintBuilder {
-            row_index: i,
-            main,
+            preprocessed: preprocessed_window
+                .as_ref()
+                .map_or_else(|| RowMajorMatrixView::new(&[], 0), |p| p.as_view()),
+            main: main_window.as_view(),
+            main_ext: main_ext_window.as_view(),
+            permutation: permutation_window
+                .as_ref()
+                .map_or_else(|| RowMajorMatrixView::new(&[], 0), |p| p.as_view()),
+            permutation_challenges,
             public_values,
+            periodic_values,
             is_first_row: F::from_bool(i == 0),
             is_last_row: F::from_bool(i == height - 1),
-            is_transition: F::from_bool(i != height - 1),
+            is_transition_windows,
+            num_constraints: 0,
+            base_failures: Vec::new(),
+            ext_failures: Vec::new(),
         };
 
         air.eval(&mut builder);
+
+        let base_failures = core::mem::take(&mut builder.base_failures)
+            .into_iter()
+            .map(|(index, value)| (index, EF::from(value)));
+        let ext_failures = core::mem::take(&mut builder.ext_failures);
+        let row_failures = base_failures
+            .merge_by(ext_failures, |(i, _), (j, _)| i < j)
+            .collect::<Vec<_>>();
+        for (constraint_index, value) in row_failures {
+            let symbolic = symbolic_constraints.and_then(|c| c.get(constraint_index));
+            let columns = symbolic.map_or_else(Vec::new, |expr| {
+                let mut variables = Vec::new();
+                collect_trace_variables(expr, &mut variables);
+                variables
+                    .into_iter()
+                    .map(|v| (v, builder.value(v)))
+                    .collect()
+            });
+            failures.push(ConstraintFailure {
+                row: i,
+                constraint_index,
+                value,
+                expression: symbolic.map(|expr| format!("{expr}")),
+                columns,
+            });
+        }
     });
+
+    ConstraintReport { failures }
+}
+
+/// Run [`check_constraints`], panicking with the report if any constraint fails.
+///
+/// The rows from `unpadded_height` onwards are the padding added by the AIR's
+/// [`trace_padding`](p3_air::BaseAir::trace_padding); failures on windows which touch them are
+/// called out separately, as they point at the padding strategy rather than the trace generator.
+pub(crate) fn assert_constraints<F, EF, A>(
+    air: &A,
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    main: &RowMajorMatrix<F>,
+    permutation: Option<&RowMajorMatrix<EF>>,
+    permutation_challenges: &[EF],
+    public_values: &[F],
+    unpadded_height: usize,
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let report = check_constraints(
+        air,
+        preprocessed,
+        main,
+        permutation,
+        permutation_challenges,
+        public_values,
+        None,
+    );
+    if report.is_ok() {
+        return;
+    }
+    let window_size = air.window_size();
+    let padding_failures = report
+        .failures
+        .iter()
+        .filter(|failure| failure.row + window_size > unpadded_height)
+        .count();
+    if unpadded_height < main.height() && padding_failures > 0 {
+        panic!(
+            "{padding_failures} constraint failures involve the padding rows {unpadded_height}..{}: {report}",
+            main.height()
+        );
+    }
+    panic!("{report}");
+}
+
+/// The rows `i, i + 1, ..., i + window_size - 1` of `matrix`, wrapping around.
+fn window_rows<T: Clone + Send + Sync>(
+    matrix: &RowMajorMatrix<T>,
+    i: usize,
+    window_size: usize,
+) -> RowMajorMatrix<T> {
+    let values = (0..window_size)
+        .flat_map(|k| matrix.row_slice((i + k) % matrix.height()).to_vec())
+        .collect();
+    RowMajorMatrix::new(values, matrix.width())
+}
+
+/// Split off the last `ext_width` extension field columns of `matrix`, which are flattened to their
+/// coefficients.
+fn split_ext_columns<F: Field, EF: ExtensionField<F>>(
+    matrix: RowMajorMatrix<F>,
+    ext_width: usize,
+) -> (RowMajorMatrix<F>, RowMajorMatrix<EF>) {
+    if ext_width == 0 {
+        return (matrix, RowMajorMatrix::new(Vec::new(), 0));
+    }
+    let base_width = matrix.width() - ext_width * EF::DIMENSION;
+    let mut base = Vec::with_capacity(matrix.height() * base_width);
+    let mut ext = Vec::with_capacity(matrix.height() * ext_width);
+    for row in matrix.values.chunks_exact(matrix.width()) {
+        let (base_row, ext_row) = row.split_at(base_width);
+        base.extend_from_slice(base_row);
+        ext.extend(
+            ext_row
+                .chunks_exact(EF::DIMENSION)
+                .map(|coeffs| EF::from_basis_coefficients_slice(coeffs).unwrap()),
+        );
+    }
+    (
+        RowMajorMatrix::new(base, base_width),
+        RowMajorMatrix::new(ext, ext_width),
+    )
 }
 
-/// An `AirBuilder` which asserts that each constraint is zero, allowing any failed constraints to
-/// be detected early.
+/// Collect the distinct trace cells `expr` refers to, in the order they first appear.
+fn collect_trace_variables<F: Field>(
+    expr: &SymbolicExpression<F>,
+    variables: &mut Vec<SymbolicVariable<F>>,
+) {
+    match expr {
+        SymbolicExpression::Variable(v) => {
+            let is_trace_cell = matches!(
+                v.entry,
+                Entry::Preprocessed { .. }
+                    | Entry::Main { .. }
+                    | Entry::MainExt { .. }
+                    | Entry::Permutation { .. }
+            );
+            if is_trace_cell
+                && !variables
+                    .iter()
+                    .any(|u| u.entry == v.entry && u.index == v.index)
+            {
+                variables.push(*v);
+            }
+        }
+        SymbolicExpression::IsFirstRow
+        | SymbolicExpression::IsLastRow
+        | SymbolicExpression::IsTransition
+        | SymbolicExpression::IsTransitionWindow(_)
+        | SymbolicExpression::Constant(_) => {}
+        SymbolicExpression::Add { x, y, .. }
+        | SymbolicExpression::Sub { x, y, .. }
+        | SymbolicExpression::Mul { x, y, .. } => {
+            collect_trace_variables(x, variables);
+            collect_trace_variables(y, variables);
+        }
+        SymbolicExpression::Neg { x, .. } => collect_trace_variables(x, variables),
+    }
+}
+
+/// An `AirBuilder` which evaluates constraints on a single row and records each one which is not
+/// zero, allowing failed constraints to be detected before proving.
 #[derive(Debug)]
-pub struct DebugConstraintBuilder<'a, F: Field> {
-    row_index: usize,
-    main: VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>,
+pub struct DebugConstraintBuilder<'a, F: Field, EF = F> {
+    preprocessed: RowMajorMatrixView<'a, F>,
+    main: RowMajorMatrixView<'a, F>,
+    main_ext: RowMajorMatrixView<'a, EF>,
+    permutation: RowMajorMatrixView<'a, EF>,
+    permutation_challenges: &'a [EF],
     public_values: &'a [F],
+    /// The value of each periodic column on the current row.
+    periodic_values: Vec<F>,
     is_first_row: F,
     is_last_row: F,
-    is_transition: F,
+    /// The transition selectors for windows of size 2, 3, ..., up to the AIR's window size.
+    is_transition_windows: Vec<F>,
+    /// The number of constraints asserted so far.
+    num_constraints: usize,
+    /// The index and value of each nonzero base field constraint.
+    base_failures: Vec<(usize, F)>,
+    /// The index and value of each nonzero extension field constraint.
+    ext_failures: Vec<(usize, EF)>,
+}
+
+impl<F: Field, EF: ExtensionField<F>> DebugConstraintBuilder<'_, F, EF> {
+    /// The value of a trace cell in the current window.
+    fn value(&self, v: SymbolicVariable<F>) -> EF {
+        match v.entry {
+            Entry::Preprocessed { offset } => self.preprocessed.get(offset, v.index).into(),
+            Entry::Main { offset } => self.main.get(offset, v.index).into(),
+            Entry::MainExt { offset } => self.main_ext.get(offset, v.index),
+            Entry::Permutation { offset } => self.permutation.get(offset, v.index),
+            Entry::Public | Entry::Challenge | Entry::Periodic => {
+                unreachable!("only trace cells are collected")
+            }
+        }
+    }
 }
 
-impl<'a, F> AirBuilder for DebugConstraintBuilder<'a, F>
+impl<'a, F, EF> AirBuilder for DebugConstraintBuilder<'a, F, EF>
 where
     F: Field,
 {
     type F = F;
     type Expr = F;
     type Var = F;
-    type M = VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>;
+    type M = RowMajorMatrixView<'a, F>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -72,39 +367,175 @@ where
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is not between `2` and the AIR's window size.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
+        transition_window_selector(&self.is_transition_windows, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
-        assert_eq!(
-            x.into(),
-            F::ZERO,
-            "constraints had nonzero value on row {}",
-            self.row_index
-        );
-    }
-
-    fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
         let x = x.into();
-        let y = y.into();
-        assert_eq!(
-            x, y,
-            "values didn't match on row {}: {} != {}",
-            self.row_index, x, y
-        );
+        if !x.is_zero() {
+            self.base_failures.push((self.num_constraints, x));
+        }
+        self.num_constraints += 1;
     }
 }
 
-impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
+impl<F: Field, EF> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F, EF> {
     type PublicVar = Self::F;
 
     fn public_values(&self) -> &[Self::F] {
         self.public_values
     }
 }
+
+impl<F: Field, EF> PeriodicAirBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type PeriodicVar = F;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
+impl<F: Field, EF> PairBuilder for DebugConstraintBuilder<'_, F, EF> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type EF = EF;
+    type ExprEF = EF;
+    type VarEF = EF;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        let x = x.into();
+        if !x.is_zero() {
+            self.ext_failures.push((self.num_constraints, x));
+        }
+        self.num_constraints += 1;
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> ExtensionMainBuilder
+    for DebugConstraintBuilder<'a, F, EF>
+{
+    type MEF = RowMajorMatrixView<'a, EF>;
+
+    fn main_ext(&self) -> Self::MEF {
+        self.main_ext
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> PermutationAirBuilder
+    for DebugConstraintBuilder<'a, F, EF>
+{
+    type MP = RowMajorMatrixView<'a, EF>;
+    type RandomVar = EF;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use p3_air::BaseAir;
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+
+    use super::*;
+    use crate::get_symbolic_constraints;
+
+    type F = BabyBear;
+
+    /// Constrains `a` to be boolean and `b` to double every row.
+    struct DoublingAir;
+
+    impl BaseAir<F> for DoublingAir {
+        fn width(&self) -> usize {
+            2
+        }
+    }
+
+    impl<AB: AirBuilder<F = F>> Air<AB> for DoublingAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let (local, next) = (main.row_slice(0), main.row_slice(1));
+            builder.assert_bool(local[0]);
+            builder
+                .when_transition()
+                .assert_eq(next[1], local[1] * AB::Expr::TWO);
+        }
+    }
+
+    fn trace(rows: &[[u32; 2]]) -> RowMajorMatrix<F> {
+        RowMajorMatrix::new(rows.iter().flatten().map(|&x| F::from_u32(x)).collect(), 2)
+    }
+
+    #[test]
+    fn test_valid_trace() {
+        let main = trace(&[[0, 1], [1, 2], [1, 4], [0, 8]]);
+        let report = check_constraints::<_, F, _>(&DoublingAir, None, &main, None, &[], &[], None);
+        assert!(report.is_ok());
+    }
+
+    #[test]
+    fn test_reports_every_failure() {
+        let main = trace(&[[0, 1], [2, 2], [1, 5], [3, 10]]);
+        let report = check_constraints::<_, F, _>(&DoublingAir, None, &main, None, &[], &[], None);
+        let failures = report
+            .failures
+            .iter()
+            .map(|f| (f.row, f.constraint_index, f.value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            failures,
+            vec![(1, 0, -F::TWO), (1, 1, F::ONE), (3, 0, -F::from_u8(6))]
+        );
+        assert!(report.failures.iter().all(|f| f.expression.is_none()));
+    }
+
+    #[test]
+    fn test_symbolic_rendering() {
+        let main = trace(&[[0, 1], [1, 3], [1, 6], [0, 12]]);
+        let symbolic_constraints = get_symbolic_constraints(&DoublingAir, 0, 0);
+        let report = check_constraints::<_, F, _>(
+            &DoublingAir,
+            None,
+            &main,
+            None,
+            &[],
+            &[],
+            Some(&symbolic_constraints),
+        );
+        assert_eq!(report.failures.len(), 1);
+        let failure = &report.failures[0];
+        assert_eq!((failure.row, failure.constraint_index), (0, 1));
+        assert_eq!(
+            failure.expression.as_deref(),
+            Some("(is_transition * (main'[1] - (main[1] * 2)))")
+        );
+        let columns = failure
+            .columns
+            .iter()
+            .map(|(v, value)| (format!("{v}"), *value))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            columns,
+            vec![
+                ("main'[1]".into(), F::from_u8(3)),
+                ("main[1]".into(), F::ONE),
+            ]
+        );
+    }
+}
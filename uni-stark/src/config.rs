@@ -36,6 +36,11 @@ pub trait StarkGenericConfig {
     fn pcs(&self) -> &Self::Pcs;
 }
 
+/// Whether the PCS of `SC` is hiding, in which case proofs are zero-knowledge.
+pub const fn is_zk<SC: StarkGenericConfig>() -> bool {
+    <SC::Pcs as Pcs<SC::Challenge, SC::Challenger>>::ZK
+}
+
 #[derive(Debug)]
 pub struct StarkConfig<Pcs, Challenge, Challenger> {
     pcs: Pcs,
@@ -0,0 +1,672 @@
+//! Symbolic constraints compiled into a flat program, which the prover evaluates over each packed
+//! row of the quotient domain in place of the AIR's `eval`.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem;
+
+use hashbrown::HashMap;
+use p3_field::{Algebra, Field, PrimeCharacteristicRing};
+
+use crate::folder::transition_window_selector;
+use crate::{
+    Entry, PackedChallenge, PackedVal, ProverConstraintFolder, StarkGenericConfig,
+    SymbolicExpression, Val,
+};
+
+/// An instruction of a [`ConstraintProgram`]. Operands are the indices of earlier instructions.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Instruction<F> {
+    Variable {
+        entry: Entry,
+        index: usize,
+    },
+    IsFirstRow,
+    IsLastRow,
+    /// The transition selector for a window of the given size, which is 2 for
+    /// [`SymbolicExpression::IsTransition`].
+    IsTransitionWindow(usize),
+    Constant(F),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Neg(usize),
+    Mul(usize, usize),
+}
+
+/// Symbolic constraints compiled into a list of instructions, in which each distinct
+/// subexpression is computed once and subexpressions of constants are folded.
+#[derive(Clone, Debug)]
+pub struct ConstraintProgram<F> {
+    instructions: Vec<Instruction<F>>,
+    /// Whether each instruction's value lies in the extension field, i.e. depends on the
+    /// permutation trace or its challenges.
+    is_ext: Vec<bool>,
+    /// The instruction computing each constraint, in the order they were asserted.
+    constraints: Vec<usize>,
+    /// The number of base and extension field values [`ProverConstraintFolder::eval_program`]
+    /// holds at once. Values share a slot once the earlier of them is no longer needed.
+    num_base_slots: usize,
+    num_ext_slots: usize,
+    /// The variables, selectors and constants, with the slots of their values, grouped by kind so
+    /// that loading them branches predictably.
+    leaves: Vec<(Instruction<F>, usize)>,
+    /// The arithmetic instructions and constraint assertions, in order, with their operands
+    /// resolved to slots.
+    ops: Vec<Op>,
+}
+
+/// An instruction of a [`ConstraintProgram`], lowered for
+/// [`ProverConstraintFolder::eval_program`]. Arithmetic names the slot of its result, then of its
+/// operands, among the base or extension field values as given by the variant.
+#[derive(Copy, Clone, Debug)]
+enum Op {
+    /// Accumulate the constraint of the given index, with its base field value in the given slot.
+    AssertBase(usize, usize),
+    /// Accumulate the constraint of the given index, with its extension field value in the given
+    /// slot.
+    AssertExt(usize, usize),
+    AddBase(usize, usize, usize),
+    SubBase(usize, usize, usize),
+    NegBase(usize, usize),
+    MulBase(usize, usize, usize),
+    AddExt(usize, usize, usize),
+    SubExt(usize, usize, usize),
+    NegExt(usize, usize),
+    MulExt(usize, usize, usize),
+    /// An extension field value plus a base field value.
+    AddExtBase(usize, usize, usize),
+    /// An extension field value minus a base field value.
+    SubExtBase(usize, usize, usize),
+    /// A base field value minus an extension field value.
+    SubBaseExt(usize, usize, usize),
+    /// An extension field value times a base field value.
+    MulExtBase(usize, usize, usize),
+}
+
+impl<F: Field> ConstraintProgram<F> {
+    pub fn compile(constraints: &[SymbolicExpression<F>]) -> Self {
+        let mut compiler = Compiler {
+            program: Self {
+                instructions: Vec::new(),
+                is_ext: Vec::new(),
+                constraints: Vec::new(),
+                num_base_slots: 0,
+                num_ext_slots: 0,
+                leaves: Vec::new(),
+                ops: Vec::new(),
+            },
+            indices: HashMap::new(),
+            shared: BTreeMap::new(),
+        };
+        for constraint in constraints {
+            let index = compiler.compile(constraint);
+            compiler.program.constraints.push(index);
+        }
+        let mut program = compiler.program;
+        program.lower();
+        program
+    }
+
+    /// Split the instructions into the leaves and ops evaluated by
+    /// [`ProverConstraintFolder::eval_program`], assigning their values to slots.
+    ///
+    /// Ops are ordered by their depth in the expression DAG, and ops of the same depth by their
+    /// kind, so that dispatching them branches predictably. Each slot is reused once its value has
+    /// been read for the last time, so that the values live at once stay few and in cache even for
+    /// AIRs with thousands of constraints.
+    fn lower(&mut self) {
+        let n = self.instructions.len();
+        let is_leaf = |instruction| {
+            !matches!(
+                instruction,
+                Instruction::Add(..)
+                    | Instruction::Sub(..)
+                    | Instruction::Neg(_)
+                    | Instruction::Mul(..)
+            )
+        };
+
+        // Constant folding can leave instructions behind which no constraint depends on.
+        let mut live = vec![false; n];
+        for &constraint in &self.constraints {
+            live[constraint] = true;
+        }
+        for i in (0..n).rev() {
+            if live[i] {
+                for_each_operand(self.instructions[i], |x| live[x] = true);
+            }
+        }
+
+        let mut depth = vec![0; n];
+        for i in 0..n {
+            let mut d = 0;
+            for_each_operand(self.instructions[i], |x| d = d.max(depth[x] + 1));
+            depth[i] = d;
+        }
+
+        // The ops refer to their operands by instruction index until slots are assigned below.
+        let mut items = (0..n)
+            .filter(|&i| live[i] && !is_leaf(self.instructions[i]))
+            .map(|i| (depth[i], self.lower_instruction(i, |x| x, 0), i))
+            .chain(
+                self.constraints
+                    .iter()
+                    .enumerate()
+                    .map(|(k, &c)| (depth[c], assert_op(self.is_ext[c], k, c), c)),
+            )
+            .collect::<Vec<_>>();
+        items.sort_by_key(|&(depth, op, i)| (depth, op.kind(), i));
+
+        // Leaves are loaded at time 0, and the ops run at times 1, 2, ....
+        let mut last_use = vec![0; n];
+        for (t, &(_, op, _)) in items.iter().enumerate() {
+            op.for_each_operand(|x| last_use[x] = t + 1);
+        }
+
+        let mut base_slots = Slots::default();
+        let mut ext_slots = Slots::default();
+        let mut slots = vec![0; n];
+        for i in (0..n).filter(|&i| live[i] && is_leaf(self.instructions[i])) {
+            slots[i] = if self.is_ext[i] {
+                ext_slots.alloc()
+            } else {
+                base_slots.alloc()
+            };
+            self.leaves.push((self.instructions[i], slots[i]));
+        }
+        self.leaves.sort_by_key(|&(leaf, _)| leaf_kind(leaf));
+
+        for (t, &(_, op, i)) in items.iter().enumerate() {
+            // Operands read for the last time free their slots before the result is assigned one,
+            // which is safe since each op reads its operands before writing its result.
+            let mut freed = None;
+            op.for_each_operand(|x| {
+                if last_use[x] == t + 1 && freed != Some(x) {
+                    freed = Some(x);
+                    if self.is_ext[x] {
+                        ext_slots.free(slots[x]);
+                    } else {
+                        base_slots.free(slots[x]);
+                    }
+                }
+            });
+            let op = match op {
+                Op::AssertBase(k, x) => Op::AssertBase(k, slots[x]),
+                Op::AssertExt(k, x) => Op::AssertExt(k, slots[x]),
+                _ => {
+                    slots[i] = if self.is_ext[i] {
+                        ext_slots.alloc()
+                    } else {
+                        base_slots.alloc()
+                    };
+                    self.lower_instruction(i, |x| slots[x], slots[i])
+                }
+            };
+            self.ops.push(op);
+        }
+
+        self.num_base_slots = base_slots.count;
+        self.num_ext_slots = ext_slots.count;
+    }
+
+    /// Lower the arithmetic instruction `i`, with its operands' slots given by `slot` and its
+    /// result's slot `dst`.
+    fn lower_instruction(&self, i: usize, slot: impl Fn(usize) -> usize, dst: usize) -> Op {
+        let binary = |x: usize, y: usize| (slot(x), self.is_ext[x], slot(y), self.is_ext[y]);
+        match self.instructions[i] {
+            Instruction::Add(x, y) => match binary(x, y) {
+                (x, false, y, false) => Op::AddBase(dst, x, y),
+                (x, true, y, true) => Op::AddExt(dst, x, y),
+                (x, true, y, false) | (y, false, x, true) => Op::AddExtBase(dst, x, y),
+            },
+            Instruction::Sub(x, y) => match binary(x, y) {
+                (x, false, y, false) => Op::SubBase(dst, x, y),
+                (x, true, y, true) => Op::SubExt(dst, x, y),
+                (x, true, y, false) => Op::SubExtBase(dst, x, y),
+                (x, false, y, true) => Op::SubBaseExt(dst, x, y),
+            },
+            Instruction::Mul(x, y) => match binary(x, y) {
+                (x, false, y, false) => Op::MulBase(dst, x, y),
+                (x, true, y, true) => Op::MulExt(dst, x, y),
+                (x, true, y, false) | (y, false, x, true) => Op::MulExtBase(dst, x, y),
+            },
+            Instruction::Neg(x) if self.is_ext[i] => Op::NegExt(dst, slot(x)),
+            Instruction::Neg(x) => Op::NegBase(dst, slot(x)),
+            _ => unreachable!("leaves are loaded separately"),
+        }
+    }
+
+    pub fn instructions(&self) -> &[Instruction<F>] {
+        &self.instructions
+    }
+
+    /// The index of the instruction computing each constraint.
+    pub fn constraints(&self) -> &[usize] {
+        &self.constraints
+    }
+
+    /// Evaluate every instruction over `T`, returning the value of each.
+    ///
+    /// Constants and arithmetic are evaluated directly, while variables and selectors are given by
+    /// `leaf`, which is also passed the values of the preceding instructions.
+    pub fn eval_with<T: Algebra<F>>(
+        &self,
+        mut leaf: impl FnMut(Instruction<F>, &[T]) -> T,
+    ) -> Vec<T> {
+        let mut values: Vec<T> = Vec::with_capacity(self.instructions.len());
+        for &instruction in &self.instructions {
+            let value = match instruction {
+                Instruction::Constant(c) => c.into(),
+                Instruction::Add(x, y) => values[x].clone() + values[y].clone(),
+                Instruction::Sub(x, y) => values[x].clone() - values[y].clone(),
+                Instruction::Neg(x) => -values[x].clone(),
+                Instruction::Mul(x, y) => values[x].clone() * values[y].clone(),
+                _ => leaf(instruction, &values),
+            };
+            values.push(value);
+        }
+        values
+    }
+}
+
+impl Op {
+    /// The variant of the op, by which ops of the same depth are grouped.
+    const fn kind(self) -> usize {
+        match self {
+            Self::AddBase(..) => 0,
+            Self::SubBase(..) => 1,
+            Self::NegBase(..) => 2,
+            Self::MulBase(..) => 3,
+            Self::AddExt(..) => 4,
+            Self::SubExt(..) => 5,
+            Self::NegExt(..) => 6,
+            Self::MulExt(..) => 7,
+            Self::AddExtBase(..) => 8,
+            Self::SubExtBase(..) => 9,
+            Self::SubBaseExt(..) => 10,
+            Self::MulExtBase(..) => 11,
+            Self::AssertBase(..) => 12,
+            Self::AssertExt(..) => 13,
+        }
+    }
+
+    /// Call `f` on each operand of the op, i.e. each slot it reads.
+    fn for_each_operand(self, mut f: impl FnMut(usize)) {
+        match self {
+            Self::AssertBase(_, x)
+            | Self::AssertExt(_, x)
+            | Self::NegBase(_, x)
+            | Self::NegExt(_, x) => f(x),
+            Self::AddBase(_, x, y)
+            | Self::SubBase(_, x, y)
+            | Self::MulBase(_, x, y)
+            | Self::AddExt(_, x, y)
+            | Self::SubExt(_, x, y)
+            | Self::MulExt(_, x, y)
+            | Self::AddExtBase(_, x, y)
+            | Self::SubExtBase(_, x, y)
+            | Self::SubBaseExt(_, x, y)
+            | Self::MulExtBase(_, x, y) => {
+                f(x);
+                f(y);
+            }
+        }
+    }
+}
+
+/// Call `f` on each operand of `instruction`.
+fn for_each_operand<F>(instruction: Instruction<F>, mut f: impl FnMut(usize)) {
+    match instruction {
+        Instruction::Add(x, y) | Instruction::Sub(x, y) | Instruction::Mul(x, y) => {
+            f(x);
+            f(y);
+        }
+        Instruction::Neg(x) => f(x),
+        _ => {}
+    }
+}
+
+const fn assert_op(is_ext: bool, constraint: usize, slot: usize) -> Op {
+    if is_ext {
+        Op::AssertExt(constraint, slot)
+    } else {
+        Op::AssertBase(constraint, slot)
+    }
+}
+
+/// The slots of values of one field, with those no longer in use.
+#[derive(Default)]
+struct Slots {
+    count: usize,
+    free: Vec<usize>,
+}
+
+impl Slots {
+    fn alloc(&mut self) -> usize {
+        self.free.pop().unwrap_or_else(|| {
+            self.count += 1;
+            self.count - 1
+        })
+    }
+
+    fn free(&mut self, slot: usize) {
+        self.free.push(slot);
+    }
+}
+
+/// The kind of a leaf instruction, by which leaves are grouped.
+fn leaf_kind<F>(leaf: Instruction<F>) -> usize {
+    match leaf {
+        Instruction::Variable { entry, .. } => match entry {
+            Entry::Main { .. } => 0,
+            Entry::MainExt { .. } => 1,
+            Entry::Preprocessed { .. } => 2,
+            Entry::Permutation { .. } => 3,
+            Entry::Periodic => 4,
+            Entry::Public => 5,
+            Entry::Challenge => 6,
+        },
+        Instruction::IsFirstRow | Instruction::IsLastRow | Instruction::IsTransitionWindow(_) => 7,
+        _ => 8,
+    }
+}
+
+struct Compiler<F> {
+    program: ConstraintProgram<F>,
+    /// The index of each instruction emitted so far.
+    indices: HashMap<Instruction<F>, usize>,
+    /// The index of each shared subexpression compiled so far, keyed by its address.
+    shared: BTreeMap<*const SymbolicExpression<F>, usize>,
+}
+
+impl<F: Field> Compiler<F> {
+    fn compile_shared(&mut self, expr: &Rc<SymbolicExpression<F>>) -> usize {
+        if let Some(&index) = self.shared.get(&Rc::as_ptr(expr)) {
+            return index;
+        }
+        let index = self.compile(expr);
+        self.shared.insert(Rc::as_ptr(expr), index);
+        index
+    }
+
+    fn compile(&mut self, expr: &SymbolicExpression<F>) -> usize {
+        match expr {
+            SymbolicExpression::Variable(v) => self.push(Instruction::Variable {
+                entry: v.entry,
+                index: v.index,
+            }),
+            SymbolicExpression::IsFirstRow => self.push(Instruction::IsFirstRow),
+            SymbolicExpression::IsLastRow => self.push(Instruction::IsLastRow),
+            SymbolicExpression::IsTransition => self.push(Instruction::IsTransitionWindow(2)),
+            SymbolicExpression::IsTransitionWindow(size) => {
+                self.push(Instruction::IsTransitionWindow(*size))
+            }
+            SymbolicExpression::Constant(c) => self.push(Instruction::Constant(*c)),
+            SymbolicExpression::Add { x, y, .. } => {
+                let (x, y) = (self.compile_shared(x), self.compile_shared(y));
+                self.add(x, y)
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                let (x, y) = (self.compile_shared(x), self.compile_shared(y));
+                self.sub(x, y)
+            }
+            SymbolicExpression::Neg { x, .. } => {
+                let x = self.compile_shared(x);
+                self.neg(x)
+            }
+            SymbolicExpression::Mul { x, y, .. } => {
+                let (x, y) = (self.compile_shared(x), self.compile_shared(y));
+                self.mul(x, y)
+            }
+        }
+    }
+
+    fn constant(&self, index: usize) -> Option<F> {
+        match self.program.instructions[index] {
+            Instruction::Constant(c) => Some(c),
+            _ => None,
+        }
+    }
+
+    fn add(&mut self, x: usize, y: usize) -> usize {
+        match (self.constant(x), self.constant(y)) {
+            (Some(a), Some(b)) => self.push(Instruction::Constant(a + b)),
+            (Some(a), _) if a.is_zero() => y,
+            (_, Some(b)) if b.is_zero() => x,
+            // Order the operands of commutative operations, so that e.g. `a + b` and `b + a` are
+            // computed once.
+            _ => self.push(Instruction::Add(x.min(y), x.max(y))),
+        }
+    }
+
+    fn sub(&mut self, x: usize, y: usize) -> usize {
+        match (self.constant(x), self.constant(y)) {
+            (Some(a), Some(b)) => self.push(Instruction::Constant(a - b)),
+            (Some(a), _) if a.is_zero() => self.neg(y),
+            (_, Some(b)) if b.is_zero() => x,
+            _ if x == y => self.push(Instruction::Constant(F::ZERO)),
+            _ => self.push(Instruction::Sub(x, y)),
+        }
+    }
+
+    fn neg(&mut self, x: usize) -> usize {
+        match self.program.instructions[x] {
+            Instruction::Constant(c) => self.push(Instruction::Constant(-c)),
+            Instruction::Neg(y) => y,
+            _ => self.push(Instruction::Neg(x)),
+        }
+    }
+
+    fn mul(&mut self, x: usize, y: usize) -> usize {
+        match (self.constant(x), self.constant(y)) {
+            (Some(a), Some(b)) => self.push(Instruction::Constant(a * b)),
+            (Some(a), _) | (_, Some(a)) if a.is_zero() => self.push(Instruction::Constant(F::ZERO)),
+            (Some(a), _) if a.is_one() => y,
+            (_, Some(b)) if b.is_one() => x,
+            _ => self.push(Instruction::Mul(x.min(y), x.max(y))),
+        }
+    }
+
+    /// Emit `instruction`, unless an identical instruction was already emitted.
+    fn push(&mut self, instruction: Instruction<F>) -> usize {
+        if let Some(&index) = self.indices.get(&instruction) {
+            return index;
+        }
+
+        let program = &mut self.program;
+        let is_ext = match instruction {
+            Instruction::Variable { entry, .. } => {
+                matches!(
+                    entry,
+                    Entry::MainExt { .. } | Entry::Permutation { .. } | Entry::Challenge
+                )
+            }
+            Instruction::IsFirstRow
+            | Instruction::IsLastRow
+            | Instruction::IsTransitionWindow(_)
+            | Instruction::Constant(_) => false,
+            Instruction::Add(x, y) | Instruction::Sub(x, y) | Instruction::Mul(x, y) => {
+                program.is_ext[x] || program.is_ext[y]
+            }
+            Instruction::Neg(x) => program.is_ext[x],
+        };
+        let index = program.instructions.len();
+        program.instructions.push(instruction);
+        program.is_ext.push(is_ext);
+        self.indices.insert(instruction, index);
+        index
+    }
+}
+
+impl<SC: StarkGenericConfig> ProverConstraintFolder<'_, SC> {
+    /// Accumulate the constraints of `program`, as evaluating the AIR it was compiled from would.
+    pub fn eval_program(&mut self, program: &ConstraintProgram<Val<SC>>) {
+        let mut base = mem::take(self.program_base);
+        let mut ext = mem::take(self.program_ext);
+        // Every slot is written before it is read, so stale values from an earlier row are fine.
+        base.resize(program.num_base_slots, PackedVal::<SC>::ZERO);
+        ext.resize(program.num_ext_slots, PackedChallenge::<SC>::ZERO);
+        let alpha_powers = &self.alpha_powers[self.constraint_index..];
+        let mut accumulator = self.accumulator;
+
+        for &(leaf, slot) in &program.leaves {
+            match leaf {
+                Instruction::Variable {
+                    entry: Entry::Main { offset },
+                    index,
+                } => base[slot] = self.main.values[offset * self.main.width + index],
+                Instruction::Variable {
+                    entry: Entry::MainExt { offset },
+                    index,
+                } => ext[slot] = self.main_ext.values[offset * self.main_ext.width + index],
+                Instruction::Variable {
+                    entry: Entry::Preprocessed { offset },
+                    index,
+                } => {
+                    base[slot] = self.preprocessed.values[offset * self.preprocessed.width + index]
+                }
+                Instruction::Variable {
+                    entry: Entry::Permutation { offset },
+                    index,
+                } => ext[slot] = self.permutation.values[offset * self.permutation.width + index],
+                Instruction::Variable {
+                    entry: Entry::Periodic,
+                    index,
+                } => base[slot] = self.periodic_values[index],
+                Instruction::Variable {
+                    entry: Entry::Public,
+                    index,
+                } => base[slot] = self.public_values[index].into(),
+                Instruction::Variable {
+                    entry: Entry::Challenge,
+                    index,
+                } => ext[slot] = self.permutation_challenges[index],
+                Instruction::IsFirstRow => base[slot] = self.is_first_row,
+                Instruction::IsLastRow => base[slot] = self.is_last_row,
+                Instruction::IsTransitionWindow(size) => {
+                    base[slot] = transition_window_selector(self.is_transition_windows, size);
+                }
+                Instruction::Constant(c) => base[slot] = c.into(),
+                Instruction::Add(..)
+                | Instruction::Sub(..)
+                | Instruction::Neg(_)
+                | Instruction::Mul(..) => unreachable!("arithmetic is lowered to ops"),
+            }
+        }
+
+        for &op in &program.ops {
+            match op {
+                Op::AssertBase(k, x) => {
+                    accumulator += PackedChallenge::<SC>::from(alpha_powers[k]) * base[x];
+                }
+                Op::AssertExt(k, x) => accumulator += ext[x] * alpha_powers[k],
+                Op::AddBase(dst, x, y) => base[dst] = base[x] + base[y],
+                Op::SubBase(dst, x, y) => base[dst] = base[x] - base[y],
+                Op::NegBase(dst, x) => base[dst] = -base[x],
+                Op::MulBase(dst, x, y) => base[dst] = base[x] * base[y],
+                Op::AddExt(dst, x, y) => ext[dst] = ext[x] + ext[y],
+                Op::SubExt(dst, x, y) => ext[dst] = ext[x] - ext[y],
+                Op::NegExt(dst, x) => ext[dst] = -ext[x],
+                Op::MulExt(dst, x, y) => ext[dst] = ext[x] * ext[y],
+                Op::AddExtBase(dst, x, y) => ext[dst] = ext[x] + base[y],
+                Op::SubExtBase(dst, x, y) => ext[dst] = ext[x] - base[y],
+                Op::SubBaseExt(dst, x, y) => {
+                    ext[dst] = PackedChallenge::<SC>::from(base[x]) - ext[y];
+                }
+                Op::MulExtBase(dst, x, y) => ext[dst] = ext[x] * base[y],
+            }
+        }
+
+        self.accumulator = accumulator;
+        self.constraint_index += program.constraints.len();
+
+        *self.program_base = base;
+        *self.program_ext = ext;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+
+    use super::*;
+    use crate::SymbolicVariable;
+
+    type F = BabyBear;
+
+    fn main_var(index: usize) -> SymbolicExpression<F> {
+        SymbolicVariable::new(Entry::Main { offset: 0 }, index).into()
+    }
+
+    #[test]
+    fn test_common_subexpressions() {
+        let (x, y) = (main_var(0), main_var(1));
+        // `x * y` and `y * x` are built separately, but computed once.
+        let constraints = [
+            x.clone() * y.clone() - x.clone(),
+            y.clone() * x.clone() - y.clone(),
+        ];
+        let program = ConstraintProgram::compile(&constraints);
+        assert_eq!(
+            program.instructions(),
+            &[
+                Instruction::Variable {
+                    entry: Entry::Main { offset: 0 },
+                    index: 0
+                },
+                Instruction::Variable {
+                    entry: Entry::Main { offset: 0 },
+                    index: 1
+                },
+                Instruction::Mul(0, 1),
+                Instruction::Sub(2, 0),
+                Instruction::Sub(2, 1),
+            ]
+        );
+        assert_eq!(program.constraints(), &[3, 4]);
+    }
+
+    #[test]
+    fn test_constant_folding() {
+        let x = main_var(0);
+        let constraints = [
+            (SymbolicExpression::Constant(F::TWO) + SymbolicExpression::Constant(F::ONE))
+                * x.clone(),
+            x.clone() * SymbolicExpression::Constant(F::ONE)
+                + SymbolicExpression::Constant(F::ZERO),
+            x.clone() - x,
+        ];
+        let program = ConstraintProgram::compile(&constraints);
+        assert_eq!(
+            program.instructions(),
+            &[
+                Instruction::Constant(F::from_u8(3)),
+                Instruction::Variable {
+                    entry: Entry::Main { offset: 0 },
+                    index: 0
+                },
+                Instruction::Mul(0, 1),
+                Instruction::Constant(F::ONE),
+                Instruction::Constant(F::ZERO),
+            ]
+        );
+        assert_eq!(program.constraints(), &[2, 1, 4]);
+    }
+
+    #[test]
+    fn test_slot_reuse() {
+        // Each variable's slot is reused by the last product which reads it, so the products need
+        // no slots beyond those of the variables.
+        let constraints = (0..10)
+            .map(|i| main_var(i) * main_var(i + 1))
+            .collect::<Vec<_>>();
+        let program = ConstraintProgram::compile(&constraints);
+        assert_eq!(program.leaves.len(), 11);
+        assert_eq!(program.ops.len(), 20);
+        assert_eq!(program.num_base_slots, 11);
+        assert_eq!(program.num_ext_slots, 0);
+    }
+}
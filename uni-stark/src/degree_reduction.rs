@@ -0,0 +1,343 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_air::{
+    Air, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues, InteractionAir,
+    InteractionBuilder, PairBuilder, PeriodicAirBuilder,
+};
+use p3_field::Field;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_maybe_rayon::prelude::*;
+use tracing::instrument;
+
+use crate::{
+    ConstraintProgram, Entry, Instruction, SymbolicAirBuilder, SymbolicExpression,
+    SymbolicVariable, get_symbolic_constraints,
+};
+
+/// Constraints rewritten to a lower degree, with the auxiliary columns they introduced.
+#[derive(Clone, Debug)]
+pub struct DegreeReduction<F> {
+    /// The rewritten constraints, followed by one constraint per auxiliary column, which binds the
+    /// column to its definition.
+    pub constraints: Vec<SymbolicExpression<F>>,
+    /// The definition of each auxiliary column. Auxiliary column `i` is main column `width + i`,
+    /// and its definition may refer to the original columns and to earlier auxiliary columns in
+    /// the same row.
+    pub aux_columns: Vec<SymbolicExpression<F>>,
+}
+
+/// Rewrite `constraints`, over a main trace of the given `width`, so that each has degree at most
+/// `max_degree`.
+///
+/// Subexpressions whose degree is too large are replaced by auxiliary main columns, appended
+/// after the original ones, which are constrained to equal the subexpressions. Constraints which
+/// are already within `max_degree` are left unchanged. The only subexpressions which cannot be
+/// reduced are transition selectors for windows larger than `max_degree + 1`.
+#[instrument(name = "reduce constraint degree", skip_all)]
+pub fn reduce_constraint_degree<F: Field>(
+    constraints: &[SymbolicExpression<F>],
+    width: usize,
+    max_degree: usize,
+) -> DegreeReduction<F> {
+    assert!(
+        max_degree >= 2,
+        "constraints can't be reduced below degree 2"
+    );
+
+    // Keep the roots alive until we're done, since subexpressions are memoized by address.
+    let roots = constraints.iter().cloned().map(Rc::new).collect_vec();
+    let mut reducer = Reducer {
+        width,
+        max_degree,
+        reduced: BTreeMap::new(),
+        columns: BTreeMap::new(),
+        aux_columns: vec![],
+    };
+    let mut constraints = roots
+        .iter()
+        .map(|root| reducer.reduce(root, max_degree))
+        .collect_vec();
+    constraints.extend(
+        reducer
+            .aux_columns
+            .iter()
+            .enumerate()
+            .map(|(i, definition)| aux_variable::<F>(width + i) - definition.clone()),
+    );
+
+    DegreeReduction {
+        constraints,
+        aux_columns: reducer.aux_columns,
+    }
+}
+
+fn aux_variable<F: Field>(index: usize) -> SymbolicExpression<F> {
+    SymbolicVariable::new(Entry::Main { offset: 0 }, index).into()
+}
+
+struct Reducer<F> {
+    width: usize,
+    max_degree: usize,
+    /// Each subexpression reduced so far, keyed by its address and the degree it was reduced to.
+    reduced: BTreeMap<(*const SymbolicExpression<F>, usize), SymbolicExpression<F>>,
+    /// The auxiliary column of each subexpression replaced so far, keyed by its address.
+    columns: BTreeMap<*const SymbolicExpression<F>, SymbolicExpression<F>>,
+    aux_columns: Vec<SymbolicExpression<F>>,
+}
+
+impl<F: Field> Reducer<F> {
+    /// Rewrite `expr` to an expression of degree at most `bound`.
+    fn reduce(&mut self, expr: &Rc<SymbolicExpression<F>>, bound: usize) -> SymbolicExpression<F> {
+        if expr.degree_multiple() <= bound {
+            return (**expr).clone();
+        }
+        let key = (Rc::as_ptr(expr), bound);
+        if let Some(reduced) = self.reduced.get(&key) {
+            return reduced.clone();
+        }
+
+        let reduced = match &**expr {
+            _ if bound == 1 => self.column(expr),
+            SymbolicExpression::Add { x, y, .. } => self.reduce(x, bound) + self.reduce(y, bound),
+            SymbolicExpression::Sub { x, y, .. } => self.reduce(x, bound) - self.reduce(y, bound),
+            SymbolicExpression::Neg { x, .. } => -self.reduce(x, bound),
+            SymbolicExpression::Mul { x, y, .. } => {
+                // If either operand fits, give the other one the rest of the degree. Otherwise we
+                // split the degree evenly.
+                let (x_degree, y_degree) = (x.degree_multiple(), y.degree_multiple());
+                let (x_bound, y_bound) = if y_degree < bound {
+                    (bound - y_degree, y_degree)
+                } else if x_degree < bound {
+                    (x_degree, bound - x_degree)
+                } else {
+                    (bound.div_ceil(2), bound / 2)
+                };
+                self.reduce(x, x_bound) * self.reduce(y, y_bound)
+            }
+            // A transition selector for a large window.
+            _ => self.column(expr),
+        };
+        self.reduced.insert(key, reduced.clone());
+        reduced
+    }
+
+    /// An auxiliary column constrained to equal `expr`.
+    fn column(&mut self, expr: &Rc<SymbolicExpression<F>>) -> SymbolicExpression<F> {
+        if let Some(column) = self.columns.get(&Rc::as_ptr(expr)) {
+            return column.clone();
+        }
+        let definition = self.reduce(expr, self.max_degree);
+        let column = aux_variable(self.width + self.aux_columns.len());
+        self.aux_columns.push(definition);
+        self.columns.insert(Rc::as_ptr(expr), column.clone());
+        column
+    }
+}
+
+/// An AIR whose constraints are those of another AIR, rewritten by [`reduce_constraint_degree`]
+/// to a lower degree.
+///
+/// This trades trace width for a smaller quotient domain. Traces of the original AIR must be
+/// converted with [`extend_trace`](Self::extend_trace) before proving. The original AIR must not
+/// have a permutation trace or extension field main columns.
+#[derive(Debug)]
+pub struct DegreeReducedAir<'a, F, A> {
+    air: &'a A,
+    preprocessed: Option<RowMajorMatrix<F>>,
+    constraints: ConstraintProgram<F>,
+    /// A program whose constraints are the definitions of the auxiliary columns.
+    aux_columns: ConstraintProgram<F>,
+}
+
+impl<'a, F: Field, A: BaseAirWithPublicValues<F>> DegreeReducedAir<'a, F, A> {
+    pub fn new(air: &'a A, max_degree: usize) -> Self
+    where
+        A: Air<SymbolicAirBuilder<F>>,
+    {
+        assert_eq!(
+            air.permutation_width(),
+            0,
+            "AIRs with a permutation trace are not supported"
+        );
+        assert_eq!(
+            air.ext_width(),
+            0,
+            "AIRs with extension field main columns are not supported"
+        );
+        let preprocessed = air.preprocessed_trace();
+        let preprocessed_width = preprocessed.as_ref().map_or(0, |p| p.width());
+        let constraints =
+            get_symbolic_constraints(air, preprocessed_width, air.num_public_values());
+        let reduction = reduce_constraint_degree(&constraints, air.width(), max_degree);
+        Self {
+            air,
+            preprocessed,
+            constraints: ConstraintProgram::compile(&reduction.constraints),
+            aux_columns: ConstraintProgram::compile(&reduction.aux_columns),
+        }
+    }
+
+    pub fn num_aux_columns(&self) -> usize {
+        self.aux_columns.constraints().len()
+    }
+
+    /// Extend a trace of the original AIR with the auxiliary columns.
+    ///
+    /// The trace is first padded with the original AIR's padding strategy, if it has one.
+    /// Auxiliary columns referring to later rows wrap around to the start of the trace, as the
+    /// constraints do.
+    #[instrument(name = "extend trace with auxiliary columns", skip_all)]
+    pub fn extend_trace(
+        &self,
+        mut trace: RowMajorMatrix<F>,
+        public_values: &[F],
+    ) -> RowMajorMatrix<F> {
+        if let Some(padding) = self.air.trace_padding() {
+            padding.pad(&mut trace);
+        }
+        let num_aux = self.num_aux_columns();
+        if num_aux == 0 {
+            return trace;
+        }
+
+        let (width, height) = (trace.width(), trace.height());
+        let periodic_columns = self.air.periodic_columns();
+        let definitions = self.aux_columns.constraints();
+        let mut aux = RowMajorMatrix::new(F::zero_vec(height * num_aux), num_aux);
+        aux.par_rows_mut().enumerate().for_each(|(row, aux_row)| {
+            let values = self.aux_columns.eval_with(|instruction, values: &[F]| {
+                match instruction {
+                    // An auxiliary column, whose definition was evaluated earlier.
+                    Instruction::Variable {
+                        entry: Entry::Main { .. },
+                        index,
+                    } if index >= width => values[definitions[index - width]],
+                    Instruction::Variable {
+                        entry: Entry::Main { offset },
+                        index,
+                    } => trace.values[(row + offset) % height * width + index],
+                    Instruction::Variable {
+                        entry: Entry::Preprocessed { offset },
+                        index,
+                    } => {
+                        let preprocessed = self.preprocessed.as_ref().unwrap();
+                        let row = (row + offset) % height;
+                        preprocessed.values[row * preprocessed.width() + index]
+                    }
+                    Instruction::Variable {
+                        entry: Entry::Public,
+                        index,
+                    } => public_values[index],
+                    Instruction::Variable {
+                        entry: Entry::Periodic,
+                        index,
+                    } => {
+                        let column = &periodic_columns[index];
+                        column[row % column.len()]
+                    }
+                    Instruction::IsFirstRow => F::from_bool(row == 0),
+                    Instruction::IsLastRow => F::from_bool(row == height - 1),
+                    Instruction::IsTransitionWindow(size) => F::from_bool(row + size - 1 < height),
+                    _ => unreachable!("AIRs with a permutation trace are not supported"),
+                }
+            });
+            for (value, &definition) in aux_row.iter_mut().zip(definitions) {
+                *value = values[definition];
+            }
+        });
+
+        let values = trace
+            .rows()
+            .zip(aux.rows())
+            .flat_map(|(row, aux_row)| row.chain(aux_row))
+            .collect();
+        RowMajorMatrix::new(values, width + num_aux)
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> BaseAir<F> for DegreeReducedAir<'_, F, A> {
+    fn width(&self) -> usize {
+        self.air.width() + self.aux_columns.constraints().len()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.preprocessed.clone()
+    }
+
+    fn window_size(&self) -> usize {
+        self.air.window_size()
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        self.air.periodic_columns()
+    }
+}
+
+impl<F: Field, A: BaseAirWithPublicValues<F>> BaseAirWithPublicValues<F>
+    for DegreeReducedAir<'_, F, A>
+{
+    fn num_public_values(&self) -> usize {
+        self.air.num_public_values()
+    }
+}
+
+impl<F: Field, A: InteractionAir<F>> InteractionAir<F> for DegreeReducedAir<'_, F, A> {
+    fn eval_interactions(&self, builder: &mut InteractionBuilder<F>) {
+        self.air.eval_interactions(builder);
+    }
+}
+
+impl<F, A, AB> Air<AB> for DegreeReducedAir<'_, F, A>
+where
+    F: Field,
+    A: BaseAir<F>,
+    AB: AirBuilderWithPublicValues<F = F> + PairBuilder + PeriodicAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let window_size = self.air.window_size();
+        let main = builder.main();
+        let main_rows = (0..window_size)
+            .map(|offset| main.row_slice(offset).to_vec())
+            .collect_vec();
+        let preprocessed_rows = if self.preprocessed.is_some() {
+            let preprocessed = builder.preprocessed();
+            (0..window_size)
+                .map(|offset| preprocessed.row_slice(offset).to_vec())
+                .collect_vec()
+        } else {
+            vec![]
+        };
+        let values = self
+            .constraints
+            .eval_with(|instruction, _: &[AB::Expr]| match instruction {
+                Instruction::Variable {
+                    entry: Entry::Main { offset },
+                    index,
+                } => main_rows[offset][index].into(),
+                Instruction::Variable {
+                    entry: Entry::Preprocessed { offset },
+                    index,
+                } => preprocessed_rows[offset][index].into(),
+                Instruction::Variable {
+                    entry: Entry::Public,
+                    index,
+                } => builder.public_values()[index].into(),
+                Instruction::Variable {
+                    entry: Entry::Periodic,
+                    index,
+                } => builder.periodic_values()[index].into(),
+                Instruction::IsFirstRow => builder.is_first_row(),
+                Instruction::IsLastRow => builder.is_last_row(),
+                Instruction::IsTransitionWindow(size) => builder.is_transition_window(size),
+                _ => unreachable!("AIRs with a permutation trace are not supported"),
+            });
+        for &constraint in self.constraints.constraints() {
+            builder.assert_zero(values[constraint].clone());
+        }
+    }
+}
@@ -0,0 +1,365 @@
+//! Estimates of proof size and prover cost, computed from an AIR and its configuration without
+//! generating a trace.
+
+use alloc::vec;
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use p3_air::Air;
+use p3_commit::{MmcsShape, ShapedPcs};
+use p3_field::BasedVectorSpace;
+use p3_util::log2_ceil_usize;
+
+use crate::{
+    StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val, get_symbolic_constraints,
+    is_zk, main_trace_width,
+};
+
+/// The predicted shape and size of a proof, and the work required to produce it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProofEstimate {
+    /// The number of constraints of the AIR.
+    pub num_constraints: usize,
+    /// The maximum degree of the AIR's constraints.
+    pub constraint_degree: usize,
+    pub log_quotient_degree: usize,
+    /// The number of chunks the quotient polynomial is split into.
+    pub num_quotient_chunks: usize,
+    /// The number of commitments in the proof: the trace, the quotient chunks, the permutation
+    /// trace if any, and one per FRI folding round.
+    pub num_commitments: usize,
+    pub num_fri_rounds: usize,
+    /// The number of challenge field values opened out of domain.
+    pub num_opened_values: usize,
+    /// The number of layers of siblings in the Merkle paths which open the committed traces at
+    /// each query, below the committed cap.
+    pub merkle_path_len: usize,
+    /// The total size of the proof in bytes.
+    pub proof_bytes: usize,
+    /// The number of base field values in the low-degree extensions committed by the prover.
+    pub lde_size: usize,
+    /// The number of points at which the prover evaluates the constraints.
+    pub quotient_domain_size: usize,
+    /// The number of leaf hashes and compressions needed to build the prover's Merkle trees.
+    pub num_hashes: usize,
+}
+
+/// Estimate the proof of `air` over a trace of height `2^log_height`, using the shape of the proofs
+/// of the PCS of `config`.
+///
+/// Field elements and digests are counted at their in-memory size, and serialization overhead such
+/// as length prefixes is ignored. Each Merkle tree is opened at all queries with one multi-proof, whose number of
+/// siblings is counted at its expected value for uniformly random queries. The preprocessed trace
+/// is committed ahead of time, so its commitment and LDE are not counted, but its openings are.
+/// For a hiding PCS, trace heights are doubled, but the random codewords it adds are not counted.
+pub fn estimate_proof<SC, A>(
+    config: &SC,
+    air: &A,
+    log_height: usize,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> ProofEstimate
+where
+    SC: StarkGenericConfig,
+    SC::Pcs: ShapedPcs<SC::Challenge, SC::Challenger>,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let pcs_shape = config.pcs().shape();
+    let input_shape = pcs_shape.input_mmcs;
+    let fri_shape = pcs_shape.fold_mmcs;
+
+    let constraints =
+        get_symbolic_constraints::<Val<SC>, A>(air, preprocessed_width, num_public_values);
+    let constraint_degree = constraints
+        .iter()
+        .map(SymbolicExpression::degree_multiple)
+        .max()
+        .unwrap_or(0);
+    // See `get_log_quotient_degree`; in zero-knowledge mode the quotient has twice as many chunks.
+    let is_zk = is_zk::<SC>() as usize;
+    let log_quotient_degree = log2_ceil_usize((constraint_degree + is_zk).max(2) - 1);
+    let num_quotient_chunks = 1 << (log_quotient_degree + is_zk);
+
+    let d = <SC::Challenge as BasedVectorSpace<Val<SC>>>::DIMENSION;
+    let main_width = main_trace_width::<SC, A>(air);
+    let permutation_width = air.permutation_width() * d;
+
+    // The widths of the matrices in each batch opened at every query. Only the trace, quotient and
+    // permutation batches are committed in the proof.
+    let mut batches = vec![vec![main_width], vec![d; num_quotient_chunks]];
+    if permutation_width > 0 {
+        batches.push(vec![permutation_width]);
+    }
+    let num_trace_commitments = batches.len();
+    if preprocessed_width > 0 {
+        batches.push(vec![preprocessed_width]);
+    }
+
+    // FRI folds the tallest codeword, of height `2^log_lde_height`, until it reaches
+    // `blowup * final_poly_len`. All inputs share that height, so every round but the last folds
+    // by the largest arity. Each commit phase tree has the cosets being folded as leaves.
+    let log_lde_height = log_height + is_zk + pcs_shape.log_blowup;
+    // The log arity and the log height of the folded codeword, which is the number of leaves of the
+    // next round's tree.
+    let mut fri_rounds = vec![];
+    let mut log_fri_height = log_lde_height;
+    loop {
+        let log_arity = pcs_shape.log_arity_of_round(log_fri_height);
+        if log_arity == 0 {
+            break;
+        }
+        log_fri_height -= log_arity;
+        fri_rounds.push((log_arity, log_fri_height));
+    }
+    let num_fri_rounds = fri_rounds.len();
+
+    let num_opened_values = air.window_size()
+        * (main_width + preprocessed_width + permutation_width)
+        + num_quotient_chunks * d;
+
+    let val_bytes = size_of::<Val<SC>>();
+    let challenge_bytes = size_of::<SC::Challenge>();
+    let commitment_bytes = input_shape.commitment_bytes(log_lde_height);
+
+    let num_queries = pcs_shape.num_queries;
+    let input_bytes: usize = batches
+        .iter()
+        .map(|widths| {
+            num_queries * widths.iter().sum::<usize>() * val_bytes
+                + expected_multi_proof_len(input_shape, log_lde_height, num_queries)
+                    * input_shape.digest_bytes
+        })
+        .sum();
+    let fri_bytes: usize = fri_rounds
+        .iter()
+        .map(|&(log_arity, log_leaves)| {
+            fri_shape.commitment_bytes(log_leaves)
+                + num_queries * ((1 << log_arity) - 1) * challenge_bytes
+                + expected_multi_proof_len(fri_shape, log_leaves, num_queries)
+                    * fri_shape.digest_bytes
+        })
+        .sum();
+    let proof_bytes = num_trace_commitments * commitment_bytes
+        + num_opened_values * challenge_bytes
+        + input_bytes
+        + fri_bytes
+        + pcs_shape.final_poly_len() * challenge_bytes
+        // The proof of work witness and the degree bits.
+        + val_bytes
+        + size_of::<usize>();
+
+    let lde_size = (main_width + num_quotient_chunks * d + permutation_width) << log_lde_height;
+    let num_hashes = num_trace_commitments * tree_hashes(input_shape, log_lde_height)
+        + fri_rounds
+            .iter()
+            .map(|&(_, log_leaves)| tree_hashes(fri_shape, log_leaves))
+            .sum::<usize>();
+
+    ProofEstimate {
+        num_constraints: constraints.len(),
+        constraint_degree,
+        log_quotient_degree,
+        num_quotient_chunks,
+        num_commitments: num_trace_commitments + num_fri_rounds,
+        num_fri_rounds,
+        num_opened_values,
+        merkle_path_len: input_shape.num_layers(log_lde_height),
+        proof_bytes,
+        lde_size,
+        quotient_domain_size: 1 << (log_height + log_quotient_degree + is_zk),
+        num_hashes,
+    }
+}
+
+/// The log of the number of nodes in each layer of a tree of the given shape with `2^log_leaves`
+/// leaves, from the leaves up to the committed cap.
+fn log_layer_lens(shape: MmcsShape, log_leaves: usize) -> impl Iterator<Item = usize> {
+    let log_arity = shape.arity.ilog2() as usize;
+    (0..=shape.num_layers(log_leaves)).map(move |i| log_leaves.saturating_sub(i * log_arity))
+}
+
+/// The number of leaf hashes and compressions needed to build a tree of the given shape with
+/// `2^log_leaves` leaves, up to its committed cap.
+fn tree_hashes(shape: MmcsShape, log_leaves: usize) -> usize {
+    log_layer_lens(shape, log_leaves)
+        .map(|log_len| 1 << log_len)
+        .sum()
+}
+
+/// The expected number of siblings in a Merkle multi-proof of `num_queries` uniformly random leaves
+/// of a tree of the given shape with `2^log_leaves` leaves.
+fn expected_multi_proof_len(shape: MmcsShape, log_leaves: usize, num_queries: usize) -> usize {
+    // The expected number of distinct nodes opened in a layer of `2^log_len` nodes.
+    let expected_distinct = |log_len: usize| {
+        let len = (1u64 << log_len) as f64;
+        let miss = (0..num_queries).fold(1.0, |acc, _| acc * (1.0 - 1.0 / len));
+        len * (1.0 - miss)
+    };
+    // Each opened node of a layer needs its siblings, unless they were opened too. The opened
+    // nodes of the next layer are their parents, each with `2^(log_len - log_parent_len)` children.
+    let log_lens = log_layer_lens(shape, log_leaves).collect::<Vec<_>>();
+    let num_siblings: f64 = log_lens
+        .windows(2)
+        .map(|w| {
+            let (log_len, log_parent_len) = (w[0], w[1]);
+            (1u64 << (log_len - log_parent_len)) as f64 * expected_distinct(log_parent_len)
+                - expected_distinct(log_len)
+        })
+        .sum();
+    (num_siblings + 0.5) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec::Vec;
+
+    use p3_air::{AirBuilder, BaseAir};
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_challenger::DuplexChallenger;
+    use p3_commit::ExtensionMmcs;
+    use p3_dft::Radix2DitParallel;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_field::{Field, PrimeCharacteristicRing};
+    use p3_fri::{FriConfig, TwoAdicFriPcs, create_test_fri_config};
+    use p3_matrix::Matrix;
+    use p3_matrix::dense::RowMajorMatrix;
+    use p3_merkle_tree::MerkleTreeMmcs;
+    use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+    use p3_util::log2_strict_usize;
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::{StarkConfig, prove, verify};
+
+    type F = BabyBear;
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type ValMmcs =
+        MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress, 8>;
+    type Challenge = BinomialExtensionField<F, 4>;
+    type ChallengeMmcs = ExtensionMmcs<F, Challenge, ValMmcs>;
+    type Challenger = DuplexChallenger<F, Perm, 16, 8>;
+    type Dft = Radix2DitParallel<F>;
+    type MyPcs = TwoAdicFriPcs<F, Dft, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<MyPcs, Challenge, Challenger>;
+
+    /// Two columns `(x, y)` with `y = x^3` on every row.
+    struct CubeAir;
+
+    impl<F> BaseAir<F> for CubeAir {
+        fn width(&self) -> usize {
+            2
+        }
+    }
+
+    impl<AB: AirBuilder> Air<AB> for CubeAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            builder.assert_eq(local[1], local[0].into().cube());
+        }
+    }
+
+    #[test]
+    fn test_multi_proof_len() {
+        let shape = |arity, cap_height| MmcsShape {
+            arity,
+            cap_height,
+            digest_bytes: 32,
+        };
+        // A single query needs all `arity - 1` siblings in every layer below the cap.
+        assert_eq!(expected_multi_proof_len(shape(2, 0), 6, 1), 6);
+        assert_eq!(expected_multi_proof_len(shape(4, 0), 6, 1), 9);
+        assert_eq!(expected_multi_proof_len(shape(4, 1), 6, 1), 6);
+        assert_eq!(expected_multi_proof_len(shape(8, 1), 4, 1), 7);
+        assert_eq!(shape(4, 1).commitment_bytes(6), 4 * 32);
+        assert_eq!(tree_hashes(shape(4, 1), 6), 64 + 16 + 4);
+    }
+
+    #[test]
+    fn test_estimate_matches_proof() {
+        for (max_log_arity, cap_height) in [(1, 0), (3, 0), (1, 2), (3, 2)] {
+            do_test_estimate(max_log_arity, cap_height);
+        }
+    }
+
+    fn do_test_estimate(max_log_arity: usize, cap_height: usize) {
+        let log_height = 6;
+        let log_final_poly_len = 1;
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let val_mmcs = ValMmcs::new_with_cap_height(
+            MyHash::new(perm.clone()),
+            MyCompress::new(perm.clone()),
+            cap_height,
+        );
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+        let fri_config = FriConfig {
+            max_log_arity,
+            // Enough queries that the multi-proofs are close to their expected size.
+            num_queries: 16,
+            ..create_test_fri_config(challenge_mmcs, log_final_poly_len)
+        };
+        let (log_blowup, num_queries) = (fri_config.log_blowup, fri_config.num_queries);
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, fri_config);
+        let config = MyConfig::new(pcs);
+
+        let estimate = estimate_proof(&config, &CubeAir, log_height, 0, 0);
+        assert_eq!(estimate.num_constraints, 1);
+        assert_eq!(estimate.constraint_degree, 3);
+        assert_eq!(estimate.num_quotient_chunks, 2);
+        assert_eq!(estimate.quotient_domain_size, 1 << (log_height + 1));
+
+        let values = (0..1 << log_height)
+            .flat_map(|i| [F::from_u32(i), F::from_u32(i).cube()])
+            .collect();
+        let trace = RowMajorMatrix::new(values, 2);
+        let challenger = Challenger::new(perm);
+        let proof = prove(&config, &CubeAir, &mut challenger.clone(), trace, &vec![]);
+        verify(&config, &CubeAir, &mut challenger.clone(), &proof, &vec![]).unwrap();
+
+        let fri_proof = &proof.opening_proof;
+        assert_eq!(
+            fri_proof.commit_phase_commits.len(),
+            estimate.num_fri_rounds
+        );
+        assert_eq!(
+            estimate.num_commitments,
+            2 + fri_proof.commit_phase_commits.len()
+        );
+        let opened = &proof.opened_values;
+        let num_opened_values: usize = [&opened.trace_window, &opened.quotient_chunks]
+            .into_iter()
+            .flatten()
+            .map(Vec::len)
+            .sum();
+        assert_eq!(num_opened_values, estimate.num_opened_values);
+
+        let log_lde_height = log_height + log_blowup;
+        assert_eq!(estimate.merkle_path_len, log_lde_height - cap_height);
+        assert_eq!(fri_proof.input_proof.len(), 2);
+        for batch in &fri_proof.input_proof {
+            assert_eq!(batch.opened_values.len(), num_queries);
+            assert!(batch.opening_proof.len() <= num_queries * estimate.merkle_path_len);
+        }
+        let query = &fri_proof.query_proofs[0];
+        let mut log_fri_height = log_lde_height;
+        for (step, opening_proof) in query
+            .commit_phase_openings
+            .iter()
+            .zip(&fri_proof.commit_phase_opening_proofs)
+        {
+            log_fri_height -= log2_strict_usize(step.sibling_values.len() + 1);
+            let num_layers = log_fri_height - cap_height.min(log_fri_height);
+            assert!(opening_proof.len() <= num_queries * num_layers);
+        }
+        assert_eq!(log_fri_height, log_blowup + log_final_poly_len);
+
+        // Postcard encodes field elements as varints of up to 5 bytes, and adds length prefixes.
+        let actual_bytes = postcard::to_allocvec(&proof).unwrap().len();
+        assert!(estimate.proof_bytes <= actual_bytes);
+        assert!(actual_bytes < estimate.proof_bytes * 3 / 2);
+    }
+}
@@ -0,0 +1,246 @@
+//! Fingerprints of AIRs and configurations, bound into the Fiat-Shamir transcript so that proofs
+//! for different instances never share a transcript prefix.
+
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_air::Air;
+use p3_challenger::CanObserve;
+use p3_commit::Pcs;
+use p3_field::{Field, PrimeCharacteristicRing};
+
+use crate::{
+    Entry, StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val,
+    get_symbolic_constraints,
+};
+
+/// The fingerprint of `air`: [`BaseAir::fingerprint`](p3_air::BaseAir::fingerprint) if the AIR
+/// provides one, and otherwise the encoding of its symbolic constraints.
+pub fn get_air_fingerprint<F, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> Vec<F>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    air.fingerprint().unwrap_or_else(|| {
+        encode_symbolic_constraints(&get_symbolic_constraints(
+            air,
+            preprocessed_width,
+            num_public_values,
+        ))
+    })
+}
+
+/// Encode symbolic constraints as a sequence of field elements.
+///
+/// The constraints are written out as a DAG in post-order. Each node is a tag followed by its
+/// data, with children referred to by their position in the list of nodes, so subexpressions
+/// shared through an `Rc` are only encoded once. The node list and the list of constraint roots
+/// are both prefixed by their lengths, which makes the encoding injective.
+pub fn encode_symbolic_constraints<F: Field>(constraints: &[SymbolicExpression<F>]) -> Vec<F> {
+    let mut encoder = ConstraintEncoder {
+        nodes: Vec::new(),
+        num_nodes: 0,
+        indices: BTreeMap::new(),
+    };
+    let roots: Vec<_> = constraints.iter().map(|c| encoder.encode(c)).collect();
+
+    let mut encoding = vec![F::from_usize(encoder.num_nodes)];
+    encoding.extend(encoder.nodes);
+    encoding.push(F::from_usize(roots.len()));
+    encoding.extend(roots.into_iter().map(F::from_usize));
+    encoding
+}
+
+struct ConstraintEncoder<F> {
+    nodes: Vec<F>,
+    num_nodes: usize,
+    /// The index of each shared subexpression encoded so far, keyed by its address.
+    indices: BTreeMap<*const SymbolicExpression<F>, usize>,
+}
+
+impl<F: Field> ConstraintEncoder<F> {
+    fn encode_shared(&mut self, expr: &Rc<SymbolicExpression<F>>) -> usize {
+        if let Some(&index) = self.indices.get(&Rc::as_ptr(expr)) {
+            return index;
+        }
+        let index = self.encode(expr);
+        self.indices.insert(Rc::as_ptr(expr), index);
+        index
+    }
+
+    fn encode(&mut self, expr: &SymbolicExpression<F>) -> usize {
+        let node = match expr {
+            SymbolicExpression::Variable(v) => {
+                let (entry, offset) = match v.entry {
+                    Entry::Preprocessed { offset } => (0, offset),
+                    Entry::Main { offset } => (1, offset),
+                    Entry::Permutation { offset } => (2, offset),
+                    Entry::Public => (3, 0),
+                    Entry::Challenge => (4, 0),
+                    Entry::Periodic => (5, 0),
+                    Entry::MainExt { offset } => (6, offset),
+                };
+                vec![
+                    F::ZERO,
+                    F::from_u8(entry),
+                    F::from_usize(offset),
+                    F::from_usize(v.index),
+                ]
+            }
+            SymbolicExpression::IsFirstRow => vec![F::from_u8(1)],
+            SymbolicExpression::IsLastRow => vec![F::from_u8(2)],
+            SymbolicExpression::IsTransition => vec![F::from_u8(3)],
+            SymbolicExpression::IsTransitionWindow(size) => {
+                vec![F::from_u8(9), F::from_usize(*size)]
+            }
+            SymbolicExpression::Constant(c) => vec![F::from_u8(4), *c],
+            SymbolicExpression::Add { x, y, .. } => self.encode_binary(5, x, y),
+            SymbolicExpression::Sub { x, y, .. } => self.encode_binary(6, x, y),
+            SymbolicExpression::Neg { x, .. } => {
+                let x = self.encode_shared(x);
+                vec![F::from_u8(7), F::from_usize(x)]
+            }
+            SymbolicExpression::Mul { x, y, .. } => self.encode_binary(8, x, y),
+        };
+        self.nodes.extend(node);
+        self.num_nodes += 1;
+        self.num_nodes - 1
+    }
+
+    fn encode_binary(
+        &mut self,
+        tag: u8,
+        x: &Rc<SymbolicExpression<F>>,
+        y: &Rc<SymbolicExpression<F>>,
+    ) -> Vec<F> {
+        let x = self.encode_shared(x);
+        let y = self.encode_shared(y);
+        vec![F::from_u8(tag), F::from_usize(x), F::from_usize(y)]
+    }
+}
+
+/// Observe the parameters of the PCS, which bound the soundness of the proof.
+pub(crate) fn observe_config<SC: StarkGenericConfig>(config: &SC, challenger: &mut SC::Challenger) {
+    let parameters = config.pcs().parameters();
+    observe_with_len(
+        challenger,
+        &parameters
+            .into_iter()
+            .map(Val::<SC>::from_usize)
+            .collect_vec(),
+    );
+}
+
+/// Observe the trace widths, window size, periodic columns and fingerprint of an AIR.
+pub(crate) fn observe_air<SC: StarkGenericConfig>(
+    challenger: &mut SC::Challenger,
+    width: usize,
+    preprocessed_width: usize,
+    permutation_width: usize,
+    window_size: usize,
+    periodic_columns: &[Vec<Val<SC>>],
+    fingerprint: &[Val<SC>],
+) {
+    challenger.observe_slice(
+        &[width, preprocessed_width, permutation_width, window_size].map(Val::<SC>::from_usize),
+    );
+    // The constraints only refer to periodic columns by index, so their values must be bound too.
+    challenger.observe(Val::<SC>::from_usize(periodic_columns.len()));
+    for column in periodic_columns {
+        observe_with_len(challenger, column);
+    }
+    observe_with_len(challenger, fingerprint);
+}
+
+/// Observe `values` after their number, so that the boundaries between consecutive lists are bound
+/// too.
+fn observe_with_len<F: Field>(challenger: &mut impl CanObserve<F>, values: &[F]) {
+    challenger.observe(F::from_usize(values.len()));
+    challenger.observe_slice(values);
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_air::{AirBuilder, BaseAir};
+    use p3_baby_bear::BabyBear;
+    use p3_field::PrimeCharacteristicRing;
+    use p3_matrix::Matrix;
+
+    use super::*;
+    use crate::SymbolicVariable;
+
+    type F = BabyBear;
+
+    fn main_var(index: usize) -> SymbolicExpression<F> {
+        SymbolicVariable::new(Entry::Main { offset: 0 }, index).into()
+    }
+
+    #[test]
+    fn test_encoding_distinguishes_constraints() {
+        let encodings = [
+            vec![main_var(0) * main_var(1)],
+            vec![main_var(1) * main_var(0)],
+            vec![main_var(0) + main_var(1)],
+            vec![main_var(0) * main_var(1) - F::ONE],
+            vec![main_var(0), main_var(1)],
+            vec![main_var(0)],
+        ]
+        .map(|constraints| encode_symbolic_constraints(&constraints));
+        for (i, a) in encodings.iter().enumerate() {
+            for b in &encodings[i + 1..] {
+                assert_ne!(a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn test_shared_subexpressions_encoded_once() {
+        // Each doubling shares a single child, so the encoding stays linear in the depth.
+        let mut expr = main_var(0);
+        for _ in 0..64 {
+            let shared = Rc::new(expr);
+            expr = SymbolicExpression::Add {
+                x: shared.clone(),
+                y: shared,
+                degree_multiple: 1,
+            };
+        }
+        let encoding = encode_symbolic_constraints(&[expr]);
+        // The variable, 64 additions, and the lengths and root.
+        assert_eq!(encoding.len(), 4 + 64 * 3 + 3);
+    }
+
+    struct FixedFingerprintAir;
+
+    impl BaseAir<F> for FixedFingerprintAir {
+        fn width(&self) -> usize {
+            1
+        }
+
+        fn fingerprint(&self) -> Option<Vec<F>> {
+            Some(vec![F::from_u8(42)])
+        }
+    }
+
+    impl<AB: AirBuilder<F = F>> Air<AB> for FixedFingerprintAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            builder.assert_zero(main.row_slice(0)[0]);
+        }
+    }
+
+    #[test]
+    fn test_fingerprint_override() {
+        assert_eq!(
+            get_air_fingerprint(&FixedFingerprintAir, 0, 0),
+            vec![F::from_u8(42)]
+        );
+    }
+}
@@ -1,34 +1,51 @@
 use alloc::vec::Vec;
 
-use p3_air::{AirBuilder, AirBuilderWithPublicValues};
+use p3_air::{
+    AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, ExtensionMainBuilder, PairBuilder,
+    PeriodicAirBuilder, PermutationAirBuilder,
+};
 use p3_field::{BasedVectorSpace, PackedField};
 use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::VerticalPair;
 
 use crate::{PackedChallenge, PackedVal, StarkGenericConfig, Val};
 
 #[derive(Debug)]
 pub struct ProverConstraintFolder<'a, SC: StarkGenericConfig> {
+    pub preprocessed: RowMajorMatrixView<'a, PackedVal<SC>>,
     pub main: RowMajorMatrixView<'a, PackedVal<SC>>,
+    pub main_ext: RowMajorMatrixView<'a, PackedChallenge<SC>>,
+    pub permutation: RowMajorMatrixView<'a, PackedChallenge<SC>>,
+    pub permutation_challenges: &'a [PackedChallenge<SC>],
     pub public_values: &'a Vec<Val<SC>>,
+    pub periodic_values: &'a [PackedVal<SC>],
     pub is_first_row: PackedVal<SC>,
     pub is_last_row: PackedVal<SC>,
-    pub is_transition: PackedVal<SC>,
+    /// The transition selectors for windows of size 2, 3, ..., up to the AIR's window size.
+    pub is_transition_windows: &'a [PackedVal<SC>],
     pub alpha_powers: &'a [SC::Challenge],
     pub decomposed_alpha_powers: &'a [Vec<Val<SC>>],
     pub accumulator: PackedChallenge<SC>,
     pub constraint_index: usize,
+    /// Scratch space for the base and extension field values computed by
+    /// [`eval_program`](Self::eval_program), shared by the folders of consecutive rows so that
+    /// evaluating a program doesn't allocate.
+    pub program_base: &'a mut Vec<PackedVal<SC>>,
+    pub program_ext: &'a mut Vec<PackedChallenge<SC>>,
 }
 
-type ViewPair<'a, T> = VerticalPair<RowMajorMatrixView<'a, T>, RowMajorMatrixView<'a, T>>;
-
 #[derive(Debug)]
 pub struct VerifierConstraintFolder<'a, SC: StarkGenericConfig> {
-    pub main: ViewPair<'a, SC::Challenge>,
+    pub preprocessed: RowMajorMatrixView<'a, SC::Challenge>,
+    pub main: RowMajorMatrixView<'a, SC::Challenge>,
+    pub main_ext: RowMajorMatrixView<'a, SC::Challenge>,
+    pub permutation: RowMajorMatrixView<'a, SC::Challenge>,
+    pub permutation_challenges: &'a [SC::Challenge],
     pub public_values: &'a Vec<Val<SC>>,
+    pub periodic_values: &'a [SC::Challenge],
     pub is_first_row: SC::Challenge,
     pub is_last_row: SC::Challenge,
-    pub is_transition: SC::Challenge,
+    /// The transition selectors for windows of size 2, 3, ..., up to the AIR's window size.
+    pub is_transition_windows: &'a [SC::Challenge],
     pub alpha: SC::Challenge,
     pub accumulator: SC::Challenge,
 }
@@ -55,14 +72,10 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for ProverConstraintFolder<'a, SC> {
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is not between `2` and the AIR's window size.
     #[inline]
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window_selector(self.is_transition_windows, size)
     }
 
     #[inline]
@@ -94,11 +107,68 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for ProverConstraintFold
     }
 }
 
+impl<SC: StarkGenericConfig> PeriodicAirBuilder for ProverConstraintFolder<'_, SC> {
+    type PeriodicVar = PackedVal<SC>;
+
+    #[inline]
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
+impl<SC: StarkGenericConfig> PairBuilder for ProverConstraintFolder<'_, SC> {
+    #[inline]
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<SC: StarkGenericConfig> ExtensionBuilder for ProverConstraintFolder<'_, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = PackedChallenge<SC>;
+    type VarEF = PackedChallenge<SC>;
+
+    #[inline]
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        let x: PackedChallenge<SC> = x.into();
+        let alpha_power = self.alpha_powers[self.constraint_index];
+        self.accumulator += x * alpha_power;
+        self.constraint_index += 1;
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> ExtensionMainBuilder for ProverConstraintFolder<'a, SC> {
+    type MEF = RowMajorMatrixView<'a, PackedChallenge<SC>>;
+
+    #[inline]
+    fn main_ext(&self) -> Self::MEF {
+        self.main_ext
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for ProverConstraintFolder<'a, SC> {
+    type MP = RowMajorMatrixView<'a, PackedChallenge<SC>>;
+    type RandomVar = PackedChallenge<SC>;
+
+    #[inline]
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    #[inline]
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
 impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC> {
     type F = Val<SC>;
     type Expr = SC::Challenge;
     type Var = SC::Challenge;
-    type M = ViewPair<'a, SC::Challenge>;
+    type M = RowMajorMatrixView<'a, SC::Challenge>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -113,13 +183,9 @@ impl<'a, SC: StarkGenericConfig> AirBuilder for VerifierConstraintFolder<'a, SC>
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is not between `2` and the AIR's window size.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
-        }
+        transition_window_selector(self.is_transition_windows, size)
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
@@ -136,3 +202,63 @@ impl<SC: StarkGenericConfig> AirBuilderWithPublicValues for VerifierConstraintFo
         self.public_values
     }
 }
+
+impl<SC: StarkGenericConfig> PeriodicAirBuilder for VerifierConstraintFolder<'_, SC> {
+    type PeriodicVar = SC::Challenge;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.periodic_values
+    }
+}
+
+impl<SC: StarkGenericConfig> PairBuilder for VerifierConstraintFolder<'_, SC> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<SC: StarkGenericConfig> ExtensionBuilder for VerifierConstraintFolder<'_, SC> {
+    type EF = SC::Challenge;
+    type ExprEF = SC::Challenge;
+    type VarEF = SC::Challenge;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.assert_zero(x);
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> ExtensionMainBuilder for VerifierConstraintFolder<'a, SC> {
+    type MEF = RowMajorMatrixView<'a, SC::Challenge>;
+
+    fn main_ext(&self) -> Self::MEF {
+        self.main_ext
+    }
+}
+
+impl<'a, SC: StarkGenericConfig> PermutationAirBuilder for VerifierConstraintFolder<'a, SC> {
+    type MP = RowMajorMatrixView<'a, SC::Challenge>;
+    type RandomVar = SC::Challenge;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+/// Look up the transition selector for a window of `size` rows, given the selectors for windows of
+/// size 2 onwards.
+#[inline]
+pub(crate) fn transition_window_selector<T: Copy>(is_transition_windows: &[T], size: usize) -> T {
+    assert!(
+        (2..=is_transition_windows.len() + 1).contains(&size),
+        "window size must be between 2 and {}",
+        is_transition_windows.len() + 1
+    );
+    is_transition_windows[size - 2]
+}
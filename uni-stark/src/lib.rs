@@ -4,8 +4,16 @@
 
 extern crate alloc;
 
+mod batch_prover;
+mod batch_verifier;
 mod config;
+mod constraint_program;
+mod degree_reduction;
+mod estimate;
+mod fingerprint;
 mod folder;
+mod lookup;
+mod preprocessed;
 mod proof;
 mod prover;
 mod symbolic_builder;
@@ -16,9 +24,17 @@ mod verifier;
 
 mod check_constraints;
 
+pub use batch_prover::*;
+pub use batch_verifier::*;
 pub use check_constraints::*;
 pub use config::*;
+pub use constraint_program::*;
+pub use degree_reduction::*;
+pub use estimate::*;
+pub use fingerprint::*;
 pub use folder::*;
+pub use lookup::*;
+pub use preprocessed::*;
 pub use proof::*;
 pub use prover::*;
 pub use symbolic_builder::*;
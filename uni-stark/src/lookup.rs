@@ -0,0 +1,250 @@
+//! LogUp bus arguments between AIRs.
+//!
+//! For lookup challenges `alpha` and `beta`, a message `(v_0, ..., v_{k-1})` on bus `b` is
+//! fingerprinted as `beta - (b + sum_j alpha^{j + 1} v_j)`. Every AIR with interactions gets a
+//! permutation trace over the challenge field with one column per interaction, holding
+//! `multiplicity / fingerprint` on each row, followed by a running-sum column accumulating these
+//! (positively for sends, negatively for receives). The last row of the running sum is the AIR's
+//! cumulative sum, and all buses balance exactly when the cumulative sums of all AIRs add up to
+//! zero.
+
+use alloc::vec::Vec;
+
+use p3_air::{
+    Air, ExtensionBuilder, Interaction, InteractionKind, PairBuilder, PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field, PrimeCharacteristicRing, batch_multiplicative_inverse};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::log2_ceil_usize;
+
+use crate::{SymbolicAirBuilder, SymbolicExpression, encode_symbolic_constraints};
+
+/// The number of challenges sampled for the LogUp argument.
+pub const NUM_LOOKUP_CHALLENGES: usize = 2;
+
+/// The width, in challenge field elements, of the permutation trace for the given interactions.
+pub const fn permutation_width<F: Field>(interactions: &[Interaction<F>]) -> usize {
+    if interactions.is_empty() {
+        0
+    } else {
+        interactions.len() + 1
+    }
+}
+
+/// The number of constraints added by [`eval_logup_constraints`] for the given interactions.
+pub const fn num_logup_constraints<F: Field>(interactions: &[Interaction<F>]) -> usize {
+    if interactions.is_empty() {
+        0
+    } else {
+        interactions.len() + 3
+    }
+}
+
+/// Like [`get_log_quotient_degree`](crate::get_log_quotient_degree), but also accounting for the
+/// LogUp constraints of the AIR's interactions.
+pub fn get_log_quotient_degree_with_interactions<F, A>(
+    air: &A,
+    interactions: &[Interaction<F>],
+    num_public_values: usize,
+    is_zk: usize,
+) -> usize
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    let constraint_degree =
+        get_symbolic_constraints_with_interactions(air, interactions, num_public_values)
+            .iter()
+            .map(SymbolicExpression::degree_multiple)
+            .max()
+            .unwrap_or(0);
+    log2_ceil_usize((constraint_degree + is_zk).max(2) - 1)
+}
+
+/// Like [`get_air_fingerprint`](crate::get_air_fingerprint), but also encoding the LogUp
+/// constraints of the AIR's interactions unless the AIR provides its own fingerprint.
+pub fn get_air_fingerprint_with_interactions<F, A>(
+    air: &A,
+    interactions: &[Interaction<F>],
+    num_public_values: usize,
+) -> Vec<F>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    air.fingerprint().unwrap_or_else(|| {
+        encode_symbolic_constraints(&get_symbolic_constraints_with_interactions(
+            air,
+            interactions,
+            num_public_values,
+        ))
+    })
+}
+
+/// The symbolic constraints of an AIR followed by the LogUp constraints of its interactions, with
+/// the cumulative sum set to zero.
+pub fn get_symbolic_constraints_with_interactions<F, A>(
+    air: &A,
+    interactions: &[Interaction<F>],
+    num_public_values: usize,
+) -> Vec<SymbolicExpression<F>>
+where
+    F: Field,
+    A: Air<SymbolicAirBuilder<F>>,
+{
+    let mut builder = SymbolicAirBuilder::new(
+        0,
+        air.width(),
+        air.ext_width(),
+        air.window_size(),
+        permutation_width(interactions),
+        NUM_LOOKUP_CHALLENGES,
+        num_public_values,
+        air.periodic_columns().len(),
+    );
+    air.eval(&mut builder);
+    // The cumulative sum enters the constraints as a constant, so it doesn't affect their degree.
+    eval_logup_constraints(&mut builder, interactions, F::ZERO);
+    builder.constraints()
+}
+
+/// Generate the permutation trace of an AIR, returning it along with the AIR's cumulative sum.
+///
+/// # Panics
+/// Panics if `challenges` has fewer than [`NUM_LOOKUP_CHALLENGES`] elements, or if some message
+/// fingerprint is zero (which happens with negligible probability).
+pub fn generate_logup_trace<F, EF>(
+    interactions: &[Interaction<F>],
+    preprocessed: Option<&RowMajorMatrix<F>>,
+    main: &RowMajorMatrix<F>,
+    challenges: &[EF],
+) -> (RowMajorMatrix<EF>, EF)
+where
+    F: Field,
+    EF: ExtensionField<F>,
+{
+    let width = permutation_width(interactions);
+    if width == 0 {
+        return (RowMajorMatrix::new(Vec::new(), 0), EF::ZERO);
+    }
+    let (alpha, beta) = (challenges[0], challenges[1]);
+    let height = main.height();
+    let alpha_powers = alpha
+        .powers()
+        .skip(1)
+        .take(
+            interactions
+                .iter()
+                .map(|i| i.values.len())
+                .max()
+                .unwrap_or(0),
+        )
+        .collect::<Vec<_>>();
+
+    let mut denominators = Vec::with_capacity(height * interactions.len());
+    let mut multiplicities = Vec::with_capacity(height * interactions.len());
+    for r in 0..height {
+        let prep_row = preprocessed.map_or_else(Vec::new, |p| p.row_slice(r).to_vec());
+        let main_row = main.row_slice(r);
+        for interaction in interactions {
+            let combined = interaction
+                .values
+                .iter()
+                .zip(&alpha_powers)
+                .map(|(col, &alpha_pow)| alpha_pow * col.apply::<F, F>(&prep_row, &main_row))
+                .sum::<EF>()
+                + EF::from_usize(interaction.bus);
+            denominators.push(beta - combined);
+            multiplicities.push(interaction.multiplicity.apply::<F, F>(&prep_row, &main_row));
+        }
+    }
+    let inverses = batch_multiplicative_inverse(&denominators);
+
+    let mut values = EF::zero_vec(height * width);
+    let mut running_sum = EF::ZERO;
+    for ((row, row_inverses), row_multiplicities) in values
+        .chunks_exact_mut(width)
+        .zip(inverses.chunks_exact(interactions.len()))
+        .zip(multiplicities.chunks_exact(interactions.len()))
+    {
+        for (i, interaction) in interactions.iter().enumerate() {
+            let quotient = row_inverses[i] * row_multiplicities[i];
+            row[i] = quotient;
+            match interaction.kind {
+                InteractionKind::Send => running_sum += quotient,
+                InteractionKind::Receive => running_sum -= quotient,
+            }
+        }
+        row[width - 1] = running_sum;
+    }
+
+    (RowMajorMatrix::new(values, width), running_sum)
+}
+
+/// Enforce that the permutation trace of an AIR was computed correctly from its interactions, and
+/// that its running sum ends in `cumulative_sum`.
+pub fn eval_logup_constraints<AB>(
+    builder: &mut AB,
+    interactions: &[Interaction<AB::F>],
+    cumulative_sum: AB::EF,
+) where
+    AB: PermutationAirBuilder + PairBuilder,
+{
+    if interactions.is_empty() {
+        return;
+    }
+
+    let main = builder.main();
+    let main_local = main.row_slice(0);
+    let preprocessed = builder.preprocessed();
+    let prep_local = preprocessed.row_slice(0);
+    let permutation = builder.permutation();
+    let (perm_local, perm_next) = (permutation.row_slice(0), permutation.row_slice(1));
+    let challenges = builder.permutation_randomness();
+    let (alpha, beta): (AB::ExprEF, AB::ExprEF) = (challenges[0].into(), challenges[1].into());
+
+    let mut signed_local = AB::ExprEF::ZERO;
+    let mut signed_next = AB::ExprEF::ZERO;
+    for (i, interaction) in interactions.iter().enumerate() {
+        let mut combined = AB::ExprEF::from_usize(interaction.bus);
+        let mut alpha_pow = alpha.clone();
+        for col in &interaction.values {
+            combined +=
+                alpha_pow.clone() * col.apply::<AB::Expr, AB::Var>(&prep_local, &main_local);
+            alpha_pow *= alpha.clone();
+        }
+        let multiplicity = interaction
+            .multiplicity
+            .apply::<AB::Expr, AB::Var>(&prep_local, &main_local);
+        let (local, next): (AB::ExprEF, AB::ExprEF) = (perm_local[i].into(), perm_next[i].into());
+        builder.assert_eq_ext(
+            local.clone() * (beta.clone() - combined),
+            AB::ExprEF::from(multiplicity),
+        );
+
+        match interaction.kind {
+            InteractionKind::Send => {
+                signed_local += local;
+                signed_next += next;
+            }
+            InteractionKind::Receive => {
+                signed_local -= local;
+                signed_next -= next;
+            }
+        }
+    }
+
+    let running_sum = interactions.len();
+    let phi_local: AB::ExprEF = perm_local[running_sum].into();
+    let phi_next: AB::ExprEF = perm_next[running_sum].into();
+    builder
+        .when_first_row()
+        .assert_eq_ext(phi_local.clone(), signed_local);
+    builder
+        .when_transition()
+        .assert_eq_ext(phi_next, phi_local.clone() + signed_next);
+    builder
+        .when_last_row()
+        .assert_eq_ext(phi_local, AB::ExprEF::from(cumulative_sum));
+}
@@ -0,0 +1,108 @@
+use alloc::vec;
+
+use p3_air::BaseAir;
+use p3_commit::Pcs;
+use p3_matrix::Matrix;
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
+use tracing::info_span;
+
+use crate::proof::Com;
+use crate::{StarkGenericConfig, Val};
+
+pub(crate) type PcsProverData<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+    <SC as StarkGenericConfig>::Challenge,
+    <SC as StarkGenericConfig>::Challenger,
+>>::ProverData;
+
+/// Prover-side data for an AIR's preprocessed (fixed) trace, computed once by [`setup_preprocessed`]
+/// and reused for every proof.
+pub struct PreprocessedProverData<SC: StarkGenericConfig> {
+    pub(crate) width: usize,
+    pub(crate) degree_bits: usize,
+    pub(crate) commitment: Com<SC>,
+    pub(crate) prover_data: PcsProverData<SC>,
+}
+
+impl<SC: StarkGenericConfig> PreprocessedProverData<SC> {
+    /// Returns the verifier key matching this prover data.
+    pub fn verifier_key(&self) -> PreprocessedVerifierKey<SC> {
+        PreprocessedVerifierKey {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+/// Verifier-side data for an AIR's preprocessed (fixed) trace: its shape and commitment.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PreprocessedVerifierKey<SC: StarkGenericConfig> {
+    pub(crate) width: usize,
+    pub(crate) degree_bits: usize,
+    pub(crate) commitment: Com<SC>,
+}
+
+impl<SC: StarkGenericConfig> Clone for PreprocessedVerifierKey<SC> {
+    fn clone(&self) -> Self {
+        Self {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> PreprocessedVerifierKey<SC> {
+    pub const fn width(&self) -> usize {
+        self.width
+    }
+
+    pub const fn degree_bits(&self) -> usize {
+        self.degree_bits
+    }
+
+    pub const fn commitment(&self) -> &Com<SC> {
+        &self.commitment
+    }
+}
+
+/// Commit to the preprocessed trace of `air`, if it has one.
+///
+/// Returns `None` if `air.preprocessed_trace()` is `None` or has zero width. Otherwise the
+/// preprocessed trace's height fixes the height of every main trace proven against these keys.
+///
+/// # Panics
+/// This function panics if the height of the preprocessed trace is not a power of two.
+pub fn setup_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+) -> Option<(PreprocessedProverData<SC>, PreprocessedVerifierKey<SC>)>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+{
+    let preprocessed = air.preprocessed_trace()?;
+    let width = preprocessed.width();
+    if width == 0 {
+        return None;
+    }
+
+    let degree = preprocessed.height();
+    let degree_bits = log2_strict_usize(degree);
+
+    let pcs = config.pcs();
+    let domain = pcs.natural_domain_for_degree(degree);
+    let (commitment, prover_data) = info_span!("commit to preprocessed trace")
+        .in_scope(|| pcs.commit(vec![(domain, preprocessed)]));
+
+    let prover_data = PreprocessedProverData {
+        width,
+        degree_bits,
+        commitment,
+        prover_data,
+    };
+    let verifier_key = prover_data.verifier_key();
+    Some((prover_data, verifier_key))
+}
@@ -5,11 +5,11 @@ use serde::{Deserialize, Serialize};
 
 use crate::StarkGenericConfig;
 
-type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+pub(crate) type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
 >>::Commitment;
-type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+pub(crate) type PcsProof<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
 >>::Proof;
@@ -26,12 +26,33 @@ pub struct Proof<SC: StarkGenericConfig> {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Commitments<Com> {
     pub(crate) trace: Com,
+    /// The commitment to the permutation traces, if any AIR has bus interactions.
+    pub(crate) permutation: Option<Com>,
     pub(crate) quotient_chunks: Com,
 }
 
+/// The values of each trace at every row of the AIR's window, i.e. at `zeta * g^k` for each row
+/// offset `k`, along with the quotient chunks at `zeta`.
+///
+/// Traces which are absent are opened as empty rows.
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenedValues<Challenge> {
-    pub(crate) trace_local: Vec<Challenge>,
-    pub(crate) trace_next: Vec<Challenge>,
+    pub(crate) preprocessed_window: Vec<Vec<Challenge>>,
+    pub(crate) trace_window: Vec<Vec<Challenge>>,
+    /// Openings of the permutation trace, flattened to its base field columns.
+    pub(crate) permutation_window: Vec<Vec<Challenge>>,
     pub(crate) quotient_chunks: Vec<Vec<Challenge>>,
 }
+
+/// A proof for several AIRs, each with its own trace, sharing a single set of commitments and a
+/// single opening proof.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct BatchProof<SC: StarkGenericConfig> {
+    pub(crate) commitments: Commitments<Com<SC>>,
+    pub(crate) opened_values: Vec<OpenedValues<SC::Challenge>>,
+    pub(crate) opening_proof: PcsProof<SC>,
+    pub(crate) degree_bits: Vec<usize>,
+    /// The LogUp cumulative sum of each AIR, zero for AIRs without interactions.
+    pub(crate) cumulative_sums: Vec<SC::Challenge>,
+}
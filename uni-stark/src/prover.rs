@@ -1,27 +1,64 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use itertools::{Itertools, izip};
-use p3_air::Air;
+use itertools::Itertools;
+use p3_air::{Air, BaseAirWithPublicValues};
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
-use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::{BasedVectorSpace, PackedValue, PrimeCharacteristicRing};
+use p3_commit::{CommitError, Pcs, PolynomialSpace};
+use p3_field::{BasedVectorSpace, ExtensionField, Field, PackedValue, PrimeCharacteristicRing};
 use p3_matrix::Matrix;
-use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::stack::HorizontalPair;
 use p3_maybe_rayon::prelude::*;
 use p3_util::{log2_ceil_usize, log2_strict_usize};
 use tracing::{debug_span, info_span, instrument};
 
+use crate::fingerprint::{observe_air, observe_config};
+use crate::verifier::{main_trace_width, window_points};
 use crate::{
-    Commitments, Domain, OpenedValues, PackedChallenge, PackedVal, Proof, ProverConstraintFolder,
-    StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val, get_symbolic_constraints,
+    Commitments, ConstraintProgram, Domain, OpenedValues, PackedChallenge, PackedVal,
+    PreprocessedProverData, Proof, ProverConstraintFolder, StarkGenericConfig, SymbolicAirBuilder,
+    SymbolicExpression, Val, encode_symbolic_constraints, get_symbolic_constraints, is_zk,
 };
 
+/// The reasons [`try_prove`] and its variants may reject their inputs.
+#[derive(Debug)]
+pub enum ProverError {
+    /// The trace's width does not match the AIR's width.
+    TraceWidthMismatch { expected: usize, actual: usize },
+    /// The trace's height is not a power of two, and the AIR does not pad its traces.
+    TraceHeightNotPowerOfTwo { height: usize },
+    /// The committed preprocessed trace's height does not match the trace's height.
+    PreprocessedHeightMismatch { expected: usize, actual: usize },
+    /// The number of public values does not match the AIR's.
+    PublicValuesLengthMismatch { expected: usize, actual: usize },
+    /// A periodic column's length is not a power of two dividing the trace's height.
+    PeriodicColumnLength { length: usize, height: usize },
+    /// The quotient domain, of size `2^log_size`, is larger than any domain of the PCS, e.g.
+    /// because the constraint degree is too high for the field's two-adicity.
+    QuotientDomainTooLarge {
+        log_size: usize,
+        max_log_size: usize,
+    },
+    /// The AIR has a nonzero permutation width, but did not generate a permutation trace.
+    MissingPermutationTrace,
+    /// The AIR's permutation trace does not have its permutation width and the main trace's
+    /// height.
+    PermutationTraceShapeMismatch {
+        expected_width: usize,
+        expected_height: usize,
+        width: usize,
+        height: usize,
+    },
+    /// The PCS could not commit to a trace.
+    Commit(CommitError),
+}
+
 #[instrument(skip_all)]
 #[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
 pub fn prove<
     SC,
-    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>>>,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
     #[cfg(not(debug_assertions))] A,
 >(
     config: &SC,
@@ -32,111 +69,364 @@ pub fn prove<
 ) -> Proof<SC>
 where
     SC: StarkGenericConfig,
-    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<ProverConstraintFolder<'a, SC>>,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    prove_with_preprocessed(config, air, challenger, trace, public_values, None)
+}
+
+/// Prove an AIR whose preprocessed trace was committed ahead of time by [`setup_preprocessed`].
+///
+/// Passing `None` for `preprocessed` is equivalent to calling [`prove`].
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_with_preprocessed<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    prove_impl(config, air, challenger, trace, public_values, preprocessed)
+        .expect("invalid prover input")
+}
+
+/// Like [`prove`], but returns an error rather than panicking on malformed input.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn try_prove<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+) -> Result<Proof<SC>, ProverError>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>> + Air<SymbolicAirBuilder<Val<SC>>>,
 {
+    try_prove_with_preprocessed(config, air, challenger, trace, public_values, None)
+}
+
+/// Like [`prove_with_preprocessed`], but returns an error rather than panicking on malformed
+/// input.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn try_prove_with_preprocessed<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+) -> Result<Proof<SC>, ProverError>
+where
+    SC: StarkGenericConfig,
+    A: BaseAirWithPublicValues<Val<SC>> + Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    if public_values.len() != air.num_public_values() {
+        return Err(ProverError::PublicValuesLengthMismatch {
+            expected: air.num_public_values(),
+            actual: public_values.len(),
+        });
+    }
+    prove_impl(config, air, challenger, trace, public_values, preprocessed)
+}
+
+/// Append extension field columns to a main trace, flattened to their base field coefficients.
+///
+/// This builds the main trace expected by [`prove`] for an AIR with
+/// [`ext_width`](p3_air::BaseAir::ext_width) extension field columns.
+pub fn append_ext_columns<F: Field, EF: ExtensionField<F>>(
+    trace: RowMajorMatrix<F>,
+    ext_trace: &RowMajorMatrix<EF>,
+) -> RowMajorMatrix<F> {
+    HorizontalPair::new(trace, ext_trace.flatten_to_base()).to_row_major_matrix()
+}
+
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+fn prove_impl<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    mut trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+) -> Result<Proof<SC>, ProverError>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>>,
+{
+    let main_width = main_trace_width::<SC, _>(air);
+    if trace.width() != main_width {
+        return Err(ProverError::TraceWidthMismatch {
+            expected: main_width,
+            actual: trace.width(),
+        });
+    }
     #[cfg(debug_assertions)]
-    crate::check_constraints::check_constraints(air, &trace, public_values);
+    let unpadded_height = trace.height();
+    if let Some(padding) = air.trace_padding() {
+        padding.pad(&mut trace);
+    }
 
     let degree = trace.height();
+    if !degree.is_power_of_two() {
+        return Err(ProverError::TraceHeightNotPowerOfTwo { height: degree });
+    }
     let log_degree = log2_strict_usize(degree);
 
-    let symbolic_constraints = get_symbolic_constraints::<Val<SC>, A>(air, 0, public_values.len());
+    let periodic_columns = air.periodic_columns();
+    if let Some(column) = periodic_columns
+        .iter()
+        .find(|c| !c.len().is_power_of_two() || c.len() > degree)
+    {
+        return Err(ProverError::PeriodicColumnLength {
+            length: column.len(),
+            height: degree,
+        });
+    }
+
+    let preprocessed_width = preprocessed.map_or(0, |p| p.width);
+    if let Some(preprocessed) = preprocessed.filter(|p| p.degree_bits != log_degree) {
+        return Err(ProverError::PreprocessedHeightMismatch {
+            expected: degree,
+            actual: 1 << preprocessed.degree_bits,
+        });
+    }
+
+    let symbolic_constraints =
+        get_symbolic_constraints::<Val<SC>, A>(air, preprocessed_width, public_values.len());
     let constraint_count = symbolic_constraints.len();
+    let program = ConstraintProgram::compile(&symbolic_constraints);
     let constraint_degree = symbolic_constraints
         .iter()
         .map(SymbolicExpression::degree_multiple)
         .max()
         .unwrap_or(0);
-    let log_quotient_degree = log2_ceil_usize(constraint_degree - 1);
-    let quotient_degree = 1 << log_quotient_degree;
+    // See `get_log_quotient_degree`; in zero-knowledge mode the quotient has twice as many chunks.
+    let is_zk = is_zk::<SC>() as usize;
+    let log_quotient_degree = log2_ceil_usize((constraint_degree + is_zk).max(2) - 1);
+    let quotient_degree = 1 << (log_quotient_degree + is_zk);
+    let log_quotient_size = log_degree + log_quotient_degree + is_zk;
+    if log_quotient_size > Domain::<SC>::MAX_LOG_SIZE {
+        return Err(ProverError::QuotientDomainTooLarge {
+            log_size: log_quotient_size,
+            max_log_size: Domain::<SC>::MAX_LOG_SIZE,
+        });
+    }
 
     let pcs = config.pcs();
     let trace_domain = pcs.natural_domain_for_degree(degree);
 
-    let (trace_commit, trace_data) =
-        info_span!("commit to trace data").in_scope(|| pcs.commit(vec![(trace_domain, trace)]));
+    let permutation_width = air.permutation_width();
+    // The main trace is consumed by its commitment, but the permutation trace is generated from it
+    // (and checked against it in debug builds) after the commitment is observed.
+    let main_trace = (permutation_width > 0).then(|| trace.clone());
+
+    #[cfg(debug_assertions)]
+    if main_trace.is_none() {
+        crate::check_constraints::assert_constraints::<_, SC::Challenge, _>(
+            air,
+            preprocessed.and_then(|_| air.preprocessed_trace()).as_ref(),
+            &trace,
+            None,
+            &[],
+            public_values,
+            unpadded_height,
+        );
+    }
+
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.try_commit(vec![(trace_domain, trace)]))
+        .map_err(ProverError::Commit)?;
 
     // Observe the instance.
     // degree < 2^255 so we can safely cast log_degree to a u8.
     challenger.observe(Val::<SC>::from_u8(log_degree as u8));
-    // TODO: Might be best practice to include other instance data here; see verifier comment.
+    observe_config(config, challenger);
+    let fingerprint = air
+        .fingerprint()
+        .unwrap_or_else(|| encode_symbolic_constraints(&symbolic_constraints));
+    observe_air::<SC>(
+        challenger,
+        main_width,
+        preprocessed_width,
+        permutation_width,
+        air.window_size(),
+        &periodic_columns,
+        &fingerprint,
+    );
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     challenger.observe(trace_commit.clone());
     challenger.observe_slice(public_values);
+
+    let mut permutation_challenges = vec![];
+    let mut permutation = None;
+    if let Some(main_trace) = main_trace {
+        permutation_challenges = (0..air.num_permutation_challenges())
+            .map(|_| challenger.sample_algebra_element())
+            .collect_vec();
+        let permutation_trace = info_span!("generate permutation trace")
+            .in_scope(|| air.permutation_trace(&main_trace, &permutation_challenges))
+            .ok_or(ProverError::MissingPermutationTrace)?;
+        if permutation_trace.width() != permutation_width || permutation_trace.height() != degree {
+            return Err(ProverError::PermutationTraceShapeMismatch {
+                expected_width: permutation_width,
+                expected_height: degree,
+                width: permutation_trace.width(),
+                height: permutation_trace.height(),
+            });
+        }
+
+        #[cfg(debug_assertions)]
+        crate::check_constraints::assert_constraints(
+            air,
+            preprocessed.and_then(|_| air.preprocessed_trace()).as_ref(),
+            &main_trace,
+            Some(&permutation_trace),
+            &permutation_challenges,
+            public_values,
+            unpadded_height,
+        );
+
+        let (permutation_commit, permutation_data) = info_span!("commit to permutation trace")
+            .in_scope(|| pcs.try_commit(vec![(trace_domain, permutation_trace.flatten_to_base())]))
+            .map_err(ProverError::Commit)?;
+        challenger.observe(permutation_commit.clone());
+        permutation = Some((permutation_commit, permutation_data));
+    }
+
     let alpha: SC::Challenge = challenger.sample_algebra_element();
 
-    let quotient_domain =
-        trace_domain.create_disjoint_domain(1 << (log_degree + log_quotient_degree));
+    let quotient_domain = trace_domain.create_disjoint_domain(1 << log_quotient_size);
 
     let trace_on_quotient_domain = pcs.get_evaluations_on_domain(&trace_data, 0, quotient_domain);
+    let preprocessed_on_quotient_domain =
+        preprocessed.map(|p| pcs.get_evaluations_on_domain(&p.prover_data, 0, quotient_domain));
+    let permutation_on_quotient_domain = permutation
+        .as_ref()
+        .map(|(_, data)| pcs.get_evaluations_on_domain(data, 0, quotient_domain));
 
     let quotient_values = quotient_values(
-        air,
+        |folder: &mut ProverConstraintFolder<'_, SC>| folder.eval_program(&program),
         public_values,
+        &periodic_columns,
+        air.ext_width(),
         trace_domain,
         quotient_domain,
+        preprocessed_on_quotient_domain,
         trace_on_quotient_domain,
+        permutation_on_quotient_domain,
+        &permutation_challenges,
+        air.window_size(),
         alpha,
         constraint_count,
     );
     let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
-    let quotient_chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
-    let qc_domains = quotient_domain.split_domains(quotient_degree);
-
     let (quotient_commit, quotient_data) = info_span!("commit to quotient poly chunks")
-        .in_scope(|| pcs.commit(izip!(qc_domains, quotient_chunks).collect_vec()));
+        .in_scope(|| pcs.commit_quotients(vec![(quotient_domain, quotient_flat, quotient_degree)]));
     challenger.observe(quotient_commit.clone());
 
+    let (permutation_commit, permutation_data) = permutation.unzip();
     let commitments = Commitments {
         trace: trace_commit,
+        permutation: permutation_commit,
         quotient_chunks: quotient_commit,
     };
 
     let zeta: SC::Challenge = challenger.sample();
-    let zeta_next = trace_domain.next_point(zeta).unwrap();
+    let window_size = air.window_size();
+    let points = window_points::<SC>(trace_domain, zeta, window_size);
 
     let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
-        pcs.open(
-            vec![
-                (&trace_data, vec![vec![zeta, zeta_next]]),
-                (
-                    &quotient_data,
-                    // open every chunk at zeta
-                    (0..quotient_degree).map(|_| vec![zeta]).collect_vec(),
-                ),
-            ],
-            challenger,
-        )
+        let mut rounds = vec![
+            (&trace_data, vec![points.clone()]),
+            (
+                &quotient_data,
+                // open every chunk at zeta
+                (0..quotient_degree).map(|_| vec![zeta]).collect_vec(),
+            ),
+        ];
+        if let Some(preprocessed) = preprocessed {
+            rounds.push((&preprocessed.prover_data, vec![points.clone()]));
+        }
+        if let Some(permutation_data) = &permutation_data {
+            rounds.push((permutation_data, vec![points.clone()]));
+        }
+        pcs.open(rounds, challenger)
     });
-    let trace_local = opened_values[0][0][0].clone();
-    let trace_next = opened_values[0][0][1].clone();
+    let trace_window = opened_values[0][0].clone();
     let quotient_chunks = opened_values[1].iter().map(|v| v[0].clone()).collect_vec();
+    // The optional preprocessed and permutation rounds follow, in that order.
+    let mut optional_rounds = opened_values[2..].iter();
+    let mut window = |present: bool| {
+        present
+            .then(|| optional_rounds.next().unwrap())
+            .map_or(vec![vec![]; window_size], |round| round[0].clone())
+    };
+    let preprocessed_window = window(preprocessed.is_some());
+    let permutation_window = window(permutation_data.is_some());
     let opened_values = OpenedValues {
-        trace_local,
-        trace_next,
+        preprocessed_window,
+        trace_window,
+        permutation_window,
         quotient_chunks,
     };
-    Proof {
+    Ok(Proof {
         commitments,
         opened_values,
         opening_proof,
         degree_bits: log_degree,
-    }
+    })
 }
 
+/// Compute the quotient of the constraints enforced by `eval` over `quotient_domain`.
+///
+/// The permutation trace, if given, is expected in its flattened base field form.
 #[instrument(name = "compute quotient polynomial", skip_all)]
-fn quotient_values<SC, A, Mat>(
-    air: &A,
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn quotient_values<SC, E, Mat>(
+    eval: E,
     public_values: &Vec<Val<SC>>,
+    periodic_columns: &[Vec<Val<SC>>],
+    ext_width: usize,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
+    preprocessed_on_quotient_domain: Option<Mat>,
     trace_on_quotient_domain: Mat,
+    permutation_on_quotient_domain: Option<Mat>,
+    permutation_challenges: &[SC::Challenge],
+    window_size: usize,
     alpha: SC::Challenge,
     constraint_count: usize,
 ) -> Vec<SC::Challenge>
 where
     SC: StarkGenericConfig,
-    A: for<'a> Air<ProverConstraintFolder<'a, SC>>,
+    E: for<'a> Fn(&mut ProverConstraintFolder<'a, SC>) + Sync,
     Mat: Matrix<Val<SC>> + Sync,
 {
     let quotient_size = quotient_domain.size();
@@ -147,12 +437,30 @@ where
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
+    // The transition selector for a window of size s must vanish on the last s - 1 rows, so we
+    // take the product of the selector for a window of 2 at the first s - 1 rows of the window.
+    let mut is_transition_windows = (0..window_size.saturating_sub(1))
+        .scan(vec![Val::<SC>::ONE; quotient_size], |selector, k| {
+            for (i, s) in selector.iter_mut().enumerate() {
+                *s *= sels.is_transition[(i + k * next_step) % quotient_size];
+            }
+            Some(selector.clone())
+        })
+        .collect_vec();
+
+    let mut periodic_values = periodic_columns
+        .iter()
+        .map(|column| trace_domain.periodic_column_on_coset(column, quotient_domain))
+        .collect_vec();
+
     // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
     // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
     for _ in quotient_size..PackedVal::<SC>::WIDTH {
         sels.is_first_row.push(Val::<SC>::default());
         sels.is_last_row.push(Val::<SC>::default());
-        sels.is_transition.push(Val::<SC>::default());
+        for selector in is_transition_windows.iter_mut().chain(&mut periodic_values) {
+            selector.push(Val::<SC>::default());
+        }
         sels.inv_vanishing.push(Val::<SC>::default());
     }
 
@@ -168,46 +476,298 @@ where
                 .collect()
         })
         .collect();
+    let permutation_challenges = permutation_challenges
+        .iter()
+        .map(|&c| PackedChallenge::<SC>::from(c))
+        .collect_vec();
 
-    (0..quotient_size)
-        .into_par_iter()
-        .step_by(PackedVal::<SC>::WIDTH)
-        .flat_map_iter(|i_start| {
-            let i_range = i_start..i_start + PackedVal::<SC>::WIDTH;
+    let packed_width = PackedVal::<SC>::WIDTH;
+    let num_packed_rows = quotient_size.div_ceil(packed_width);
+    let packed_rows_per_chunk = num_packed_rows.div_ceil(16 * current_num_threads());
 
-            let is_first_row = *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
-            let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
-            let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
-            let inv_vanishing = *PackedVal::<SC>::from_slice(&sels.inv_vanishing[i_range]);
+    let mut quotient_values = SC::Challenge::zero_vec(quotient_size);
+    quotient_values
+        .par_chunks_mut(packed_rows_per_chunk * packed_width)
+        .enumerate()
+        .for_each(|(chunk_index, chunk)| {
+            // The windows of each packed row, in buffers which are reused for every row of the chunk.
+            let mut main_window = Vec::new();
+            let mut main_base = Vec::new();
+            let mut main_ext = Vec::new();
+            let mut preprocessed_window = Vec::new();
+            let mut permutation_window = Vec::new();
+            let mut permutation_ext = Vec::new();
+            let mut packed_is_transition_windows = Vec::with_capacity(is_transition_windows.len());
+            let mut packed_periodic_values = Vec::with_capacity(periodic_values.len());
+            let mut program_base = Vec::new();
+            let mut program_ext = Vec::new();
 
-            let main = RowMajorMatrix::new(
-                trace_on_quotient_domain.vertically_packed_row_pair(i_start, next_step),
-                width,
+            for (j, quotient_values) in chunk.chunks_mut(packed_width).enumerate() {
+                let i_start = (chunk_index * packed_rows_per_chunk + j) * packed_width;
+                let i_range = i_start..i_start + packed_width;
+
+                let is_first_row =
+                    *PackedVal::<SC>::from_slice(&sels.is_first_row[i_range.clone()]);
+                let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
+                packed_is_transition_windows.clear();
+                packed_is_transition_windows.extend(
+                    is_transition_windows
+                        .iter()
+                        .map(|selector| *PackedVal::<SC>::from_slice(&selector[i_range.clone()])),
+                );
+                packed_periodic_values.clear();
+                packed_periodic_values.extend(
+                    periodic_values
+                        .iter()
+                        .map(|column| *PackedVal::<SC>::from_slice(&column[i_range.clone()])),
+                );
+                let inv_vanishing = *PackedVal::<SC>::from_slice(&sels.inv_vanishing[i_range]);
+
+                trace_on_quotient_domain.vertically_packed_row_window_into(
+                    i_start,
+                    next_step,
+                    window_size,
+                    &mut main_window,
+                );
+                let main = split_main_window::<SC>(
+                    &main_window,
+                    width,
+                    ext_width,
+                    &mut main_base,
+                    &mut main_ext,
+                );
+                let preprocessed = if let Some(p) = &preprocessed_on_quotient_domain {
+                    p.vertically_packed_row_window_into(
+                        i_start,
+                        next_step,
+                        window_size,
+                        &mut preprocessed_window,
+                    );
+                    RowMajorMatrixView::new(&preprocessed_window, p.width())
+                } else {
+                    RowMajorMatrixView::new(&[], 0)
+                };
+                let permutation = if let Some(p) = &permutation_on_quotient_domain {
+                    p.vertically_packed_row_window_into(
+                        i_start,
+                        next_step,
+                        window_size,
+                        &mut permutation_window,
+                    );
+                    permutation_ext.clear();
+                    permutation_ext.extend(
+                        permutation_window
+                            .chunks_exact(SC::Challenge::DIMENSION)
+                            .map(|coeffs| {
+                                PackedChallenge::<SC>::from_basis_coefficients_fn(|k| coeffs[k])
+                            }),
+                    );
+                    RowMajorMatrixView::new(&permutation_ext, p.width() / SC::Challenge::DIMENSION)
+                } else {
+                    RowMajorMatrixView::new(&[], 0)
+                };
+
+                let accumulator = PackedChallenge::<SC>::ZERO;
+                let mut folder = ProverConstraintFolder {
+                    preprocessed,
+                    main: RowMajorMatrixView::new(
+                        main,
+                        width - ext_width * SC::Challenge::DIMENSION,
+                    ),
+                    main_ext: RowMajorMatrixView::new(&main_ext, ext_width),
+                    permutation,
+                    permutation_challenges: &permutation_challenges,
+                    public_values,
+                    periodic_values: &packed_periodic_values,
+                    is_first_row,
+                    is_last_row,
+                    is_transition_windows: &packed_is_transition_windows,
+                    alpha_powers: &alpha_powers,
+                    decomposed_alpha_powers: &decomposed_alpha_powers,
+                    accumulator,
+                    constraint_index: 0,
+                    program_base: &mut program_base,
+                    program_ext: &mut program_ext,
+                };
+                eval(&mut folder);
+
+                // quotient(x) = constraints(x) / Z_H(x)
+                let quotient = folder.accumulator * inv_vanishing;
+
+                // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
+                for (idx_in_packing, value) in quotient_values.iter_mut().enumerate() {
+                    *value = SC::Challenge::from_basis_coefficients_fn(|coeff_idx| {
+                        quotient.as_basis_coefficients_slice()[coeff_idx].as_slice()[idx_in_packing]
+                    });
+                }
+            }
+        });
+    quotient_values
+}
+
+/// Split a window of packed main trace rows of the given `width` into its base field columns, and
+/// its last `ext_width` extension field columns, recombined from their coefficients into `ext`.
+///
+/// Returns the base field columns, which are either `values` itself or written to `base`.
+fn split_main_window<'a, SC: StarkGenericConfig>(
+    values: &'a [PackedVal<SC>],
+    width: usize,
+    ext_width: usize,
+    base: &'a mut Vec<PackedVal<SC>>,
+    ext: &mut Vec<PackedChallenge<SC>>,
+) -> &'a [PackedVal<SC>] {
+    ext.clear();
+    if ext_width == 0 {
+        return values;
+    }
+    let base_width = width - ext_width * SC::Challenge::DIMENSION;
+    base.clear();
+    for row in values.chunks_exact(width) {
+        let (base_row, ext_row) = row.split_at(base_width);
+        base.extend_from_slice(base_row);
+        ext.extend(
+            ext_row
+                .chunks_exact(SC::Challenge::DIMENSION)
+                .map(|coeffs| PackedChallenge::<SC>::from_basis_coefficients_fn(|k| coeffs[k])),
+        );
+    }
+    base
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_air::{AirBuilder, BaseAir};
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_challenger::DuplexChallenger;
+    use p3_commit::ExtensionMmcs;
+    use p3_dft::Radix2DitParallel;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_field::{Field, PrimeField64};
+    use p3_fri::{HidingFriPcs, create_test_fri_config};
+    use p3_merkle_tree::MerkleTreeHidingMmcs;
+    use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+    use rand::SeedableRng;
+    use rand::rngs::SmallRng;
+
+    use super::*;
+    use crate::StarkConfig;
+
+    type F = BabyBear;
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type ValMmcs = MerkleTreeHidingMmcs<
+        <F as Field>::Packing,
+        <F as Field>::Packing,
+        MyHash,
+        MyCompress,
+        SmallRng,
+        8,
+        4,
+    >;
+    type Challenge = BinomialExtensionField<F, 4>;
+    type ChallengeMmcs = ExtensionMmcs<F, Challenge, ValMmcs>;
+    type Challenger = DuplexChallenger<F, Perm, 16, 8>;
+    type Dft = Radix2DitParallel<F>;
+    type MyPcs = HidingFriPcs<F, Dft, ValMmcs, ChallengeMmcs, SmallRng>;
+    type MyConfig = StarkConfig<MyPcs, Challenge, Challenger>;
+
+    /// A single column of booleans.
+    struct BoolAir;
+
+    impl<F> BaseAir<F> for BoolAir {
+        fn width(&self) -> usize {
+            1
+        }
+    }
+
+    impl<AB: AirBuilder> Air<AB> for BoolAir {
+        fn eval(&self, builder: &mut AB) {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            builder.assert_bool(local[0]);
+        }
+    }
+
+    const NUM_BUCKETS: usize = 8;
+
+    /// Prove `trace` `num_runs` times with fresh prover randomness, and count how many base field
+    /// coordinates of the opened trace and quotient values fall into each of `NUM_BUCKETS` equal
+    /// ranges of the field.
+    fn opened_values_histogram(trace: &RowMajorMatrix<F>, num_runs: u64) -> [usize; NUM_BUCKETS] {
+        let mut rng = SmallRng::seed_from_u64(1);
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let mut histogram = [0; NUM_BUCKETS];
+        for run in 0..num_runs {
+            let hash = MyHash::new(perm.clone());
+            let compress = MyCompress::new(perm.clone());
+            let val_mmcs = ValMmcs::new(hash, compress, SmallRng::seed_from_u64(2 * run));
+            let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+            let fri_config = create_test_fri_config(challenge_mmcs, 1);
+            let pcs = MyPcs::new(
+                Dft::default(),
+                val_mmcs,
+                fri_config,
+                4,
+                SmallRng::seed_from_u64(2 * run + 1),
             );
+            let config = MyConfig::new(pcs);
+            let mut challenger = Challenger::new(perm.clone());
+            let proof = prove(&config, &BoolAir, &mut challenger, trace.clone(), &vec![]);
 
-            let accumulator = PackedChallenge::<SC>::ZERO;
-            let mut folder = ProverConstraintFolder {
-                main: main.as_view(),
-                public_values,
-                is_first_row,
-                is_last_row,
-                is_transition,
-                alpha_powers: &alpha_powers,
-                decomposed_alpha_powers: &decomposed_alpha_powers,
-                accumulator,
-                constraint_index: 0,
-            };
-            air.eval(&mut folder);
-
-            // quotient(x) = constraints(x) / Z_H(x)
-            let quotient = folder.accumulator * inv_vanishing;
-
-            // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
-            (0..core::cmp::min(quotient_size, PackedVal::<SC>::WIDTH)).map(move |idx_in_packing| {
-                SC::Challenge::from_basis_coefficients_fn(|coeff_idx| {
-                    quotient.as_basis_coefficients_slice()[coeff_idx].as_slice()[idx_in_packing]
-                })
-            })
-        })
-        .collect()
+            let opened_values = proof.opened_values;
+            opened_values
+                .trace_window
+                .iter()
+                .flatten()
+                .chain(opened_values.quotient_chunks.iter().flatten())
+                .flat_map(BasedVectorSpace::<F>::as_basis_coefficients_slice)
+                .for_each(|x: &F| {
+                    let bucket = x.as_canonical_u64() * NUM_BUCKETS as u64 / F::ORDER_U64;
+                    histogram[bucket as usize] += 1;
+                });
+        }
+        histogram
+    }
+
+    /// The chi-squared statistic of `observed` against `expected`.
+    fn chi_squared(observed: &[usize], expected: &[f64]) -> f64 {
+        observed
+            .iter()
+            .zip(expected)
+            .map(|(&o, &e)| (o as f64 - e) * (o as f64 - e) / e)
+            .sum()
+    }
+
+    #[test]
+    fn test_zk_opened_values_independent_of_witness() {
+        let height = 1 << 5;
+        let zeros = RowMajorMatrix::new_col(F::zero_vec(height));
+        let bits = RowMajorMatrix::new_col((0..height).map(|i| F::from_bool(i % 3 == 0)).collect());
+
+        let zeros_histogram = opened_values_histogram(&zeros, 32);
+        let bits_histogram = opened_values_histogram(&bits, 32);
+
+        // With NUM_BUCKETS - 1 = 7 degrees of freedom, a statistic of 30 has a p-value below 1e-4.
+        // Both witnesses should give uniformly distributed openings...
+        for histogram in [zeros_histogram, bits_histogram] {
+            let total = histogram.iter().sum::<usize>() as f64;
+            let uniform = [total / NUM_BUCKETS as f64; NUM_BUCKETS];
+            let statistic = chi_squared(&histogram, &uniform);
+            assert!(statistic < 30.0, "{histogram:?} is not uniform");
+        }
+
+        // ...and so should be indistinguishable from each other.
+        let pooled = zeros_histogram
+            .iter()
+            .zip(&bits_histogram)
+            .map(|(&a, &b)| (a + b) as f64 / 2.0)
+            .collect::<Vec<_>>();
+        let statistic =
+            chi_squared(&zeros_histogram, &pooled) + chi_squared(&bits_histogram, &pooled);
+        assert!(
+            statistic < 30.0,
+            "{zeros_histogram:?} and {bits_histogram:?} differ"
+        );
+    }
 }
@@ -1,7 +1,10 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, PairBuilder};
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, ExtensionMainBuilder,
+    PairBuilder, PeriodicAirBuilder, PermutationAirBuilder,
+};
 use p3_field::Field;
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_ceil_usize;
@@ -16,14 +19,18 @@ pub fn get_log_quotient_degree<F, A>(
     air: &A,
     preprocessed_width: usize,
     num_public_values: usize,
+    is_zk: usize,
 ) -> usize
 where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
+    // In zero-knowledge mode, trace polynomials are randomized to degree 2n rather than n, so the
+    // quotient's degree is approximately (2 max_constraint_degree - 1) n. Callers then double the
+    // number of quotient chunks, and padding the constraint degree by one leaves room for this.
     // We pad to at least degree 2, since a quotient argument doesn't make sense with smaller degrees.
     let constraint_degree =
-        get_max_constraint_degree(air, preprocessed_width, num_public_values).max(2);
+        (get_max_constraint_degree(air, preprocessed_width, num_public_values) + is_zk).max(2);
 
     // The quotient's actual degree is approximately (max_constraint_degree - 1) n,
     // where subtracting 1 comes from division by the vanishing polynomial.
@@ -58,7 +65,16 @@ where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
-    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values);
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed_width,
+        air.width(),
+        air.ext_width(),
+        air.window_size(),
+        air.permutation_width(),
+        air.num_permutation_challenges(),
+        num_public_values,
+        air.periodic_columns().len(),
+    );
     air.eval(&mut builder);
     builder.constraints()
 }
@@ -68,32 +84,68 @@ where
 pub struct SymbolicAirBuilder<F: Field> {
     preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
+    main_ext: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation: RowMajorMatrix<SymbolicVariable<F>>,
+    permutation_challenges: Vec<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
+    periodic_values: Vec<SymbolicVariable<F>>,
+    window_size: usize,
     constraints: Vec<SymbolicExpression<F>>,
 }
 
 impl<F: Field> SymbolicAirBuilder<F> {
-    pub(crate) fn new(preprocessed_width: usize, width: usize, num_public_values: usize) -> Self {
-        let prep_values = [0, 1]
-            .into_iter()
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        preprocessed_width: usize,
+        width: usize,
+        ext_width: usize,
+        window_size: usize,
+        permutation_width: usize,
+        num_permutation_challenges: usize,
+        num_public_values: usize,
+        num_periodic_columns: usize,
+    ) -> Self {
+        let prep_values = (0..window_size)
             .flat_map(|offset| {
                 (0..preprocessed_width)
                     .map(move |index| SymbolicVariable::new(Entry::Preprocessed { offset }, index))
             })
             .collect();
-        let main_values = [0, 1]
-            .into_iter()
+        let main_values = (0..window_size)
             .flat_map(|offset| {
                 (0..width).map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
             })
             .collect();
+        let main_ext_values = (0..window_size)
+            .flat_map(|offset| {
+                (0..ext_width)
+                    .map(move |index| SymbolicVariable::new(Entry::MainExt { offset }, index))
+            })
+            .collect();
+        let permutation_values = (0..window_size)
+            .flat_map(|offset| {
+                (0..permutation_width)
+                    .map(move |index| SymbolicVariable::new(Entry::Permutation { offset }, index))
+            })
+            .collect();
+        let permutation_challenges = (0..num_permutation_challenges)
+            .map(|index| SymbolicVariable::new(Entry::Challenge, index))
+            .collect();
         let public_values = (0..num_public_values)
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
+        let periodic_values = (0..num_periodic_columns)
+            .map(move |index| SymbolicVariable::new(Entry::Periodic, index))
+            .collect();
         Self {
             preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
             main: RowMajorMatrix::new(main_values, width),
+            main_ext: RowMajorMatrix::new(main_ext_values, ext_width),
+            permutation: RowMajorMatrix::new(permutation_values, permutation_width),
+            permutation_challenges,
             public_values,
+            periodic_values,
+            window_size,
             constraints: vec![],
         }
     }
@@ -122,12 +174,17 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     /// # Panics
-    /// This function panics if `size` is not `2`.
+    /// This function panics if `size` is not between `2` and the AIR's window size.
     fn is_transition_window(&self, size: usize) -> Self::Expr {
+        assert!(
+            (2..=self.window_size).contains(&size),
+            "window size must be between 2 and {}",
+            self.window_size
+        );
         if size == 2 {
             SymbolicExpression::IsTransition
         } else {
-            panic!("uni-stark only supports a window size of 2")
+            SymbolicExpression::IsTransitionWindow(size)
         }
     }
 
@@ -143,12 +200,55 @@ impl<F: Field> AirBuilderWithPublicValues for SymbolicAirBuilder<F> {
     }
 }
 
+impl<F: Field> PeriodicAirBuilder for SymbolicAirBuilder<F> {
+    type PeriodicVar = SymbolicVariable<F>;
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
 impl<F: Field> PairBuilder for SymbolicAirBuilder<F> {
     fn preprocessed(&self) -> Self::M {
         self.preprocessed.clone()
     }
 }
 
+/// Extension field constraints are recorded over the base field, which is enough to infer their
+/// degree; the challenge field itself plays no role in the symbolic representation.
+impl<F: Field> ExtensionBuilder for SymbolicAirBuilder<F> {
+    type EF = F;
+    type ExprEF = SymbolicExpression<F>;
+    type VarEF = SymbolicVariable<F>;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.constraints.push(x.into());
+    }
+}
+
+impl<F: Field> ExtensionMainBuilder for SymbolicAirBuilder<F> {
+    type MEF = RowMajorMatrix<Self::VarEF>;
+
+    fn main_ext(&self) -> Self::MEF {
+        self.main_ext.clone()
+    }
+}
+
+impl<F: Field> PermutationAirBuilder for SymbolicAirBuilder<F> {
+    type MP = RowMajorMatrix<Self::VarEF>;
+    type RandomVar = SymbolicVariable<F>;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation.clone()
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        &self.permutation_challenges
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use alloc::vec;
@@ -156,6 +256,7 @@ mod tests {
 
     use p3_air::BaseAir;
     use p3_baby_bear::BabyBear;
+    use p3_matrix::Matrix;
 
     use super::*;
 
@@ -185,7 +286,7 @@ mod tests {
             constraints: vec![],
             width: 4,
         };
-        let log_degree = get_log_quotient_degree(&air, 3, 2);
+        let log_degree = get_log_quotient_degree(&air, 3, 2, 0);
         assert_eq!(log_degree, 0);
     }
 
@@ -195,7 +296,7 @@ mod tests {
             constraints: vec![SymbolicVariable::new(Entry::Main { offset: 0 }, 0)],
             width: 4,
         };
-        let log_degree = get_log_quotient_degree(&air, 3, 2);
+        let log_degree = get_log_quotient_degree(&air, 3, 2, 0);
         assert_eq!(log_degree, log2_ceil_usize(1));
     }
 
@@ -209,7 +310,7 @@ mod tests {
             ],
             width: 4,
         };
-        let log_degree = get_log_quotient_degree(&air, 3, 2);
+        let log_degree = get_log_quotient_degree(&air, 3, 2, 0);
         assert_eq!(log_degree, log2_ceil_usize(1));
     }
 
@@ -269,7 +370,7 @@ mod tests {
 
     #[test]
     fn test_symbolic_air_builder_initialization() {
-        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 3);
+        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 0, 2, 0, 0, 3, 0);
 
         let expected_main = [
             SymbolicVariable::<BabyBear>::new(Entry::Main { offset: 0 }, 0),
@@ -298,7 +399,7 @@ mod tests {
 
     #[test]
     fn test_symbolic_air_builder_is_first_last_row() {
-        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 3);
+        let builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 0, 2, 0, 0, 3, 0);
 
         assert!(
             matches!(builder.is_first_row(), SymbolicExpression::IsFirstRow),
@@ -313,7 +414,7 @@ mod tests {
 
     #[test]
     fn test_symbolic_air_builder_assert_zero() {
-        let mut builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 3);
+        let mut builder = SymbolicAirBuilder::<BabyBear>::new(2, 4, 0, 2, 0, 0, 3, 0);
         let expr = SymbolicExpression::Constant(BabyBear::new(5));
         builder.assert_zero(expr.clone());
 
@@ -327,4 +428,23 @@ mod tests {
             "Constraint should match the asserted one"
         );
     }
+
+    #[test]
+    fn test_symbolic_air_builder_permutation() {
+        let mut builder = SymbolicAirBuilder::<BabyBear>::new(0, 1, 0, 2, 2, 3, 0, 0);
+
+        let permutation = builder.permutation();
+        assert_eq!(permutation.width(), 2);
+        assert_eq!(permutation.height(), 2);
+        let next = permutation.row_slice(1);
+        assert_eq!(next[1].entry, Entry::Permutation { offset: 1 });
+        assert_eq!(next[1].index, 1);
+        assert_eq!(builder.permutation_randomness().len(), 3);
+
+        let challenge = builder.permutation_randomness()[2];
+        builder.assert_zero_ext(next[0] * challenge);
+        let constraints = builder.constraints();
+        assert_eq!(constraints.len(), 1);
+        assert_eq!(constraints[0].degree_multiple(), 1);
+    }
 }
@@ -1,5 +1,5 @@
 use alloc::rc::Rc;
-use core::fmt::Debug;
+use core::fmt::{Debug, Display, Formatter};
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
@@ -14,6 +14,9 @@ pub enum SymbolicExpression<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    /// The selector for rows which have `size - 1` rows after them, for a window `size` larger
+    /// than 2. [`IsTransition`](Self::IsTransition) is the selector for a window of 2.
+    IsTransitionWindow(usize),
     Constant(F),
     Add {
         x: Rc<Self>,
@@ -42,6 +45,9 @@ impl<F> SymbolicExpression<F> {
         match self {
             Self::Variable(v) => v.degree_multiple(),
             Self::IsFirstRow | Self::IsLastRow => 1,
+            // The product of `size - 1` transition selectors. On some domains, such as circle domains,
+            // these are not linear, so we count each one like a row selector.
+            Self::IsTransitionWindow(size) => *size - 1,
             Self::IsTransition | Self::Constant(_) => 0,
             Self::Add {
                 degree_multiple, ..
@@ -59,6 +65,26 @@ impl<F> SymbolicExpression<F> {
     }
 }
 
+/// Renders the expression as a fully parenthesized formula, e.g. `((main[0] * main'[1]) - 1)`.
+///
+/// Subexpressions shared through an `Rc` are rendered at every use.
+impl<F: Display> Display for SymbolicExpression<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Self::Variable(v) => write!(f, "{v}"),
+            Self::IsFirstRow => write!(f, "is_first_row"),
+            Self::IsLastRow => write!(f, "is_last_row"),
+            Self::IsTransition => write!(f, "is_transition"),
+            Self::IsTransitionWindow(size) => write!(f, "is_transition_window({size})"),
+            Self::Constant(c) => write!(f, "{c}"),
+            Self::Add { x, y, .. } => write!(f, "({x} + {y})"),
+            Self::Sub { x, y, .. } => write!(f, "({x} - {y})"),
+            Self::Neg { x, .. } => write!(f, "-{x}"),
+            Self::Mul { x, y, .. } => write!(f, "({x} * {y})"),
+        }
+    }
+}
+
 impl<F: Field> Default for SymbolicExpression<F> {
     fn default() -> Self {
         Self::Constant(F::ZERO)
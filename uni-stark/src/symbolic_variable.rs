@@ -1,3 +1,4 @@
+use core::fmt::{Display, Formatter};
 use core::marker::PhantomData;
 use core::ops::{Add, Mul, Sub};
 
@@ -9,9 +10,11 @@ use crate::symbolic_expression::SymbolicExpression;
 pub enum Entry {
     Preprocessed { offset: usize },
     Main { offset: usize },
+    MainExt { offset: usize },
     Permutation { offset: usize },
     Public,
     Challenge,
+    Periodic,
 }
 
 /// A variable within the evaluation window, i.e. a column in either the local or next row.
@@ -33,12 +36,38 @@ impl<F> SymbolicVariable<F> {
 
     pub const fn degree_multiple(&self) -> usize {
         match self.entry {
-            Entry::Preprocessed { .. } | Entry::Main { .. } | Entry::Permutation { .. } => 1,
+            // Periodic columns are polynomials of degree less than the trace height.
+            Entry::Preprocessed { .. }
+            | Entry::Main { .. }
+            | Entry::MainExt { .. }
+            | Entry::Permutation { .. }
+            | Entry::Periodic => 1,
             Entry::Public | Entry::Challenge => 0,
         }
     }
 }
 
+/// Renders the variable as e.g. `main[3]`, with one prime per row offset, so `main''[3]` is column
+/// 3 two rows down.
+impl<F> Display for SymbolicVariable<F> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        let (name, offset) = match self.entry {
+            Entry::Preprocessed { offset } => ("preprocessed", offset),
+            Entry::Main { offset } => ("main", offset),
+            Entry::MainExt { offset } => ("main_ext", offset),
+            Entry::Permutation { offset } => ("permutation", offset),
+            Entry::Public => ("public", 0),
+            Entry::Challenge => ("challenge", 0),
+            Entry::Periodic => ("periodic", 0),
+        };
+        write!(f, "{name}")?;
+        for _ in 0..offset {
+            write!(f, "'")?;
+        }
+        write!(f, "[{}]", self.index)
+    }
+}
+
 impl<F: Field> From<SymbolicVariable<F>> for SymbolicExpression<F> {
     fn from(value: SymbolicVariable<F>) -> Self {
         Self::Variable(value)
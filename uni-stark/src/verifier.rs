@@ -1,5 +1,6 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
 use itertools::Itertools;
 use p3_air::{Air, BaseAir};
@@ -7,12 +8,15 @@ use p3_challenger::{CanObserve, CanSample, FieldChallenger};
 use p3_commit::{Pcs, PolynomialSpace};
 use p3_field::{BasedVectorSpace, Field, PrimeCharacteristicRing};
 use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::VerticalPair;
 use p3_util::zip_eq::zip_eq;
 use tracing::instrument;
 
+use crate::fingerprint::{observe_air, observe_config};
 use crate::symbolic_builder::{SymbolicAirBuilder, get_log_quotient_degree};
-use crate::{PcsError, Proof, StarkGenericConfig, Val, VerifierConstraintFolder};
+use crate::{
+    Domain, OpenedValues, PcsError, PreprocessedVerifierKey, Proof, StarkGenericConfig, Val,
+    VerifierConstraintFolder, get_air_fingerprint, is_zk,
+};
 
 #[instrument(skip_all)]
 pub fn verify<SC, A>(
@@ -22,6 +26,26 @@ pub fn verify<SC, A>(
     proof: &Proof<SC>,
     public_values: &Vec<Val<SC>>,
 ) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    verify_with_preprocessed(config, air, challenger, proof, public_values, None)
+}
+
+/// Verify a proof produced by [`prove_with_preprocessed`](crate::prove_with_preprocessed), using
+/// the key returned by [`setup_preprocessed`](crate::setup_preprocessed).
+///
+/// Passing `None` for `preprocessed` is equivalent to calling [`verify`].
+#[instrument(skip_all)]
+pub fn verify_with_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedVerifierKey<SC>>,
+) -> Result<(), VerificationError<PcsError<SC>>>
 where
     SC: StarkGenericConfig,
     A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
@@ -34,71 +58,207 @@ where
     } = proof;
 
     let degree = 1 << degree_bits;
-    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len());
-    let quotient_degree = 1 << log_quotient_degree;
+    let preprocessed_width = preprocessed.map_or(0, |p| p.width);
+    let is_zk = is_zk::<SC>() as usize;
+    let log_quotient_degree =
+        get_log_quotient_degree::<Val<SC>, A>(air, preprocessed_width, public_values.len(), is_zk);
+    let quotient_degree = 1 << (log_quotient_degree + is_zk);
 
     let pcs = config.pcs();
     let trace_domain = pcs.natural_domain_for_degree(degree);
     let quotient_domain =
-        trace_domain.create_disjoint_domain(1 << (degree_bits + log_quotient_degree));
+        trace_domain.create_disjoint_domain(1 << (degree_bits + log_quotient_degree + is_zk));
     let quotient_chunks_domains = quotient_domain.split_domains(quotient_degree);
 
-    let air_width = <A as BaseAir<Val<SC>>>::width(air);
-    let valid_shape = opened_values.trace_local.len() == air_width
-        && opened_values.trace_next.len() == air_width
-        && opened_values.quotient_chunks.len() == quotient_degree
-        && opened_values
-            .quotient_chunks
-            .iter()
-            .all(|qc| qc.len() == <SC::Challenge as BasedVectorSpace<Val<SC>>>::DIMENSION);
+    let air_width = main_trace_width::<SC, _>(air);
+    let ext_width = <A as BaseAir<Val<SC>>>::ext_width(air);
+    let window_size = <A as BaseAir<Val<SC>>>::window_size(air);
+    let permutation_width = <A as BaseAir<Val<SC>>>::permutation_width(air);
+    let periodic_columns = <A as BaseAir<Val<SC>>>::periodic_columns(air);
+    let valid_shape = preprocessed.is_none_or(|p| p.degree_bits == *degree_bits)
+        && has_valid_periodic_columns(&periodic_columns, degree)
+        && commitments.permutation.is_some() == (permutation_width > 0)
+        && has_valid_shape::<Val<SC>, _>(
+            opened_values,
+            preprocessed_width,
+            air_width,
+            permutation_width,
+            window_size,
+            quotient_degree,
+        );
     if !valid_shape {
         return Err(VerificationError::InvalidProofShape);
     }
 
     // Observe the instance.
     challenger.observe(Val::<SC>::from_usize(proof.degree_bits));
-    // TODO: Might be best practice to include other instance data here in the transcript, like some
-    // encoding of the AIR. This protects against transcript collisions between distinct instances.
-    // Practically speaking though, the only related known attack is from failing to include public
-    // values. It's not clear if failing to include other instance data could enable a transcript
-    // collision, since most such changes would completely change the set of satisfying witnesses.
+    // Binding the AIR and the PCS parameters protects against transcript collisions between
+    // distinct instances.
+    observe_config(config, challenger);
+    observe_air::<SC>(
+        challenger,
+        air_width,
+        preprocessed_width,
+        permutation_width,
+        window_size,
+        &periodic_columns,
+        &get_air_fingerprint(air, preprocessed_width, public_values.len()),
+    );
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     challenger.observe(commitments.trace.clone());
     challenger.observe_slice(public_values);
+
+    let mut permutation_challenges = vec![];
+    if let Some(permutation_commit) = &commitments.permutation {
+        permutation_challenges = (0..air.num_permutation_challenges())
+            .map(|_| challenger.sample_algebra_element())
+            .collect_vec();
+        challenger.observe(permutation_commit.clone());
+    }
+
     let alpha: SC::Challenge = challenger.sample_algebra_element();
     challenger.observe(commitments.quotient_chunks.clone());
 
     let zeta: SC::Challenge = challenger.sample();
-    let zeta_next = trace_domain.next_point(zeta).unwrap();
-
-    pcs.verify(
-        vec![
-            (
-                commitments.trace.clone(),
-                vec![(
-                    trace_domain,
-                    vec![
-                        (zeta, opened_values.trace_local.clone()),
-                        (zeta_next, opened_values.trace_next.clone()),
-                    ],
-                )],
-            ),
-            (
-                commitments.quotient_chunks.clone(),
-                zip_eq(
-                    quotient_chunks_domains.iter(),
-                    &opened_values.quotient_chunks,
-                    VerificationError::InvalidProofShape,
-                )?
-                .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
-                .collect_vec(),
-            ),
-        ],
-        opening_proof,
-        challenger,
+    let points = window_points::<SC>(trace_domain, zeta, window_size);
+
+    let mut rounds = vec![
+        (
+            commitments.trace.clone(),
+            vec![(
+                trace_domain,
+                zip_window(&points, &opened_values.trace_window),
+            )],
+        ),
+        (
+            commitments.quotient_chunks.clone(),
+            zip_eq(
+                quotient_chunks_domains.iter(),
+                &opened_values.quotient_chunks,
+                VerificationError::InvalidProofShape,
+            )?
+            .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
+            .collect_vec(),
+        ),
+    ];
+    if let Some(preprocessed) = preprocessed {
+        rounds.push((
+            preprocessed.commitment.clone(),
+            vec![(
+                trace_domain,
+                zip_window(&points, &opened_values.preprocessed_window),
+            )],
+        ));
+    }
+    if let Some(permutation_commit) = &commitments.permutation {
+        rounds.push((
+            permutation_commit.clone(),
+            vec![(
+                trace_domain,
+                zip_window(&points, &opened_values.permutation_window),
+            )],
+        ));
+    }
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    verify_constraints::<SC, _>(
+        |folder: &mut VerifierConstraintFolder<'_, SC>| air.eval(folder),
+        opened_values,
+        ext_width,
+        trace_domain,
+        &quotient_chunks_domains,
+        zeta,
+        alpha,
+        &permutation_challenges,
+        public_values,
+        &periodic_columns,
     )
-    .map_err(VerificationError::InvalidOpeningArgument)?;
+}
+
+/// The number of base field columns in the main trace of `air`, counting each extension field
+/// column as its coefficients.
+pub(crate) fn main_trace_width<SC: StarkGenericConfig, A: BaseAir<Val<SC>>>(air: &A) -> usize {
+    air.width() + air.ext_width() * SC::Challenge::DIMENSION
+}
+
+/// Check that each periodic column's length is a power of two dividing the trace's height.
+pub(crate) fn has_valid_periodic_columns<F>(periodic_columns: &[Vec<F>], degree: usize) -> bool {
+    periodic_columns
+        .iter()
+        .all(|c| c.len().is_power_of_two() && c.len() <= degree)
+}
+
+/// Check that the opened values of a single AIR have the expected shape.
+pub(crate) fn has_valid_shape<F: Field, Challenge: BasedVectorSpace<F>>(
+    opened_values: &OpenedValues<Challenge>,
+    preprocessed_width: usize,
+    air_width: usize,
+    permutation_width: usize,
+    window_size: usize,
+    quotient_degree: usize,
+) -> bool {
+    let has_valid_window = |window: &[Vec<Challenge>], width: usize| {
+        window.len() == window_size && window.iter().all(|row| row.len() == width)
+    };
+    has_valid_window(&opened_values.preprocessed_window, preprocessed_width)
+        && has_valid_window(&opened_values.trace_window, air_width)
+        && has_valid_window(
+            &opened_values.permutation_window,
+            permutation_width * Challenge::DIMENSION,
+        )
+        && opened_values.quotient_chunks.len() == quotient_degree
+        && opened_values
+            .quotient_chunks
+            .iter()
+            .all(|qc| qc.len() == Challenge::DIMENSION)
+}
 
+/// The points `zeta, zeta * g, ..., zeta * g^(window_size - 1)` at which the traces are opened,
+/// where `g` generates the trace domain.
+pub(crate) fn window_points<SC: StarkGenericConfig>(
+    trace_domain: Domain<SC>,
+    zeta: SC::Challenge,
+    window_size: usize,
+) -> Vec<SC::Challenge> {
+    iter::successors(Some(zeta), |&point| trace_domain.next_point(point))
+        .take(window_size)
+        .collect()
+}
+
+/// Pair each opening point with the values opened there.
+pub(crate) fn zip_window<Challenge: Clone>(
+    points: &[Challenge],
+    window: &[Vec<Challenge>],
+) -> Vec<(Challenge, Vec<Challenge>)> {
+    points.iter().cloned().zip(window.iter().cloned()).collect()
+}
+
+/// Check that the constraints enforced by `eval`, evaluated on the opened values at `zeta` and
+/// folded with `alpha`, agree with the opened quotient.
+///
+/// The opened values must already have passed [`has_valid_shape`].
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_constraints<SC, E>(
+    eval: E,
+    opened_values: &OpenedValues<SC::Challenge>,
+    ext_width: usize,
+    trace_domain: Domain<SC>,
+    quotient_chunks_domains: &[Domain<SC>],
+    zeta: SC::Challenge,
+    alpha: SC::Challenge,
+    permutation_challenges: &[SC::Challenge],
+    public_values: &Vec<Val<SC>>,
+    periodic_columns: &[Vec<Val<SC>>],
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    E: for<'a> Fn(&mut VerifierConstraintFolder<'a, SC>),
+{
     let zps = quotient_chunks_domains
         .iter()
         .enumerate()
@@ -122,7 +282,7 @@ where
         .iter()
         .enumerate()
         .map(|(ch_i, ch)| {
-            // We checked in valid_shape the length of "ch" is equal to
+            // We checked in has_valid_shape the length of "ch" is equal to
             // <SC::Challenge as BasedVectorSpace<Val<SC>>>::DIMENSION. Hence
             // the unwrap() will never panic.
             zps[ch_i]
@@ -134,22 +294,85 @@ where
         .sum::<SC::Challenge>();
 
     let sels = trace_domain.selectors_at_point(zeta);
+    // The transition selector for a window of size s must vanish on the last s - 1 rows, so we
+    // take the product of the selector for a window of 2 at the first s - 1 rows of the window.
+    let points = window_points::<SC>(trace_domain, zeta, opened_values.trace_window.len());
+    let is_transition_windows = points[..points.len().saturating_sub(1)]
+        .iter()
+        .scan(SC::Challenge::ONE, |selector, &point| {
+            *selector *= trace_domain.selectors_at_point(point).is_transition;
+            Some(*selector)
+        })
+        .collect_vec();
+    let periodic_values = periodic_columns
+        .iter()
+        .map(|column| trace_domain.periodic_column_at_point(column, zeta))
+        .collect_vec();
+
+    let window_width = |window: &[Vec<SC::Challenge>]| window.first().map_or(0, Vec::len);
+    // The extension field main columns follow the base field ones, flattened to their
+    // coefficients.
+    let base_width =
+        window_width(&opened_values.trace_window) - ext_width * SC::Challenge::DIMENSION;
+
+    let preprocessed_values = opened_values.preprocessed_window.concat();
+    let preprocessed = RowMajorMatrixView::new(
+        &preprocessed_values,
+        window_width(&opened_values.preprocessed_window),
+    );
+
+    // The permutation trace was committed column by column over the base field, so recombine
+    // the opened base columns into challenge field values.
+    let recombine = |values: &[SC::Challenge]| {
+        values
+            .chunks_exact(SC::Challenge::DIMENSION)
+            .map(|coeffs| {
+                coeffs
+                    .iter()
+                    .enumerate()
+                    .map(|(e_i, &c)| SC::Challenge::ith_basis_element(e_i).unwrap() * c)
+                    .sum::<SC::Challenge>()
+            })
+            .collect_vec()
+    };
+    let main_values = opened_values
+        .trace_window
+        .iter()
+        .flat_map(|row| row[..base_width].to_vec())
+        .collect_vec();
+    let main = RowMajorMatrixView::new(&main_values, base_width);
+    let main_ext_values = opened_values
+        .trace_window
+        .iter()
+        .flat_map(|row| recombine(&row[base_width..]))
+        .collect_vec();
+    let main_ext = RowMajorMatrixView::new(&main_ext_values, ext_width);
 
-    let main = VerticalPair::new(
-        RowMajorMatrixView::new_row(&opened_values.trace_local),
-        RowMajorMatrixView::new_row(&opened_values.trace_next),
+    let permutation_values = opened_values
+        .permutation_window
+        .iter()
+        .flat_map(|row| recombine(row))
+        .collect_vec();
+    let permutation = RowMajorMatrixView::new(
+        &permutation_values,
+        window_width(&opened_values.permutation_window) / SC::Challenge::DIMENSION,
     );
 
     let mut folder = VerifierConstraintFolder {
+        preprocessed,
         main,
+        main_ext,
+        permutation,
+        permutation_challenges,
         public_values,
+        periodic_values: &periodic_values,
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
-        is_transition: sels.is_transition,
+        is_transition_windows: &is_transition_windows,
         alpha,
         accumulator: SC::Challenge::ZERO,
     };
-    air.eval(&mut folder);
+    eval(&mut folder);
     let folded_constraints = folder.accumulator;
 
     // Finally, check that
@@ -169,4 +392,6 @@ pub enum VerificationError<PcsErr> {
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
     /// `quotient(zeta) Z_H(zeta)`.
     OodEvaluationMismatch,
+    /// The cumulative sums of all AIRs did not add up to zero, i.e. some bus is unbalanced.
+    UnbalancedBuses,
 }
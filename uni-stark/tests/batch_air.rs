@@ -0,0 +1,165 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, InteractionAir};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_config};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{StarkConfig, StarkInstance, VerificationError, prove_batch, verify_batch};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// Two toy tables with different shapes, proven together.
+enum Table {
+    /// Two columns `(a, b)` with `(a, b) -> (b, a + b)` and the final `b` as public value.
+    Fibonacci,
+    /// Three columns `(a, b, c)` with `a^3 * b = c` on every row.
+    Cube,
+}
+
+impl<F> BaseAir<F> for Table {
+    fn width(&self) -> usize {
+        match self {
+            Self::Fibonacci => 2,
+            Self::Cube => 3,
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for Table {}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for Table {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        match self {
+            Self::Fibonacci => {
+                let x = builder.public_values()[0];
+                builder.when_first_row().assert_zero(local[0]);
+                builder.when_first_row().assert_one(local[1]);
+                let mut when_transition = builder.when_transition();
+                when_transition.assert_eq(local[1], next[0]);
+                when_transition.assert_eq(local[0] + local[1], next[1]);
+                builder.when_last_row().assert_eq(local[1], x);
+            }
+            Self::Cube => {
+                builder.assert_eq(local[0].into().cube() * local[1], local[2]);
+            }
+        }
+    }
+}
+
+fn fibonacci_trace<F: Field>(n: usize) -> (RowMajorMatrix<F>, F) {
+    let mut values = Vec::with_capacity(2 * n);
+    let (mut a, mut b) = (F::ZERO, F::ONE);
+    for _ in 0..n {
+        values.extend([a, b]);
+        (a, b) = (b, a + b);
+    }
+    // The last `b` written to the trace is the `a` of the (unwritten) next row.
+    (RowMajorMatrix::new(values, 2), a)
+}
+
+fn cube_trace<F: Field>(n: usize) -> RowMajorMatrix<F> {
+    let values = (0..n)
+        .flat_map(|i| {
+            let a = F::from_usize(i + 1);
+            let b = F::from_usize(3 * i + 7);
+            [a, b, a.cube() * b]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn setup() -> (MyConfig, Perm) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 1);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config);
+    (MyConfig::new(pcs), perm)
+}
+
+fn prove_and_verify(
+    log_fib_height: usize,
+    log_cube_height: usize,
+    verifier_public_values: Option<Vec<Vec<Val>>>,
+) -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    let (config, perm) = setup();
+
+    let (fib_trace, fib_result) = fibonacci_trace::<Val>(1 << log_fib_height);
+    let cube_trace = cube_trace::<Val>(1 << log_cube_height);
+    let public_values = vec![vec![fib_result], vec![]];
+
+    let instances = vec![
+        StarkInstance {
+            air: &Table::Fibonacci,
+            trace: fib_trace,
+            public_values: public_values[0].clone(),
+        },
+        StarkInstance {
+            air: &Table::Cube,
+            trace: cube_trace,
+            public_values: public_values[1].clone(),
+        },
+    ];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_batch(&config, instances, &mut challenger);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof = postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm);
+    verify_batch(
+        &config,
+        &[&Table::Fibonacci, &Table::Cube],
+        &mut challenger,
+        &proof,
+        &verifier_public_values.unwrap_or(public_values),
+    )
+}
+
+#[test]
+fn test_batch_same_height() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    prove_and_verify(4, 4, None)
+}
+
+#[test]
+fn test_batch_different_heights() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    prove_and_verify(3, 6, None)?;
+    prove_and_verify(7, 2, None)
+}
+
+#[test]
+fn test_batch_wrong_public_value() {
+    let result = prove_and_verify(3, 5, Some(vec![vec![Val::from_u8(42)], vec![]]));
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_batch_wrong_number_of_tables() {
+    let result = prove_and_verify(3, 5, Some(vec![vec![Val::from_u8(42)]]));
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
@@ -0,0 +1,157 @@
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_config};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{
+    DegreeReducedAir, StarkConfig, check_constraints, get_log_quotient_degree,
+    get_max_constraint_degree, prove, verify,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+/// An AIR with three main columns `(x, y, z)`, where `x` counts up from the public value, and
+/// `y = x^9` and `z = x^4 x'^3` on every row.
+struct PowAir;
+
+impl BaseAir<Val> for PowAir {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl BaseAirWithPublicValues<Val> for PowAir {
+    fn num_public_values(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues<F = Val>> Air<AB> for PowAir {
+    fn eval(&self, builder: &mut AB) {
+        let start = builder.public_values()[0].into();
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let (x, y, z) = (local[0].into(), local[1], local[2]);
+        let next_x = next[0].into();
+
+        builder.when_first_row().assert_eq(x.clone(), start);
+        builder
+            .when_transition()
+            .assert_eq(next_x.clone(), x.clone() + AB::Expr::ONE);
+        builder.assert_eq(y, x.exp_const_u64::<9>());
+        builder
+            .when_transition()
+            .assert_eq(z, x.exp_const_u64::<4>() * next_x.exp_const_u64::<3>());
+    }
+}
+
+fn generate_trace(height: usize, start: Val) -> RowMajorMatrix<Val> {
+    let values = (0..height)
+        .flat_map(|i| {
+            let x = start + Val::from_usize(i);
+            let z = x.exp_const_u64::<4>() * (x + Val::ONE).exp_const_u64::<3>();
+            [x, x.exp_const_u64::<9>(), z]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+fn setup() -> (MyConfig, Challenger) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config);
+    (MyConfig::new(pcs), Challenger::new(perm))
+}
+
+fn do_test(max_degree: usize) {
+    let (config, challenger) = setup();
+    let air = DegreeReducedAir::new(&PowAir, max_degree);
+    assert!(get_max_constraint_degree(&air, 0, 1) <= max_degree);
+
+    let public_values = vec![Val::from_u8(5)];
+    let trace = air.extend_trace(generate_trace(1 << 6, public_values[0]), &public_values);
+    assert_eq!(trace.width(), 3 + air.num_aux_columns());
+
+    let proof = prove(
+        &config,
+        &air,
+        &mut challenger.clone(),
+        trace,
+        &public_values,
+    );
+    verify(
+        &config,
+        &air,
+        &mut challenger.clone(),
+        &proof,
+        &public_values,
+    )
+    .expect("verification failed");
+}
+
+#[test]
+fn test_reduced_quotient_degree() {
+    assert_eq!(get_max_constraint_degree(&PowAir, 0, 1), 9);
+    assert_eq!(get_log_quotient_degree(&PowAir, 0, 1, 0), 3);
+
+    let air = DegreeReducedAir::new(&PowAir, 3);
+    assert_eq!(get_log_quotient_degree(&air, 0, 1, 0), 1);
+
+    // Constraints within the target degree are left as they are.
+    let air = DegreeReducedAir::new(&PowAir, 9);
+    assert_eq!(air.num_aux_columns(), 0);
+}
+
+#[test]
+fn prove_degree_reduced_to_2() {
+    do_test(2);
+}
+
+#[test]
+fn prove_degree_reduced_to_3() {
+    do_test(3);
+}
+
+#[test]
+fn prove_degree_reduced_to_5() {
+    do_test(5);
+}
+
+#[test]
+fn test_invalid_trace_fails_reduced_constraints() {
+    let air = DegreeReducedAir::new(&PowAir, 3);
+    let public_values = vec![Val::from_u8(5)];
+    let mut trace = generate_trace(1 << 4, public_values[0]);
+    trace.values[3 * 7 + 1] += Val::ONE;
+    let trace = air.extend_trace(trace, &public_values);
+
+    let report =
+        check_constraints::<_, Challenge, _>(&air, None, &trace, None, &[], &public_values, None);
+    assert!(!report.is_ok());
+    assert!(report.failures.iter().all(|failure| failure.row == 7));
+}
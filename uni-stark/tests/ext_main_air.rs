@@ -0,0 +1,243 @@
+use core::marker::PhantomData;
+
+use p3_air::{
+    Air, AirBuilder, BaseAir, BaseAirWithPublicValues, ExtensionBuilder, ExtensionMainBuilder,
+    InteractionAir,
+};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{BasedVectorSpace, ExtensionField, Field, PrimeCharacteristicRing};
+use p3_fri::{FriConfig, TwoAdicFriPcs, create_test_fri_config};
+use p3_keccak::Keccak256Hash;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
+};
+use p3_uni_stark::{
+    ProverError, StarkConfig, StarkGenericConfig, StarkInstance, Val, VerificationError,
+    append_ext_columns, check_constraints, prove, prove_batch, try_prove, verify, verify_batch,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// An AIR with a base field main column `x`, counting up from zero, and two extension field main
+/// columns `(y, z)`, with `y' = y^2 + x` and `z = x y` on every row.
+pub struct ExtMainAir;
+
+impl<F> BaseAir<F> for ExtMainAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn ext_width(&self) -> usize {
+        2
+    }
+}
+
+impl<F> BaseAirWithPublicValues<F> for ExtMainAir {}
+
+impl<F: Field> InteractionAir<F> for ExtMainAir {}
+
+impl<AB: ExtensionMainBuilder> Air<AB> for ExtMainAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let main_ext = builder.main_ext();
+        let (local_ext, next_ext) = (main_ext.row_slice(0), main_ext.row_slice(1));
+
+        let (x, x_next): (AB::Expr, AB::Expr) = (local[0].into(), next[0].into());
+        let (y, z): (AB::ExprEF, AB::ExprEF) = (local_ext[0].into(), local_ext[1].into());
+        let y_next: AB::ExprEF = next_ext[0].into();
+
+        builder.when_first_row().assert_zero(x.clone());
+        builder
+            .when_transition()
+            .assert_eq(x_next, x.clone() + AB::Expr::ONE);
+        builder
+            .when_transition()
+            .assert_eq_ext(y_next, y.square() + x.clone());
+        builder.assert_eq_ext(z, y * x);
+    }
+}
+
+/// A main trace for [`ExtMainAir`], with the extension field columns flattened after `x`.
+fn generate_trace<F: Field, EF: ExtensionField<F>>(log_height: usize) -> RowMajorMatrix<F> {
+    let n = 1 << log_height;
+    let x = (0..n).map(F::from_usize).collect::<Vec<_>>();
+    let mut y = EF::from_basis_coefficients_fn(|i| F::from_usize(i + 1));
+    let mut ext_values = Vec::with_capacity(2 * n);
+    for &x in &x {
+        ext_values.extend([y, y * x]);
+        y = y.square() + x;
+    }
+    append_ext_columns(
+        RowMajorMatrix::new_col(x),
+        &RowMajorMatrix::new(ext_values, 2),
+    )
+}
+
+fn do_test<SC: StarkGenericConfig>(
+    config: SC,
+    challenger: SC::Challenger,
+    log_height: usize,
+) -> Result<(), VerificationError<p3_uni_stark::PcsError<SC>>>
+where
+    SC::Challenger: Clone,
+{
+    let trace = generate_trace::<Val<SC>, SC::Challenge>(log_height);
+    assert_eq!(trace.width(), 1 + 2 * SC::Challenge::DIMENSION);
+
+    let proof = prove(
+        &config,
+        &ExtMainAir,
+        &mut challenger.clone(),
+        trace,
+        &vec![],
+    );
+    verify(
+        &config,
+        &ExtMainAir,
+        &mut challenger.clone(),
+        &proof,
+        &vec![],
+    )
+}
+
+type BbVal = BabyBear;
+type BbPerm = Poseidon2BabyBear<16>;
+type BbHash = PaddingFreeSponge<BbPerm, 16, 8, 8>;
+type BbCompress = TruncatedPermutation<BbPerm, 2, 8, 16>;
+type BbValMmcs =
+    MerkleTreeMmcs<<BbVal as Field>::Packing, <BbVal as Field>::Packing, BbHash, BbCompress, 8>;
+type BbChallenge = BinomialExtensionField<BbVal, 4>;
+type BbChallengeMmcs = ExtensionMmcs<BbVal, BbChallenge, BbValMmcs>;
+type BbChallenger = DuplexChallenger<BbVal, BbPerm, 16, 8>;
+type BbDft = Radix2DitParallel<BbVal>;
+type BbPcs = TwoAdicFriPcs<BbVal, BbDft, BbValMmcs, BbChallengeMmcs>;
+type BbConfig = StarkConfig<BbPcs, BbChallenge, BbChallenger>;
+
+fn bb_setup() -> (BbConfig, BbChallenger) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = BbPerm::new_from_rng_128(&mut rng);
+    let hash = BbHash::new(perm.clone());
+    let compress = BbCompress::new(perm.clone());
+    let val_mmcs = BbValMmcs::new(hash, compress);
+    let challenge_mmcs = BbChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = BbPcs::new(BbDft::default(), val_mmcs, fri_config);
+    (BbConfig::new(pcs), BbChallenger::new(perm))
+}
+
+#[test]
+fn prove_bb_twoadic_ext_main() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    let (config, challenger) = bb_setup();
+    do_test(config, challenger, 6)
+}
+
+#[test]
+fn prove_m31_circle_ext_main() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+    let compress = MyCompress::new(byte_hash);
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_config,
+        _phantom: PhantomData,
+    };
+    let config = MyConfig::new(pcs);
+
+    do_test(config, Challenger::from_hasher(vec![], byte_hash), 6)
+}
+
+#[test]
+fn prove_batch_ext_main() {
+    let (config, challenger) = bb_setup();
+    let instances = vec![
+        StarkInstance {
+            air: &ExtMainAir,
+            trace: generate_trace::<BbVal, BbChallenge>(4),
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: &ExtMainAir,
+            trace: generate_trace::<BbVal, BbChallenge>(6),
+            public_values: vec![],
+        },
+    ];
+
+    let proof = prove_batch(&config, instances, &mut challenger.clone());
+    verify_batch(
+        &config,
+        &[&ExtMainAir, &ExtMainAir],
+        &mut challenger.clone(),
+        &proof,
+        &[vec![], vec![]],
+    )
+    .expect("verification failed");
+}
+
+#[test]
+fn test_ext_main_check_constraints() {
+    let mut trace = generate_trace::<BbVal, BbChallenge>(4);
+    // Perturb a coefficient of `z` on row 5.
+    let (width, d) = (
+        trace.width(),
+        <BbChallenge as BasedVectorSpace<BbVal>>::DIMENSION,
+    );
+    trace.values[5 * width + 1 + d + 2] += BbVal::ONE;
+
+    let report =
+        check_constraints::<_, BbChallenge, _>(&ExtMainAir, None, &trace, None, &[], &[], None);
+    let failing_rows = report.failures.iter().map(|f| f.row).collect::<Vec<_>>();
+    assert_eq!(failing_rows, vec![5]);
+}
+
+#[test]
+fn test_ext_main_width_mismatch() {
+    let (config, challenger) = bb_setup();
+    // The extension field columns are missing.
+    let trace = RowMajorMatrix::new_col((0..16).map(BbVal::from_usize).collect());
+
+    let result = try_prove(
+        &config,
+        &ExtMainAir,
+        &mut challenger.clone(),
+        trace,
+        &vec![],
+    );
+    assert!(matches!(
+        result,
+        Err(ProverError::TraceWidthMismatch {
+            expected: 9,
+            actual: 1
+        })
+    ));
+}
@@ -1,18 +1,18 @@
 use core::borrow::Borrow;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, BaseAirWithPublicValues};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::DuplexChallenger;
 use p3_commit::ExtensionMmcs;
 use p3_dft::Radix2DitParallel;
 use p3_field::extension::BinomialExtensionField;
 use p3_field::{Field, PrimeCharacteristicRing, PrimeField64};
-use p3_fri::{TwoAdicFriPcs, create_test_fri_config};
+use p3_fri::{HidingFriPcs, TwoAdicFriPcs, create_test_fri_config};
 use p3_matrix::Matrix;
 use p3_matrix::dense::RowMajorMatrix;
-use p3_merkle_tree::MerkleTreeMmcs;
+use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
 use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
-use p3_uni_stark::{StarkConfig, prove, verify};
+use p3_uni_stark::{ProverError, StarkConfig, prove, try_prove, verify};
 use rand::SeedableRng;
 use rand::rngs::SmallRng;
 
@@ -25,6 +25,12 @@ impl<F> BaseAir<F> for FibonacciAir {
     }
 }
 
+impl<F> BaseAirWithPublicValues<F> for FibonacciAir {
+    fn num_public_values(&self) -> usize {
+        3
+    }
+}
+
 impl<AB: AirBuilderWithPublicValues> Air<AB> for FibonacciAir {
     fn eval(&self, builder: &mut AB) {
         let main = builder.main();
@@ -143,9 +149,184 @@ fn test_public_value() {
     test_public_value_impl(1 << 3, 21, 2);
 }
 
+#[test]
+fn test_public_value_zk() {
+    type HidingValMmcs = MerkleTreeHidingMmcs<
+        <Val as Field>::Packing,
+        <Val as Field>::Packing,
+        MyHash,
+        MyCompress,
+        SmallRng,
+        8,
+        4,
+    >;
+    type HidingChallengeMmcs = ExtensionMmcs<Val, Challenge, HidingValMmcs>;
+    type HidingPcs = HidingFriPcs<Val, Dft, HidingValMmcs, HidingChallengeMmcs, SmallRng>;
+    type HidingConfig = StarkConfig<HidingPcs, Challenge, Challenger>;
+
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = HidingValMmcs::new(hash, compress, SmallRng::seed_from_u64(2));
+    let challenge_mmcs = HidingChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = HidingPcs::new(dft, val_mmcs, fri_config, 4, SmallRng::seed_from_u64(3));
+    let config = HidingConfig::new(pcs);
+    let mut challenger = Challenger::new(perm.clone());
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+    let proof = prove(&config, &FibonacciAir {}, &mut challenger, trace, &pis);
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &FibonacciAir {}, &mut challenger, &proof, &pis).expect("verification failed");
+}
+
+/// A [`FibonacciAir`] with an explicit fingerprint.
+struct FingerprintedFibonacciAir(u32);
+
+impl<F: PrimeCharacteristicRing> BaseAir<F> for FingerprintedFibonacciAir {
+    fn width(&self) -> usize {
+        NUM_FIBONACCI_COLS
+    }
+
+    fn fingerprint(&self) -> Option<Vec<F>> {
+        Some(vec![F::from_u32(self.0)])
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for FingerprintedFibonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        FibonacciAir {}.eval(builder);
+    }
+}
+
+#[test]
+fn test_fingerprint_mismatch() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = Pcs::new(dft, val_mmcs, fri_config);
+    let config = MyConfig::new(pcs);
+    let mut challenger = Challenger::new(perm.clone());
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+    let proof = prove(
+        &config,
+        &FingerprintedFibonacciAir(1),
+        &mut challenger,
+        trace,
+        &pis,
+    );
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify(
+        &config,
+        &FingerprintedFibonacciAir(1),
+        &mut challenger,
+        &proof,
+        &pis,
+    )
+    .expect("verification failed");
+
+    // The same constraints under a different fingerprint give a different transcript.
+    let mut challenger = Challenger::new(perm);
+    assert!(
+        verify(
+            &config,
+            &FingerprintedFibonacciAir(2),
+            &mut challenger,
+            &proof,
+            &pis,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_try_prove_errors() {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let dft = Dft::default();
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = Pcs::new(dft, val_mmcs, fri_config);
+    let config = MyConfig::new(pcs);
+    let pis = vec![BabyBear::ZERO, BabyBear::ONE, BabyBear::from_u64(21)];
+
+    let wide_trace = RowMajorMatrix::new(Val::zero_vec(3 << 3), 3);
+    let result = try_prove(
+        &config,
+        &FibonacciAir {},
+        &mut Challenger::new(perm.clone()),
+        wide_trace,
+        &pis,
+    );
+    assert!(matches!(
+        result,
+        Err(ProverError::TraceWidthMismatch {
+            expected: 2,
+            actual: 3
+        })
+    ));
+
+    let short_trace = RowMajorMatrix::new(Val::zero_vec(2 * 6), 2);
+    let result = try_prove(
+        &config,
+        &FibonacciAir {},
+        &mut Challenger::new(perm.clone()),
+        short_trace,
+        &pis,
+    );
+    assert!(matches!(
+        result,
+        Err(ProverError::TraceHeightNotPowerOfTwo { height: 6 })
+    ));
+
+    let result = try_prove(
+        &config,
+        &FibonacciAir {},
+        &mut Challenger::new(perm.clone()),
+        generate_trace_rows::<Val>(0, 1, 1 << 3),
+        &pis[..2].to_vec(),
+    );
+    assert!(matches!(
+        result,
+        Err(ProverError::PublicValuesLengthMismatch {
+            expected: 3,
+            actual: 2
+        })
+    ));
+
+    let proof = try_prove(
+        &config,
+        &FibonacciAir {},
+        &mut Challenger::new(perm.clone()),
+        generate_trace_rows::<Val>(0, 1, 1 << 3),
+        &pis,
+    )
+    .expect("valid input");
+    verify(
+        &config,
+        &FibonacciAir {},
+        &mut Challenger::new(perm),
+        &proof,
+        &pis,
+    )
+    .expect("verification failed");
+}
+
 #[cfg(debug_assertions)]
 #[test]
-#[should_panic(expected = "assertion `left == right` failed: constraints had nonzero value")]
+#[should_panic(expected = "failing constraints")]
 fn test_incorrect_public_value() {
     let mut rng = SmallRng::seed_from_u64(1);
     let perm = Perm::new_from_rng_128(&mut rng);
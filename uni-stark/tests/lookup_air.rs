@@ -0,0 +1,225 @@
+use p3_air::{Air, AirBuilder, BaseAir, InteractionAir, InteractionBuilder, VirtualPairCol};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_config};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{StarkConfig, StarkInstance, VerificationError, prove_batch, verify_batch};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+const SQUARES_BUS: usize = 0;
+
+/// Two tables connected by a bus: `Lookups` looks up pairs `(x, x^2)` in `Squares`.
+enum Table {
+    /// Two columns `(x, y)`, sending `(x, y)` on every row.
+    Lookups,
+    /// `Lookups` with a window of a single row, which is too small for the running sum of its
+    /// permutation trace.
+    SingleRowLookups,
+    /// Three columns `(x, x^2, multiplicity)` with `x` counting up from zero, receiving
+    /// `(x, x^2)` `multiplicity` times on every row.
+    Squares,
+}
+
+impl<F> BaseAir<F> for Table {
+    fn width(&self) -> usize {
+        match self {
+            Self::Lookups | Self::SingleRowLookups => 2,
+            Self::Squares => 3,
+        }
+    }
+
+    fn window_size(&self) -> usize {
+        match self {
+            Self::SingleRowLookups => 1,
+            Self::Lookups | Self::Squares => 2,
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for Table {
+    fn eval_interactions(&self, builder: &mut InteractionBuilder<F>) {
+        match self {
+            Self::Lookups | Self::SingleRowLookups => builder.send(
+                SQUARES_BUS,
+                [
+                    VirtualPairCol::single_main(0),
+                    VirtualPairCol::single_main(1),
+                ],
+                VirtualPairCol::constant(F::ONE),
+            ),
+            Self::Squares => builder.receive(
+                SQUARES_BUS,
+                [
+                    VirtualPairCol::single_main(0),
+                    VirtualPairCol::single_main(1),
+                ],
+                VirtualPairCol::single_main(2),
+            ),
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for Table {
+    fn eval(&self, builder: &mut AB) {
+        match self {
+            // The bus is responsible for all constraints on this table.
+            Self::Lookups | Self::SingleRowLookups => {}
+            Self::Squares => {
+                let main = builder.main();
+                let (local, next) = (main.row_slice(0), main.row_slice(1));
+                builder.when_first_row().assert_zero(local[0]);
+                builder
+                    .when_transition()
+                    .assert_eq(next[0], local[0] + AB::Expr::ONE);
+                builder.assert_eq(local[0].into().square(), local[1]);
+            }
+        }
+    }
+}
+
+fn lookups_trace<F: Field>(n: usize, log_squares_height: usize) -> RowMajorMatrix<F> {
+    let values = (0..n)
+        .flat_map(|i| {
+            let x = F::from_usize((7 * i + 3) % (1 << log_squares_height));
+            [x, x.square()]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+fn squares_trace<F: Field>(lookups: &RowMajorMatrix<F>, log_height: usize) -> RowMajorMatrix<F> {
+    let mut multiplicities = vec![0; 1 << log_height];
+    for row in lookups.values.chunks_exact(2) {
+        let x = (0..1 << log_height)
+            .position(|x| F::from_usize(x) == row[0])
+            .unwrap();
+        multiplicities[x] += 1;
+    }
+    let values = multiplicities
+        .into_iter()
+        .enumerate()
+        .flat_map(|(x, m)| {
+            let x = F::from_usize(x);
+            [x, x.square(), F::from_usize(m)]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+fn prove_and_verify(
+    log_lookups_height: usize,
+    log_squares_height: usize,
+    tamper: bool,
+) -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    prove_and_verify_with(
+        &Table::Lookups,
+        &Table::Lookups,
+        log_lookups_height,
+        log_squares_height,
+        tamper,
+    )
+}
+
+/// Prove with `prover_lookups` as the lookup table's AIR, and verify with `verifier_lookups`.
+fn prove_and_verify_with(
+    prover_lookups: &Table,
+    verifier_lookups: &Table,
+    log_lookups_height: usize,
+    log_squares_height: usize,
+    tamper: bool,
+) -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 1);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config);
+    let config = MyConfig::new(pcs);
+
+    let lookups = lookups_trace::<Val>(1 << log_lookups_height, log_squares_height);
+    let mut squares = squares_trace(&lookups, log_squares_height);
+    if tamper {
+        // Claim one lookup too many of `(0, 0)`.
+        squares.values[2] += Val::ONE;
+    }
+
+    let instances = vec![
+        StarkInstance {
+            air: prover_lookups,
+            trace: lookups,
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: &Table::Squares,
+            trace: squares,
+            public_values: vec![],
+        },
+    ];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_batch(&config, instances, &mut challenger);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof = postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm);
+    verify_batch(
+        &config,
+        &[verifier_lookups, &Table::Squares],
+        &mut challenger,
+        &proof,
+        &[vec![], vec![]],
+    )
+}
+
+#[test]
+fn test_lookup_same_height() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    prove_and_verify(4, 4, false)
+}
+
+#[test]
+fn test_lookup_different_heights() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    prove_and_verify(6, 3, false)?;
+    prove_and_verify(2, 5, false)
+}
+
+#[test]
+fn test_lookup_unbalanced_bus() {
+    let result = prove_and_verify(5, 3, true);
+    assert!(matches!(result, Err(VerificationError::UnbalancedBuses)));
+}
+
+#[test]
+#[should_panic(expected = "AIRs with interactions need a window of at least two rows")]
+fn test_lookup_single_row_window_rejected_by_prover() {
+    let _ = prove_and_verify_with(&Table::SingleRowLookups, &Table::Lookups, 4, 4, false);
+}
+
+#[test]
+fn test_lookup_single_row_window_rejected_by_verifier() {
+    let result = prove_and_verify_with(&Table::Lookups, &Table::SingleRowLookups, 4, 4, false);
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
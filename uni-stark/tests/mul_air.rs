@@ -2,7 +2,7 @@ use core::fmt::Debug;
 use core::marker::PhantomData;
 
 use itertools::Itertools;
-use p3_air::{Air, AirBuilder, BaseAir};
+use p3_air::{Air, AirBuilder, BaseAir, BaseAirWithPublicValues};
 use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
 use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
 use p3_circle::CirclePcs;
@@ -20,7 +20,7 @@ use p3_mersenne_31::Mersenne31;
 use p3_symmetric::{
     CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
 };
-use p3_uni_stark::{StarkConfig, StarkGenericConfig, Val, prove, verify};
+use p3_uni_stark::{ProverError, StarkConfig, StarkGenericConfig, Val, prove, try_prove, verify};
 use rand::distr::{Distribution, StandardUniform};
 use rand::rngs::SmallRng;
 use rand::{Rng, SeedableRng};
@@ -88,6 +88,8 @@ impl<F> BaseAir<F> for MulAir {
     }
 }
 
+impl<F> BaseAirWithPublicValues<F> for MulAir {}
+
 impl<AB: AirBuilder> Air<AB> for MulAir {
     fn eval(&self, builder: &mut AB) {
         let main = builder.main();
@@ -190,6 +192,44 @@ fn prove_bb_trivial_deg4() -> Result<(), impl Debug> {
     do_test_bb_trivial(4, 8)
 }
 
+#[test]
+fn prove_bb_trivial_degree_too_large() {
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+    type Pcs = TrivialPcs<Val, Radix2DitParallel<Val>>;
+    let pcs = TrivialPcs {
+        dft: Radix2DitParallel::default(),
+        log_n: 3,
+        _phantom: PhantomData,
+    };
+
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+    let config = MyConfig::new(pcs);
+
+    // The quotient domain must be 2^25 times larger than the trace's, which exceeds BabyBear's
+    // two-adicity of 27.
+    let air = MulAir {
+        degree: (1 << 25) + 1,
+        ..Default::default()
+    };
+    let trace = air.random_valid_trace(1 << 3, true);
+    let result = try_prove(&config, &air, &mut Challenger::new(perm), trace, &vec![]);
+    assert!(matches!(
+        result,
+        Err(ProverError::QuotientDomainTooLarge {
+            log_size: 28,
+            max_log_size: 27
+        })
+    ));
+}
+
 fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<(), impl Debug> {
     type Val = BabyBear;
     type Challenge = BinomialExtensionField<Val, 4>;
@@ -219,6 +259,7 @@ fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
     let fri_config = FriConfig {
         log_blowup,
         log_final_poly_len: 5,
+        max_log_arity: 1,
         num_queries: 40,
         proof_of_work_bits: 8,
         mmcs: challenge_mmcs,
@@ -280,6 +321,7 @@ fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
     let fri_config = FriConfig {
         log_blowup,
         log_final_poly_len: 0,
+        max_log_arity: 1,
         num_queries: 40,
         proof_of_work_bits: 8,
         mmcs: challenge_mmcs,
@@ -0,0 +1,160 @@
+use p3_air::{Air, AirBuilder, BaseAir, InteractionAir, TracePadding};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{TwoAdicFriPcs, create_test_fri_config};
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::{StarkConfig, StarkInstance, prove, prove_batch, verify, verify_batch};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+type Val = BabyBear;
+type Perm = Poseidon2BabyBear<16>;
+type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+type Challenge = BinomialExtensionField<Val, 4>;
+type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+type Dft = Radix2DitParallel<Val>;
+type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+/// An AIR with three main columns `(x, y, z)`, which must satisfy `x * y = z` on every row, and
+/// `x = 1` on the first row. Traces are padded with the given strategy.
+struct PaddedMulAir {
+    padding: TracePadding<Val>,
+}
+
+impl BaseAir<Val> for PaddedMulAir {
+    fn width(&self) -> usize {
+        3
+    }
+
+    fn trace_padding(&self) -> Option<TracePadding<Val>> {
+        Some(self.padding.clone())
+    }
+}
+
+impl InteractionAir<Val> for PaddedMulAir {}
+
+impl<AB: AirBuilder<F = Val>> Air<AB> for PaddedMulAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+
+        builder.when_first_row().assert_one(local[0]);
+        builder.assert_eq(local[0] * local[1], local[2]);
+    }
+}
+
+/// A trace for [`PaddedMulAir`] with `height` rows, which need not be a power of two.
+fn generate_trace(height: usize) -> RowMajorMatrix<Val> {
+    let values = (0..height as u32)
+        .flat_map(|i| {
+            let (x, y) = (Val::from_u32(i + 1), Val::from_u32(i + 7));
+            [x, y, x * y]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+fn setup() -> (MyConfig, Challenger) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config);
+    (MyConfig::new(pcs), Challenger::new(perm))
+}
+
+fn do_test(padding: TracePadding<Val>, height: usize) {
+    let (config, challenger) = setup();
+    let air = PaddedMulAir { padding };
+
+    let proof = prove(
+        &config,
+        &air,
+        &mut challenger.clone(),
+        generate_trace(height),
+        &vec![],
+    );
+    verify(&config, &air, &mut challenger.clone(), &proof, &vec![]).expect("verification failed");
+}
+
+#[test]
+fn prove_padding_zero() {
+    do_test(TracePadding::Zero, 100);
+}
+
+#[test]
+fn prove_padding_repeat_last_row() {
+    do_test(TracePadding::RepeatLastRow, 100);
+}
+
+#[test]
+fn prove_padding_row() {
+    let row = vec![Val::TWO, Val::from_u8(3), Val::from_u8(6)];
+    do_test(TracePadding::Row(row), 100);
+}
+
+#[test]
+fn prove_padding_rows() {
+    do_test(TracePadding::Rows(generate_trace(5)), 100);
+}
+
+#[test]
+fn prove_padding_already_power_of_two() {
+    do_test(TracePadding::RepeatLastRow, 64);
+}
+
+#[test]
+fn prove_batch_padding() {
+    let (config, challenger) = setup();
+    let zero_air = PaddedMulAir {
+        padding: TracePadding::Zero,
+    };
+    let repeat_air = PaddedMulAir {
+        padding: TracePadding::RepeatLastRow,
+    };
+    let instances = vec![
+        StarkInstance {
+            air: &zero_air,
+            trace: generate_trace(20),
+            public_values: vec![],
+        },
+        StarkInstance {
+            air: &repeat_air,
+            trace: generate_trace(70),
+            public_values: vec![],
+        },
+    ];
+
+    let proof = prove_batch(&config, instances, &mut challenger.clone());
+    verify_batch(
+        &config,
+        &[&zero_air, &repeat_air],
+        &mut challenger.clone(),
+        &proof,
+        &[vec![], vec![]],
+    )
+    .expect("verification failed");
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "involve the padding rows 100..128")]
+fn prove_padding_invalid_row() {
+    let row = vec![Val::TWO, Val::from_u8(3), Val::from_u8(5)];
+    do_test(TracePadding::Row(row), 100);
+}
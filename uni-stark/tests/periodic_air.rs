@@ -0,0 +1,183 @@
+use core::marker::PhantomData;
+
+use p3_air::{Air, AirBuilder, BaseAir, PeriodicAirBuilder};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{FriConfig, TwoAdicFriPcs, create_test_fri_config};
+use p3_keccak::Keccak256Hash;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
+};
+use p3_uni_stark::{
+    StarkConfig, StarkGenericConfig, Val, VerificationError, check_constraints, prove, verify,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// An AIR with three main columns `(x, y, z)` and three periodic columns: `c`, cycling through
+/// `1, 2, 3, 4`, `is_last`, which is 1 on every eighth row, and the constant `k`.
+///
+/// `x` must equal `c^2`, `y` counts up from zero and resets after each row where `is_last` is set,
+/// and `z = k * y`.
+pub struct PeriodicAir {
+    k: u32,
+}
+
+impl<F: Field> BaseAir<F> for PeriodicAir {
+    fn width(&self) -> usize {
+        3
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![
+            (1..=4).map(F::from_u32).collect(),
+            (0..8).map(|i| F::from_bool(i == 7)).collect(),
+            vec![F::from_u32(self.k)],
+        ]
+    }
+}
+
+impl<AB: PeriodicAirBuilder> Air<AB> for PeriodicAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let periodic = builder.periodic_values();
+        let (c, is_last, k): (AB::Expr, AB::Expr, AB::Expr) =
+            (periodic[0].into(), periodic[1].into(), periodic[2].into());
+
+        builder.assert_eq(local[0], c.square());
+        builder.when_first_row().assert_zero(local[1]);
+        builder.when_transition().assert_eq(
+            next[1],
+            (local[1] + AB::Expr::ONE) * (AB::Expr::ONE - is_last),
+        );
+        builder.assert_eq(local[2], k * local[1]);
+    }
+}
+
+fn generate_trace<F: Field>(log_height: usize, k: u32) -> RowMajorMatrix<F> {
+    let values = (0..1 << log_height)
+        .flat_map(|i| {
+            let c = F::from_usize(i % 4 + 1);
+            let y = F::from_usize(i % 8);
+            [c.square(), y, F::from_u32(k) * y]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+fn do_test<SC: StarkGenericConfig>(
+    config: SC,
+    challenger: SC::Challenger,
+    log_height: usize,
+) -> Result<(), VerificationError<p3_uni_stark::PcsError<SC>>>
+where
+    SC::Challenger: Clone,
+{
+    let air = PeriodicAir { k: 5 };
+    let trace = generate_trace::<Val<SC>>(log_height, air.k);
+
+    let proof = prove(&config, &air, &mut challenger.clone(), trace, &vec![]);
+
+    // The periodic columns are bound to the transcript, so a verifier with different ones rejects.
+    let other_air = PeriodicAir { k: 6 };
+    assert!(
+        verify(
+            &config,
+            &other_air,
+            &mut challenger.clone(),
+            &proof,
+            &vec![]
+        )
+        .is_err()
+    );
+
+    verify(&config, &air, &mut challenger.clone(), &proof, &vec![])
+}
+
+type BbVal = BabyBear;
+type BbPerm = Poseidon2BabyBear<16>;
+type BbHash = PaddingFreeSponge<BbPerm, 16, 8, 8>;
+type BbCompress = TruncatedPermutation<BbPerm, 2, 8, 16>;
+type BbValMmcs =
+    MerkleTreeMmcs<<BbVal as Field>::Packing, <BbVal as Field>::Packing, BbHash, BbCompress, 8>;
+type BbChallenge = BinomialExtensionField<BbVal, 4>;
+type BbChallengeMmcs = ExtensionMmcs<BbVal, BbChallenge, BbValMmcs>;
+type BbChallenger = DuplexChallenger<BbVal, BbPerm, 16, 8>;
+type BbDft = Radix2DitParallel<BbVal>;
+type BbPcs = TwoAdicFriPcs<BbVal, BbDft, BbValMmcs, BbChallengeMmcs>;
+type BbConfig = StarkConfig<BbPcs, BbChallenge, BbChallenger>;
+
+#[test]
+fn prove_bb_twoadic_periodic() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = BbPerm::new_from_rng_128(&mut rng);
+    let hash = BbHash::new(perm.clone());
+    let compress = BbCompress::new(perm.clone());
+    let val_mmcs = BbValMmcs::new(hash, compress);
+    let challenge_mmcs = BbChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = BbPcs::new(BbDft::default(), val_mmcs, fri_config);
+    do_test(BbConfig::new(pcs), BbChallenger::new(perm), 6)
+}
+
+#[test]
+fn prove_m31_circle_periodic() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+    let compress = MyCompress::new(byte_hash);
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_config,
+        _phantom: PhantomData,
+    };
+    let config = MyConfig::new(pcs);
+
+    do_test(config, Challenger::from_hasher(vec![], byte_hash), 6)
+}
+
+#[test]
+fn test_periodic_check_constraints() {
+    let air = PeriodicAir { k: 5 };
+    let mut trace = generate_trace::<BbVal>(4, air.k);
+    assert!(
+        check_constraints::<_, BbChallenge, _>(&air, None, &trace, None, &[], &[], None).is_ok()
+    );
+
+    // Row 8 starts a new period, so its counter must have been reset.
+    trace.values[3 * 8 + 1] = BbVal::from_u8(8);
+    trace.values[3 * 8 + 2] = BbVal::from_u8(40);
+    let report = check_constraints::<_, BbChallenge, _>(&air, None, &trace, None, &[], &[], None);
+    let failing_rows = report.failures.iter().map(|f| f.row).collect::<Vec<_>>();
+    assert_eq!(failing_rows, vec![7, 8]);
+}
@@ -0,0 +1,262 @@
+use core::marker::PhantomData;
+
+use p3_air::{Air, BaseAir, BaseAirWithPublicValues, ExtensionBuilder, PermutationAirBuilder};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field, batch_multiplicative_inverse};
+use p3_fri::{FriConfig, TwoAdicFriPcs, create_test_fri_config};
+use p3_keccak::Keccak256Hash;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
+};
+use p3_uni_stark::{
+    ProverError, StarkConfig, StarkGenericConfig, Val, VerificationError, prove, try_prove, verify,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// An AIR with two main columns `(a, b)`, proving that `b` is a permutation of `a` with a grand
+/// product argument.
+///
+/// For a challenge `beta`, the permutation trace has a single column `z` holding the running
+/// product of `(beta - a) / (beta - b)`, which must end in one.
+pub struct PermutationCheckAir;
+
+impl<F: Field> BaseAir<F> for PermutationCheckAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn permutation_width(&self) -> usize {
+        1
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        1
+    }
+
+    fn permutation_trace<EF: ExtensionField<F>>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>> {
+        let beta = challenges[0];
+        let denominators = main
+            .values
+            .chunks_exact(2)
+            .map(|row| beta - row[1])
+            .collect::<Vec<_>>();
+        let inverses = batch_multiplicative_inverse(&denominators);
+        let mut z = EF::ONE;
+        let values = main
+            .values
+            .chunks_exact(2)
+            .zip(inverses)
+            .map(|(row, inverse)| {
+                z *= (beta - row[0]) * inverse;
+                z
+            })
+            .collect();
+        Some(RowMajorMatrix::new_col(values))
+    }
+}
+
+impl<AB: PermutationAirBuilder> Air<AB> for PermutationCheckAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.row_slice(0), main.row_slice(1));
+        let permutation = builder.permutation();
+        let (z_local, z_next) = (permutation.row_slice(0), permutation.row_slice(1));
+        let beta: AB::ExprEF = builder.permutation_randomness()[0].into();
+
+        let (a, b): (AB::Expr, AB::Expr) = (local[0].into(), local[1].into());
+        let (a_next, b_next): (AB::Expr, AB::Expr) = (next[0].into(), next[1].into());
+        let (z, z_next): (AB::ExprEF, AB::ExprEF) = (z_local[0].into(), z_next[0].into());
+
+        builder
+            .when_first_row()
+            .assert_eq_ext(z.clone() * (beta.clone() - b), beta.clone() - a);
+        builder.when_transition().assert_eq_ext(
+            z_next * (beta.clone() - b_next),
+            z.clone() * (beta - a_next),
+        );
+        builder.when_last_row().assert_one_ext(z);
+    }
+}
+
+/// [`PermutationCheckAir`], but failing to generate its permutation trace.
+pub struct MissingPermutationTraceAir;
+
+impl<F: Field> BaseAir<F> for MissingPermutationTraceAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn permutation_width(&self) -> usize {
+        1
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        1
+    }
+}
+
+impl<F: Field> BaseAirWithPublicValues<F> for MissingPermutationTraceAir {}
+
+impl<AB: PermutationAirBuilder> Air<AB> for MissingPermutationTraceAir {
+    fn eval(&self, builder: &mut AB) {
+        PermutationCheckAir.eval(builder);
+    }
+}
+
+/// A trace whose second column is a permutation of the first, unless `valid` is false, in which
+/// case one entry of the second column is changed.
+fn generate_trace<F: Field>(log_height: usize, valid: bool) -> RowMajorMatrix<F> {
+    let n = 1 << log_height;
+    let mut values = F::zero_vec(2 * n);
+    for i in 0..n {
+        values[2 * i] = F::from_usize(i * i + 1);
+        values[2 * (n - 1 - i) + 1] = F::from_usize(i * i + 1);
+    }
+    if !valid {
+        values[1] += F::ONE;
+    }
+    RowMajorMatrix::new(values, 2)
+}
+
+fn do_test<SC: StarkGenericConfig>(
+    config: SC,
+    challenger: SC::Challenger,
+    log_height: usize,
+) -> Result<(), VerificationError<p3_uni_stark::PcsError<SC>>>
+where
+    SC::Challenger: Clone,
+{
+    let trace = generate_trace::<Val<SC>>(log_height, true);
+
+    let mut p_challenger = challenger.clone();
+    let proof = prove(
+        &config,
+        &PermutationCheckAir,
+        &mut p_challenger,
+        trace,
+        &vec![],
+    );
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let deserialized_proof =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut v_challenger = challenger;
+    verify(
+        &config,
+        &PermutationCheckAir,
+        &mut v_challenger,
+        &deserialized_proof,
+        &vec![],
+    )
+}
+
+type BbVal = BabyBear;
+type BbPerm = Poseidon2BabyBear<16>;
+type BbHash = PaddingFreeSponge<BbPerm, 16, 8, 8>;
+type BbCompress = TruncatedPermutation<BbPerm, 2, 8, 16>;
+type BbValMmcs =
+    MerkleTreeMmcs<<BbVal as Field>::Packing, <BbVal as Field>::Packing, BbHash, BbCompress, 8>;
+type BbChallenge = BinomialExtensionField<BbVal, 4>;
+type BbChallengeMmcs = ExtensionMmcs<BbVal, BbChallenge, BbValMmcs>;
+type BbChallenger = DuplexChallenger<BbVal, BbPerm, 16, 8>;
+type BbDft = Radix2DitParallel<BbVal>;
+type BbPcs = TwoAdicFriPcs<BbVal, BbDft, BbValMmcs, BbChallengeMmcs>;
+type BbConfig = StarkConfig<BbPcs, BbChallenge, BbChallenger>;
+
+fn bb_config() -> (BbConfig, BbChallenger) {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = BbPerm::new_from_rng_128(&mut rng);
+    let hash = BbHash::new(perm.clone());
+    let compress = BbCompress::new(perm.clone());
+    let val_mmcs = BbValMmcs::new(hash, compress);
+    let challenge_mmcs = BbChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = BbPcs::new(BbDft::default(), val_mmcs, fri_config);
+    (BbConfig::new(pcs), BbChallenger::new(perm))
+}
+
+#[test]
+fn prove_bb_twoadic_permutation() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    let (config, challenger) = bb_config();
+    do_test(config, challenger, 6)
+}
+
+#[test]
+fn prove_m31_circle_permutation() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+    let compress = MyCompress::new(byte_hash);
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_config,
+        _phantom: PhantomData,
+    };
+    let config = MyConfig::new(pcs);
+
+    do_test(config, Challenger::from_hasher(vec![], byte_hash), 6)
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "failing constraints")]
+fn test_not_a_permutation() {
+    let (config, mut challenger) = bb_config();
+    let trace = generate_trace::<BbVal>(4, false);
+    prove(
+        &config,
+        &PermutationCheckAir,
+        &mut challenger,
+        trace,
+        &vec![],
+    );
+}
+
+#[test]
+fn test_missing_permutation_trace() {
+    let (config, mut challenger) = bb_config();
+    let trace = generate_trace::<BbVal>(4, true);
+    let result = try_prove(
+        &config,
+        &MissingPermutationTraceAir,
+        &mut challenger,
+        trace,
+        &vec![],
+    );
+    assert!(matches!(result, Err(ProverError::MissingPermutationTrace)));
+}
@@ -0,0 +1,196 @@
+use core::marker::PhantomData;
+
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{FriConfig, TwoAdicFriPcs, create_test_fri_config};
+use p3_keccak::Keccak256Hash;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
+};
+use p3_uni_stark::{
+    StarkConfig, StarkGenericConfig, Val, VerificationError, prove_with_preprocessed,
+    setup_preprocessed, verify_with_preprocessed,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// An AIR with a single preprocessed column holding `offset + i` on row `i`, and a single main
+/// column which must hold the square of the preprocessed value on each row.
+pub struct SquaresAir {
+    log_height: usize,
+    offset: u64,
+}
+
+impl SquaresAir {
+    fn generate_trace<F: Field>(&self) -> RowMajorMatrix<F> {
+        let values = (0..1 << self.log_height)
+            .map(|i| F::from_u64(self.offset + i).square())
+            .collect();
+        RowMajorMatrix::new_col(values)
+    }
+}
+
+impl<F: Field> BaseAir<F> for SquaresAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = (0..1 << self.log_height)
+            .map(|i| F::from_u64(self.offset + i))
+            .collect();
+        Some(RowMajorMatrix::new_col(values))
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for SquaresAir {
+    fn eval(&self, builder: &mut AB) {
+        let preprocessed = builder.preprocessed();
+        let main = builder.main();
+
+        let (prep_local, prep_next) = (preprocessed.row_slice(0), preprocessed.row_slice(1));
+        let main_local = main.row_slice(0);
+
+        builder.assert_eq(main_local[0], prep_local[0].into().square());
+        builder
+            .when_transition()
+            .assert_eq(prep_next[0], prep_local[0] + AB::Expr::ONE);
+    }
+}
+
+fn do_test<SC: StarkGenericConfig>(
+    config: SC,
+    challenger: SC::Challenger,
+    log_height: usize,
+) -> Result<(), VerificationError<p3_uni_stark::PcsError<SC>>>
+where
+    SC::Challenger: Clone,
+{
+    let air = SquaresAir {
+        log_height,
+        offset: 3,
+    };
+    let (preprocessed_pk, preprocessed_vk) =
+        setup_preprocessed(&config, &air).expect("AIR has a preprocessed trace");
+    let trace = air.generate_trace::<Val<SC>>();
+
+    let mut p_challenger = challenger.clone();
+    let proof = prove_with_preprocessed(
+        &config,
+        &air,
+        &mut p_challenger,
+        trace,
+        &vec![],
+        Some(&preprocessed_pk),
+    );
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let deserialized_proof =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut v_challenger = challenger.clone();
+    verify_with_preprocessed(
+        &config,
+        &air,
+        &mut v_challenger,
+        &deserialized_proof,
+        &vec![],
+        Some(&preprocessed_vk),
+    )?;
+
+    // The same proof must not verify against the key of a different preprocessed trace.
+    let other_air = SquaresAir {
+        log_height,
+        offset: 4,
+    };
+    let (_, other_vk) = setup_preprocessed(&config, &other_air).unwrap();
+    let mut v_challenger = challenger;
+    let result = verify_with_preprocessed(
+        &config,
+        &other_air,
+        &mut v_challenger,
+        &deserialized_proof,
+        &vec![],
+        Some(&other_vk),
+    );
+    assert!(
+        result.is_err(),
+        "proof verified against the wrong preprocessed key"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn prove_bb_twoadic_preprocessed() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    type Val = BabyBear;
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    type Challenge = BinomialExtensionField<Val, 4>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type Dft = Radix2DitParallel<Val>;
+    type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = Perm::new_from_rng_128(&mut rng);
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config);
+    let config = MyConfig::new(pcs);
+
+    do_test(config, Challenger::new(perm), 6)
+}
+
+#[test]
+fn prove_m31_circle_preprocessed() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+    let compress = MyCompress::new(byte_hash);
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_config,
+        _phantom: PhantomData,
+    };
+    let config = MyConfig::new(pcs);
+
+    do_test(config, Challenger::from_hasher(vec![], byte_hash), 6)
+}
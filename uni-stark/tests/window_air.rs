@@ -0,0 +1,205 @@
+use core::marker::PhantomData;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::{DuplexChallenger, HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{Field, PrimeCharacteristicRing};
+use p3_fri::{FriConfig, TwoAdicFriPcs, create_test_fri_config};
+use p3_keccak::Keccak256Hash;
+use p3_matrix::Matrix;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{
+    CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
+};
+use p3_uni_stark::{
+    StarkConfig, StarkGenericConfig, Val, VerificationError, check_constraints, prove, verify,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+/// An AIR with a window of three rows and three main columns `(x, y, z)`: `x` holds the Fibonacci
+/// sequence and `z` its multiplicative analogue, with each value constrained by the two rows
+/// before it, and `y` counts up from zero.
+pub struct FibonacciWindowAir;
+
+impl<F> BaseAir<F> for FibonacciWindowAir {
+    fn width(&self) -> usize {
+        3
+    }
+
+    fn window_size(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for FibonacciWindowAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (row0, row1, row2) = (main.row_slice(0), main.row_slice(1), main.row_slice(2));
+
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_one(row0[0]);
+        when_first_row.assert_one(row1[0]);
+        when_first_row.assert_zero(row0[1]);
+        when_first_row.assert_one(row0[2]);
+        when_first_row.assert_eq(row1[2], AB::Expr::TWO);
+
+        let mut when_transition_window = builder.when_transition_window(3);
+        when_transition_window.assert_eq(row2[0], row1[0] + row0[0]);
+        // Of degree 2, so the quotient must also cover the degree of the window's selector.
+        when_transition_window.assert_eq(row2[2], row1[2] * row0[2]);
+        builder
+            .when_transition()
+            .assert_eq(row1[1], row0[1] + AB::Expr::ONE);
+    }
+}
+
+/// A trace for [`FibonacciWindowAir`]. Neither column wraps around from the last row to the first,
+/// so the windows starting on the last rows must not be constrained.
+fn generate_trace<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let n = 1 << log_height;
+    let mut values = F::zero_vec(3 * n);
+    let (mut a, mut b) = (F::ONE, F::ONE);
+    let (mut c, mut d) = (F::ONE, F::TWO);
+    for i in 0..n {
+        values[3 * i] = a;
+        values[3 * i + 1] = F::from_usize(i);
+        values[3 * i + 2] = c;
+        (a, b) = (b, a + b);
+        (c, d) = (d, c * d);
+    }
+    RowMajorMatrix::new(values, 3)
+}
+
+fn do_test<SC: StarkGenericConfig>(
+    config: SC,
+    challenger: SC::Challenger,
+    log_height: usize,
+) -> Result<(), VerificationError<p3_uni_stark::PcsError<SC>>>
+where
+    SC::Challenger: Clone,
+{
+    let trace = generate_trace::<Val<SC>>(log_height);
+
+    let mut p_challenger = challenger.clone();
+    let proof = prove(
+        &config,
+        &FibonacciWindowAir,
+        &mut p_challenger,
+        trace,
+        &vec![],
+    );
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let deserialized_proof =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut v_challenger = challenger;
+    verify(
+        &config,
+        &FibonacciWindowAir,
+        &mut v_challenger,
+        &deserialized_proof,
+        &vec![],
+    )
+}
+
+type BbVal = BabyBear;
+type BbPerm = Poseidon2BabyBear<16>;
+type BbHash = PaddingFreeSponge<BbPerm, 16, 8, 8>;
+type BbCompress = TruncatedPermutation<BbPerm, 2, 8, 16>;
+type BbValMmcs =
+    MerkleTreeMmcs<<BbVal as Field>::Packing, <BbVal as Field>::Packing, BbHash, BbCompress, 8>;
+type BbChallenge = BinomialExtensionField<BbVal, 4>;
+type BbChallengeMmcs = ExtensionMmcs<BbVal, BbChallenge, BbValMmcs>;
+type BbChallenger = DuplexChallenger<BbVal, BbPerm, 16, 8>;
+type BbDft = Radix2DitParallel<BbVal>;
+type BbPcs = TwoAdicFriPcs<BbVal, BbDft, BbValMmcs, BbChallengeMmcs>;
+type BbConfig = StarkConfig<BbPcs, BbChallenge, BbChallenger>;
+
+#[test]
+fn prove_bb_twoadic_window() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    let mut rng = SmallRng::seed_from_u64(1);
+    let perm = BbPerm::new_from_rng_128(&mut rng);
+    let hash = BbHash::new(perm.clone());
+    let compress = BbCompress::new(perm.clone());
+    let val_mmcs = BbValMmcs::new(hash, compress);
+    let challenge_mmcs = BbChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = create_test_fri_config(challenge_mmcs, 2);
+    let pcs = BbPcs::new(BbDft::default(), val_mmcs, fri_config);
+    do_test(BbConfig::new(pcs), BbChallenger::new(perm), 6)
+}
+
+#[test]
+fn prove_m31_circle_window() -> Result<(), VerificationError<impl core::fmt::Debug>> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+    let compress = MyCompress::new(byte_hash);
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        max_log_arity: 1,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_config,
+        _phantom: PhantomData,
+    };
+    let config = MyConfig::new(pcs);
+
+    do_test(config, Challenger::from_hasher(vec![], byte_hash), 6)
+}
+
+#[test]
+fn test_window_check_constraints() {
+    let mut trace = generate_trace::<BbVal>(4);
+    assert!(
+        check_constraints::<_, BbChallenge, _>(
+            &FibonacciWindowAir,
+            None,
+            &trace,
+            None,
+            &[],
+            &[],
+            None
+        )
+        .is_ok()
+    );
+
+    // Row 5 is the third row of the window starting at row 3, and the second of the window
+    // starting at row 4.
+    trace.values[3 * 5] += BbVal::ONE;
+    let report = check_constraints::<_, BbChallenge, _>(
+        &FibonacciWindowAir,
+        None,
+        &trace,
+        None,
+        &[],
+        &[],
+        None,
+    );
+    let failing_rows = report.failures.iter().map(|f| f.row).collect::<Vec<_>>();
+    assert_eq!(failing_rows, vec![3, 4, 5]);
+}